@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::template::{expected_columns_for_mode, template_fingerprint};
+
+const MODES: &[&str] = &["DDNS", "minION"];
+
+/// Column template a build writes for one mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVersion {
+    pub mode: String,
+    pub columns: usize,
+    pub fingerprint: String,
+}
+
+/// What a build is: shown in About, logged at startup, printed by
+/// `merger --version` and recorded in run packages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    // "unknown" when built outside a git checkout
+    pub git_hash: String,
+    // YYYY-MM-DD, UTC
+    pub build_date: String,
+    pub templates: Vec<TemplateVersion>,
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("MERGER_GIT_HASH").to_string(),
+        build_date: env!("MERGER_BUILD_DATE").to_string(),
+        templates: MODES
+            .iter()
+            .map(|mode| TemplateVersion {
+                mode: mode.to_string(),
+                columns: expected_columns_for_mode(mode).len(),
+                fingerprint: template_fingerprint(mode),
+            })
+            .collect(),
+    }
+}
+
+impl BuildInfo {
+    /// "1.2.1 (abc1234567, 2026-02-11)"
+    pub fn short(&self) -> String {
+        format!("{} ({}, {})", self.version, self.git_hash, self.build_date)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("Merger {}", self.version),
+            format!("Commit: {}", self.git_hash),
+            format!("Built: {}", self.build_date),
+        ];
+        for template in &self.templates {
+            lines.push(format!(
+                "{} template: {} columns, {}",
+                template.mode, template.columns, template.fingerprint
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_hex(s: &str) -> bool {
+        !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    #[test]
+    fn build_info_is_fully_populated() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.version.is_empty());
+        assert!(info.git_hash == "unknown" || is_hex(&info.git_hash), "{}", info.git_hash);
+        assert_eq!(info.git_hash, env!("MERGER_GIT_HASH"));
+
+        let date: Vec<&str> = info.build_date.split('-').collect();
+        assert_eq!(date.iter().map(|p| p.len()).collect::<Vec<_>>(), [4, 2, 2], "{}", info.build_date);
+        assert!(date.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())));
+        assert!(chrono::NaiveDate::parse_from_str(&info.build_date, "%Y-%m-%d").is_ok());
+
+        let modes: Vec<&str> = info.templates.iter().map(|t| t.mode.as_str()).collect();
+        assert_eq!(modes, MODES);
+        for template in &info.templates {
+            assert_eq!(template.columns, expected_columns_for_mode(&template.mode).len());
+            assert!(template.columns > 0);
+            assert_eq!(template.fingerprint.len(), 8);
+            assert!(is_hex(&template.fingerprint));
+        }
+        assert_ne!(info.templates[0].fingerprint, info.templates[1].fingerprint);
+    }
+
+    #[test]
+    fn build_info_text_and_json() {
+        let info = build_info();
+        let text = info.to_text();
+        assert!(text.starts_with(&format!("Merger {}\nCommit: {}", info.version, info.git_hash)));
+        assert!(text.contains(&format!("minION template: {} columns", info.templates[1].columns)));
+        assert_eq!(info.short(), format!("{} ({}, {})", info.version, info.git_hash, info.build_date));
+
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(serde_json::from_str::<BuildInfo>(&json).unwrap(), info);
+
+        for flag in ["--version", "-V", "--about"] {
+            assert_eq!(crate::cli::run_cli(&[flag.to_string()]), Some(0), "{}", flag);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::settings::{Settings, ThemePreference};
+
+// Scale factors outside this range are ignored as likely typos
+pub const MIN_SCALE: f32 = 0.5;
+pub const MAX_SCALE: f32 = 3.0;
+
+/// Asks the OS whether a dark colour scheme is active
+pub fn system_prefers_dark() -> bool {
+    matches!(dark_light::detect(), dark_light::Mode::Dark)
+}
+
+/// Resolves the user preference against the detected system mode
+pub fn resolve_dark_mode(pref: ThemePreference, system_dark: bool) -> bool {
+    match pref {
+        ThemePreference::System => system_dark,
+        ThemePreference::Light => false,
+        ThemePreference::Dark => true,
+    }
+}
+
+// Converts a UI scale label ("125%") to a factor, "Auto" = None
+pub fn scale_from_label(label: &str) -> Option<f32> {
+    let pct = label.trim().strip_suffix('%')?.trim().parse::<f32>().ok()?;
+    let factor = pct / 100.0;
+    if (MIN_SCALE..=MAX_SCALE).contains(&factor) {
+        Some(factor)
+    } else {
+        None
+    }
+}
+
+pub fn scale_label(scale: Option<f32>) -> String {
+    match scale {
+        Some(f) => format!("{}%", (f * 100.0).round() as i32),
+        None => "Auto".to_string(),
+    }
+}
+
+/// Applies the persisted scale override. Must run before the first window is
+/// created, as Slint reads SLINT_SCALE_FACTOR once at backend start-up.
+/// An explicit environment variable always wins over the stored setting.
+pub fn apply_scale_override(settings: &Settings) {
+    if std::env::var_os("SLINT_SCALE_FACTOR").is_some() {
+        return;
+    }
+    if let Some(scale) = settings.ui_scale {
+        if (MIN_SCALE..=MAX_SCALE).contains(&scale) {
+            std::env::set_var("SLINT_SCALE_FACTOR", scale.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preference_overrides_the_system_mode() {
+        for system_dark in [false, true] {
+            assert_eq!(resolve_dark_mode(ThemePreference::System, system_dark), system_dark);
+            assert!(!resolve_dark_mode(ThemePreference::Light, system_dark));
+            assert!(resolve_dark_mode(ThemePreference::Dark, system_dark));
+        }
+    }
+
+    #[test]
+    fn scale_labels_parse_within_range() {
+        assert_eq!(scale_from_label("125%"), Some(1.25));
+        assert_eq!(scale_from_label(" 200 % "), Some(2.0));
+        assert_eq!(scale_from_label("50%"), Some(MIN_SCALE));
+        assert_eq!(scale_from_label("300%"), Some(MAX_SCALE));
+        for label in ["Auto", "", "125", "abc%", "40%", "350%"] {
+            assert_eq!(scale_from_label(label), None, "{}", label);
+        }
+    }
+
+    #[test]
+    fn scale_labels_round_trip() {
+        assert_eq!(scale_label(None), "Auto");
+        for label in ["75%", "100%", "125%", "150%", "200%"] {
+            assert_eq!(scale_label(scale_from_label(label)), label);
+        }
+    }
+
+    #[test]
+    fn theme_and_scale_persist() {
+        for theme in [ThemePreference::System, ThemePreference::Light, ThemePreference::Dark] {
+            assert_eq!(ThemePreference::from_label(theme.label()), theme);
+        }
+        assert_eq!(ThemePreference::from_label("Sepia"), ThemePreference::System);
+
+        let settings = Settings { theme: ThemePreference::Dark, ui_scale: Some(1.5), ..Settings::default() };
+        let read: Settings = serde_json::from_str(&serde_json::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(read.theme, ThemePreference::Dark);
+        assert_eq!(read.ui_scale, Some(1.5));
+
+        // Files from before the setting existed keep the defaults
+        let old: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.theme, ThemePreference::System);
+        assert_eq!(old.ui_scale, None);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+// Merge stays disabled this long after a failed attempt
+pub const FAILURE_COOLDOWN: Duration = Duration::from_millis(1500);
+
+/// Decides whether a merge attempt may start, and counts how often the
+/// same error came back so repeated clicks give one dialog, not a cascade.
+#[derive(Debug, Default)]
+pub struct MergeGuard {
+    in_flight: bool,
+    cooldown_until: Option<Instant>,
+    // (title, message) of the last failure
+    last_error: Option<(String, String)>,
+    // How many attempts in a row ended with `last_error`
+    repeats: u32,
+}
+
+impl MergeGuard {
+    pub fn can_start(&self, now: Instant) -> bool {
+        !self.in_flight && self.cooldown_until.is_none_or(|until| now >= until)
+    }
+
+    /// Starts an attempt; false while one is running or cooling down
+    pub fn try_begin(&mut self, now: Instant) -> bool {
+        if !self.can_start(now) {
+            return false;
+        }
+        self.in_flight = true;
+        true
+    }
+
+    /// Ends an attempt that did not fail (written, or stopped to ask the
+    /// user something). A later error starts counting from one again.
+    pub fn finished(&mut self) {
+        self.in_flight = false;
+        self.cooldown_until = None;
+        self.last_error = None;
+        self.repeats = 0;
+    }
+
+    /// Ends a failed attempt and starts the cool-down. Returns how many
+    /// attempts in a row failed with exactly this error.
+    pub fn failed(&mut self, title: &str, message: &str, now: Instant) -> u32 {
+        self.in_flight = false;
+        self.cooldown_until = Some(now + FAILURE_COOLDOWN);
+
+        let error = (title.to_string(), message.to_string());
+        if self.last_error.as_ref() == Some(&error) {
+            self.repeats += 1;
+        } else {
+            self.last_error = Some(error);
+            self.repeats = 1;
+        }
+        self.repeats
+    }
+}
+
+/// `message` with the attempt counter added when it happened more than once
+pub fn with_attempt_count(message: &str, count: u32, french: bool) -> String {
+    if count <= 1 {
+        return message.to_string();
+    }
+    if french {
+        format!("{}\n\n(Cette erreur s'est produite {} fois.)", message, count)
+    } else {
+        format!("{}\n\n(This error occurred {} times.)", message, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_attempt_is_refused_while_one_runs() {
+        let mut guard = MergeGuard::default();
+        let now = Instant::now();
+        assert!(guard.try_begin(now));
+        assert!(!guard.can_start(now));
+        assert!(!guard.try_begin(now));
+        guard.finished();
+        assert!(guard.try_begin(now));
+    }
+
+    #[test]
+    fn failure_cools_down_until_the_boundary() {
+        let mut guard = MergeGuard::default();
+        let start = Instant::now();
+        assert!(guard.try_begin(start));
+        guard.failed("Error", "No sample file", start);
+
+        assert!(!guard.try_begin(start));
+        assert!(!guard.try_begin(start + FAILURE_COOLDOWN - Duration::from_millis(1)));
+        assert!(guard.try_begin(start + FAILURE_COOLDOWN));
+    }
+
+    #[test]
+    fn same_error_counts_up_and_a_new_one_restarts() {
+        let mut guard = MergeGuard::default();
+        let mut now = Instant::now();
+        for expected in 1..=3 {
+            assert!(guard.try_begin(now));
+            assert_eq!(guard.failed("Error", "No sample file", now), expected);
+            now += FAILURE_COOLDOWN;
+        }
+        assert!(guard.try_begin(now));
+        assert_eq!(guard.failed("Error", "No EpiInfo file", now), 1);
+        now += FAILURE_COOLDOWN;
+        assert!(guard.try_begin(now));
+        assert_eq!(guard.failed("Erreur", "No EpiInfo file", now), 1);
+    }
+
+    #[test]
+    fn finishing_resets_the_count_and_cool_down() {
+        let mut guard = MergeGuard::default();
+        let now = Instant::now();
+        assert!(guard.try_begin(now));
+        guard.failed("Error", "No sample file", now);
+        assert!(!guard.can_start(now));
+
+        guard.finished();
+        assert!(guard.try_begin(now));
+        assert_eq!(guard.failed("Error", "No sample file", now), 1);
+    }
+
+    #[test]
+    fn attempt_count_is_shown_from_the_second_time() {
+        assert_eq!(with_attempt_count("Failed.", 1, false), "Failed.");
+        assert_eq!(with_attempt_count("Failed.", 3, false), "Failed.\n\n(This error occurred 3 times.)");
+        assert_eq!(with_attempt_count("Échec.", 2, true), "Échec.\n\n(Cette erreur s'est produite 2 fois.)");
+    }
+}
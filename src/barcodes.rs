@@ -0,0 +1,229 @@
+use polars::prelude::*;
+use regex::Regex;
+
+use crate::domains::line_of;
+
+/// Column names MinKNOW and Guppy/Dorado outputs put before a barcode
+/// name when a column is pasted with its header ("barcode_arrangement
+/// barcode01"). Lowercase; compared after trimming.
+pub const ARRANGEMENT_PREFIXES: &[&str] = &["barcode_arrangement"];
+
+/// What MinKNOW calls reads that matched no barcode
+pub const UNCLASSIFIED: &str = "unclassified";
+
+/// What a barcode cell holds, once normalized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarcodeToken {
+    // "barcode01"; the form Piranha expects
+    Barcode(String),
+    Unclassified,
+    // Nothing Piranha could use
+    Unmappable,
+}
+
+// "barcode1", "BC01", "NB01", "RB01", optionally after a kit name ("SQK-NBD114-96_")
+const BARCODE_PATTERN: &str = r"^(?:sqk-[a-z0-9-]+[_ ])?(?:barcode|bc|nb|rb)[ _-]?0*(\d{1,3})$";
+
+/// The compiled BARCODE_PATTERN, built once per check and passed to
+/// `classify_barcode`
+pub fn barcode_pattern() -> Regex {
+    Regex::new(BARCODE_PATTERN).unwrap()
+}
+
+/// Classifies one barcode cell. Shared by validation and anything that
+/// counts barcodes, so both see the same names.
+pub fn classify_barcode(raw: &str, pattern: &Regex) -> BarcodeToken {
+    let mut value = raw.trim().to_lowercase();
+    for prefix in ARRANGEMENT_PREFIXES {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            value = rest.trim_start_matches([' ', '_', ':', '=', '\t']).to_string();
+            break;
+        }
+    }
+    if value == UNCLASSIFIED {
+        return BarcodeToken::Unclassified;
+    }
+    match pattern
+        .captures(&value)
+        .and_then(|c| c[1].parse::<u32>().ok())
+        .filter(|&n| n > 0)
+    {
+        Some(n) => BarcodeToken::Barcode(format!("barcode{:02}", n)),
+        None => BarcodeToken::Unmappable,
+    }
+}
+
+/// The barcode column checked row by row. Lines are file lines (header = 1).
+#[derive(Debug, Default)]
+pub struct BarcodeCheck {
+    // Every row's normalized barcode; the raw value when it has none
+    pub values: Vec<String>,
+    // Rows whose value was rewritten, as (line, before, after)
+    pub renamed: Vec<(usize, String, String)>,
+    // 0-based rows holding "unclassified"
+    pub unclassified: Vec<usize>,
+    // (line, value) of rows that name no barcode
+    pub unmappable: Vec<(usize, String)>,
+}
+
+/// Checks the `barcode` column. Empty cells and a missing column are left
+/// to the sample/barcode completeness check.
+pub fn check_barcodes(df: &DataFrame) -> PolarsResult<BarcodeCheck> {
+    let mut check = BarcodeCheck::default();
+    // No barcode column: the completeness check reports it
+    let Ok(column) = df.column("barcode") else {
+        return Ok(check);
+    };
+    let column = column.cast(&DataType::String)?;
+    let pattern = barcode_pattern();
+    for (idx, value) in column.str()?.into_iter().enumerate() {
+        let raw = value.unwrap_or("").to_string();
+        if raw.trim().is_empty() {
+            check.values.push(raw);
+            continue;
+        }
+        match classify_barcode(&raw, &pattern) {
+            BarcodeToken::Barcode(name) => {
+                if name != raw {
+                    check.renamed.push((line_of(idx), raw, name.clone()));
+                }
+                check.values.push(name);
+            }
+            BarcodeToken::Unclassified => {
+                check.unclassified.push(idx);
+                check.values.push(raw);
+            }
+            BarcodeToken::Unmappable => {
+                check.unmappable.push((line_of(idx), raw.clone()));
+                check.values.push(raw);
+            }
+        }
+    }
+    Ok(check)
+}
+
+/// `df` with the barcode column replaced by the normalized values
+pub fn apply_barcodes(mut df: DataFrame, check: &BarcodeCheck) -> PolarsResult<DataFrame> {
+    if check.renamed.is_empty() {
+        return Ok(df);
+    }
+    df.with_column(Series::new(PlSmallStr::from_str("barcode"), check.values.clone()))?;
+    Ok(df)
+}
+
+/// Some row has no barcode (or the column is missing)
+pub fn has_empty_barcodes(df: &DataFrame) -> bool {
+    let Ok(column) = df.column("barcode").and_then(|c| c.cast(&DataType::String)) else {
+        return true;
+    };
+    column
+        .str()
+        .map(|s| s.into_iter().any(|v| v.unwrap_or("").trim().is_empty()))
+        .unwrap_or(false)
+}
+
+/// For runs without barcoding: `df` with every barcode cell emptied, and
+/// how many cells held a value. A frame without the column is unchanged.
+pub fn clear_barcodes(mut df: DataFrame) -> PolarsResult<(DataFrame, usize)> {
+    let Ok(column) = df.column("barcode") else {
+        return Ok((df, 0));
+    };
+    let column = column.cast(&DataType::String)?;
+    let cleared = column
+        .str()?
+        .into_iter()
+        .filter(|v| v.is_some_and(|v| !v.trim().is_empty()))
+        .count();
+    let empty: Vec<Option<&str>> = vec![None; df.height()];
+    df.with_column(Series::new(PlSmallStr::from_str("barcode"), empty))?;
+    Ok((df, cleared))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barcode(name: &str) -> BarcodeToken {
+        BarcodeToken::Barcode(name.to_string())
+    }
+
+    #[test]
+    fn barcode_cells_are_classified() {
+        let pattern = barcode_pattern();
+        let cases = [
+            // the form Piranha expects, and other spellings of it
+            ("barcode01", barcode("barcode01")),
+            ("barcode1", barcode("barcode01")),
+            ("  Barcode 7 ", barcode("barcode07")),
+            ("barcode-96", barcode("barcode96")),
+            ("BC12", barcode("barcode12")),
+            ("bc_3", barcode("barcode03")),
+            ("NB05", barcode("barcode05")),
+            ("RB96", barcode("barcode96")),
+            // kit and MinKNOW column prefixes
+            ("SQK-NBD114-96_barcode01", barcode("barcode01")),
+            ("sqk-rbk114-24 RB02", barcode("barcode02")),
+            ("barcode_arrangement barcode01", barcode("barcode01")),
+            ("barcode_arrangement: BC02", barcode("barcode02")),
+            // reads MinKNOW could not assign
+            ("unclassified", BarcodeToken::Unclassified),
+            (" UNCLASSIFIED ", BarcodeToken::Unclassified),
+            ("barcode_arrangement unclassified", BarcodeToken::Unclassified),
+            // nothing Piranha could use
+            ("barcode00", BarcodeToken::Unmappable),
+            ("barcode", BarcodeToken::Unmappable),
+            ("BC1234", BarcodeToken::Unmappable),
+            ("sample1", BarcodeToken::Unmappable),
+            ("12", BarcodeToken::Unmappable),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(classify_barcode(raw, &pattern), expected, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn check_reports_renames_unclassified_and_unmappable_rows() {
+        let df = df!("barcode" => ["barcode01", "BC2", "unclassified", "", "oops"]).unwrap();
+        let check = check_barcodes(&df).unwrap();
+        assert_eq!(check.values, ["barcode01", "barcode02", "unclassified", "", "oops"]);
+        assert_eq!(check.renamed, vec![(3, "BC2".to_string(), "barcode02".to_string())]);
+        assert_eq!(check.unclassified, vec![2]);
+        assert_eq!(check.unmappable, vec![(6, "oops".to_string())]);
+
+        let applied = apply_barcodes(df, &check).unwrap();
+        assert_eq!(applied.column("barcode").unwrap().str().unwrap().get(1), Some("barcode02"));
+    }
+
+    #[test]
+    fn clearing_barcodes_blanks_the_column_and_counts_values() {
+        let df = df!("sample" => ["S1", "S2", "S3"], "barcode" => ["barcode01", "", " "]).unwrap();
+        assert!(has_empty_barcodes(&df));
+        let (cleared, count) = clear_barcodes(df).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(cleared.height(), 3);
+        assert_eq!(cleared.column("barcode").unwrap().null_count(), 3);
+        assert_eq!(cleared.column("sample").unwrap().str().unwrap().get(0), Some("S1"));
+    }
+
+    #[test]
+    fn clearing_without_a_barcode_column_changes_nothing() {
+        let df = df!("sample" => ["S1"]).unwrap();
+        assert!(has_empty_barcodes(&df));
+        let (cleared, count) = clear_barcodes(df.clone()).unwrap();
+        assert_eq!(count, 0);
+        assert!(cleared.equals(&df));
+    }
+
+    #[test]
+    fn filled_barcodes_are_not_empty() {
+        let df = df!("barcode" => ["barcode01", "barcode02"]).unwrap();
+        assert!(!has_empty_barcodes(&df));
+    }
+
+    #[test]
+    fn check_without_a_barcode_column_finds_nothing() {
+        let df = df!("sample" => ["S1"]).unwrap();
+        let check = check_barcodes(&df).unwrap();
+        assert!(check.values.is_empty() && check.unmappable.is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::config::{checker_for, CONFIG_FILES};
+use crate::input::open_read_only;
+use crate::naming::sanitize_file_component;
+use crate::settings::{settings_dir, Settings};
+
+// Bump when the bundle layout changes; older apps refuse newer bundles
+pub const BUNDLE_VERSION: u32 = 1;
+
+const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// Describes a country configuration bundle. The configuration itself
+/// (lab prefixes, EPID sources, redaction patterns, output name pattern,
+/// ...) is in the config files listed in `files`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub bundle_version: u32,
+    // Shown in the bundle dropdown and used as its folder name
+    pub name: String,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub description: String,
+    // YYYY-MM-DD HH:MM
+    #[serde(default)]
+    pub created: String,
+    #[serde(default)]
+    pub app_version: String,
+    // Config file names, each one of CONFIG_FILES
+    pub files: Vec<String>,
+}
+
+/// Where imported bundles are kept, one folder per bundle
+pub fn bundles_dir() -> Option<PathBuf> {
+    settings_dir().map(|d| d.join("bundles"))
+}
+
+pub fn bundle_file_name(name: &str) -> String {
+    let name = sanitize_file_component(name);
+    let name = if name.is_empty() { "merger".to_string() } else { name };
+    format!("{}_config_bundle.zip", name)
+}
+
+// Every top-level file of a bundle zip, as text
+fn read_zip(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let file = open_read_only(path)
+        .map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| format!("'{}' is not a configuration bundle: {e}", path.display()))?;
+
+    let mut files = BTreeMap::new();
+    for idx in 0..archive.len() {
+        let mut entry = archive
+            .by_index(idx)
+            .map_err(|e| format!("Failed to read the bundle: {e}"))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut text = String::new();
+        entry
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Failed to read '{}' from the bundle: {e}", name))?;
+        files.insert(name, text);
+    }
+    Ok(files)
+}
+
+// Every file of a bundle folder, as text. Subfolders are not part of a bundle.
+fn read_dir(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let entries = fs::read_dir(path)
+        .map_err(|e| format!("Failed to read '{}': {e}", path.display()))?;
+
+    let mut files = BTreeMap::new();
+    for entry in entries.flatten() {
+        let file = entry.path();
+        if !file.is_file() {
+            continue;
+        }
+        let text = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read '{}': {e}", file.display()))?;
+        files.insert(entry.file_name().to_string_lossy().to_string(), text);
+    }
+    Ok(files)
+}
+
+/// Checks the manifest against the files next to it and validates each
+/// config file with its loader. Returns the manifest, or every problem found.
+pub fn check_bundle(files: &BTreeMap<String, String>) -> Result<BundleManifest, String> {
+    let text = files
+        .get(BUNDLE_MANIFEST)
+        .ok_or_else(|| format!("Not a configuration bundle: no {}.", BUNDLE_MANIFEST))?;
+    let manifest: BundleManifest = serde_json::from_str(text)
+        .map_err(|e| format!("Invalid bundle manifest: {e}"))?;
+    if manifest.bundle_version > BUNDLE_VERSION {
+        return Err(format!(
+            "This bundle was made by a newer Merger (bundle version {}, this app reads up to {}). Please update Merger.",
+            manifest.bundle_version, BUNDLE_VERSION
+        ));
+    }
+    if sanitize_file_component(&manifest.name).is_empty() {
+        return Err("Invalid bundle manifest: the bundle has no name.".to_string());
+    }
+    if manifest.files.is_empty() {
+        return Err("Invalid bundle manifest: no configuration files are listed.".to_string());
+    }
+
+    let mut problems = Vec::new();
+    for name in &manifest.files {
+        let Some(check) = checker_for(name) else {
+            problems.push(format!(
+                "'{}' is not a configuration file (expected one of: {})",
+                name,
+                CONFIG_FILES.iter().map(|(f, _)| *f).collect::<Vec<_>>().join(", ")
+            ));
+            continue;
+        };
+        match files.get(name) {
+            Some(text) => problems.extend(check(name, text).iter().map(|i| i.to_string())),
+            None => problems.push(format!("'{}' is listed in the manifest but missing", name)),
+        }
+    }
+    for name in files.keys().filter(|f| *f != BUNDLE_MANIFEST && !manifest.files.contains(f)) {
+        problems.push(format!("'{}' is not listed in the manifest", name));
+    }
+
+    if problems.is_empty() {
+        Ok(manifest)
+    } else {
+        Err(format!("The bundle '{}' has problems:\n{}", manifest.name, problems.join("\n")))
+    }
+}
+
+/// Reads and validates a bundle (zip, or folder holding bundle.json)
+/// without storing it
+pub fn check_bundle_source(source: &Path) -> Result<BundleManifest, String> {
+    let files = if source.is_dir() { read_dir(source)? } else { read_zip(source)? };
+    check_bundle(&files)
+}
+
+/// Validates a bundle (zip, or folder holding bundle.json) and stores it
+/// under `dir`, replacing an earlier bundle of the same name
+pub fn import_bundle_into(dir: &Path, source: &Path) -> Result<BundleManifest, String> {
+    let files = if source.is_dir() { read_dir(source)? } else { read_zip(source)? };
+    let manifest = check_bundle(&files)?;
+
+    let target = dir.join(sanitize_file_component(&manifest.name));
+    if target.exists() {
+        fs::remove_dir_all(&target)
+            .map_err(|e| format!("Failed to replace '{}': {e}", target.display()))?;
+    }
+    fs::create_dir_all(&target)
+        .map_err(|e| format!("Failed to create '{}': {e}", target.display()))?;
+    for (name, text) in &files {
+        let path = target.join(name);
+        fs::write(&path, text).map_err(|e| format!("Failed to write '{}': {e}", path.display()))?;
+    }
+    Ok(manifest)
+}
+
+pub fn import_bundle(source: &Path) -> Result<BundleManifest, String> {
+    let dir = bundles_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    import_bundle_into(&dir, source)
+}
+
+/// Names of the imported bundles, sorted. Folders without a readable
+/// manifest are skipped.
+pub fn list_bundles() -> Vec<String> {
+    let Some(dir) = bundles_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|e| fs::read_to_string(e.path().join(BUNDLE_MANIFEST)).ok())
+        .filter_map(|text| serde_json::from_str::<BundleManifest>(&text).ok())
+        .map(|m| m.name)
+        .collect();
+    names.sort();
+    names
+}
+
+/// Settings of an imported bundle; defaults when it carries no settings.json
+pub fn bundle_settings(name: &str) -> Result<Settings, String> {
+    let dir = bundles_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    let path = dir.join(sanitize_file_component(name)).join("settings.json");
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid settings in bundle '{}': {e}", name)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(format!("Failed to read '{}': {e}", path.display())),
+    }
+}
+
+/// Replaces `current` with a bundle's settings, keeping what belongs to
+/// this computer's user rather than to the country: theme, scale, language,
+/// lab, folders, update checks and the revision counter
+pub fn apply_bundle(current: &mut Settings, bundle: Settings, name: &str) {
+    let kept = std::mem::replace(current, bundle);
+    current.revision = kept.revision;
+    current.theme = kept.theme;
+    current.ui_scale = kept.ui_scale;
+    current.last_dirs = kept.last_dirs;
+    current.language = kept.language;
+    current.lab_name = kept.lab_name;
+    current.default_destination = kept.default_destination;
+    current.check_updates = kept.check_updates;
+    current.active_bundle = name.to_string();
+}
+
+fn to_json<T: Serialize>(value: &T, what: &str) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialise {}: {e}", what))
+}
+
+/// Writes `settings` as a bundle zip into `dest_dir` and returns its path.
+/// Per-user values (theme, scale, language, lab, folders) are left out.
+pub fn export_bundle(dest_dir: &Path, name: &str, settings: &Settings) -> Result<PathBuf, String> {
+    let mut shared = Settings::default();
+    apply_bundle(&mut shared, settings.clone(), "");
+    shared.revision = 0;
+
+    let manifest = BundleManifest {
+        bundle_version: BUNDLE_VERSION,
+        name: name.trim().to_string(),
+        country: String::new(),
+        description: String::new(),
+        created: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: vec!["settings.json".to_string()],
+    };
+
+    let entries = [
+        (BUNDLE_MANIFEST, to_json(&manifest, "bundle manifest")?),
+        ("settings.json", to_json(&shared, "settings")?),
+    ];
+
+    let path = dest_dir.join(bundle_file_name(name));
+    let file = File::create(&path)
+        .map_err(|e| format!("Failed to create '{}': {e}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (entry, text) in entries {
+        zip.start_file(entry, options)
+            .and_then(|_| zip.write_all(text.as_bytes()).map_err(Into::into))
+            .map_err(|e| format!("Failed to write '{}' to the bundle: {e}", entry))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to finish '{}': {e}", path.display()))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ThemePreference;
+    use crate::workspace::TempWorkspace;
+
+    const MANIFEST: &str = r#"{
+        "bundle_version": 1,
+        "name": "DRC 2026",
+        "country": "COD",
+        "files": ["settings.json"]
+    }"#;
+
+    const SETTINGS: &str = r#"{
+        "language": "fr",
+        "lab_name": "Country default lab",
+        "epid_sources": ["EpidNumber", "EpidNo"],
+        "sample_id_prefixes": ["COD"],
+        "sample_match_fallbacks": true,
+        "sensitive_column_patterns": ["Nom", "Telephone"],
+        "output_name_pattern": "{run}_COD.csv",
+        "min_epi_match_percent": 60
+    }"#;
+
+    fn files(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect()
+    }
+
+    fn bundle_folder(dir: &mut TempWorkspace, pairs: &[(&str, &str)]) -> PathBuf {
+        let folder = dir.file("source");
+        fs::create_dir_all(&folder).unwrap();
+        for (name, text) in pairs {
+            fs::write(folder.join(name), text).unwrap();
+        }
+        folder
+    }
+
+    #[test]
+    fn imported_bundle_configures_each_subsystem() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let source = bundle_folder(&mut dir, &[("bundle.json", MANIFEST), ("settings.json", SETTINGS)]);
+        let store = dir.file("bundles");
+
+        let manifest = import_bundle_into(&store, &source).unwrap();
+        assert_eq!((manifest.name.as_str(), manifest.country.as_str()), ("DRC 2026", "COD"));
+        let stored = store.join(sanitize_file_component("DRC 2026"));
+        assert_eq!(fs::read_to_string(stored.join("settings.json")).unwrap(), SETTINGS);
+
+        let bundle: Settings = serde_json::from_str(&fs::read_to_string(stored.join("settings.json")).unwrap()).unwrap();
+        let mut current = Settings {
+            theme: ThemePreference::Dark,
+            language: "en".to_string(),
+            lab_name: "INRB".to_string(),
+            revision: 7,
+            ..Settings::default()
+        };
+        apply_bundle(&mut current, bundle, &manifest.name);
+
+        // Country settings come from the bundle
+        assert_eq!(current.epid_sources, ["EpidNumber", "EpidNo"]);
+        assert_eq!(current.sample_id_prefixes, ["COD"]);
+        assert!(current.sample_match_fallbacks);
+        assert_eq!(current.sensitive_column_patterns, ["Nom", "Telephone"]);
+        assert_eq!(current.output_name_pattern, "{run}_COD.csv");
+        assert_eq!(current.min_epi_match_percent, 60);
+        assert_eq!(current.active_bundle, "DRC 2026");
+        // This user's own choices stay
+        assert_eq!(current.theme, ThemePreference::Dark);
+        assert_eq!((current.language.as_str(), current.lab_name.as_str()), ("en", "INRB"));
+        assert_eq!(current.revision, 7);
+    }
+
+    #[test]
+    fn exported_bundle_imports_again() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let settings = Settings {
+            epid_sources: vec!["EpidNo".to_string()],
+            lab_name: "INRB".to_string(),
+            ..Settings::default()
+        };
+        let zip = export_bundle(dir.path(), "DRC 2026", &settings).unwrap();
+        assert_eq!(zip.file_name().unwrap().to_string_lossy(), bundle_file_name("DRC 2026"));
+
+        let manifest = check_bundle_source(&zip).unwrap();
+        assert_eq!(manifest.files, ["settings.json"]);
+        let store = dir.file("bundles");
+        import_bundle_into(&store, &zip).unwrap();
+        let text = fs::read_to_string(store.join(sanitize_file_component("DRC 2026")).join("settings.json")).unwrap();
+        let exported: Settings = serde_json::from_str(&text).unwrap();
+        assert_eq!(exported.epid_sources, ["EpidNo"]);
+        // Per-user values are not shared
+        assert_eq!(exported.lab_name, "");
+
+        // Importing again replaces the earlier copy
+        import_bundle_into(&store, &zip).unwrap();
+        assert_eq!(fs::read_dir(&store).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn bad_manifests_are_rejected() {
+        let err = check_bundle(&files(&[("settings.json", SETTINGS)])).unwrap_err();
+        assert_eq!(err, "Not a configuration bundle: no bundle.json.");
+
+        let err = check_bundle(&files(&[("bundle.json", "{\"name\": \"x\""), ("settings.json", SETTINGS)])).unwrap_err();
+        assert!(err.starts_with("Invalid bundle manifest"), "{}", err);
+
+        let newer = MANIFEST.replace("\"bundle_version\": 1", "\"bundle_version\": 2");
+        let err = check_bundle(&files(&[("bundle.json", &newer), ("settings.json", SETTINGS)])).unwrap_err();
+        assert!(err.contains("newer Merger"), "{}", err);
+
+        let unnamed = MANIFEST.replace("DRC 2026", "  ");
+        let err = check_bundle(&files(&[("bundle.json", &unnamed), ("settings.json", SETTINGS)])).unwrap_err();
+        assert!(err.contains("no name"), "{}", err);
+
+        let empty = MANIFEST.replace("[\"settings.json\"]", "[]");
+        let err = check_bundle(&files(&[("bundle.json", &empty)])).unwrap_err();
+        assert!(err.contains("no configuration files"), "{}", err);
+    }
+
+    #[test]
+    fn bundle_contents_must_match_the_manifest_and_validate() {
+        let listed = MANIFEST.replace("[\"settings.json\"]", "[\"settings.json\", \"labs.json\"]");
+        let err = check_bundle(&files(&[("bundle.json", &listed), ("notes.txt", "hi")])).unwrap_err();
+        assert!(err.contains("'settings.json' is listed in the manifest but missing"), "{}", err);
+        assert!(err.contains("'labs.json' is not a configuration file"), "{}", err);
+        assert!(err.contains("'notes.txt' is not listed in the manifest"), "{}", err);
+
+        let bad = SETTINGS.replace("\"min_epi_match_percent\": 60", "\"min_epi_match_percnt\": 60");
+        let err = check_bundle(&files(&[("bundle.json", MANIFEST), ("settings.json", &bad)])).unwrap_err();
+        assert!(err.starts_with("The bundle 'DRC 2026' has problems:"), "{}", err);
+        assert!(err.contains("min_epi_match_percnt"), "{}", err);
+
+        // Nothing is stored for a rejected bundle
+        let mut dir = TempWorkspace::new().unwrap();
+        let source = bundle_folder(&mut dir, &[("bundle.json", MANIFEST), ("settings.json", &bad)]);
+        let store = dir.file("bundles");
+        assert!(import_bundle_into(&store, &source).is_err());
+        assert!(!store.exists());
+    }
+}
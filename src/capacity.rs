@@ -0,0 +1,265 @@
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::domains::line_of;
+use crate::warnings::ValidationWarning;
+
+// One flow cell run is one 96-well plate
+pub const DEFAULT_MAX_ROWS: usize = 96;
+// Off by default; small runs are legitimate
+pub const DEFAULT_MIN_ROWS: usize = 0;
+
+/// Row limits for one run, controls included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlateCapacity {
+    pub max_rows: usize,
+    // 0 = no minimum
+    pub min_rows: usize,
+}
+
+impl Default for PlateCapacity {
+    fn default() -> Self {
+        Self {
+            max_rows: DEFAULT_MAX_ROWS,
+            min_rows: DEFAULT_MIN_ROWS,
+        }
+    }
+}
+
+fn barcode_column(df: &DataFrame) -> Result<StringChunked, String> {
+    df.column("barcode")
+        .and_then(|c| c.str().cloned())
+        .map_err(|e| format!("Failed to read the barcode column: {e}"))
+}
+
+// Barcodes used by more than one of the rows at `rows`, with the
+// spreadsheet lines they appear on. Barcodes compare case-insensitively;
+// empty cells are ignored.
+fn duplicates_among(column: &StringChunked, rows: &[usize]) -> Vec<(String, Vec<usize>)> {
+    let mut lines: BTreeMap<String, (String, Vec<usize>)> = BTreeMap::new();
+    for &idx in rows {
+        let Some(barcode) = column.get(idx).map(str::trim).filter(|b| !b.is_empty()) else {
+            continue;
+        };
+        lines
+            .entry(barcode.to_ascii_lowercase())
+            .or_insert_with(|| (barcode.to_string(), Vec::new()))
+            .1
+            .push(line_of(idx));
+    }
+
+    lines
+        .into_values()
+        .filter(|(_, lines)| lines.len() > 1)
+        .collect()
+}
+
+// Over-capacity and duplicate-barcode problems of one plate's rows
+fn plate_problems(column: &StringChunked, rows: &[usize], capacity: &PlateCapacity) -> Vec<String> {
+    let duplicates = duplicates_among(column, rows);
+
+    let mut problems = Vec::new();
+    if rows.len() > capacity.max_rows {
+        problems.push(format!(
+            "The run has {} rows but a plate holds at most {} (controls included).",
+            rows.len(),
+            capacity.max_rows
+        ));
+    }
+    if !duplicates.is_empty() {
+        let listed: Vec<String> = duplicates
+            .iter()
+            .map(|(barcode, lines)| {
+                let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+                format!("{} (lines {})", barcode, lines.join(", "))
+            })
+            .collect();
+        problems.push(format!(
+            "Each barcode may be used by only one row. Used more than once: {}",
+            listed.join("; ")
+        ));
+    }
+    problems
+}
+
+fn refuse(problems: &[String]) -> String {
+    format!(
+        "{}\n\nPlease remove the duplicated samples or controls before merging.",
+        problems.join("\n\n")
+    )
+}
+
+/// Enforces one row per barcode and at most `max_rows` rows. Exceeding
+/// either is an error; fewer than `min_rows` rows is a warning, as the
+/// sheet may have been cut short.
+pub fn check_plate_capacity(
+    df: &DataFrame,
+    capacity: &PlateCapacity,
+) -> Result<Option<ValidationWarning>, String> {
+    let rows = df.height();
+    let column = barcode_column(df)?;
+    let problems = plate_problems(&column, &(0..rows).collect::<Vec<_>>(), capacity);
+    if !problems.is_empty() {
+        return Err(refuse(&problems));
+    }
+
+    if rows < capacity.min_rows {
+        return Ok(Some(ValidationWarning::FewRows {
+            rows,
+            minimum: capacity.min_rows,
+        }));
+    }
+    Ok(None)
+}
+
+/// Re-applies the plate limits to an output that earlier runs were
+/// appended to. Each flow cell is its own plate, so rows are grouped by
+/// FlowCellID (rows without one form a single group) and barcodes may
+/// repeat across flow cells. Lines refer to the appended output.
+pub fn check_appended_capacity(df: &DataFrame, capacity: &PlateCapacity) -> Result<(), String> {
+    let column = barcode_column(df)?;
+    let flow_cells = match df.column("FlowCellID") {
+        Ok(c) => Some(
+            c.cast(&DataType::String)
+                .and_then(|c| c.str().cloned())
+                .map_err(|e| format!("Failed to read the FlowCellID column: {e}"))?,
+        ),
+        Err(_) => None,
+    };
+
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for idx in 0..df.height() {
+        let flow_cell = flow_cells
+            .as_ref()
+            .and_then(|c| c.get(idx))
+            .map(str::trim)
+            .unwrap_or_default();
+        match groups.iter_mut().find(|(id, _)| id.eq_ignore_ascii_case(flow_cell)) {
+            Some((_, rows)) => rows.push(idx),
+            None => groups.push((flow_cell.to_string(), vec![idx])),
+        }
+    }
+
+    let mut problems = Vec::new();
+    for (flow_cell, rows) in &groups {
+        for problem in plate_problems(&column, rows, capacity) {
+            problems.push(match flow_cell.as_str() {
+                "" if groups.len() == 1 => problem,
+                "" => format!("Rows without a flow cell: {}", problem),
+                id => format!("Flow cell {}: {}", id, problem),
+            });
+        }
+    }
+    if !problems.is_empty() {
+        return Err(refuse(&problems));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `rows` samples on distinct barcodes, the last two being controls
+    fn plate(rows: usize) -> DataFrame {
+        let samples: Vec<String> = (1..=rows)
+            .map(|i| match rows - i {
+                0 => "NTC".to_string(),
+                1 => "PC".to_string(),
+                _ => format!("S{:03}", i),
+            })
+            .collect();
+        let barcodes: Vec<String> = (1..=rows).map(|i| format!("barcode{:02}", i)).collect();
+        df!("sample" => samples, "barcode" => barcodes).unwrap()
+    }
+
+    fn with_barcodes(barcodes: &[&str]) -> DataFrame {
+        let samples: Vec<String> = (1..=barcodes.len()).map(|i| format!("S{:02}", i)).collect();
+        df!("sample" => samples, "barcode" => barcodes).unwrap()
+    }
+
+    #[test]
+    fn a_full_plate_is_accepted() {
+        let capacity = PlateCapacity::default();
+        assert_eq!(check_plate_capacity(&plate(95), &capacity), Ok(None));
+        assert_eq!(check_plate_capacity(&plate(96), &capacity), Ok(None));
+    }
+
+    #[test]
+    fn one_row_over_the_plate_is_refused() {
+        let err = check_plate_capacity(&plate(97), &PlateCapacity::default()).unwrap_err();
+        assert!(err.starts_with("The run has 97 rows but a plate holds at most 96 (controls included)."), "{}", err);
+        assert!(!err.contains("Used more than once"), "{}", err);
+
+        let smaller = PlateCapacity { max_rows: 24, min_rows: 0 };
+        assert!(check_plate_capacity(&plate(25), &smaller).is_err());
+    }
+
+    #[test]
+    fn repeated_barcodes_are_listed_with_their_lines() {
+        let df = with_barcodes(&["barcode01", "barcode02", "Barcode01", "", "barcode03", "barcode02", " "]);
+        assert_eq!(
+            duplicates_among(&barcode_column(&df).unwrap(), &(0..df.height()).collect::<Vec<_>>()),
+            vec![("barcode01".to_string(), vec![2, 4]), ("barcode02".to_string(), vec![3, 7])]
+        );
+
+        let err = check_plate_capacity(&df, &PlateCapacity::default()).unwrap_err();
+        assert!(
+            err.contains("Used more than once: barcode01 (lines 2, 4); barcode02 (lines 3, 7)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn a_control_added_twice_is_a_duplicate() {
+        let mut df = plate(95);
+        let extra = df!("sample" => ["NTC"], "barcode" => ["barcode95"]).unwrap();
+        df.vstack_mut(&extra).unwrap();
+        let err = check_plate_capacity(&df, &PlateCapacity::default()).unwrap_err();
+        assert!(err.contains("barcode95 (lines 96, 97)"), "{}", err);
+        assert!(!err.contains("rows but a plate"), "{}", err);
+    }
+
+    #[test]
+    fn short_sheet_only_warns() {
+        let capacity = PlateCapacity { max_rows: 96, min_rows: 24 };
+        assert_eq!(
+            check_plate_capacity(&plate(12), &capacity),
+            Ok(Some(ValidationWarning::FewRows { rows: 12, minimum: 24 }))
+        );
+        assert_eq!(check_plate_capacity(&plate(24), &capacity), Ok(None));
+    }
+
+    fn on_flow_cells(flow_cells: &[&str], barcodes: &[&str]) -> DataFrame {
+        let mut df = with_barcodes(barcodes);
+        df.with_column(Series::new("FlowCellID".into(), flow_cells)).unwrap();
+        df
+    }
+
+    #[test]
+    fn appended_runs_may_reuse_barcodes_on_other_flow_cells() {
+        let df = on_flow_cells(&["FAX001", "FAX001", "FAX002", "FAX002"], &["barcode01", "barcode02", "barcode01", "barcode02"]);
+        assert_eq!(check_appended_capacity(&df, &PlateCapacity::default()), Ok(()));
+    }
+
+    #[test]
+    fn appended_duplicates_are_found_per_flow_cell() {
+        let df = on_flow_cells(&["FAX001", "FAX002", "fax001"], &["barcode01", "barcode01", "Barcode01"]);
+        let err = check_appended_capacity(&df, &PlateCapacity::default()).unwrap_err();
+        assert!(err.contains("Flow cell FAX001: Each barcode may be used by only one row. Used more than once: barcode01 (lines 2, 4)"), "{}", err);
+        assert!(!err.contains("FAX002"), "{}", err);
+    }
+
+    #[test]
+    fn appended_rows_are_capped_per_flow_cell() {
+        let capacity = PlateCapacity { max_rows: 2, min_rows: 0 };
+        let df = on_flow_cells(&["FAX001", "FAX001", "FAX002", "FAX002", "FAX002"], &["barcode01", "barcode02", "barcode01", "barcode02", "barcode03"]);
+        let err = check_appended_capacity(&df, &capacity).unwrap_err();
+        assert!(err.starts_with("Flow cell FAX002: The run has 3 rows but a plate holds at most 2"), "{}", err);
+
+        // Without FlowCellID the whole output is one plate
+        let err = check_appended_capacity(&plate(97), &PlateCapacity::default()).unwrap_err();
+        assert!(err.starts_with("The run has 97 rows"), "{}", err);
+    }
+}
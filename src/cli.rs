@@ -0,0 +1,140 @@
+use crate::about::build_info;
+use crate::csv::read_csv_normalized;
+use crate::minknow::{choose_run, parse_minknow_report, run_key};
+use crate::run_constants::RunConstants;
+use crate::verify::{verify_output, VerifyOutcome};
+use crate::warnings::{render_warnings, Lang};
+
+const USAGE: &str = "Usage: merger verify <output.csv> [--mode DDNS|minION]";
+const REPORT_USAGE: &str = "Usage: merger report <report.html|report.json> [--position POSITION]";
+const CONSTANTS_USAGE: &str = "Usage: merger check-constants <constants.json> [--mode DDNS|minION]";
+
+/// Runs a command-line subcommand when one is given.
+/// Returns the process exit code, or None to start the GUI.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    match args.first().map(|s| s.as_str()) {
+        Some("verify") => Some(verify_command(&args[1..])),
+        Some("check-constants") => Some(check_constants_command(&args[1..])),
+        Some("report") => Some(report_command(&args[1..])),
+        Some("--version" | "-V" | "--about") => {
+            println!("{}", build_info().to_text());
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+// Exit codes: 0 clean, 1 warnings, 2 structural failure or unreadable file
+fn verify_command(args: &[String]) -> i32 {
+    let mut path: Option<&str> = None;
+    let mut mode = "DDNS".to_string();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--mode" => match iter.next().map(|s| s.as_str()) {
+                Some(m @ ("DDNS" | "minION")) => mode = m.to_string(),
+                _ => {
+                    eprintln!("{USAGE}");
+                    return 2;
+                }
+            },
+            other if path.is_none() => path = Some(other),
+            _ => {
+                eprintln!("{USAGE}");
+                return 2;
+            }
+        }
+    }
+
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return 2;
+    };
+
+    let df = match read_csv_normalized(path) {
+        Ok((df, _)) => df,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+
+    let outcome = verify_output(&df, &mode);
+    match &outcome {
+        VerifyOutcome::Clean => println!("{}: OK ({} rows, {} mode)", path, df.height(), mode),
+        VerifyOutcome::Warnings(warnings) => println!("{}", render_warnings(warnings, Lang::En)),
+        VerifyOutcome::Structural(msg) => eprintln!("{msg}"),
+    }
+    outcome.exit_code()
+}
+
+// Exit codes: 0 valid, 1 format errors, 2 unreadable file or bad arguments
+fn check_constants_command(args: &[String]) -> i32 {
+    let (path, mode) = match args {
+        [path] => (path.as_str(), "DDNS"),
+        [path, flag, m] if flag == "--mode" && (m == "DDNS" || m == "minION") => (path.as_str(), m.as_str()),
+        _ => {
+            eprintln!("{CONSTANTS_USAGE}");
+            return 2;
+        }
+    };
+
+    let constants = match RunConstants::from_file(path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+
+    match constants.validate(mode) {
+        Ok(()) => {
+            println!("{}: OK ({} mode)", path, mode);
+            0
+        }
+        Err(e) => {
+            println!("{e}");
+            1
+        }
+    }
+}
+
+// Exit codes: 0 values printed, 1 several runs and no (or an unknown)
+// --position, 2 unreadable report or bad arguments
+fn report_command(args: &[String]) -> i32 {
+    let (path, position) = match args {
+        [path] => (path.as_str(), ""),
+        [path, flag, p] if flag == "--position" => (path.as_str(), p.as_str()),
+        _ => {
+            eprintln!("{REPORT_USAGE}");
+            return 2;
+        }
+    };
+
+    let runs = match parse_minknow_report(path) {
+        Ok(runs) => runs,
+        Err(e) => {
+            eprintln!("{e}");
+            return 2;
+        }
+    };
+
+    match choose_run(&runs, position) {
+        Some(data) => {
+            println!("{}", data.to_text());
+            0
+        }
+        None => {
+            if position.is_empty() {
+                println!("{} holds {} runs; pick one with --position:", path, runs.len());
+            } else {
+                println!("{} has no position '{}'; pick one with --position:", path, position);
+            }
+            for (idx, data) in runs.iter().enumerate() {
+                println!("  {}\tflow cell {}\tended {}", run_key(idx, data), data.fc_id, data.run_end_time);
+            }
+            1
+        }
+    }
+}
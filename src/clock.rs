@@ -0,0 +1,94 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Which calendar a date is counted in. MinKNOW writes run_end_time in
+/// UTC, so a run ending at 00:30 local time on the 31st is the 30th in
+/// UTC; dates are normally converted to the machine's local day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DateZone {
+    // The computer's own time zone
+    #[default]
+    Local,
+    // For labs whose sequencing computers are kept on UTC
+    Utc,
+}
+
+impl DateZone {
+    /// Today as YYYY-MM-DD
+    pub fn today(self) -> String {
+        match self {
+            DateZone::Local => Local::now().date_naive(),
+            DateZone::Utc => Utc::now().date_naive(),
+        }
+        .format("%Y-%m-%d")
+        .to_string()
+    }
+}
+
+/// Calendar day of `timestamp` in the time zone `tz`
+pub fn date_in<Tz: TimeZone>(timestamp: &DateTime<FixedOffset>, tz: &Tz) -> NaiveDate {
+    timestamp.with_timezone(tz).date_naive()
+}
+
+/// YYYY-MM-DD of a report timestamp in `zone`. Timestamps without an
+/// offset are taken as written; anything unparseable gives None.
+pub fn report_date(timestamp: &str, zone: DateZone) -> Option<String> {
+    match zone {
+        DateZone::Local => report_date_in(timestamp, &Local),
+        DateZone::Utc => report_date_in(timestamp, &Utc),
+    }
+}
+
+/// `report_date` in the time zone `tz`
+pub fn report_date_in<Tz: TimeZone>(timestamp: &str, tz: &Tz) -> Option<String> {
+    let timestamp = timestamp.trim();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(timestamp) {
+        return Some(date_in(&parsed, tz).format("%Y-%m-%d").to_string());
+    }
+    let date = timestamp.split(['T', ' ']).next()?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(timestamp: &str, tz: &FixedOffset) -> String {
+        report_date_in(timestamp, tz).unwrap()
+    }
+
+    #[test]
+    fn midnight_boundary_east_of_utc() {
+        let paris_summer = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(day("2024-05-30T21:59:59Z", &paris_summer), "2024-05-30");
+        assert_eq!(day("2024-05-30T22:00:00Z", &paris_summer), "2024-05-31");
+        assert_eq!(day("2024-05-30T22:30:00Z", &paris_summer), "2024-05-31");
+        assert_eq!(day("2024-05-31T00:30:00+02:00", &paris_summer), "2024-05-31");
+    }
+
+    #[test]
+    fn midnight_boundary_west_of_utc() {
+        let eastern = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(day("2024-05-31T04:59:59Z", &eastern), "2024-05-30");
+        assert_eq!(day("2024-05-31T05:00:00Z", &eastern), "2024-05-31");
+        // 23:30 UTC the day before
+        assert_eq!(day("2024-05-31T01:30:00+02:00", &eastern), "2024-05-30");
+    }
+
+    #[test]
+    fn utc_zone_uses_the_utc_day() {
+        assert_eq!(report_date("2024-05-31T01:30:00+02:00", DateZone::Utc).as_deref(), Some("2024-05-30"));
+        assert_eq!(report_date("2024-05-30T23:30:00Z", DateZone::Utc).as_deref(), Some("2024-05-30"));
+    }
+
+    #[test]
+    fn timestamps_without_offset_are_taken_as_written() {
+        let eastern = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(day("2024-05-31T01:30:00", &eastern), "2024-05-31");
+        assert_eq!(day("2024-05-31 01:30:00", &eastern), "2024-05-31");
+        assert_eq!(report_date_in("yesterday", &eastern), None);
+        assert_eq!(report_date_in("", &Utc), None);
+    }
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::Path;
+
+// Windows file attributes of cloud files whose content is not on disk
+const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+
+const PLACEHOLDER_ATTRIBUTES: u32 =
+    FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS;
+
+// Folder names sync clients create under the user's profile
+const SYNC_FOLDERS: &[&str] = &["onedrive", "dropbox", "google drive", "my drive", "icloud drive", "icloud~"];
+
+// Markers sync clients put in the names of conflicting copies (Dropbox in
+// English and French, OneDrive's "-conflict" suffix on some setups)
+const CONFLICT_MARKERS: &[&str] = &["conflicted copy", "copie en conflit", "-conflict"];
+
+#[cfg(windows)]
+fn file_attributes(path: &Path) -> Option<u32> {
+    use std::os::windows::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| m.file_attributes())
+}
+
+// No placeholder attributes outside Windows
+#[cfg(not(windows))]
+fn file_attributes(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// `is_placeholder` with the attribute lookup supplied by the caller
+pub fn is_placeholder_with(path: &Path, attributes: impl Fn(&Path) -> Option<u32>) -> bool {
+    attributes(path).is_some_and(|a| a & PLACEHOLDER_ATTRIBUTES != 0)
+}
+
+/// A cloud file whose content is only downloaded when it is read
+/// (OneDrive "Files On-Demand" and the like)
+pub fn is_placeholder(path: &Path) -> bool {
+    is_placeholder_with(path, file_attributes)
+}
+
+/// Named like a copy a sync client made of a file changed in two places,
+/// e.g. "samples (conflicted copy 2025-01-30).csv"
+pub fn is_conflicted_copy(path: &Path) -> bool {
+    let name = path
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    CONFLICT_MARKERS.iter().any(|m| name.contains(m))
+}
+
+/// Inside a folder a sync client manages
+pub fn is_cloud_synced(path: &Path) -> bool {
+    path.components().any(|c| {
+        let part = c.as_os_str().to_string_lossy().to_lowercase();
+        SYNC_FOLDERS.iter().any(|f| part == *f || part.starts_with(&format!("{} -", f)))
+    })
+}
+
+/// `check_materialized` with the attribute lookup supplied by the caller
+pub fn check_materialized_with(path: &Path, attributes: impl Fn(&Path) -> Option<u32>) -> Result<(), String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("The output could not be found after saving: {e}"))?
+        .len();
+    if size == 0 {
+        return Err("The output is empty (0 bytes) after saving.".to_string());
+    }
+    if is_placeholder_with(path, attributes) {
+        return Err("The output's content is not on this computer after saving (it is only an online placeholder).".to_string());
+    }
+    Ok(())
+}
+
+/// Checks that a file just written has its content on disk: not empty and
+/// not turned into an online-only placeholder
+pub fn check_materialized(path: &Path) -> Result<(), String> {
+    check_materialized_with(path, file_attributes)
+}
+
+/// Adds a hint about the sync client to a read-back error for outputs in
+/// a synced folder
+pub fn with_sync_hint(path: &Path, error: String) -> String {
+    if is_cloud_synced(path) {
+        format!(
+            "{error} The destination is in a cloud-synced folder; check that the sync client (OneDrive, Dropbox, ...) is not paused or in conflict, or save to a local folder."
+        )
+    } else {
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    // Attribute providers standing in for the file system
+    fn attributes(value: u32) -> impl Fn(&Path) -> Option<u32> {
+        move |_| Some(value)
+    }
+
+    fn unknown(_: &Path) -> Option<u32> {
+        None
+    }
+
+    const ARCHIVE: u32 = 0x20;
+    const PINNED: u32 = 0x80000;
+
+    #[test]
+    fn placeholder_attributes_are_recognised() {
+        let path = Path::new("OneDrive/runs/samples.csv");
+        for placeholder in [
+            FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS,
+            FILE_ATTRIBUTE_RECALL_ON_OPEN,
+            FILE_ATTRIBUTE_OFFLINE,
+            FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | ARCHIVE,
+        ] {
+            assert!(is_placeholder_with(path, attributes(placeholder)), "{placeholder:#x}");
+        }
+        // A downloaded (or pinned) cloud file and a missing lookup are fine
+        assert!(!is_placeholder_with(path, attributes(ARCHIVE)));
+        assert!(!is_placeholder_with(path, attributes(ARCHIVE | PINNED)));
+        assert!(!is_placeholder_with(path, unknown));
+    }
+
+    #[test]
+    fn conflicted_copies_and_synced_folders() {
+        for name in [
+            "samples (conflicted copy 2025-01-30).csv",
+            "samples (Copie en conflit de LAB-PC 2025-01-30).csv",
+            "samples-LAB-PC-conflict.csv",
+        ] {
+            assert!(is_conflicted_copy(Path::new(name)), "{name}");
+        }
+        assert!(!is_conflicted_copy(Path::new("samples.csv")));
+        // Only the file name counts, not the folder it is in
+        assert!(!is_conflicted_copy(Path::new("conflicted copy/samples.csv")));
+
+        for path in ["C:/Users/ana/OneDrive/runs/out.csv", "C:/Users/ana/OneDrive - INRB/runs", "/home/ana/Dropbox/out.csv"] {
+            assert!(is_cloud_synced(Path::new(path)), "{path}");
+        }
+        assert!(!is_cloud_synced(Path::new("/home/ana/OneDriveBackup/out.csv")));
+        assert!(!is_cloud_synced(Path::new("D:/runs/out.csv")));
+    }
+
+    #[test]
+    fn written_outputs_must_have_their_content() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let output = dir.file("out.csv");
+        fs::write(&output, "sample,barcode\nS01,barcode01\n").unwrap();
+        assert!(check_materialized_with(&output, attributes(ARCHIVE)).is_ok());
+
+        let err = check_materialized_with(&output, attributes(FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)).unwrap_err();
+        assert!(err.contains("placeholder"), "{err}");
+
+        let empty = dir.file("empty.csv");
+        fs::write(&empty, "").unwrap();
+        assert!(check_materialized_with(&empty, unknown).unwrap_err().contains("0 bytes"));
+        assert!(check_materialized_with(&dir.file("gone.csv"), unknown).unwrap_err().contains("could not be found"));
+    }
+
+    #[test]
+    fn sync_hint_only_for_synced_destinations() {
+        let synced = with_sync_hint(Path::new("/home/ana/Dropbox/out.csv"), "Read-back failed.".to_string());
+        assert!(synced.starts_with("Read-back failed. ") && synced.contains("cloud-synced"), "{synced}");
+        assert_eq!(with_sync_hint(Path::new("/data/out.csv"), "Read-back failed.".to_string()), "Read-back failed.");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn local_files_are_not_placeholders() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let output = dir.file("out.csv");
+        fs::write(&output, "sample\nS01\n").unwrap();
+        assert!(file_attributes(&output).is_some());
+        assert!(!is_placeholder(&output));
+        assert!(check_materialized(&output).is_ok());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn no_placeholders_outside_windows() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let output = dir.file("out.csv");
+        fs::write(&output, "sample\nS01\n").unwrap();
+        assert_eq!(file_attributes(&output), None);
+        assert!(!is_placeholder(&output));
+        assert!(check_materialized(&output).is_ok());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::palette::fuzzy_score;
+use crate::warnings::Lang;
+
+/// One way a samples file's header differs from the mode's template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deviation {
+    Missing { expected: String },
+    Extra { actual: String },
+    // Present, but not where the template puts it (1-based positions)
+    Reordered { column: String, expected_position: usize, actual_position: usize },
+    CaseMismatch { expected: String, actual: String },
+    // Probably a misspelling or an outdated name of `expected`
+    NearMatch { expected: String, actual: String },
+}
+
+impl Deviation {
+    pub fn category(&self) -> &'static str {
+        match self {
+            Deviation::Missing { .. } => "missing",
+            Deviation::Extra { .. } => "extra",
+            Deviation::Reordered { .. } => "reordered",
+            Deviation::CaseMismatch { .. } => "case-mismatch",
+            Deviation::NearMatch { .. } => "near-match",
+        }
+    }
+
+    /// (expected column, column in the file)
+    fn columns(&self) -> (&str, &str) {
+        match self {
+            Deviation::Missing { expected } => (expected, ""),
+            Deviation::Extra { actual } => ("", actual),
+            Deviation::Reordered { column, .. } => (column, column),
+            Deviation::CaseMismatch { expected, actual } | Deviation::NearMatch { expected, actual } => {
+                (expected, actual)
+            }
+        }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        match (self, lang) {
+            (Deviation::Missing { expected }, Lang::En) => format!("Missing: {}", expected),
+            (Deviation::Missing { expected }, Lang::Fr) => format!("Manquante : {}", expected),
+            (Deviation::Extra { actual }, Lang::En) => format!("Not in the template: {}", actual),
+            (Deviation::Extra { actual }, Lang::Fr) => format!("Absente du modèle : {}", actual),
+            (Deviation::Reordered { column, expected_position, actual_position }, Lang::En) => format!(
+                "Out of order: {} is column {} (template: column {})",
+                column, actual_position, expected_position
+            ),
+            (Deviation::Reordered { column, expected_position, actual_position }, Lang::Fr) => format!(
+                "Mal placée : {} est en colonne {} (modèle : colonne {})",
+                column, actual_position, expected_position
+            ),
+            (Deviation::CaseMismatch { expected, actual }, Lang::En) => {
+                format!("Wrong capitalisation: {} should be {}", actual, expected)
+            }
+            (Deviation::CaseMismatch { expected, actual }, Lang::Fr) => {
+                format!("Majuscules incorrectes : {} devrait être {}", actual, expected)
+            }
+            (Deviation::NearMatch { expected, actual }, Lang::En) => {
+                format!("Probably misspelt: {} (did you mean {}?)", actual, expected)
+            }
+            (Deviation::NearMatch { expected, actual }, Lang::Fr) => {
+                format!("Probablement mal orthographiée : {} (vouliez-vous dire {} ?)", actual, expected)
+            }
+        }
+    }
+}
+
+// Lowercase letters and digits only: "Date of onset" ~ "DateOfOnset"
+fn squash(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// How alike two names are, None if not close enough to suggest. Uses the
+// palette's subsequence matcher both ways, so dropped and added letters
+// both count, and rejects pairs of very different length.
+fn near_match_score(expected: &str, actual: &str) -> Option<i32> {
+    let (e, a) = (squash(expected), squash(actual));
+    if e.is_empty() || a.is_empty() {
+        return None;
+    }
+    if e == a {
+        return Some(i32::MAX);
+    }
+    let (short, long) = if e.len() <= a.len() { (e.len(), a.len()) } else { (a.len(), e.len()) };
+    if short * 10 < long * 8 {
+        return None;
+    }
+    fuzzy_score(&a, &e).max(fuzzy_score(&e, &a))
+}
+
+/// How sure a near match is, 0-100: the matcher's score against the
+/// score of two identical names of the longer length
+pub fn match_confidence(expected: &str, actual: &str) -> u8 {
+    let longest = squash(expected).chars().count().max(squash(actual).chars().count()) as i64;
+    match near_match_score(expected, actual) {
+        Some(i32::MAX) => 100,
+        Some(score) if longest > 0 => (score as i64 * 100 / (4 * longest + 2)).clamp(0, 100) as u8,
+        _ => 0,
+    }
+}
+
+// Positions (into `seq`) of one longest increasing subsequence
+fn longest_increasing(seq: &[usize]) -> Vec<usize> {
+    let n = seq.len();
+    let mut length = vec![1usize; n];
+    let mut previous: Vec<Option<usize>> = vec![None; n];
+    for i in 0..n {
+        for j in 0..i {
+            if seq[j] < seq[i] && length[j] + 1 > length[i] {
+                length[i] = length[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut best = (0..n).max_by_key(|&i| (length[i], std::cmp::Reverse(i)));
+    let mut out = Vec::new();
+    while let Some(i) = best {
+        out.push(i);
+        best = previous[i];
+    }
+    out.reverse();
+    out
+}
+
+/// Compares a header against the template's columns. Exact matches are
+/// paired first, then case-only differences, then near matches; whatever
+/// is left is missing or extra. Paired columns that break the template's
+/// order are reported as reordered (the fewest moves that restore it).
+pub fn compare_headers(expected: &[&str], actual: &[String]) -> Vec<Deviation> {
+    // expected index paired with each actual column
+    let mut paired: Vec<Option<usize>> = vec![None; actual.len()];
+    let mut taken = vec![false; expected.len()];
+    let mut deviations = Vec::new();
+
+    for (a, name) in actual.iter().enumerate() {
+        if let Some(e) = expected.iter().position(|x| *x == name.trim()) {
+            if !taken[e] {
+                taken[e] = true;
+                paired[a] = Some(e);
+            }
+        }
+    }
+    for (a, name) in actual.iter().enumerate() {
+        if paired[a].is_some() {
+            continue;
+        }
+        if let Some(e) = (0..expected.len()).find(|&e| !taken[e] && expected[e].eq_ignore_ascii_case(name.trim())) {
+            taken[e] = true;
+            paired[a] = Some(e);
+            deviations.push(Deviation::CaseMismatch {
+                expected: expected[e].to_string(),
+                actual: name.clone(),
+            });
+        }
+    }
+    for (a, name) in actual.iter().enumerate() {
+        if paired[a].is_some() {
+            continue;
+        }
+        let best = (0..expected.len())
+            .filter(|&e| !taken[e])
+            .filter_map(|e| near_match_score(expected[e], name).map(|score| (score, e)))
+            .max_by_key(|&(score, e)| (score, std::cmp::Reverse(e)));
+        if let Some((_, e)) = best {
+            taken[e] = true;
+            paired[a] = Some(e);
+            deviations.push(Deviation::NearMatch {
+                expected: expected[e].to_string(),
+                actual: name.clone(),
+            });
+        }
+    }
+
+    for (e, name) in expected.iter().enumerate() {
+        if !taken[e] {
+            deviations.push(Deviation::Missing { expected: name.to_string() });
+        }
+    }
+    for (a, name) in actual.iter().enumerate() {
+        if paired[a].is_none() {
+            deviations.push(Deviation::Extra { actual: name.clone() });
+        }
+    }
+
+    // Columns outside the longest run already in template order are the ones to move
+    let order: Vec<(usize, usize)> = paired
+        .iter()
+        .enumerate()
+        .filter_map(|(a, e)| e.map(|e| (a, e)))
+        .collect();
+    let in_order = longest_increasing(&order.iter().map(|(_, e)| *e).collect::<Vec<_>>());
+    for (i, (a, e)) in order.iter().enumerate() {
+        if !in_order.contains(&i) {
+            deviations.push(Deviation::Reordered {
+                column: expected[*e].to_string(),
+                expected_position: e + 1,
+                actual_position: a + 1,
+            });
+        }
+    }
+
+    deviations
+}
+
+/// Dialog text: one line per deviation, grouped by category
+pub fn report_text(deviations: &[Deviation], lang: Lang) -> String {
+    if deviations.is_empty() {
+        return match lang {
+            Lang::En => "The header matches the template exactly.".to_string(),
+            Lang::Fr => "L'en-tête correspond exactement au modèle.".to_string(),
+        };
+    }
+    let categories = ["missing", "near-match", "case-mismatch", "extra", "reordered"];
+    categories
+        .iter()
+        .flat_map(|category| deviations.iter().filter(move |d| d.category() == *category))
+        .map(|d| format!("• {}", d.render(lang)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// CSV report: category, expected column, column in the file, detail
+pub fn report_csv(deviations: &[Deviation]) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+    let mut lines = vec!["category,expected,actual,detail".to_string()];
+    for deviation in deviations {
+        let (expected, actual) = deviation.columns();
+        lines.push(
+            [deviation.category(), expected, actual, &deviation.render(Lang::En)]
+                .iter()
+                .map(|s| quote(s))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &[&str] = &["sample", "barcode", "IsQCRetest", "EPID", "DateOfOnset", "Province"];
+
+    fn header(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn template_header_has_no_deviations() {
+        assert!(compare_headers(TEMPLATE, &header(TEMPLATE)).is_empty());
+        assert_eq!(report_text(&[], Lang::En), "The header matches the template exactly.");
+    }
+
+    #[test]
+    fn missing_and_extra_columns() {
+        let actual = header(&["sample", "barcode", "IsQCRetest", "EPID", "DateOfOnset", "Notes"]);
+        assert_eq!(
+            compare_headers(TEMPLATE, &actual),
+            vec![Deviation::Missing { expected: "Province".to_string() }, Deviation::Extra { actual: "Notes".to_string() }]
+        );
+    }
+
+    #[test]
+    fn case_changes_and_misspellings() {
+        let actual = header(&["Sample", "barcode", "IsQCRetest", "epid", "DateOfOnst", "Province"]);
+        assert_eq!(
+            compare_headers(TEMPLATE, &actual),
+            vec![
+                Deviation::CaseMismatch { expected: "sample".to_string(), actual: "Sample".to_string() },
+                Deviation::CaseMismatch { expected: "EPID".to_string(), actual: "epid".to_string() },
+                Deviation::NearMatch { expected: "DateOfOnset".to_string(), actual: "DateOfOnst".to_string() },
+            ]
+        );
+        // Spacing and punctuation alone are a certain match
+        assert_eq!(match_confidence("DateOfOnset", "Date of onset"), 100);
+        assert!(match_confidence("DateOfOnset", "DateOfOnst") > 50);
+        assert_eq!(match_confidence("DateOfOnset", "Province"), 0);
+    }
+
+    #[test]
+    fn swapped_columns_are_reported_once() {
+        let actual = header(&["sample", "barcode", "IsQCRetest", "DateOfOnset", "EPID", "Province"]);
+        assert_eq!(
+            compare_headers(TEMPLATE, &actual),
+            vec![Deviation::Reordered { column: "EPID".to_string(), expected_position: 4, actual_position: 5 }]
+        );
+
+        // One column moved to the front is one move, not five
+        let actual = header(&["Province", "sample", "barcode", "IsQCRetest", "EPID", "DateOfOnset"]);
+        let deviations = compare_headers(TEMPLATE, &actual);
+        assert_eq!(
+            deviations,
+            vec![Deviation::Reordered { column: "Province".to_string(), expected_position: 6, actual_position: 1 }]
+        );
+    }
+
+    #[test]
+    fn reports_group_by_category() {
+        let actual = header(&["barcode", "sample", "isqcretest", "EPID", "DateOfOnst", "Notes"]);
+        let deviations = compare_headers(TEMPLATE, &actual);
+        let categories: Vec<&str> = deviations.iter().map(|d| d.category()).collect();
+        assert_eq!(categories, ["case-mismatch", "near-match", "missing", "extra", "reordered"]);
+
+        let text = report_text(&deviations, Lang::En);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "• Missing: Province");
+        assert_eq!(lines[1], "• Probably misspelt: DateOfOnst (did you mean DateOfOnset?)");
+        assert_eq!(lines[4], "• Out of order: sample is column 2 (template: column 1)");
+        assert!(report_text(&deviations, Lang::Fr).starts_with("• Manquante : Province"));
+
+        let csv = report_csv(&deviations);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "category,expected,actual,detail");
+        assert_eq!(rows.len(), deviations.len() + 1);
+        assert!(rows.contains(&"\"extra\",\"\",\"Notes\",\"Not in the template: Notes\""));
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use crate::appearance::{MAX_SCALE, MIN_SCALE};
+use crate::hooks::validate_hook;
+use crate::naming::validate_pattern;
+use crate::settings::{settings_dir, Settings};
+use crate::template::{expected_ddns_columns, expected_minion_columns};
+
+/// One problem found in a user-editable configuration file
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub file: String,
+    // "line 3, column 5" for syntax errors, the field name otherwise
+    pub location: String,
+    pub expected: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.file, self.location, self.expected)
+    }
+}
+
+/// Outcome of validating one configuration file
+pub struct ConfigReport {
+    pub file: String,
+    pub present: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
+fn issue(file: &str, location: impl Into<String>, expected: impl Into<String>) -> ConfigIssue {
+    ConfigIssue {
+        file: file.to_string(),
+        location: location.into(),
+        expected: expected.into(),
+    }
+}
+
+fn known_columns() -> HashSet<&'static str> {
+    expected_ddns_columns()
+        .into_iter()
+        .chain(expected_minion_columns())
+        .collect()
+}
+
+/// Checks the text of settings.json: syntax, field types, then values
+pub fn check_settings(file: &str, text: &str) -> Vec<ConfigIssue> {
+    let settings: Settings = match serde_json::from_str(text) {
+        Ok(s) => s,
+        Err(e) => {
+            return vec![issue(
+                file,
+                format!("line {}, column {}", e.line(), e.column()),
+                format!("valid settings JSON ({e})"),
+            )]
+        }
+    };
+
+    let mut issues = Vec::new();
+
+    // serde(default) silently ignores misspelled keys, so list them here
+    let known_keys = serde_json::to_value(Settings::default()).ok();
+    if let (Ok(serde_json::Value::Object(found)), Some(serde_json::Value::Object(known))) =
+        (serde_json::from_str::<serde_json::Value>(text), known_keys)
+    {
+        for key in found.keys().filter(|k| !known.contains_key(*k)) {
+            issues.push(issue(
+                file,
+                key.clone(),
+                format!(
+                    "one of the known settings ({})",
+                    known.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            ));
+        }
+    }
+
+    if let Some(scale) = settings.ui_scale {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+            issues.push(issue(
+                file,
+                "ui_scale",
+                format!("a scale between {} and {} (found {})", MIN_SCALE, MAX_SCALE, scale),
+            ));
+        }
+    }
+
+    let known = known_columns();
+    for column in &settings.excel_safe_columns {
+        if !known.contains(column.as_str()) {
+            issues.push(issue(
+                file,
+                "excel_safe_columns",
+                format!("a column of the DDNS or minION template (unknown column '{}')", column),
+            ));
+        }
+    }
+
+    for column in settings.comment_columns.keys() {
+        if !known.contains(column.as_str()) {
+            issues.push(issue(
+                file,
+                "comment_columns",
+                format!("a column of the DDNS or minION template (unknown column '{}')", column),
+            ));
+        }
+    }
+
+    if let Err(e) = validate_pattern(&settings.output_name_pattern) {
+        issues.push(issue(file, "output_name_pattern", format!("a valid output name pattern ({e})")));
+    }
+
+    if let Err(e) = settings.date_reported.validate() {
+        issues.push(issue(file, "date_reported", format!("a YYYY-MM-DD date ({e})")));
+    }
+
+    if settings.plate_max_rows == 0 {
+        issues.push(issue(file, "plate_max_rows", "at least 1 row"));
+    }
+    if settings.plate_min_rows > settings.plate_max_rows {
+        issues.push(issue(
+            file,
+            "plate_min_rows",
+            format!("no more than plate_max_rows ({})", settings.plate_max_rows),
+        ));
+    }
+
+    if !settings.post_merge_hook.trim().is_empty() {
+        if let Err(e) = validate_hook(&settings.post_merge_hook) {
+            issues.push(issue(file, "post_merge_hook", format!("a command using only known placeholders ({e})")));
+        }
+        if settings.post_merge_hook_timeout_secs == 0 {
+            issues.push(issue(file, "post_merge_hook_timeout_secs", "at least 1 second"));
+        }
+    }
+
+    let mut control_barcodes = HashSet::new();
+    for (idx, control) in settings.control_rows.iter().enumerate() {
+        if control.sample.trim().is_empty() || control.barcode.trim().is_empty() {
+            issues.push(issue(file, format!("control_rows[{}]", idx), "a sample name and a barcode"));
+        } else if !control_barcodes.insert(control.barcode.trim().to_ascii_lowercase()) {
+            issues.push(issue(
+                file,
+                format!("control_rows[{}]", idx),
+                format!("a barcode no other control uses ({} is repeated)", control.barcode),
+            ));
+        }
+    }
+
+    if let Err(e) = settings.unsampled_epi_report.validate() {
+        issues.push(issue(file, "unsampled_epi_report", format!("a valid report window ({e})")));
+    }
+
+    if settings.min_epi_match_percent > 100 {
+        issues.push(issue(file, "min_epi_match_percent", "a percentage from 0 to 100"));
+    }
+
+    issues
+}
+
+/// Checks the text of one configuration file; the first argument names
+/// the file in the issues
+pub type ConfigChecker = fn(&str, &str) -> Vec<ConfigIssue>;
+
+/// Every user-editable configuration file in the config dir, with its
+/// checker. Configuration bundles may carry any of these.
+pub const CONFIG_FILES: &[(&str, ConfigChecker)] = &[("settings.json", check_settings)];
+
+/// Checker of a configuration file by name, None if it is not one
+pub fn checker_for(name: &str) -> Option<ConfigChecker> {
+    CONFIG_FILES.iter().find(|(file, _)| *file == name).map(|(_, check)| *check)
+}
+
+fn check_file(path: &Path, check: ConfigChecker) -> ConfigReport {
+    let file = path.display().to_string();
+    match std::fs::read_to_string(path) {
+        Ok(text) => ConfigReport {
+            issues: check(&file, &text),
+            file,
+            present: true,
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ConfigReport {
+            file,
+            present: false,
+            issues: Vec::new(),
+        },
+        Err(e) => ConfigReport {
+            issues: vec![issue(&file, "file", format!("a readable file ({e})"))],
+            file,
+            present: true,
+        },
+    }
+}
+
+/// Runs every configuration loader and collects what each one found.
+/// New user-editable files get a checker in CONFIG_FILES.
+pub fn validate_all() -> Vec<ConfigReport> {
+    let Some(dir) = settings_dir() else {
+        return Vec::new();
+    };
+
+    CONFIG_FILES
+        .iter()
+        .map(|(name, check)| check_file(&dir.join(name), *check))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    fn locations(issues: &[ConfigIssue]) -> Vec<&str> {
+        issues.iter().map(|i| i.location.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_settings_have_no_issues() {
+        assert!(check_settings("settings.json", "{}").is_empty());
+        let defaults = serde_json::to_string_pretty(&Settings::default()).unwrap();
+        assert!(check_settings("settings.json", &defaults).is_empty());
+        let chosen = r#"{"ui_scale": 1.25, "excel_safe_columns": ["EPID", "FlowCellID"], "min_epi_match_percent": 80}"#;
+        assert!(check_settings("settings.json", chosen).is_empty());
+    }
+
+    #[test]
+    fn structurally_invalid_settings_report_where() {
+        let issues = check_settings("settings.json", "{\n  \"ui_scale\": 1.0,\n  \"theme\": \n}");
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].location.starts_with("line 4, column"), "{}", issues[0].location);
+        assert!(issues[0].to_string().starts_with("settings.json (line 4"));
+
+        // Right syntax, wrong type
+        let issues = check_settings("settings.json", r#"{"excel_safe_columns": "EPID"}"#);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].expected.starts_with("valid settings JSON"));
+
+        let issues = check_settings("settings.json", r#"{"ui_scael": 1.5}"#);
+        assert_eq!(locations(&issues), ["ui_scael"]);
+    }
+
+    #[test]
+    fn semantically_invalid_settings_name_the_field() {
+        let text = r#"{
+            "ui_scale": 9.0,
+            "excel_safe_columns": ["EPID", "EpidNumbr"],
+            "comment_columns": {"NoSuchColumn": "PreferSample"},
+            "plate_max_rows": 2,
+            "plate_min_rows": 5,
+            "min_epi_match_percent": 150
+        }"#;
+        let issues = check_settings("settings.json", text);
+        assert_eq!(
+            locations(&issues),
+            ["ui_scale", "excel_safe_columns", "comment_columns", "plate_min_rows", "min_epi_match_percent"]
+        );
+        assert!(issues[1].expected.contains("unknown column 'EpidNumbr'"));
+        assert!(issues[2].expected.contains("unknown column 'NoSuchColumn'"));
+    }
+
+    #[test]
+    fn checked_files_report_presence() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let missing = check_file(&dir.path().join("settings.json"), check_settings);
+        assert!(!missing.present);
+        assert!(missing.issues.is_empty());
+
+        let path = dir.file("settings.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let broken = check_file(&path, check_settings);
+        assert!(broken.present);
+        assert_eq!(broken.issues.len(), 1);
+
+        assert!(checker_for("settings.json").is_some());
+        assert!(checker_for("notes.txt").is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::warnings::ValidationWarning;
+
+/// A control every output should have a row for, added when the samples
+/// file leaves it out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlRow {
+    // Written to the sample column. Also what finds an existing row: the
+    // name itself, or the name followed by a separator ("NC-1", "NC_2")
+    pub sample: String,
+    pub barcode: String,
+    // Written to SampleType where the template has it (DDNS)
+    #[serde(default)]
+    pub sample_type: String,
+}
+
+impl ControlRow {
+    pub fn matches(&self, sample: &str) -> bool {
+        let sample = sample.trim();
+        let name = self.sample.trim();
+        match (sample.get(..name.len()), sample.get(name.len()..)) {
+            (Some(prefix), Some(rest)) if prefix.eq_ignore_ascii_case(name) => {
+                rest.chars().next().is_none_or(|c| !c.is_alphanumeric())
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn default_control_rows() -> Vec<ControlRow> {
+    vec![
+        ControlRow {
+            sample: "PC".to_string(),
+            barcode: "barcode95".to_string(),
+            sample_type: "Positive control".to_string(),
+        },
+        ControlRow {
+            sample: "NC".to_string(),
+            barcode: "barcode96".to_string(),
+            sample_type: "Negative control".to_string(),
+        },
+    ]
+}
+
+fn text_column(df: &DataFrame, name: &str) -> Result<Vec<String>, String> {
+    let column = df
+        .column(name)
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(|e| format!("Failed to read the {} column: {e}", name))?;
+    let values = column
+        .str()
+        .map_err(|e| format!("Failed to read the {} column: {e}", name))?
+        .into_iter()
+        .map(|v| v.unwrap_or("").trim().to_string())
+        .collect();
+    Ok(values)
+}
+
+/// Appends a row for each control in `controls` that `df` has no row for:
+/// its identifiers, the run `constants` (column, value) and every other
+/// cell empty. A control whose barcode is already taken, or that would
+/// take the run past `max_rows`, is reported instead of added.
+pub fn ensure_control_rows(
+    df: DataFrame,
+    controls: &[ControlRow],
+    constants: &[(&str, String)],
+    max_rows: usize,
+) -> Result<(DataFrame, Vec<ValidationWarning>), String> {
+    let mut samples = text_column(&df, "sample")?;
+    let mut barcodes = text_column(&df, "barcode")?;
+    let columns: Vec<String> = df.get_column_names().iter().map(|c| c.to_string()).collect();
+
+    let mut rows: Vec<&ControlRow> = Vec::new();
+    let mut warnings = Vec::new();
+    for control in controls {
+        if samples.iter().any(|s| control.matches(s)) {
+            continue;
+        }
+        let used_by = barcodes
+            .iter()
+            .position(|b| b.eq_ignore_ascii_case(control.barcode.trim()))
+            .map(|idx| samples[idx].clone());
+        if used_by.is_some() || samples.len() >= max_rows {
+            warnings.push(ValidationWarning::ControlRowSkipped {
+                sample: control.sample.clone(),
+                barcode: control.barcode.clone(),
+                used_by,
+            });
+            continue;
+        }
+        samples.push(control.sample.clone());
+        barcodes.push(control.barcode.clone());
+        rows.push(control);
+    }
+
+    if rows.is_empty() {
+        return Ok((df, warnings));
+    }
+
+    let new_columns: Vec<Column> = columns
+        .iter()
+        .map(|name| {
+            let values: Vec<String> = rows
+                .iter()
+                .map(|control| match name.as_str() {
+                    "sample" => control.sample.clone(),
+                    "barcode" => control.barcode.clone(),
+                    "SampleType" => control.sample_type.clone(),
+                    _ => constants
+                        .iter()
+                        .find(|(column, _)| *column == name.as_str())
+                        .map(|(_, value)| value.clone())
+                        .unwrap_or_default(),
+                })
+                .collect();
+            Series::new(PlSmallStr::from_str(name), values).into_column()
+        })
+        .collect();
+    let new_rows = DataFrame::new(new_columns).map_err(|e| format!("Failed to create control rows: {e}"))?;
+
+    // Everything is text by now; align types so vstack accepts the frames
+    let df = df
+        .lazy()
+        .with_columns(columns.iter().map(|c| col(c.as_str()).cast(DataType::String)).collect::<Vec<_>>())
+        .collect()
+        .map_err(|e| format!("Failed to prepare control rows: {e}"))?;
+    let df = df.vstack(&new_rows).map_err(|e| format!("Failed to add control rows: {e}"))?;
+
+    warnings.insert(
+        0,
+        ValidationWarning::ControlRowsAdded {
+            samples: rows.iter().map(|c| c.sample.clone()).collect(),
+        },
+    );
+    Ok((df, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(rows: &[(&str, &str)]) -> DataFrame {
+        let names: Vec<&str> = rows.iter().map(|(s, _)| *s).collect();
+        let barcodes: Vec<&str> = rows.iter().map(|(_, b)| *b).collect();
+        let types = vec![""; rows.len()];
+        let runs: Vec<Option<&str>> = vec![None; rows.len()];
+        df!("sample" => names, "barcode" => barcodes, "SampleType" => types.clone(), "RunNumber" => runs, "EPID" => types)
+            .unwrap()
+    }
+
+    fn constants() -> Vec<(&'static str, String)> {
+        vec![("RunNumber", "20240305_001".to_string())]
+    }
+
+    fn text(df: &DataFrame, column: &str) -> Vec<String> {
+        text_column(df, column).unwrap()
+    }
+
+    #[test]
+    fn control_names_match_with_a_separator_only() {
+        let nc = &default_control_rows()[1];
+        for name in ["NC", "nc", " NC-1", "NC_2", "NC 3"] {
+            assert!(nc.matches(name), "{}", name);
+        }
+        for name in ["NCD001", "N", "", "S01", "ANC"] {
+            assert!(!nc.matches(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn missing_controls_are_appended_with_the_run_constants() {
+        let df = samples(&[("S01", "barcode01"), ("S02", "barcode02")]);
+        let (df, warnings) = ensure_control_rows(df, &default_control_rows(), &constants(), 96).unwrap();
+
+        assert_eq!(text(&df, "sample"), ["S01", "S02", "PC", "NC"]);
+        assert_eq!(text(&df, "barcode"), ["barcode01", "barcode02", "barcode95", "barcode96"]);
+        assert_eq!(text(&df, "SampleType")[2..], ["Positive control", "Negative control"]);
+        assert_eq!(text(&df, "RunNumber")[2..], ["20240305_001", "20240305_001"]);
+        assert_eq!(text(&df, "EPID")[2..], ["", ""]);
+        assert_eq!(
+            warnings,
+            vec![ValidationWarning::ControlRowsAdded { samples: vec!["PC".to_string(), "NC".to_string()] }]
+        );
+    }
+
+    #[test]
+    fn controls_already_listed_are_not_added_again() {
+        let df = samples(&[("S01", "barcode01"), ("PC-1", "barcode93"), ("nc_2", "barcode94")]);
+        let (df, warnings) = ensure_control_rows(df, &default_control_rows(), &constants(), 96).unwrap();
+        assert_eq!(df.height(), 3);
+        assert!(warnings.is_empty());
+        // Left as it was, types included
+        assert!(df.column("RunNumber").unwrap().null_count() == 3);
+    }
+
+    #[test]
+    fn taken_barcodes_and_a_full_plate_skip_the_control() {
+        let df = samples(&[("S01", "barcode01"), ("S95", "BARCODE95")]);
+        let (df, warnings) = ensure_control_rows(df, &default_control_rows(), &constants(), 96).unwrap();
+        assert_eq!(text(&df, "sample"), ["S01", "S95", "NC"]);
+        assert_eq!(
+            warnings,
+            vec![
+                ValidationWarning::ControlRowsAdded { samples: vec!["NC".to_string()] },
+                ValidationWarning::ControlRowSkipped {
+                    sample: "PC".to_string(),
+                    barcode: "barcode95".to_string(),
+                    used_by: Some("S95".to_string()),
+                },
+            ]
+        );
+
+        // One free row: the first control takes it, the second is reported
+        let df = samples(&[("S01", "barcode01"), ("S02", "barcode02")]);
+        let (df, warnings) = ensure_control_rows(df, &default_control_rows(), &constants(), 3).unwrap();
+        assert_eq!(df.height(), 3);
+        assert_eq!(
+            warnings[1],
+            ValidationWarning::ControlRowSkipped { sample: "NC".to_string(), barcode: "barcode96".to_string(), used_by: None }
+        );
+    }
+}
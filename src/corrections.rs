@@ -0,0 +1,229 @@
+use polars::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Columns a corrections sheet may use as its key (first column)
+const KEY_COLUMNS: [&str; 2] = ["sample", "EPID"];
+
+/// One overwritten cell
+#[derive(Debug, Clone)]
+pub struct CellChange {
+    pub key: String,
+    pub column: String,
+    pub old: String,
+    pub new: String,
+}
+
+pub struct CorrectionsResult {
+    pub df: DataFrame,
+    pub changes: Vec<CellChange>,
+}
+
+fn column_values(df: &DataFrame, name: &str) -> Result<Vec<Option<String>>, String> {
+    let ca = df
+        .column(name)
+        .and_then(|c| c.str().cloned())
+        .map_err(|e| format!("Failed to read column '{}': {e}", name))?;
+    Ok(ca.into_iter().map(|v| v.map(|s| s.to_string())).collect())
+}
+
+/// Overwrites cells of `output` with the values of a corrections sheet.
+/// The first column of `corrections` names the key (sample or EPID), the
+/// others are the fields to overwrite. Empty correction cells are left alone.
+/// Unknown keys, unknown columns or repeated keys abort with a list.
+pub fn apply_corrections(
+    mut output: DataFrame,
+    corrections: &DataFrame,
+) -> Result<CorrectionsResult, String> {
+    let corr_cols: Vec<String> = corrections
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let key_col = corr_cols
+        .first()
+        .cloned()
+        .ok_or_else(|| "The corrections file has no columns.".to_string())?;
+    if !KEY_COLUMNS.contains(&key_col.as_str()) {
+        return Err(format!(
+            "The first column of the corrections file must be one of: {} (found '{}').",
+            KEY_COLUMNS.join(", "),
+            key_col
+        ));
+    }
+
+    let output_cols: HashSet<String> = output
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let fields: Vec<String> = corr_cols[1..].to_vec();
+    if fields.is_empty() {
+        return Err("The corrections file has no columns to apply.".to_string());
+    }
+
+    let unknown_cols: Vec<&str> = std::iter::once(&key_col)
+        .chain(fields.iter())
+        .filter(|c| !output_cols.contains(*c))
+        .map(|c| c.as_str())
+        .collect();
+    if !unknown_cols.is_empty() {
+        return Err(format!(
+            "The corrections file has columns that are not in the output:\n{}",
+            unknown_cols.join(", ")
+        ));
+    }
+
+    // key -> output row indices (an EPID can cover several samples)
+    let mut rows_by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, key) in column_values(&output, &key_col)?.into_iter().enumerate() {
+        if let Some(key) = key {
+            rows_by_key.entry(key.trim().to_string()).or_default().push(idx);
+        }
+    }
+
+    let corr_keys: Vec<String> = column_values(corrections, &key_col)?
+        .into_iter()
+        .map(|k| k.unwrap_or_default().trim().to_string())
+        .collect();
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut repeated: Vec<&str> = Vec::new();
+    let mut unknown_keys: Vec<&str> = Vec::new();
+    for key in &corr_keys {
+        if !seen.insert(key.as_str()) && !repeated.contains(&key.as_str()) {
+            repeated.push(key);
+        }
+        if !rows_by_key.contains_key(key) {
+            unknown_keys.push(key);
+        }
+    }
+    if !unknown_keys.is_empty() {
+        return Err(format!(
+            "These {} value(s) in the corrections file were not found in the output:\n{}",
+            key_col,
+            unknown_keys.join(", ")
+        ));
+    }
+    if !repeated.is_empty() {
+        return Err(format!(
+            "These {} value(s) appear more than once in the corrections file:\n{}",
+            key_col,
+            repeated.join(", ")
+        ));
+    }
+
+    let mut changes = Vec::new();
+    for field in &fields {
+        let new_values = column_values(corrections, field)?;
+        let mut values = column_values(&output, field)?;
+        let mut touched = false;
+
+        for (key, new) in corr_keys.iter().zip(new_values) {
+            let Some(new) = new.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            for &idx in &rows_by_key[key] {
+                let old = values[idx].clone().unwrap_or_default();
+                if old != new {
+                    changes.push(CellChange {
+                        key: key.clone(),
+                        column: field.clone(),
+                        old,
+                        new: new.clone(),
+                    });
+                    values[idx] = Some(new.clone());
+                    touched = true;
+                }
+            }
+        }
+
+        if touched {
+            output
+                .with_column(Series::new(PlSmallStr::from_str(field), values))
+                .map_err(|e| format!("Failed to update column '{}': {e}", field))?;
+        }
+    }
+
+    Ok(CorrectionsResult { df: output, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::read_csv_normalized;
+    use crate::workspace::TempWorkspace;
+
+    fn output() -> DataFrame {
+        df!(
+            "sample" => ["S1", "S2", "S3", "S4"],
+            "EPID" => [Some("E1"), Some("E2"), Some("E2"), None],
+            "SampleQC" => [Some("Pass"), Some("Pass"), None, Some("Fail")],
+            "QCComments" => [None::<&str>, None, None, None]
+        )
+        .unwrap()
+    }
+
+    // The corrections sheet as the user saves it
+    fn corrections(text: &str) -> DataFrame {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("corrections.csv");
+        std::fs::write(&path, text).unwrap();
+        read_csv_normalized(&path.to_string_lossy()).unwrap().0
+    }
+
+    fn cell(df: &DataFrame, column: &str, row: usize) -> Option<String> {
+        column_values(df, column).unwrap()[row].clone()
+    }
+
+    #[test]
+    fn only_targeted_cells_change() {
+        let before = output();
+        let fixed = apply_corrections(before.clone(), &corrections("sample,SampleQC,QCComments\nS3,Fail,re-run\nS1,,checked\n")).unwrap();
+
+        let targeted = [("SampleQC", 2), ("QCComments", 2), ("QCComments", 0)];
+        for column in ["sample", "EPID", "SampleQC", "QCComments"] {
+            for row in 0..before.height() {
+                if !targeted.contains(&(column, row)) {
+                    assert_eq!(cell(&fixed.df, column, row), cell(&before, column, row), "{} row {}", column, row);
+                }
+            }
+        }
+        assert_eq!(cell(&fixed.df, "SampleQC", 2).as_deref(), Some("Fail"));
+        assert_eq!(cell(&fixed.df, "QCComments", 2).as_deref(), Some("re-run"));
+        // The empty cell left S1's SampleQC alone
+        assert_eq!(cell(&fixed.df, "SampleQC", 0).as_deref(), Some("Pass"));
+
+        let changes: Vec<(&str, &str, &str, &str)> = fixed
+            .changes
+            .iter()
+            .map(|c| (c.key.as_str(), c.column.as_str(), c.old.as_str(), c.new.as_str()))
+            .collect();
+        assert_eq!(changes, [("S3", "SampleQC", "", "Fail"), ("S3", "QCComments", "", "re-run"), ("S1", "QCComments", "", "checked")]);
+    }
+
+    #[test]
+    fn unchanged_values_are_not_reported() {
+        let fixed = apply_corrections(output(), &corrections("sample,SampleQC\nS1,Pass\n")).unwrap();
+        assert!(fixed.changes.is_empty());
+        assert!(fixed.df.equals_missing(&output()));
+    }
+
+    #[test]
+    fn epid_key_corrects_every_sample_of_the_case() {
+        let fixed = apply_corrections(output(), &corrections("EPID,SampleQC\nE2,Fail\n")).unwrap();
+        assert_eq!(cell(&fixed.df, "SampleQC", 1).as_deref(), Some("Fail"));
+        assert_eq!(cell(&fixed.df, "SampleQC", 2).as_deref(), Some("Fail"));
+        assert_eq!(fixed.changes.len(), 2);
+    }
+
+    #[test]
+    fn bad_sheets_are_refused_with_a_list() {
+        let error = |text: &str| apply_corrections(output(), &corrections(text)).err().unwrap();
+        assert!(error("barcode,SampleQC\nbarcode01,Fail\n").contains("must be one of: sample, EPID"));
+        assert!(error("sample\nS1\n").contains("no columns to apply"));
+        assert!(error("sample,RunQC,Extra\nS1,Pass,x\n").contains("RunQC, Extra"));
+        assert!(error("sample,SampleQC\nS9,Fail\nS8,Pass\n").contains("not found in the output:\nS9, S8"));
+        assert!(error("sample,SampleQC\nS1,Fail\nS1,Pass\n").contains("more than once in the corrections file:\nS1"));
+    }
+}
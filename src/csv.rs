@@ -1,26 +1,207 @@
 use polars::prelude::*;
 use polars::prelude::NullValues;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::collections::HashSet;
+use std::io::{self, Read};
 use std::sync::Arc;
 use unicode_normalization::UnicodeNormalization;
 
-pub fn detect_delimiter(path: &str) -> io::Result<u8> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
+use crate::input::{local_input, open_read_only, read_input_bytes};
+use crate::ods::{is_ods, read_ods_header, read_ods_projected};
 
-    let mut header = String::new();
-    if reader.read_line(&mut header)? == 0 {
-        // Empty file, default to comma
-        return Ok(b',');
+// Enough of a file to hold its header line (and a sep= line before it)
+const HEADER_PREFIX_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // Excel's default "CSV (Comma delimited)" on Western Windows
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf16Le => "UTF-16LE",
+            TextEncoding::Utf16Be => "UTF-16BE",
+            TextEncoding::Windows1252 => "Windows-1252",
+        }
+    }
+}
+
+// Encoding from the byte order mark, and the BOM's length
+fn text_encoding(bytes: &[u8]) -> (TextEncoding, usize) {
+    match bytes {
+        [0xFF, 0xFE, ..] => (TextEncoding::Utf16Le, 2),
+        [0xFE, 0xFF, ..] => (TextEncoding::Utf16Be, 2),
+        [0xEF, 0xBB, 0xBF, ..] => (TextEncoding::Utf8, 3),
+        _ => (TextEncoding::Utf8, 0),
+    }
+}
+
+fn utf16_units(bytes: &[u8], encoding: TextEncoding) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| match encoding {
+            TextEncoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+            _ => u16::from_le_bytes([pair[0], pair[1]]),
+        })
+        .collect()
+}
+
+// Windows-1252 0x80..=0x9F; the rest of the code page is Latin-1.
+// Unassigned bytes map to the C1 control of the same value.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+fn decode_cp1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => CP1252_HIGH[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
+
+/// Text of a CSV file without its BOM, and the encoding it was in. UTF-16
+/// files (Excel's "Unicode text") are transcoded; files without a BOM that
+/// are not valid UTF-8 are read as Windows-1252.
+pub fn decode_csv(bytes: &[u8], path: &str) -> Result<(String, TextEncoding), String> {
+    let (encoding, bom) = text_encoding(bytes);
+    let body = &bytes[bom..];
+    match encoding {
+        TextEncoding::Utf8 => match String::from_utf8(body.to_vec()) {
+            Ok(text) => Ok((text, TextEncoding::Utf8)),
+            // A UTF-8 BOM followed by invalid UTF-8 is a broken file, not 1252
+            Err(_) if bom > 0 => Err(format!(
+                "'{}' is not a valid UTF-8 CSV file. Please open it in Excel and save it again as \"CSV UTF-8 (Comma delimited)\".",
+                path
+            )),
+            Err(_) => Ok((decode_cp1252(body), TextEncoding::Windows1252)),
+        },
+        _ => String::from_utf16(&utf16_units(body, encoding))
+            .map(|text| (text, encoding))
+            .map_err(|_| {
+                format!(
+                    "'{}' looks like a UTF-16 file but could not be decoded. Please open it in Excel and save it again as \"CSV UTF-8 (Comma delimited)\".",
+                    path
+                )
+            }),
+    }
+}
+
+// For peeking at the header only: the prefix may end mid-character
+fn decode_prefix_lossy(bytes: &[u8]) -> String {
+    let (encoding, bom) = text_encoding(bytes);
+    let body = &bytes[bom..];
+    match encoding {
+        TextEncoding::Utf8 => match std::str::from_utf8(body) {
+            Ok(text) => text.to_string(),
+            // Only cut short at the end of the prefix: still UTF-8
+            Err(e) if e.error_len().is_none() => String::from_utf8_lossy(body).to_string(),
+            Err(_) if bom == 0 => decode_cp1252(body),
+            Err(_) => String::from_utf8_lossy(body).to_string(),
+        },
+        _ => String::from_utf16_lossy(&utf16_units(body, encoding)),
+    }
+}
+
+/// How one input file is written. Each frame is parsed with its own
+/// profile, so a semicolon/UTF-8 sample sheet and a comma/Windows-1252
+/// EpiInfo export can be merged together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileProfile {
+    pub delimiter: u8,
+    pub encoding: TextEncoding,
+    // The delimiter came from a sep= line rather than detection
+    pub sep_directive: bool,
+    // Numbers use a decimal comma (semicolon-separated files)
+    pub decimal_comma: bool,
+    // Dates used French month names and were translated
+    pub french_months: bool,
+    // Read from an ODS spreadsheet; delimiter and encoding do not apply
+    pub spreadsheet: bool,
+}
+
+impl FileProfile {
+    /// e.g. "semicolon, UTF-8, decimal comma, French month names"
+    pub fn describe(&self) -> String {
+        let mut parts = if self.spreadsheet {
+            vec!["ODS spreadsheet".to_string()]
+        } else {
+            vec![
+            match self.delimiter {
+                b';' => "semicolon",
+                b'\t' => "tab",
+                b',' => "comma",
+                _ => "other delimiter",
+            }
+            .to_string(),
+            self.encoding.label().to_string(),
+            ]
+        };
+        if self.sep_directive {
+            parts.push("sep= line".to_string());
+        }
+        if self.decimal_comma {
+            parts.push("decimal comma".to_string());
+        }
+        if self.french_months {
+            parts.push("French month names".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Turns CRLF and lone CR (classic Mac, some macOS editors) line endings
+/// into LF, so every line is seen however the sheet was saved
+pub fn normalize_line_endings(content: &str) -> String {
+    if !content.contains('\r') {
+        return content.to_string();
+    }
+    content.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Delimiter named by Excel's "sep=;" first line, if the line is one
+fn sep_directive(line: &str) -> Option<u8> {
+    let line = line.trim_end_matches(['\r', '\n']).trim_matches('"');
+    if !line.get(..4)?.eq_ignore_ascii_case("sep=") {
+        return None;
     }
+    match &line[4..] {
+        "\\t" | "\t" => Some(b'\t'),
+        v if v.len() == 1 => v.bytes().next(),
+        _ => None,
+    }
+}
 
+/// Splits off a leading sep= directive: its delimiter, and the text from
+/// the header line on
+pub fn strip_sep_directive(content: &str) -> (Option<u8>, &str) {
+    let first_end = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    match sep_directive(&content[..first_end]) {
+        Some(delim) => (Some(delim), &content[first_end..]),
+        None => (None, content),
+    }
+}
+
+// Most frequent of , ; and tab in the header line outside quoted names,
+// comma on ties
+fn delimiter_for_header(header: &str) -> u8 {
     let mut comma = 0usize;
     let mut semi = 0usize;
     let mut tab = 0usize;
+    let mut quoted = false;
 
     for &b in header.as_bytes() {
         match b {
+            b'"' => quoted = !quoted,
+            _ if quoted => {}
             b',' => comma += 1,
             b';' => semi += 1,
             b'\t' => tab += 1,
@@ -28,51 +209,285 @@ pub fn detect_delimiter(path: &str) -> io::Result<u8> {
         }
     }
 
-    let delim = if semi > comma && semi > tab {
+    if semi > comma && semi > tab {
         b';'
     } else if tab > comma {
         b'\t'
     } else {
         b','
+    }
+}
+
+/// Fields of a header record split on `delim` outside quoted names, so a
+/// name like "Site, region" stays one column as the CSV reader sees it.
+/// `delim` is ASCII, so every split lands on a character boundary.
+pub fn split_header(header: &str, delim: u8) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (idx, &b) in header.as_bytes().iter().enumerate() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == delim && !quoted {
+            fields.push(&header[start..idx]);
+            start = idx + 1;
+        }
+    }
+    fields.push(&header[start..]);
+    fields
+}
+
+/// Header line of a CSV and its delimiter, read from the start of the
+/// file only. A sep= directive wins over detection and is skipped.
+pub fn read_header_line(path: &str) -> io::Result<(String, u8)> {
+    let mut bytes = Vec::new();
+    open_read_only(path)?.take(HEADER_PREFIX_BYTES).read_to_end(&mut bytes)?;
+    let text = normalize_line_endings(&decode_prefix_lossy(&bytes));
+
+    let (directive, rest) = strip_sep_directive(&text);
+    let header = header_record(rest).unwrap_or("").to_string();
+    let delim = directive.unwrap_or_else(|| delimiter_for_header(&header));
+    Ok((header, delim))
+}
+
+/// The header record at the start of `content`: its first line, or more
+/// lines when a quoted header name contains a line break
+fn header_record(content: &str) -> Option<&str> {
+    if content.is_empty() {
+        return None;
+    }
+    let mut quoted = false;
+    for (idx, c) in content.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => return Some(&content[..idx]),
+            _ => {}
+        }
+    }
+    Some(content)
+}
+
+/// One header name as the app uses it: outer quotes removed, control
+/// characters (tabs, line breaks) and repeated spaces turned into a
+/// single space, then trimmed
+pub fn sanitize_header(raw: &str) -> String {
+    let raw = raw.trim();
+    let unquoted = match raw.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => raw.to_string(),
+    };
+    unquoted
+        .split(|c: char| c.is_control() || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Sanitized and de-duplicated names for a raw header, plus (raw, name)
+/// pairs for every header that had control characters or was renamed
+pub fn clean_headers(raw: &[&str]) -> (Vec<String>, Vec<(String, String)>) {
+    let cleaned: Vec<String> = raw.iter().map(|h| sanitize_header(h)).collect();
+    let refs: Vec<&str> = cleaned.iter().map(String::as_str).collect();
+    let (names, _) = dedupe_headers(&refs);
+    let renames = raw
+        .iter()
+        .zip(&cleaned)
+        .zip(&names)
+        .filter(|((raw, cleaned), name)| raw.chars().any(char::is_control) || cleaned != name)
+        .map(|((raw, _), name)| (raw.to_string(), name.clone()))
+        .collect();
+    (names, renames)
+}
+
+/// Column names of a CSV's header, sanitized, blanks left out
+pub fn read_header_columns(path: &str) -> Result<Vec<String>, String> {
+    let headers = if is_ods(path) {
+        read_ods_header(path)?
+    } else {
+        let (header, delimiter) = read_header_line(path)
+            .map_err(|e| format!("Failed to read '{}': {e}", path))?;
+        split_header(&header, delimiter).into_iter().map(str::to_string).collect()
     };
+    Ok(headers
+        .iter()
+        .map(|h| sanitize_header(h))
+        .filter(|c| !c.is_empty())
+        .collect())
+}
+
+/// Makes header names usable by Polars: blank headers become Unnamed_1,
+/// Unnamed_2, ... and repeats of a name get a numeric suffix (Comments,
+/// Comments_2). The first occurrence keeps its name, so required columns
+/// still resolve. Returns the names plus (original, renamed) pairs.
+pub fn dedupe_headers(headers: &[&str]) -> (Vec<String>, Vec<(String, String)>) {
+    let originals: HashSet<&str> = headers.iter().copied().filter(|h| !h.trim().is_empty()).collect();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut names = Vec::with_capacity(headers.len());
+    let mut renamed = Vec::new();
+    let mut unnamed = 0;
+
+    for &header in headers {
+        let name = if header.trim().is_empty() {
+            loop {
+                unnamed += 1;
+                let candidate = format!("Unnamed_{}", unnamed);
+                if !used.contains(&candidate) && !originals.contains(candidate.as_str()) {
+                    break candidate;
+                }
+            }
+        } else if !used.contains(header) {
+            header.to_string()
+        } else {
+            (2..)
+                .map(|n| format!("{}_{}", header, n))
+                .find(|c| !used.contains(c) && !originals.contains(c.as_str()))
+                .unwrap_or_default()
+        };
+
+        if name != header {
+            renamed.push((header.to_string(), name.clone()));
+        }
+        used.insert(name.clone());
+        names.push(name);
+    }
+
+    (names, renamed)
+}
+
+/// Header renames `read_csv_projected` applies to this file (sanitized,
+/// blank or repeated names), for reporting
+pub fn header_renames(path: &str) -> Result<Vec<(String, String)>, String> {
+    if is_ods(path) {
+        let headers = read_ods_header(path)?;
+        let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+        return Ok(clean_headers(&headers).1);
+    }
+    let (header, delim) = read_header_line(path)
+        .map_err(|e| format!("Failed to read file '{}': {e}", path))?;
+    let headers = split_header(&header, delim);
+    Ok(clean_headers(&headers).1)
+}
+
+/// Samples file columns matched whatever their case ("Sample", "BARCODE"):
+/// the join keys, where the intent is never in doubt. Every other column
+/// must match the template exactly.
+pub const CASE_INSENSITIVE_COLUMNS: &[&str] = &["sample", "barcode", "EPID"];
+
+/// The CASE_INSENSITIVE_COLUMNS name a header stands for, if any
+pub fn canonical_column(header: &str) -> Option<&'static str> {
+    CASE_INSENSITIVE_COLUMNS
+        .iter()
+        .copied()
+        .find(|c| c.eq_ignore_ascii_case(header.trim()))
+}
+
+/// Renames headers that differ from a CASE_INSENSITIVE_COLUMNS name only by
+/// case to the canonical spelling. Left alone when the canonical name is
+/// already there or two headers would take it. Returns (from, to) pairs.
+pub fn canonicalize_header_case(df: &mut DataFrame) -> PolarsResult<Vec<(String, String)>> {
+    let names: Vec<String> = df.get_column_names().iter().map(|c| c.to_string()).collect();
+    let mut renames = Vec::new();
+    for canonical in CASE_INSENSITIVE_COLUMNS {
+        if names.iter().any(|n| n == canonical) {
+            continue;
+        }
+        let candidates: Vec<&String> = names.iter().filter(|n| canonical_column(n) == Some(canonical)).collect();
+        if let [from] = candidates.as_slice() {
+            renames.push((from.to_string(), canonical.to_string()));
+        }
+    }
+    for (from, to) in &renames {
+        df.rename(from, PlSmallStr::from_str(to))?;
+    }
+    Ok(renames)
+}
 
-    Ok(delim)
+pub fn read_csv_normalized(path: &str) -> Result<(DataFrame, FileProfile), String> {
+    read_csv_projected(path, None).map(|(df, profile, _)| (df, profile))
 }
 
-pub fn read_csv_normalized(path: &str) -> Result<(DataFrame, u8), String> {
-    let delim = detect_delimiter(path)
-        .map_err(|e| format!("Failed to detect delimiter for '{}': {e}", path))?;
+/// Reads a CSV loading only the columns in `keep` (all columns when None).
+/// Also returns the file's profile and how many columns were skipped.
+/// ODS spreadsheets are read from their first sheet.
+pub fn read_csv_projected(
+    path: &str,
+    keep: Option<&HashSet<String>>,
+) -> Result<(DataFrame, FileProfile, usize), String> {
+    // Network inputs are parsed from a local copy
+    let input = local_input(path)?;
+    let local_path = input.path_str();
+
+    if is_ods(path) {
+        let (df, skipped) = read_ods_projected(&local_path, keep)?;
+        let (df, french_months) = translate_french_months(df)
+            .map_err(|e| format!("Failed to translate French dates in '{}': {e}", path))?;
+        let profile = FileProfile {
+            delimiter: b',',
+            encoding: TextEncoding::Utf8,
+            sep_directive: false,
+            decimal_comma: false,
+            french_months,
+            spreadsheet: true,
+        };
+        return Ok((df, profile, skipped));
+    }
 
-    let content = std::fs::read_to_string(path)
+    let bytes = read_input_bytes(&local_path)
         .map_err(|e| format!("Failed to read file '{}': {e}", path))?;
+    let (content, encoding) = decode_csv(&bytes, path)?;
+    let content = normalize_line_endings(&content);
 
-    let content = content.strip_prefix('\u{FEFF}').unwrap_or(&content).to_string();
+    // Excel's "sep=;" line names the delimiter and is not the header
+    let (directive, content) = strip_sep_directive(&content);
+    let content = content.to_string();
 
-    let mut lines = content.lines();
-    let header_line = lines
-        .next()
+    let header_line = header_record(&content)
         .ok_or_else(|| format!("CSV file '{}' appears to be empty", path))?;
-    let delim_ch = delim as char;
-    let headers: Vec<&str> = header_line.split(delim_ch).collect();
+    let delim = directive.unwrap_or_else(|| delimiter_for_header(header_line));
+    let mut profile = FileProfile {
+        delimiter: delim,
+        encoding,
+        sep_directive: directive.is_some(),
+        decimal_comma: delim == b';',
+        french_months: false,
+        spreadsheet: false,
+    };
+    let headers = split_header(header_line, delim);
+    // Control characters are dropped; duplicate or blank names would make
+    // Polars reject the schema
+    let (headers, _) = clean_headers(&headers);
 
     let fields: Vec<(PlSmallStr, DataType)> = headers
-        .into_iter()
+        .iter()
         .map(|name| (PlSmallStr::from_str(name), DataType::String))
         .collect();
 
+    let projection: Option<Vec<PlSmallStr>> = keep.map(|keep| {
+        fields
+            .iter()
+            .filter(|(name, _)| keep.contains(name.as_str()))
+            .map(|(name, _)| name.clone())
+            .collect()
+    });
+    let skipped = projection
+        .as_ref()
+        .map(|p| fields.len() - p.len())
+        .unwrap_or(0);
+
     let schema: Schema = fields.into_iter().collect();
     let schema_ref: SchemaRef = Arc::new(schema);
 
     let options = CsvReadOptions::default()
         .with_has_header(true)
         .with_schema(Some(schema_ref))
+        .with_columns(projection.map(|p| p.into()))
         .map_parse_options(move |po| {
             let po = po
                 .with_separator(delim)
                 .with_truncate_ragged_lines(true)
                 .with_null_values(Some(NullValues::AllColumnsSingle("".into())));
 
-            if delim == b';' {
+            if profile.decimal_comma {
                 po.with_decimal_comma(true)
             } else {
                 po
@@ -86,10 +501,11 @@ pub fn read_csv_normalized(path: &str) -> Result<(DataFrame, u8), String> {
         .finish()
         .map_err(|e| format!("Failed to read CSV '{}': {e}", path))?;
 
-    let df = translate_french_months(df)
+    let (df, french_months) = translate_french_months(df)
         .map_err(|e| format!("Failed to translate French dates in '{}': {e}", path))?;
+    profile.french_months = french_months;
 
-    Ok((df, delim))
+    Ok((df, profile, skipped))
 }
 
 /// French to english month pairs
@@ -125,9 +541,10 @@ fn strip_accents(s: &str) -> String {
     s.nfd().filter(|c| !unicode_normalization::char::is_combining_mark(*c)).collect()
 }
 
-// Translates months
-fn translate_french_months(df: DataFrame) -> Result<DataFrame, PolarsError> {
+// Translates months; also says whether the file used French month names
+fn translate_french_months(df: DataFrame) -> Result<(DataFrame, bool), PolarsError> {
     let mut df = df;
+    let mut any_translated = false;
     let col_names: Vec<String> = df.get_column_names().iter().map(|s| s.to_string()).collect();
 
     for col_name in &col_names {
@@ -139,14 +556,20 @@ fn translate_french_months(df: DataFrame) -> Result<DataFrame, PolarsError> {
         let str_col = col.str()?;
         let translated: Vec<Option<String>> = str_col
             .into_iter()
-            .map(|opt_val| opt_val.map(|val| translate_french_month_in_value(val)))
+            .map(|opt_val| {
+                opt_val.map(|val| {
+                    let new_val = translate_french_month_in_value(val);
+                    any_translated |= new_val != val;
+                    new_val
+                })
+            })
             .collect();
         let translated = StringChunked::from_iter(translated.into_iter());
 
         df.with_column(translated.into_series().with_name(PlSmallStr::from_str(col_name)))?;
     }
 
-    Ok(df)
+    Ok((df, any_translated))
 }
 
 fn translate_french_month_in_value(val: &str) -> String {
@@ -175,6 +598,43 @@ fn translate_french_month_in_value(val: &str) -> String {
     val.to_string()
 }
 
+/// Rows where every cell is empty, by 0-based index
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BlankRows {
+    // At the end of the sheet; always safe to drop
+    pub trailing: Vec<usize>,
+    // Between rows with data, e.g. from a broken paste
+    pub interior: Vec<usize>,
+}
+
+pub fn find_blank_rows(df: &DataFrame) -> PolarsResult<BlankRows> {
+    let mut blank = vec![true; df.height()];
+    for column in df.get_columns() {
+        let column = column.cast(&DataType::String)?;
+        for (idx, value) in column.str()?.into_iter().enumerate() {
+            if value.is_some_and(|v| !v.trim().is_empty()) {
+                blank[idx] = false;
+            }
+        }
+    }
+
+    let data_end = blank.iter().rposition(|b| !b).map(|i| i + 1).unwrap_or(0);
+    Ok(BlankRows {
+        trailing: (data_end..blank.len()).collect(),
+        interior: (0..data_end).filter(|&i| blank[i]).collect(),
+    })
+}
+
+/// `df` without the rows at `rows` (0-based)
+pub fn drop_rows(df: &DataFrame, rows: &[usize]) -> PolarsResult<DataFrame> {
+    if rows.is_empty() {
+        return Ok(df.clone());
+    }
+    let drop: HashSet<usize> = rows.iter().copied().collect();
+    let keep: BooleanChunked = (0..df.height()).map(|i| !drop.contains(&i)).collect();
+    df.filter(&keep)
+}
+
 #[derive(Debug)]
 pub enum SampleBarcodeStatus {
     // All rows have both sample and barcode filled
@@ -185,8 +645,9 @@ pub enum SampleBarcodeStatus {
     Incomplete { missing_rows: Vec<usize> },
 }
 
-// Checks the status of sample and barcode
-pub fn check_sample_barcode_status(df: &DataFrame) -> PolarsResult<SampleBarcodeStatus> {
+// Checks the status of sample and barcode. Runs without barcoding
+// (`require_barcode` false) only need the sample.
+pub fn check_sample_barcode_status(df: &DataFrame, require_barcode: bool) -> PolarsResult<SampleBarcodeStatus> {
 
     if df.height() == 0 {
         return Ok(SampleBarcodeStatus::Empty);
@@ -195,21 +656,26 @@ pub fn check_sample_barcode_status(df: &DataFrame) -> PolarsResult<SampleBarcode
     let has_sample_col = df.get_column_names().iter().any(|c| c.as_str() == "sample");
     let has_barcode_col = df.get_column_names().iter().any(|c| c.as_str() == "barcode");
 
-    if !has_sample_col || !has_barcode_col {
+    if !has_sample_col || (require_barcode && !has_barcode_col) {
         return Ok(SampleBarcodeStatus::Empty);
     }
 
     let sample_col = df.column("sample")?.str()?;
-    let barcode_col = df.column("barcode")?.str()?;
+    let barcode_col = if has_barcode_col { Some(df.column("barcode")?.str()?) } else { None };
 
     let mut has_any_data = false;
     let mut missing_rows: Vec<usize> = Vec::new();
 
-    for (idx, (sample, barcode)) in sample_col.into_iter().zip(barcode_col.into_iter()).enumerate() {
+    for (idx, sample) in sample_col.into_iter().enumerate() {
         let sample_empty = sample.map(|s| s.trim().is_empty()).unwrap_or(true);
-        let barcode_empty = barcode.map(|s| s.trim().is_empty()).unwrap_or(true);
+        let barcode_empty = barcode_col
+            .and_then(|c| c.get(idx))
+            .map(|s| s.trim().is_empty())
+            .unwrap_or(true);
+        // Without barcoding an empty barcode is neither data nor missing
+        let barcode_empty = require_barcode && barcode_empty;
 
-        if !sample_empty || !barcode_empty {
+        if !sample_empty || (require_barcode && !barcode_empty) {
             has_any_data = true;
         }
 
@@ -229,3 +695,513 @@ pub fn check_sample_barcode_status(df: &DataFrame) -> PolarsResult<SampleBarcode
         Ok(SampleBarcodeStatus::Incomplete { missing_rows })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    // Writes `bytes` to a scratch file and reads it like an input
+    fn read_bytes(bytes: &[u8]) -> Result<(DataFrame, FileProfile), String> {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("input.csv");
+        std::fs::write(&path, bytes).unwrap();
+        read_csv_normalized(&path.to_string_lossy())
+    }
+
+    fn read_text(content: &str) -> (DataFrame, FileProfile) {
+        read_bytes(content.as_bytes()).unwrap()
+    }
+
+    // xorshift64: the same "random" inputs on every run
+    struct Inputs(u64);
+
+    impl Inputs {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.below(items.len())]
+        }
+    }
+
+    const DELIMITERS: [u8; 3] = [b',', b';', b'\t'];
+    // Header names as they come out of Excel and EpiInfo exports; none has
+    // an unquoted delimiter
+    const HEADER_NAMES: &[&str] = &[
+        "sample",
+        "barcode",
+        "EPID",
+        "Date",
+        "Équipe",
+        "",
+        " padded ",
+        "\"Site, region\"",
+        "\"a;b\"",
+        "\"tab\tname\"",
+        "\"say \"\"hi\"\"\"",
+    ];
+    const VALUES: &[&str] =
+        &["S1", "", "barcode01", "12,5", "\"x, y\"", "15 janv. 2024", "2024-03-01", "Équipe A", "\"\"", " "];
+    const LINE_ENDINGS: &[&str] = &["\n", "\r\n", "\r"];
+
+    // A small CSV-ish file and how many columns its header has
+    fn generated_csv(inputs: &mut Inputs) -> (Vec<u8>, usize) {
+        let delim = DELIMITERS[inputs.below(3)] as char;
+        let eol = inputs.pick(LINE_ENDINGS);
+        let columns = 1 + inputs.below(6);
+
+        let mut text = String::new();
+        if inputs.below(5) == 0 {
+            text.push_str(&format!("sep={}{}", delim, eol));
+        }
+        let header: Vec<&str> = (0..columns).map(|_| inputs.pick(HEADER_NAMES)).collect();
+        text.push_str(&header.join(&delim.to_string()));
+        text.push_str(eol);
+        for _ in 0..inputs.below(6) {
+            // Short, long and empty rows
+            let fields: Vec<&str> = (0..inputs.below(columns + 2)).map(|_| inputs.pick(VALUES)).collect();
+            text.push_str(&fields.join(&delim.to_string()));
+            text.push_str(eol);
+        }
+
+        let bytes = match inputs.below(3) {
+            0 => text.into_bytes(),
+            1 => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+            // Windows-1252; every character above is in Latin-1
+            _ => text.chars().map(|c| c as u32 as u8).collect(),
+        };
+        (bytes, columns)
+    }
+
+    #[test]
+    fn quoted_header_names_keep_their_delimiter() {
+        assert_eq!(split_header("\"Site, region\",Sample", b','), vec!["\"Site, region\"", "Sample"]);
+        assert_eq!(split_header("a;\"b;c\";d", b';'), vec!["a", "\"b;c\"", "d"]);
+        assert_eq!(split_header("a,,b,", b','), vec!["a", "", "b", ""]);
+        assert_eq!(split_header("", b','), vec![""]);
+        // A quote left open runs to the end of the header
+        assert_eq!(split_header("a,\"b,c", b','), vec!["a", "\"b,c"]);
+    }
+
+    #[test]
+    fn quoted_header_name_is_one_column() {
+        let (df, profile) = read_text("\"Site, region\",Sample\nKano,S1\n");
+        assert_eq!(profile.delimiter, b',');
+        assert_eq!(df.width(), 2);
+        assert_eq!(df.column("Sample").unwrap().str().unwrap().get(0), Some("S1"));
+    }
+
+    #[test]
+    fn sniffer_ignores_delimiters_in_quoted_names() {
+        assert_eq!(delimiter_for_header("\"a,b,c,d\";x;y"), b';');
+        assert_eq!(delimiter_for_header("\"a;b;c\",x"), b',');
+        assert_eq!(delimiter_for_header("a\tb\tc"), b'\t');
+        // Ties and headers without a delimiter fall back to comma
+        assert_eq!(delimiter_for_header("a,b;c"), b',');
+        assert_eq!(delimiter_for_header("sample"), b',');
+        assert_eq!(delimiter_for_header(""), b',');
+    }
+
+    #[test]
+    fn sniffer_always_picks_one_of_three_delimiters() {
+        let mut inputs = Inputs(0x5eed_1234_abcd_0001);
+        let alphabet: Vec<char> = ",;\t\"| a\u{feff}é\r\n".chars().collect();
+        for _ in 0..2000 {
+            let header: String = (0..inputs.below(24)).map(|_| alphabet[inputs.below(alphabet.len())]).collect();
+            let delim = delimiter_for_header(&header);
+            assert!(DELIMITERS.contains(&delim), "{:?} gave {:?}", header, delim as char);
+            // Splitting never loses or invents text
+            assert_eq!(split_header(&header, delim).join(&(delim as char).to_string()), header);
+        }
+    }
+
+    #[test]
+    fn generated_files_read_cleanly_or_fail_cleanly() {
+        let mut inputs = Inputs(0x0dd_ba11_cafe_f00d);
+        for case in 0..300 {
+            let (bytes, columns) = generated_csv(&mut inputs);
+            if let Ok((df, profile)) = read_bytes(&bytes) {
+                assert_eq!(df.width(), columns, "case {}: {:?}", case, String::from_utf8_lossy(&bytes));
+                assert!(DELIMITERS.contains(&profile.delimiter));
+            }
+        }
+    }
+
+    #[test]
+    fn month_translation_is_idempotent() {
+        let values = [
+            "15 janv. 2024",
+            "1-févr-2024",
+            "03/mars/2024",
+            "2024 mai 12",
+            "15-JUIL-2024",
+            "1 août 2024 10-déc-x",
+            "a-b-c 1 mai 2",
+            "15 Jan 2024",
+            "S1",
+            "",
+        ];
+        for value in values {
+            let once = translate_french_month_in_value(value);
+            assert_eq!(translate_french_month_in_value(&once), once, "{}", value);
+        }
+        assert_eq!(translate_french_month_in_value("15 janv. 2024"), "15 Jan 2024");
+        assert_eq!(translate_french_month_in_value("1-févr-2024"), "1-Feb-2024");
+    }
+
+    #[test]
+    fn line_endings_become_lf() {
+        assert_eq!(normalize_line_endings("a,b\rc,d\r"), "a,b\nc,d\n");
+        assert_eq!(normalize_line_endings("a,b\r\nc,d\r\n"), "a,b\nc,d\n");
+        assert_eq!(normalize_line_endings("a\r\nb\rc\n"), "a\nb\nc\n");
+        assert_eq!(normalize_line_endings("a,b\nc,d\n"), "a,b\nc,d\n");
+    }
+
+    #[test]
+    fn cr_only_sheet_with_blank_rows_splits_trailing_from_interior() {
+        // Classic Mac line endings, one empty row between samples and 50
+        // empty rows Excel left at the end
+        let mut content = String::from("sample,barcode\rS1,barcode01\r,\rS2,barcode02\r");
+        content.push_str(&",\r".repeat(50));
+        let (df, _) = read_text(&content);
+        assert_eq!(df.height(), 53);
+
+        let blank = find_blank_rows(&df).unwrap();
+        assert_eq!(blank.interior, vec![1]);
+        assert_eq!(blank.trailing, (3..53).collect::<Vec<_>>());
+
+        let kept = drop_rows(&df, &[blank.interior, blank.trailing].concat()).unwrap();
+        assert_eq!(kept.height(), 2);
+        assert_eq!(kept.column("sample").unwrap().str().unwrap().get(1), Some("S2"));
+    }
+
+    #[test]
+    fn sheet_without_blank_rows_has_none() {
+        let (df, _) = read_text("sample,barcode\nS1,barcode01\nS2,barcode02\n");
+        assert_eq!(find_blank_rows(&df).unwrap(), BlankRows::default());
+    }
+
+    // A single-sample validation run: no barcode in the sheet
+    const UNBARCODED_SHEET: &str = "sample,barcode,SampleType\nVAL-01,,Validation\n";
+
+    #[test]
+    fn unbarcoded_sample_is_incomplete_when_barcodes_are_required() {
+        let (df, _) = read_text(UNBARCODED_SHEET);
+        assert!(matches!(
+            check_sample_barcode_status(&df, true).unwrap(),
+            SampleBarcodeStatus::Incomplete { missing_rows } if missing_rows == vec![1]
+        ));
+    }
+
+    #[test]
+    fn unbarcoded_sample_is_complete_without_barcoding() {
+        let (df, _) = read_text(UNBARCODED_SHEET);
+        assert!(matches!(check_sample_barcode_status(&df, false).unwrap(), SampleBarcodeStatus::Complete));
+    }
+
+    #[test]
+    fn without_barcoding_a_row_still_needs_its_sample() {
+        let (df, _) = read_text("sample,barcode\nVAL-01,\n,barcode02\n");
+        assert!(matches!(
+            check_sample_barcode_status(&df, false).unwrap(),
+            SampleBarcodeStatus::Incomplete { missing_rows } if missing_rows == vec![2]
+        ));
+        let (empty, _) = read_text("sample,barcode\n,\n");
+        assert!(matches!(check_sample_barcode_status(&empty, false).unwrap(), SampleBarcodeStatus::Empty));
+    }
+
+    #[test]
+    fn whitespace_only_rows_count_as_blank() {
+        let (df, _) = read_text("sample,barcode\nS1,barcode01\n  , \n");
+        let blank = find_blank_rows(&df).unwrap();
+        assert!(blank.interior.is_empty());
+        assert_eq!(blank.trailing, vec![1]);
+    }
+
+    // EpiInfo-like export: the columns a merge uses among `extra` unused ones
+    fn wide_export(extra: usize, rows: usize) -> String {
+        let mut header = vec!["ICLabID".to_string(), "EpidNumber".to_string(), "DateOfOnset".to_string()];
+        header.extend((0..extra).map(|i| format!("Unused{}", i)));
+        let mut text = header.join(",") + "\n";
+        for row in 0..rows {
+            let mut cells = vec![format!("S{:04}", row), format!("EPID-{}", row), "2024-03-01".to_string()];
+            cells.extend((0..extra).map(|i| format!("v{}x{}", row, i)));
+            text.push_str(&(cells.join(",") + "\n"));
+        }
+        text
+    }
+
+    // Benchmark-style: the wide fixture loads faster and smaller when
+    // projected, with the kept columns identical to a full read
+    #[test]
+    fn projected_read_matches_full_read_on_a_wide_export() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("epiinfo.csv");
+        std::fs::write(&path, wide_export(400, 300)).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let keep: HashSet<String> =
+            ["ICLabID", "EpidNumber", "DateOfOnset", "NotInFile"].iter().map(|s| s.to_string()).collect();
+
+        let started = std::time::Instant::now();
+        let (full, _) = read_csv_normalized(&path).unwrap();
+        let full_time = started.elapsed();
+        let started = std::time::Instant::now();
+        let (projected, _, skipped) = read_csv_projected(&path, Some(&keep)).unwrap();
+        let projected_time = started.elapsed();
+        eprintln!("full read {:?}, projected read {:?}", full_time, projected_time);
+
+        assert_eq!(skipped, 400);
+        let names: Vec<String> = projected.get_column_names().iter().map(|c| c.to_string()).collect();
+        assert_eq!(names, ["ICLabID", "EpidNumber", "DateOfOnset"]);
+        assert!(projected.equals_missing(&full.select(["ICLabID", "EpidNumber", "DateOfOnset"]).unwrap()));
+        assert!(projected.estimated_size() * 20 < full.estimated_size());
+    }
+
+    #[test]
+    fn no_projection_skips_nothing() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("epiinfo.csv");
+        std::fs::write(&path, wide_export(5, 2)).unwrap();
+        let (df, _, skipped) = read_csv_projected(&path.to_string_lossy(), None).unwrap();
+        assert_eq!((df.width(), skipped), (8, 0));
+    }
+
+    fn renamed(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn blank_and_repeated_headers_get_unique_names() {
+        let (names, renames) = dedupe_headers(&["ICLabID", "", "Comments", " ", "Comments", "Comments"]);
+        assert_eq!(names, ["ICLabID", "Unnamed_1", "Comments", "Unnamed_2", "Comments_2", "Comments_3"]);
+        assert_eq!(
+            renames,
+            renamed(&[("", "Unnamed_1"), (" ", "Unnamed_2"), ("Comments", "Comments_2"), ("Comments", "Comments_3")])
+        );
+
+        // Suffixes never take a name the file already has
+        let (names, _) = dedupe_headers(&["Comments", "Comments", "Comments_2", "", "Unnamed_1"]);
+        assert_eq!(names, ["Comments", "Comments_3", "Comments_2", "Unnamed_2", "Unnamed_1"]);
+    }
+
+    #[test]
+    fn file_with_blank_and_repeated_headers_reads() {
+        let (df, _) = read_text("ICLabID,,Comments,Comments,\nS01,x,first,second,y\n");
+        let columns = df.get_column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        assert_eq!(columns, ["ICLabID", "Unnamed_1", "Comments", "Comments_2", "Unnamed_2"]);
+        assert_eq!(df.column("Comments_2").unwrap().str().unwrap().get(0), Some("second"));
+    }
+
+    #[test]
+    fn repeated_required_column_still_resolves_by_name() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("samples.csv");
+        std::fs::write(&path, "sample,barcode,sample\nS01,barcode01,S01-old\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let (df, _) = read_csv_normalized(&path).unwrap();
+        assert_eq!(df.column("sample").unwrap().str().unwrap().get(0), Some("S01"));
+        assert_eq!(df.column("sample_2").unwrap().str().unwrap().get(0), Some("S01-old"));
+        assert_eq!(header_renames(&path).unwrap(), renamed(&[("sample", "sample_2")]));
+    }
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        let bom: &[u8] = if little_endian { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] };
+        let units = text.encode_utf16().flat_map(|u| if little_endian { u.to_le_bytes() } else { u.to_be_bytes() });
+        bom.iter().copied().chain(units).collect()
+    }
+
+    fn column_values(df: &DataFrame, column: &str) -> Vec<Option<String>> {
+        df.column(column).unwrap().str().unwrap().into_iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn sep_comma_line_wins_over_detection() {
+        // The header's semicolons would otherwise win the detection
+        let (df, profile) = read_text("sep=,\nsample,notes;remarks;flags\nS01,a;b;c\nS02,d\n");
+        assert_eq!(profile.delimiter, b',');
+        assert!(profile.sep_directive);
+        let columns = df.get_column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        assert_eq!(columns, ["sample", "notes;remarks;flags"]);
+        assert_eq!(column_values(&df, "notes;remarks;flags"), [Some("a;b;c".to_string()), Some("d".to_string())]);
+    }
+
+    #[test]
+    fn sep_semicolon_line_is_skipped() {
+        let (df, profile) = read_text("SEP=;\r\nsample;barcode;Ct\r\nS01;barcode01;12,5\r\n");
+        assert_eq!(profile.delimiter, b';');
+        assert!(profile.sep_directive);
+        assert_eq!(df.height(), 1);
+        assert_eq!(column_values(&df, "barcode"), [Some("barcode01".to_string())]);
+        assert_eq!(strip_sep_directive("sep=;\nsample;barcode\n"), (Some(b';'), "sample;barcode\n"));
+        assert_eq!(strip_sep_directive("sample;barcode\n"), (None, "sample;barcode\n"));
+    }
+
+    #[test]
+    fn utf16_files_are_transcoded() {
+        let text = "sample\tbarcode\tÉquipe\r\nS01\tbarcode01\tKinshasa\r\n";
+        for (little_endian, encoding) in [(true, TextEncoding::Utf16Le), (false, TextEncoding::Utf16Be)] {
+            let (df, profile) = read_bytes(&utf16(text, little_endian)).unwrap();
+            assert_eq!(profile.encoding, encoding);
+            assert_eq!(profile.delimiter, b'\t');
+            assert_eq!(column_values(&df, "Équipe"), [Some("Kinshasa".to_string())]);
+        }
+
+        // Excel's sep= line in a UTF-16 file
+        let (df, profile) = read_bytes(&utf16("sep=;\r\nsample;barcode\r\nS01;barcode01\r\n", true)).unwrap();
+        assert!(profile.sep_directive);
+        assert_eq!(column_values(&df, "sample"), [Some("S01".to_string())]);
+    }
+
+    #[test]
+    fn undecodable_utf16_asks_for_a_utf8_copy() {
+        // An unpaired surrogate
+        let mut bytes = utf16("sample,barcode\r\n", true);
+        bytes.extend_from_slice(&0xD800u16.to_le_bytes());
+        let err = read_bytes(&bytes).unwrap_err();
+        assert!(err.contains("looks like a UTF-16 file"), "{}", err);
+        assert!(err.contains("CSV UTF-8 (Comma delimited)"), "{}", err);
+    }
+
+    #[test]
+    fn each_file_gets_its_own_profile() {
+        let (df, profile) = read_text("sample;date\nS01;12 janv. 2024\n");
+        assert_eq!(
+            profile,
+            FileProfile {
+                delimiter: b';',
+                encoding: TextEncoding::Utf8,
+                sep_directive: false,
+                decimal_comma: true,
+                french_months: true,
+                spreadsheet: false,
+            }
+        );
+        assert_eq!(profile.describe(), "semicolon, UTF-8, decimal comma, French month names");
+        assert_eq!(column_values(&df, "date"), vec![Some("12 Jan 2024".to_string())]);
+
+        // Read straight after, a comma/1252 file is not given the first one's conventions
+        let (df, profile) = read_bytes(b"ICLabID,Province\nS01,\xC9quateur\n").unwrap();
+        assert_eq!(profile.describe(), "comma, Windows-1252");
+        assert!(!profile.decimal_comma && !profile.french_months);
+        assert_eq!(column_values(&df, "Province"), vec![Some("Équateur".to_string())]);
+    }
+
+    #[test]
+    fn windows_1252_covers_the_high_range() {
+        let (text, encoding) = decode_csv(b"a\x80\x92\xE9\x81", "x.csv").unwrap();
+        assert_eq!(encoding, TextEncoding::Windows1252);
+        assert_eq!(text, "a€’é\u{81}");
+
+        // Valid UTF-8 stays UTF-8; a BOM with broken UTF-8 is an error
+        assert_eq!(decode_csv("é".as_bytes(), "x.csv").unwrap(), ("é".to_string(), TextEncoding::Utf8));
+        assert!(decode_csv(b"\xEF\xBB\xBFa\xE9", "x.csv").unwrap_err().contains("not a valid UTF-8"));
+    }
+
+    #[test]
+    fn join_key_headers_are_matched_without_case() {
+        let (mut df, _) = read_text("Sample,BARCODE,epid,province,DATEOFONSET\nS01,barcode01,x,Kano,2024-02-01\n");
+        let renames = canonicalize_header_case(&mut df).unwrap();
+        assert_eq!(
+            renames,
+            [("Sample", "sample"), ("BARCODE", "barcode"), ("epid", "EPID")]
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+        );
+        // Every other column keeps its spelling
+        let columns = df.get_column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        assert_eq!(columns, ["sample", "barcode", "EPID", "province", "DATEOFONSET"]);
+        assert_eq!(canonical_column("Province"), None);
+        assert_eq!(canonical_column(" Barcode "), Some("barcode"));
+    }
+
+    #[test]
+    fn header_case_is_left_alone_when_ambiguous() {
+        // The canonical name is already there
+        let (mut df, _) = read_text("sample,Sample,barcode\nS01,S01b,barcode01\n");
+        assert!(canonicalize_header_case(&mut df).unwrap().is_empty());
+
+        // Two headers would take the same name
+        let (mut df, _) = read_text("SAMPLE,Sample,barcode\nS01,S01b,barcode01\n");
+        assert!(canonicalize_header_case(&mut df).unwrap().is_empty());
+        assert!(df.column("sample").is_err());
+    }
+
+    // Tabs and a line break inside quoted names, a bare tab, and columns
+    // named like Windows devices
+    const ODD_HEADERS: &str = "sample,\"Date\tCollected\",\"QC\ncomments\",Site\tName,CON,nul.txt\n\
+                               S01,2024-03-01,ok,Kano,x,y\n\
+                               S02,2024-03-02,,Lagos,,z\n";
+
+    fn column_names(df: &DataFrame) -> Vec<String> {
+        df.get_column_names().iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn control_characters_are_dropped_from_headers() {
+        let (df, profile) = read_text(ODD_HEADERS);
+        assert_eq!(profile.delimiter, b',');
+        assert_eq!(column_names(&df), ["sample", "Date Collected", "QC comments", "Site Name", "CON", "nul.txt"]);
+
+        // The header spanning two lines does not shift the data
+        assert_eq!(df.height(), 2);
+        assert_eq!(column_values(&df, "Site Name"), [Some("Kano".to_string()), Some("Lagos".to_string())]);
+        assert_eq!(column_values(&df, "QC comments"), [Some("ok".to_string()), None]);
+        assert_eq!(column_values(&df, "nul.txt"), [Some("y".to_string()), Some("z".to_string())]);
+
+        assert_eq!(sanitize_header(" \"Date \t\t Collected\r\" "), "Date Collected");
+        assert_eq!(sanitize_header("\"Say \"\"hi\"\"\""), "Say \"hi\"");
+        assert_eq!(sanitize_header("\t\n"), "");
+    }
+
+    #[test]
+    fn header_checks_see_the_cleaned_names() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("samples.csv");
+        std::fs::write(&path, ODD_HEADERS).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let columns = read_header_columns(&path).unwrap();
+        assert_eq!(columns, ["sample", "Date Collected", "QC comments", "Site Name", "CON", "nul.txt"]);
+        let reserved: Vec<&String> = columns.iter().filter(|c| crate::naming::is_windows_reserved(c)).collect();
+        assert_eq!(reserved, ["CON", "nul.txt"]);
+
+        // Every header that lost a control character is listed as it was
+        let renames = header_renames(&path).unwrap();
+        assert_eq!(
+            renames,
+            [
+                ("\"Date\tCollected\"".to_string(), "Date Collected".to_string()),
+                ("\"QC\ncomments\"".to_string(), "QC comments".to_string()),
+                ("Site\tName".to_string(), "Site Name".to_string()),
+            ]
+        );
+
+        // Cleaning can make two names the same; the second is numbered
+        let (names, renames) = clean_headers(&["Site Name", "Site\tName"]);
+        assert_eq!(names, ["Site Name", "Site Name_2"]);
+        assert_eq!(renames, [("Site\tName".to_string(), "Site Name_2".to_string())]);
+    }
+
+    #[test]
+    fn cleaned_headers_survive_the_csv_output() {
+        let (mut df, _) = read_text(ODD_HEADERS);
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("output.csv");
+        let mut file = std::fs::File::create(&path).unwrap();
+        CsvWriter::new(&mut file).finish(&mut df).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().next(), Some("sample,Date Collected,QC comments,Site Name,CON,nul.txt"));
+        let (back, _) = read_bytes(written.as_bytes()).unwrap();
+        assert!(back.equals_missing(&df));
+    }
+}
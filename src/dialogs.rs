@@ -0,0 +1,198 @@
+use rfd::FileDialog;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::minknow::SUPPORTED_REPORT_EXTENSIONS;
+
+// Always offered last so an oddly named file can still be picked
+const ALL_FILES: (&str, &[&str]) = ("All files", &["*"]);
+
+/// Everything needed to open one file dialog, resolved up front so the
+/// platform dialog only has to apply it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogSpec {
+    pub filters: Vec<(&'static str, Vec<&'static str>)>,
+    pub start_dir: Option<PathBuf>,
+    // Suggested name for save dialogs
+    pub file_name: Option<String>,
+}
+
+/// File filters for a dialog slot ("sample_file", "epiinfo_file",
+/// "minknow_file", "template", ...). Unknown slots get no filter.
+pub fn slot_filters(slot: &str) -> Vec<(&'static str, Vec<&'static str>)> {
+    let mut filters = match slot {
+        // Inputs are read from CSV or LibreOffice sheets; templates are saved as CSV
+        "sample_file" | "epiinfo_file" => vec![("CSV, ODS", vec!["csv", "ods"])],
+        "template" => vec![("CSV", vec!["csv"])],
+        "minknow_file" => vec![("MinKNOW report", SUPPORTED_REPORT_EXTENSIONS.to_vec())],
+        _ => Vec::new(),
+    };
+    if !filters.is_empty() {
+        filters.push((ALL_FILES.0, ALL_FILES.1.to_vec()));
+    }
+    filters
+}
+
+/// Last-used directory for the slot if it still exists, else `fallback`
+/// if that exists
+pub fn resolve_start_dir(
+    slot: &str,
+    last_dirs: &BTreeMap<String, String>,
+    fallback: Option<&Path>,
+    is_dir: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    last_dirs
+        .get(slot)
+        .map(PathBuf::from)
+        .filter(|d| is_dir(d))
+        .or_else(|| fallback.map(Path::to_path_buf).filter(|d| is_dir(d)))
+}
+
+pub fn dialog_spec(
+    slot: &str,
+    last_dirs: &BTreeMap<String, String>,
+    fallback: Option<&Path>,
+    file_name: Option<&str>,
+) -> DialogSpec {
+    DialogSpec {
+        filters: slot_filters(slot),
+        start_dir: resolve_start_dir(slot, last_dirs, fallback, Path::is_dir),
+        file_name: file_name.map(str::to_string),
+    }
+}
+
+/// Directory to remember after a pick: the folder itself for folder
+/// pickers, the parent for files
+pub fn remembered_dir(picked: &Path) -> Option<String> {
+    let dir = if picked.is_dir() { Some(picked) } else { picked.parent() };
+    dir.map(|d| d.to_string_lossy().to_string())
+}
+
+/// The platform dialog, behind a trait so the resolution logic can be
+/// exercised without opening windows
+pub trait FilePicker {
+    fn pick_file(&self, spec: &DialogSpec) -> Option<PathBuf>;
+    fn pick_folder(&self, spec: &DialogSpec) -> Option<PathBuf>;
+    fn save_file(&self, spec: &DialogSpec) -> Option<PathBuf>;
+}
+
+/// Native dialogs through rfd
+pub struct NativePicker;
+
+impl NativePicker {
+    fn dialog(spec: &DialogSpec) -> FileDialog {
+        let mut dialog = FileDialog::new();
+        for (name, extensions) in &spec.filters {
+            dialog = dialog.add_filter(*name, extensions);
+        }
+        if let Some(dir) = &spec.start_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = &spec.file_name {
+            dialog = dialog.set_file_name(name);
+        }
+        dialog
+    }
+}
+
+impl FilePicker for NativePicker {
+    fn pick_file(&self, spec: &DialogSpec) -> Option<PathBuf> {
+        Self::dialog(spec).pick_file()
+    }
+
+    fn pick_folder(&self, spec: &DialogSpec) -> Option<PathBuf> {
+        Self::dialog(spec).pick_folder()
+    }
+
+    fn save_file(&self, spec: &DialogSpec) -> Option<PathBuf> {
+        Self::dialog(spec).save_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use std::cell::RefCell;
+
+    fn last_dirs(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn each_slot_gets_its_filters_then_all_files() {
+        for slot in ["sample_file", "epiinfo_file"] {
+            assert_eq!(slot_filters(slot), [("CSV, ODS", vec!["csv", "ods"]), ("All files", vec!["*"])], "{}", slot);
+        }
+        assert_eq!(slot_filters("template"), [("CSV", vec!["csv"]), ("All files", vec!["*"])]);
+        assert_eq!(
+            slot_filters("minknow_file"),
+            [("MinKNOW report", SUPPORTED_REPORT_EXTENSIONS.to_vec()), ("All files", vec!["*"])]
+        );
+        assert!(slot_filters("destination").is_empty());
+    }
+
+    #[test]
+    fn start_dir_prefers_the_slot_last_dir_when_it_still_exists() {
+        let dirs = last_dirs(&[("sample_file", "/data/runs"), ("epiinfo_file", "/gone")]);
+        let exists = |d: &Path| d == Path::new("/data/runs") || d == Path::new("/data/out");
+        let fallback = Some(Path::new("/data/out"));
+
+        assert_eq!(resolve_start_dir("sample_file", &dirs, fallback, exists), Some(PathBuf::from("/data/runs")));
+        // A remembered folder that was removed falls back
+        assert_eq!(resolve_start_dir("epiinfo_file", &dirs, fallback, exists), Some(PathBuf::from("/data/out")));
+        assert_eq!(resolve_start_dir("minknow_file", &dirs, fallback, exists), Some(PathBuf::from("/data/out")));
+        assert_eq!(resolve_start_dir("minknow_file", &dirs, Some(Path::new("/gone")), exists), None);
+        assert_eq!(resolve_start_dir("minknow_file", &dirs, None, exists), None);
+    }
+
+    #[test]
+    fn remembered_dir_is_the_folder_of_a_file() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let file = dir.file("samples.csv");
+        std::fs::write(&file, "sample,barcode\n").unwrap();
+        let folder = dir.path().to_string_lossy().to_string();
+
+        assert_eq!(remembered_dir(&file), Some(folder.clone()));
+        assert_eq!(remembered_dir(dir.path()), Some(folder));
+    }
+
+    // Records what the dialog would have been opened with
+    struct FakePicker {
+        seen: RefCell<Vec<DialogSpec>>,
+        answer: Option<PathBuf>,
+    }
+
+    impl FilePicker for FakePicker {
+        fn pick_file(&self, spec: &DialogSpec) -> Option<PathBuf> {
+            self.seen.borrow_mut().push(spec.clone());
+            self.answer.clone()
+        }
+
+        fn pick_folder(&self, spec: &DialogSpec) -> Option<PathBuf> {
+            self.pick_file(spec)
+        }
+
+        fn save_file(&self, spec: &DialogSpec) -> Option<PathBuf> {
+            self.pick_file(spec)
+        }
+    }
+
+    #[test]
+    fn save_dialog_gets_the_suggested_name_and_start_dir() {
+        let dir = TempWorkspace::new().unwrap();
+        let picker = FakePicker {
+            seen: RefCell::new(Vec::new()),
+            answer: Some(dir.path().join("DDNS_template.csv")),
+        };
+        let folder = dir.path().to_string_lossy().to_string();
+        let dirs = last_dirs(&[("template", folder.as_str())]);
+
+        let picked = picker.save_file(&dialog_spec("template", &dirs, None, Some("DDNS_template.csv")));
+        assert_eq!(picked, Some(dir.path().join("DDNS_template.csv")));
+        let seen = picker.seen.borrow();
+        assert_eq!(seen[0].file_name.as_deref(), Some("DDNS_template.csv"));
+        assert_eq!(seen[0].start_dir.as_deref(), Some(dir.path()));
+        assert_eq!(seen[0].filters, slot_filters("template"));
+    }
+}
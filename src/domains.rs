@@ -0,0 +1,164 @@
+use polars::prelude::*;
+
+use crate::merge::validate_run_number;
+use crate::template::expected_columns_for_mode;
+use crate::warnings::ValidationWarning;
+
+/// What a column may contain. Empty cells always pass; required-ness is
+/// handled by cross-field rules.
+pub enum Domain {
+    // one of the listed values, compared case-insensitively
+    OneOf(&'static [&'static str]),
+    RunNumber,
+    WholeNumber,
+}
+
+impl Domain {
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            Domain::OneOf(values) => values.iter().any(|v| v.eq_ignore_ascii_case(value)),
+            Domain::RunNumber => validate_run_number(value).is_none(),
+            Domain::WholeNumber => value.parse::<u32>().is_ok(),
+        }
+    }
+
+    fn expected(&self) -> String {
+        match self {
+            Domain::OneOf(values) => values.join(" or "),
+            Domain::RunNumber => "YYYYMMDD_XXX".to_string(),
+            Domain::WholeNumber => "a whole number".to_string(),
+        }
+    }
+}
+
+const YES_NO: &[&str] = &["Yes", "No"];
+// "NA" is the template token for a control the run did not have
+const PASS_FAIL: &[&str] = &["Pass", "Fail", "NA"];
+
+// Column → domain. Only columns present in the mode's template are checked,
+// so both spellings of the minION negative control can live here.
+static DOMAINS: &[(&str, Domain)] = &[
+    ("IsQCRetest", Domain::OneOf(YES_NO)),
+    ("SameAliquot", Domain::OneOf(YES_NO)),
+    ("IfRetestOriginalRun", Domain::RunNumber),
+    ("RunNumber", Domain::RunNumber),
+    ("PositiveControlPCRCheck", Domain::OneOf(PASS_FAIL)),
+    ("NegativeControlPCRCheck", Domain::OneOf(PASS_FAIL)),
+    ("NegativeControlPCRheck", Domain::OneOf(PASS_FAIL)),
+    ("FlowCellPriorUses", Domain::WholeNumber),
+];
+
+/// Value domains that apply to a mode's template
+pub fn domains_for_mode(mode: &str) -> Vec<(&'static str, &'static Domain)> {
+    let expected = expected_columns_for_mode(mode);
+    DOMAINS
+        .iter()
+        .filter(|(column, _)| expected.contains(column))
+        .map(|(column, domain)| (*column, domain))
+        .collect()
+}
+
+fn cell(df: &DataFrame, column: &str, idx: usize) -> Option<String> {
+    df.column(column)
+        .ok()
+        .and_then(|c| c.str().ok().and_then(|s| s.get(idx).map(|v| v.trim().to_string())))
+        .filter(|v| !v.is_empty())
+}
+
+// Spreadsheet line of a data row (header is line 1)
+pub fn line_of(idx: usize) -> usize {
+    idx + 2
+}
+
+/// Checks every row against the mode's value domains and the retest
+/// linkage: a retest (IsQCRetest = Yes) must name its original run.
+pub fn check_value_domains(df: &DataFrame, mode: &str) -> Vec<ValidationWarning> {
+    let domains = domains_for_mode(mode);
+    let mut warnings = Vec::new();
+
+    for idx in 0..df.height() {
+        for (column, domain) in &domains {
+            if let Some(value) = cell(df, column, idx) {
+                if !domain.accepts(&value) {
+                    warnings.push(ValidationWarning::InvalidValue {
+                        line: line_of(idx),
+                        column: column.to_string(),
+                        value,
+                        expected: domain.expected(),
+                    });
+                }
+            }
+        }
+
+        let is_retest = cell(df, "IsQCRetest", idx).is_some_and(|v| v.eq_ignore_ascii_case("Yes"));
+        if is_retest && cell(df, "IfRetestOriginalRun", idx).is_none() {
+            warnings.push(ValidationWarning::RetestWithoutOriginalRun { line: line_of(idx) });
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(mode: &str) -> Vec<&'static str> {
+        domains_for_mode(mode).into_iter().map(|(column, _)| column).collect()
+    }
+
+    fn rows(is_retest: &[&str], original: &[&str], same_aliquot: &[&str]) -> DataFrame {
+        df!(
+            "sample" => (1..=is_retest.len()).map(|i| format!("S{:02}", i)).collect::<Vec<_>>(),
+            "IsQCRetest" => is_retest,
+            "IfRetestOriginalRun" => original,
+            "SameAliquot" => same_aliquot
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn domains_follow_the_mode_template() {
+        let ddns = columns("DDNS");
+        assert!(ddns.contains(&"SameAliquot") && ddns.contains(&"NegativeControlPCRCheck"));
+        assert!(!ddns.contains(&"NegativeControlPCRheck"));
+
+        let minion = columns("minION");
+        assert!(minion.contains(&"IsQCRetest") && minion.contains(&"NegativeControlPCRheck"));
+        assert!(!minion.contains(&"SameAliquot"));
+    }
+
+    #[test]
+    fn yes_no_columns_accept_only_yes_or_no() {
+        let df = rows(&["yes", "NO", "Y", ""], &["20240101_001", "", "", ""], &["No", "Yes", "", "maybe"]);
+        let warnings = check_value_domains(&df, "DDNS");
+        let found: Vec<(usize, &str, &str)> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidValue { line, column, value, .. } => Some((*line, column.as_str(), value.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(found, [(4, "IsQCRetest", "Y"), (5, "SameAliquot", "maybe")]);
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(&warnings[0], ValidationWarning::InvalidValue { expected, .. } if expected == "Yes or No"));
+    }
+
+    #[test]
+    fn retest_needs_a_well_formed_original_run() {
+        let df = rows(
+            &["Yes", "Yes", "Yes", "No", ""],
+            &["20240101_001", "", "2024-01-01 run 1", "", ""],
+            &["", "", "", "", ""],
+        );
+        let warnings = check_value_domains(&df, "DDNS");
+        assert_eq!(warnings.len(), 2);
+        // Line 3: a retest naming no run
+        assert!(matches!(warnings[0], ValidationWarning::RetestWithoutOriginalRun { line: 3 }));
+        // Line 4: a run number that is not YYYYMMDD_XXX
+        assert!(matches!(
+            &warnings[1],
+            ValidationWarning::InvalidValue { line: 4, column, expected, .. } if column == "IfRetestOriginalRun" && expected == "YYYYMMDD_XXX"
+        ));
+    }
+}
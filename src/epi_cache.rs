@@ -0,0 +1,139 @@
+use polars::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+use crate::csv::{read_csv_projected, FileProfile};
+use crate::input::read_input_bytes;
+
+/// Identifies one version of an EpiInfo export read with one projection
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheKey {
+    path: String,
+    size: u64,
+    modified: Option<SystemTime>,
+    hash: u64,
+    // sorted projected columns, None when every column was loaded
+    columns: Option<Vec<String>>,
+}
+
+impl CacheKey {
+    fn for_file(path: &str, keep: Option<&HashSet<String>>) -> Result<Self, String> {
+        let meta = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read file info for '{}': {e}", path))?;
+        let bytes = read_input_bytes(path).map_err(|e| format!("Failed to read file '{}': {e}", path))?;
+
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+
+        let columns = keep.map(|keep| {
+            let mut cols: Vec<String> = keep.iter().cloned().collect();
+            cols.sort();
+            cols
+        });
+
+        Ok(Self {
+            path: path.to_string(),
+            size: meta.len(),
+            modified: meta.modified().ok(),
+            hash: hasher.finish(),
+            columns,
+        })
+    }
+}
+
+/// Session cache of the last parsed EpiInfo export. Hub labs merge many
+/// runs against the same export, so re-parsing it each time is wasted work.
+#[derive(Default)]
+pub struct EpiInfoCache {
+    entry: Option<(CacheKey, DataFrame, FileProfile, usize)>,
+}
+
+impl EpiInfoCache {
+    /// Same contract as `read_csv_projected`, plus whether the cache was used.
+    /// A changed size, mtime or content invalidates the entry.
+    pub fn read(
+        &mut self,
+        path: &str,
+        keep: Option<&HashSet<String>>,
+    ) -> Result<(DataFrame, FileProfile, usize, bool), String> {
+        let key = CacheKey::for_file(path, keep)?;
+
+        if let Some((cached_key, df, profile, skipped)) = &self.entry {
+            if *cached_key == key {
+                return Ok((df.clone(), *profile, *skipped, true));
+            }
+        }
+
+        let (df, profile, skipped) = read_csv_projected(path, keep)?;
+        self.entry = Some((key, df.clone(), profile, skipped));
+        Ok((df, profile, skipped, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use std::time::Duration;
+
+    const EXPORT: &str = "ICLabID,EpidNumber,Province\nS01,COD-KIN-24-001,Kinshasa\nS02,COD-KIN-24-002,Kinshasa\n";
+
+    fn export(dir: &mut TempWorkspace) -> String {
+        let path = dir.file("epi.csv");
+        std::fs::write(&path, EXPORT).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn second_read_of_the_same_export_uses_the_cache() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = export(&mut dir);
+        let mut cache = EpiInfoCache::default();
+
+        let (first, _, _, cached) = cache.read(&path, None).unwrap();
+        assert!(!cached);
+        let (second, _, _, cached) = cache.read(&path, None).unwrap();
+        assert!(cached);
+        assert!(first.equals_missing(&second));
+    }
+
+    #[test]
+    fn touching_the_export_invalidates_the_cache() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = export(&mut dir);
+        let mut cache = EpiInfoCache::default();
+        cache.read(&path, None).unwrap();
+
+        // Same content, newer modification time
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        drop(file);
+        let (_, _, _, cached) = cache.read(&path, None).unwrap();
+        assert!(!cached);
+
+        // New content is read, not served from the cache
+        std::fs::write(&path, EXPORT.replace("Kinshasa", "Kongo Central")).unwrap();
+        let (df, _, _, cached) = cache.read(&path, None).unwrap();
+        assert!(!cached);
+        let province = df.column("Province").unwrap().str().unwrap().get(0).map(|v| v.to_string());
+        assert_eq!(province.as_deref(), Some("Kongo Central"));
+    }
+
+    #[test]
+    fn a_different_projection_is_a_cache_miss() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = export(&mut dir);
+        let mut cache = EpiInfoCache::default();
+        let keep: HashSet<String> = ["ICLabID".to_string(), "EpidNumber".to_string()].into_iter().collect();
+
+        cache.read(&path, None).unwrap();
+        let (df, _, skipped, cached) = cache.read(&path, Some(&keep)).unwrap();
+        assert!(!cached);
+        assert_eq!(skipped, 1);
+        assert_eq!(df.width(), 2);
+        let (_, _, _, cached) = cache.read(&path, Some(&keep)).unwrap();
+        assert!(cached);
+    }
+}
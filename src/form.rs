@@ -0,0 +1,99 @@
+/// Text fields of the main window reset by Clear, in UI order. File slots
+/// come last so a slot and the values read from it go together.
+pub const FORM_FIELDS: &[&str] = &[
+    // general
+    "lab", "run_num", "pir_ver", "minknow_ver",
+    // dates/seq
+    "rt_date", "vp1_date", "seq_date", "seq_kit", "seq_hours", "fc_id", "fc_pores",
+    "basecaller", "basecall_model", "sequencer_host", "sequencer_position", "fc_uses", "fasta_date",
+    // PCR extras
+    "rtpcr_primers", "pcr_machine", "vp1_pcr_machine", "vp1_primers",
+    // files
+    "minknow_file", "sample_file", "epiinfo_file", "destination",
+];
+
+/// File slots that have their own ✕ button
+pub const FILE_SLOTS: &[&str] = &["sample_file", "minknow_file", "epiinfo_file", "destination"];
+
+// Clear asks first when more fields than this are filled in
+pub const CLEAR_CONFIRM_ABOVE: usize = 3;
+
+/// Fields filled from the file in `slot`, which no longer apply once the
+/// slot is cleared
+pub fn derived_fields(slot: &str) -> &'static [&'static str] {
+    match slot {
+        // What "Extract" and the merge copy out of the MinKNOW report
+        "minknow_file" => &[
+            "minknow_ver", "fc_id", "seq_kit", "seq_hours", "seq_date", "fc_pores",
+            "basecaller", "basecall_model", "sequencer_host", "sequencer_position",
+        ],
+        _ => &[],
+    }
+}
+
+/// Every field clearing `slot` resets: the slot itself, then what was read
+/// from it. Empty for anything that is not a file slot.
+pub fn slot_reset(slot: &str) -> Vec<&'static str> {
+    match FILE_SLOTS.iter().find(|s| **s == slot) {
+        Some(slot) => std::iter::once(*slot).chain(derived_fields(slot).iter().copied()).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Whether Clear should ask before wiping `values` (one per field)
+pub fn clear_needs_confirmation(values: &[String]) -> bool {
+    values.iter().filter(|v| !v.trim().is_empty()).count() > CLEAR_CONFIRM_ABOVE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(n: usize) -> Vec<String> {
+        FORM_FIELDS.iter().enumerate().map(|(i, _)| if i < n { "x".to_string() } else { String::new() }).collect()
+    }
+
+    #[test]
+    fn clearing_the_report_resets_what_was_read_from_it() {
+        let reset = slot_reset("minknow_file");
+        assert_eq!(reset[0], "minknow_file");
+        for field in ["minknow_ver", "fc_id", "seq_kit", "seq_hours", "seq_date", "fc_pores", "basecaller", "basecall_model"] {
+            assert!(reset.contains(&field), "{}", field);
+        }
+        // Typed-in run constants are not the report's
+        for field in ["lab", "run_num", "fc_uses", "rt_date", "sample_file"] {
+            assert!(!reset.contains(&field), "{}", field);
+        }
+    }
+
+    #[test]
+    fn other_slots_reset_only_themselves() {
+        for slot in ["sample_file", "epiinfo_file", "destination"] {
+            assert_eq!(slot_reset(slot), vec![slot]);
+        }
+        // Not a file slot: nothing to reset
+        assert!(slot_reset("run_num").is_empty());
+        assert!(slot_reset("unknown").is_empty());
+    }
+
+    #[test]
+    fn every_reset_field_is_a_form_field() {
+        for slot in FILE_SLOTS {
+            for field in slot_reset(slot) {
+                assert!(FORM_FIELDS.contains(&field), "{} {}", slot, field);
+            }
+        }
+    }
+
+    #[test]
+    fn clear_asks_only_when_the_form_is_mostly_filled() {
+        assert!(!clear_needs_confirmation(&filled(0)));
+        assert!(!clear_needs_confirmation(&filled(CLEAR_CONFIRM_ABOVE)));
+        assert!(clear_needs_confirmation(&filled(CLEAR_CONFIRM_ABOVE + 1)));
+
+        // Whitespace does not count as filled
+        let mut values = filled(CLEAR_CONFIRM_ABOVE);
+        values.push("   ".to_string());
+        assert!(!clear_needs_confirmation(&values));
+    }
+}
@@ -1,9 +1,114 @@
 use slint::{ComponentHandle, SharedString};
+use std::cell::RefCell;
+use std::rc::Rc;
 
-use crate::AppWindow;
+use super::quick_check::refresh_slot_check;
+use crate::form::{clear_needs_confirmation, slot_reset, FORM_FIELDS};
+use crate::report_fields::{ReportFields, REPORT_FIELDS};
+use crate::{set_report_hint, AppWindow};
 
-pub fn setup_clear_handler(ui: &AppWindow) {
+fn field_value(ui: &AppWindow, field: &str) -> SharedString {
+    match field {
+        "lab" => ui.get_lab(),
+        "run_num" => ui.get_run_num(),
+        "pir_ver" => ui.get_pir_ver(),
+        "minknow_ver" => ui.get_minknow_ver(),
+        "rt_date" => ui.get_rt_date(),
+        "vp1_date" => ui.get_vp1_date(),
+        "seq_date" => ui.get_seq_date(),
+        "seq_kit" => ui.get_seq_kit(),
+        "seq_hours" => ui.get_seq_hours(),
+        "fc_id" => ui.get_fc_id(),
+        "fc_pores" => ui.get_fc_pores(),
+        "basecaller" => ui.get_basecaller(),
+        "basecall_model" => ui.get_basecall_model(),
+        "sequencer_host" => ui.get_sequencer_host(),
+        "sequencer_position" => ui.get_sequencer_position(),
+        "fc_uses" => ui.get_fc_uses(),
+        "fasta_date" => ui.get_fasta_date(),
+        "rtpcr_primers" => ui.get_rtpcr_primers(),
+        "pcr_machine" => ui.get_pcr_machine(),
+        "vp1_pcr_machine" => ui.get_vp1_pcr_machine(),
+        "vp1_primers" => ui.get_vp1_primers(),
+        "minknow_file" => ui.get_minknow_file(),
+        "sample_file" => ui.get_sample_file(),
+        "epiinfo_file" => ui.get_epiinfo_file(),
+        "destination" => ui.get_destination(),
+        _ => SharedString::new(),
+    }
+}
+
+fn reset_field(ui: &AppWindow, field: &str) {
+    let empty = SharedString::from("");
+    match field {
+        "lab" => ui.set_lab(empty),
+        "run_num" => ui.set_run_num(empty),
+        "pir_ver" => ui.set_pir_ver(empty),
+        "minknow_ver" => ui.set_minknow_ver(empty),
+        "rt_date" => ui.set_rt_date(empty),
+        "vp1_date" => ui.set_vp1_date(empty),
+        "seq_date" => ui.set_seq_date(empty),
+        "seq_kit" => ui.set_seq_kit(empty),
+        "seq_hours" => ui.set_seq_hours(empty),
+        "fc_id" => ui.set_fc_id(empty),
+        "fc_pores" => ui.set_fc_pores(empty),
+        "basecaller" => ui.set_basecaller(empty),
+        "basecall_model" => ui.set_basecall_model(empty),
+        "sequencer_host" => ui.set_sequencer_host(empty),
+        "sequencer_position" => ui.set_sequencer_position(empty),
+        "fc_uses" => ui.set_fc_uses(empty),
+        "fasta_date" => ui.set_fasta_date(empty),
+        "rtpcr_primers" => ui.set_rtpcr_primers(empty),
+        "pcr_machine" => ui.set_pcr_machine(empty),
+        "vp1_pcr_machine" => ui.set_vp1_pcr_machine(empty),
+        "vp1_primers" => ui.set_vp1_primers(empty),
+        "minknow_file" => {
+            ui.set_minknow_file(empty);
+            // A date choice made against the old report no longer applies
+            ui.set_seq_date_decision("".into());
+            ui.set_report_position("".into());
+            // Values held back from the old report
+            ui.set_minknow_ver_hint("".into());
+            ui.set_fc_id_hint("".into());
+            ui.set_fc_pores_hint("".into());
+            ui.set_seq_kit_hint("".into());
+            ui.set_seq_hours_hint("".into());
+            ui.set_seq_date_hint("".into());
+        }
+        "sample_file" => {
+            ui.set_sample_file(empty);
+            ui.set_column_review_confirmed(false);
+        }
+        "epiinfo_file" => {
+            ui.set_epiinfo_file(empty);
+            ui.set_epi_review_confirmed(false);
+            ui.set_low_match_confirmed(false);
+        }
+        "destination" => ui.set_destination(empty),
+        _ => eprintln!("Unknown field: {}", field),
+    }
+    // An emptied file slot loses its status mark
+    refresh_slot_check(ui, field);
+}
+
+fn clear_all(ui: &AppWindow) {
+    for field in FORM_FIELDS {
+        reset_field(ui, field);
+    }
+    ui.set_no_minknow_report(false);
+}
+
+// Cleared fields count as untouched again, with no report hints left over
+fn forget_report_fields(ui: &AppWindow, report_fields: &RefCell<ReportFields>) {
+    report_fields.borrow_mut().reset();
+    for &field in REPORT_FIELDS {
+        set_report_hint(ui, field, "");
+    }
+}
+
+pub fn setup_clear_handler(ui: &AppWindow, report_fields: Rc<RefCell<ReportFields>>) {
     let ui_handle = ui.as_weak();
+    let fields = report_fields.clone();
 
     ui.on_clear(move || {
         let ui = match ui_handle.upgrade() {
@@ -14,35 +119,41 @@ pub fn setup_clear_handler(ui: &AppWindow) {
             }
         };
 
-        let empty = SharedString::from("");
-
-        // general
-        ui.set_lab(empty.clone());
-        ui.set_run_num(empty.clone());
-        ui.set_pir_ver(empty.clone());
-        ui.set_minknow_ver(empty.clone());
-
-        // dates/seq
-        ui.set_rt_date(empty.clone());
-        ui.set_vp1_date(empty.clone());
-        ui.set_seq_date(empty.clone());
-        ui.set_seq_kit(empty.clone());
-        ui.set_seq_hours(empty.clone());
-        ui.set_fc_id(empty.clone());
-        ui.set_fc_pores(empty.clone());
-        ui.set_fc_uses(empty.clone());
-        ui.set_fasta_date(empty.clone());
-
-        // PCR extras
-        ui.set_rtpcr_primers(empty.clone());
-        ui.set_pcr_machine(empty.clone());
-        ui.set_vp1_pcr_machine(empty.clone());
-        ui.set_vp1_primers(empty.clone());
-
-        // files
-        ui.set_minknow_file(empty.clone());
-        ui.set_sample_file(empty.clone());
-        ui.set_epiinfo_file(empty.clone());
-        ui.set_destination(empty.clone());
+        // Ask before wiping a mostly filled-in form
+        let values: Vec<String> = FORM_FIELDS.iter().map(|f| field_value(&ui, f).to_string()).collect();
+        if clear_needs_confirmation(&values) {
+            let fr = ui.get_is_french();
+            ui.set_info_title(if fr { "Effacer tous les champs ?" } else { "Clear All Fields?" }.into());
+            ui.set_info_message(if fr {
+                "Tous les champs et fichiers sélectionnés seront effacés. Pour retirer un seul fichier, utilisez le bouton ✕ à côté.".into()
+            } else {
+                "Every field and selected file will be cleared. To remove a single file, use the ✕ button next to it.".into()
+            });
+            ui.set_info_action("clear".into());
+            ui.set_show_info(1.0);
+            return;
+        }
+        clear_all(&ui);
+        forget_report_fields(&ui, &fields);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_clear_confirmed(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            clear_all(&ui);
+            forget_report_fields(&ui, &report_fields);
+        }
+    });
+
+    // ✕ next to a file: only that slot and what was read from it
+    let ui_handle = ui.as_weak();
+    ui.on_clear_slot(move |slot| {
+        if let Some(ui) = ui_handle.upgrade() {
+            for field in slot_reset(slot.as_str()) {
+                reset_field(&ui, field);
+            }
+        }
     });
 }
@@ -0,0 +1,138 @@
+use polars::prelude::*;
+use rfd::FileDialog;
+use slint::ComponentHandle;
+use std::path::Path;
+
+use crate::corrections::apply_corrections;
+use crate::csv::read_csv_normalized;
+use crate::output::backup_existing;
+use crate::AppWindow;
+
+// Changes listed in the success dialog before summarising the rest
+const MAX_CHANGES_SHOWN: usize = 30;
+
+pub fn setup_corrections_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+
+    ui.on_apply_corrections(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+
+            let mut output_dialog = FileDialog::new()
+                .set_title(if fr { "Sélectionner la sortie à corriger" } else { "Select the output to correct" })
+                .add_filter("CSV", &["csv"]);
+            if !ui.get_destination().is_empty() {
+                output_dialog = output_dialog.set_directory(ui.get_destination().as_str());
+            }
+            let Some(output_path) = output_dialog.pick_file() else {
+                return;
+            };
+            let Some(corrections_path) = FileDialog::new()
+                .set_title(if fr { "Sélectionner le fichier de corrections" } else { "Select the corrections file" })
+                .add_filter("CSV", &["csv"])
+                .pick_file()
+            else {
+                return;
+            };
+
+            let output_str = output_path.to_string_lossy().to_string();
+            let corrections_str = corrections_path.to_string_lossy().to_string();
+
+            let (output_df, delim) = match read_csv_normalized(&output_str) {
+                Ok((df, profile)) => (df, profile.delimiter),
+                Err(msg) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(msg.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            let (corrections_df, _) = match read_csv_normalized(&corrections_str) {
+                Ok(v) => v,
+                Err(msg) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(msg.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            let mut result = match apply_corrections(output_df, &corrections_df) {
+                Ok(r) => r,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Corrections refusées" } else { "Corrections Rejected" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            if result.changes.is_empty() {
+                ui.set_info_title(if fr { "Aucune modification" } else { "No Changes" }.into());
+                ui.set_info_message(if fr {
+                    "Les corrections correspondent déjà à la sortie ; rien n'a été écrit."
+                } else {
+                    "The corrections already match the output; nothing was written."
+                }.into());
+                ui.set_show_info(1.0);
+                return;
+            }
+
+            let backup = match backup_existing(Path::new(&output_str)) {
+                Ok(b) => b,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de sauvegarde" } else { "Backup Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            let write_result = std::fs::File::create(&output_path)
+                .map_err(|e| format!("{e}"))
+                .and_then(|mut file| {
+                    CsvWriter::new(&mut file)
+                        .with_separator(delim)
+                        .finish(&mut result.df)
+                        .map_err(|e| format!("{e}"))
+                });
+            if let Err(e) = write_result {
+                ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
+                ui.set_error_message((if fr {
+                    format!("Échec de l'écriture de '{}' : {}", output_str, e)
+                } else {
+                    format!("Failed to write '{}': {}", output_str, e)
+                }).into());
+                ui.set_show_error(1.0);
+                return;
+            }
+
+            let mut lines: Vec<String> = result
+                .changes
+                .iter()
+                .take(MAX_CHANGES_SHOWN)
+                .map(|c| format!("{} · {}: '{}' → '{}'", c.key, c.column, c.old, c.new))
+                .collect();
+            if result.changes.len() > MAX_CHANGES_SHOWN {
+                lines.push(format!("… (+{})", result.changes.len() - MAX_CHANGES_SHOWN));
+            }
+            let backup_text = backup
+                .map(|b| {
+                    if fr {
+                        format!("\n\nSauvegarde : {}", b.display())
+                    } else {
+                        format!("\n\nBackup: {}", b.display())
+                    }
+                })
+                .unwrap_or_default();
+
+            ui.set_info_title(if fr { "Corrections appliquées" } else { "Corrections Applied" }.into());
+            ui.set_info_message((if fr {
+                format!("{} cellule(s) modifiée(s) :\n{}{}", result.changes.len(), lines.join("\n"), backup_text)
+            } else {
+                format!("{} cell(s) changed:\n{}{}", result.changes.len(), lines.join("\n"), backup_text)
+            }).into());
+            ui.set_show_info(1.0);
+        }
+    });
+}
@@ -1,27 +1,112 @@
-use rfd::FileDialog;
 use slint::{ComponentHandle, SharedString};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
 
+use super::quick_check::refresh_slot_check;
+use crate::dialogs::{dialog_spec, remembered_dir, FilePicker, NativePicker};
+use crate::input::normalize_pasted_path;
+use crate::settings::{update_settings, Settings};
+use crate::staleness::stale_file_age;
+use crate::warnings::{Lang, ValidationWarning};
 use crate::AppWindow;
 
-pub fn setup_file_handlers(ui: &AppWindow) {
+pub fn setup_file_handlers(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    // Paths typed or pasted into a file field (often quoted, or a file://
+    // link from Outlook or Teams) are cleaned up as they arrive
+    let ui_handle = ui.as_weak();
+    ui.on_path_edited(move |slot: SharedString, text: SharedString| {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let path = normalize_pasted_path(&text);
+        let changed = path != text.as_str();
+        match slot.as_str() {
+            "sample_file" => {
+                if changed {
+                    ui.set_sample_file(path.into());
+                }
+                ui.set_column_review_confirmed(false);
+            }
+            "minknow_file" => {
+                if changed {
+                    ui.set_minknow_file(path.into());
+                }
+                ui.set_report_position("".into());
+            }
+            "epiinfo_file" => {
+                if changed {
+                    ui.set_epiinfo_file(path.into());
+                }
+            }
+            _ => return,
+        }
+        refresh_slot_check(&ui, slot.as_str());
+    });
+
     let ui_handle = ui.as_weak();
 
     ui.on_select_file(move |file_type: SharedString| {
+        // Filters for the slot, starting in the folder it was last used with
+        let spec = {
+            let settings = settings.borrow();
+            let destination = ui_handle.upgrade().map(|ui| ui.get_destination().to_string()).unwrap_or_default();
+            let fallback = (!destination.is_empty()).then(|| Path::new(&destination).to_path_buf());
+            dialog_spec(file_type.as_str(), &settings.last_dirs, fallback.as_deref(), None)
+        };
+        let remember = |picked: &Path| {
+            if let Some(dir) = remembered_dir(picked) {
+                let slot = file_type.to_string();
+                match update_settings(|s| {
+                    s.last_dirs.insert(slot.clone(), dir.clone());
+                }) {
+                    Ok(saved) => *settings.borrow_mut() = saved,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        settings.borrow_mut().last_dirs.insert(slot, dir);
+                    }
+                }
+            }
+        };
+
         match file_type.as_str() {
             "sample_file" | "epiinfo_file" | "minknow_file" => {
-                if let Some(file_path) = FileDialog::new().pick_file() {
+                if let Some(file_path) = NativePicker.pick_file(&spec) {
+                    remember(&file_path);
                     let path_str = file_path.to_string_lossy().to_string();
                     if let Some(ui) = ui_handle.upgrade() {
                         match file_type.as_str() {
-                            "sample_file" => ui.set_sample_file(SharedString::from(path_str)),
-                            "minknow_file" => ui.set_minknow_file(SharedString::from(path_str)),
-                            _ => ui.set_epiinfo_file(SharedString::from(path_str)),
+                            "sample_file" => {
+                                ui.set_sample_file(SharedString::from(path_str));
+                                // Column renames were reviewed for the previous file
+                                ui.set_column_review_confirmed(false);
+                            }
+                            "minknow_file" => {
+                                ui.set_minknow_file(SharedString::from(path_str));
+                                // A position picked in the previous report does not apply
+                                ui.set_report_position("".into());
+                            }
+                            _ => {
+                                // Non-blocking heads-up; the merge repeats it in the summary
+                                let threshold = settings.borrow().epiinfo_stale_days;
+                                if let Some(age_days) = stale_file_age(&file_path, threshold) {
+                                    let fr = ui.get_is_french();
+                                    ui.set_info_title(if fr { "Export Epi Info ancien" } else { "Old Epi Info Export" }.into());
+                                    ui.set_info_message(
+                                        ValidationWarning::StaleEpiInfo { age_days }
+                                            .render(Lang::from_french(fr))
+                                            .into(),
+                                    );
+                                    ui.set_show_info(1.0);
+                                }
+                                ui.set_epiinfo_file(SharedString::from(path_str));
+                            }
                         }
+                        refresh_slot_check(&ui, file_type.as_str());
                     }
                 }
             }
             "destination" => {
-                if let Some(dir_path) = FileDialog::new().pick_folder() {
+                if let Some(dir_path) = NativePicker.pick_folder(&spec) {
+                    remember(&dir_path);
                     let path_str = dir_path.to_string_lossy().to_string();
                     if let Some(ui) = ui_handle.upgrade() {
                         ui.set_destination(SharedString::from(path_str));
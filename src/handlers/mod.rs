@@ -1,7 +1,11 @@
 mod file;
 mod clear;
 mod plate_map;
+mod corrections;
+mod quick_check;
 
 pub use file::setup_file_handlers;
 pub use clear::setup_clear_handler;
+pub use corrections::setup_corrections_handler;
+pub use quick_check::refresh_slot_check;
 pub use plate_map::{setup_plate_map_handlers, setup_standalone_plate_map_handler};
@@ -141,7 +141,7 @@ pub fn setup_plate_map_handlers(
 
                         // Read the sample CSV
                         let (df, delim) = match read_csv_normalized(&pm.piranha_path) {
-                            Ok((df, delim)) => (df, delim),
+                            Ok((df, profile)) => (df, profile.delimiter),
                             Err(e) => {
                                 ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
                                 ui.set_error_message(e.into());
@@ -0,0 +1,71 @@
+use slint::{ComponentHandle, SharedString};
+
+use crate::quick_check::{check_path_exists, quick_check};
+use crate::warnings::Lang;
+use crate::{AppWindow, SlotStatus};
+
+fn slot_path(ui: &AppWindow, slot: &str) -> SharedString {
+    match slot {
+        "sample_file" => ui.get_sample_file(),
+        "epiinfo_file" => ui.get_epiinfo_file(),
+        "minknow_file" => ui.get_minknow_file(),
+        _ => SharedString::new(),
+    }
+}
+
+fn set_slot_status(ui: &AppWindow, slot: &str, status: SlotStatus) {
+    match slot {
+        "sample_file" => ui.set_sample_status(status),
+        "epiinfo_file" => ui.set_epiinfo_status(status),
+        "minknow_file" => ui.set_minknow_status(status),
+        _ => {}
+    }
+}
+
+/// Re-checks a file slot on a worker thread and shows the result next to
+/// it. A result for a file that is no longer selected is dropped. The
+/// full validation still runs at merge time.
+pub fn refresh_slot_check(ui: &AppWindow, slot: &str) {
+    let path = slot_path(ui, slot).to_string();
+    set_slot_status(ui, slot, SlotStatus::default());
+    if path.is_empty() {
+        return;
+    }
+
+    let lang = Lang::from_french(ui.get_is_french());
+    // Typed and pasted paths may point nowhere; no need for a worker then
+    if let Some(missing) = check_path_exists(&path, lang) {
+        set_slot_status(
+            ui,
+            slot,
+            SlotStatus {
+                level: missing.level.ui_value(),
+                message: missing.message.into(),
+            },
+        );
+        return;
+    }
+
+    let slot = slot.to_string();
+    let mode = ui.get_mode().to_string();
+    let ui_weak = ui.as_weak();
+    std::thread::spawn(move || {
+        let Some(check) = quick_check(&slot, &path, &mode, lang) else {
+            return;
+        };
+        let _ = slint::invoke_from_event_loop(move || {
+            if let Some(ui) = ui_weak.upgrade() {
+                if slot_path(&ui, &slot).as_str() == path {
+                    set_slot_status(
+                        &ui,
+                        &slot,
+                        SlotStatus {
+                            level: check.level.ui_value(),
+                            message: check.message.into(),
+                        },
+                    );
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,276 @@
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const HOOK_PLACEHOLDERS: &[&str] = &["output", "run", "destination"];
+
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 60;
+
+// Lines of the hook's output kept for the log and the summary
+const OUTPUT_LINES: usize = 5;
+
+/// Values the hook placeholders expand to
+pub struct HookValues<'a> {
+    pub output: &'a str,
+    pub run: &'a str,
+    pub destination: &'a str,
+}
+
+/// How a post-merge hook ended
+#[derive(Debug, Clone)]
+pub struct HookOutcome {
+    // The command as run, placeholders expanded
+    pub command: String,
+    // None when it was killed or could not report one
+    pub status: Option<i32>,
+    pub timed_out: bool,
+    // Could not be started at all
+    pub spawn_error: Option<String>,
+    // First lines of stdout then stderr
+    pub output: Vec<String>,
+}
+
+impl HookOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// "exit 0", "exit 3", "timed out after 60 s", "could not start: ..."
+    pub fn status_text(&self, timeout: Duration) -> String {
+        match (&self.spawn_error, self.timed_out, self.status) {
+            (Some(e), _, _) => format!("could not start: {}", e),
+            (None, true, _) => format!("timed out after {} s", timeout.as_secs()),
+            (None, false, Some(code)) => format!("exit {}", code),
+            (None, false, None) => "ended by a signal".to_string(),
+        }
+    }
+}
+
+/// Checks the placeholders of a hook command without running it
+pub fn validate_hook(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            return Err(format!("Unclosed '{{' in post-merge hook '{}'.", template));
+        };
+        let name = &after[..end];
+        if !HOOK_PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder {{{}}} in post-merge hook. Known placeholders: {}.",
+                name,
+                HOOK_PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        rest = &after[end + 1..];
+    }
+    Ok(())
+}
+
+// Environment variable each placeholder's value is passed in
+const HOOK_VARIABLES: &[(&str, &str)] = &[
+    ("output", "MERGER_OUTPUT"),
+    ("run", "MERGER_RUN"),
+    ("destination", "MERGER_DESTINATION"),
+];
+
+// Quoted reference to an environment variable in the platform shell
+fn variable_reference(variable: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("\"%{}%\"", variable)
+    } else {
+        format!("\"${}\"", variable)
+    }
+}
+
+/// Expands the placeholders to quoted references to the MERGER_OUTPUT,
+/// MERGER_RUN and MERGER_DESTINATION variables `run_hook` sets, so quotes,
+/// `$` or `&` in a path or run name stay part of the value. The template
+/// should not quote them.
+pub fn expand_hook(template: &str) -> Result<String, String> {
+    validate_hook(template)?;
+    Ok(HOOK_VARIABLES
+        .iter()
+        .fold(template.to_string(), |command, (name, variable)| {
+            command.replace(&format!("{{{}}}", name), &variable_reference(variable))
+        }))
+}
+
+// Variables for the hook's environment. cmd cannot escape a double quote
+// inside a quoted value, and Windows paths never contain one.
+fn hook_environment(values: &HookValues) -> Vec<(&'static str, String)> {
+    let clean = |value: &str| {
+        if cfg!(target_os = "windows") {
+            value.replace('"', "")
+        } else {
+            value.to_string()
+        }
+    };
+    vec![
+        ("MERGER_OUTPUT", clean(values.output)),
+        ("MERGER_RUN", clean(values.run)),
+        ("MERGER_DESTINATION", clean(values.destination)),
+    ]
+}
+
+// cmd parses the command line itself, so the hook is passed as typed:
+// Rust's argument quoting would turn the quotes around %MERGER_OUTPUT%
+// into \" and split a path with a space
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").raw_arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+// Read on their own threads so a chatty hook cannot fill a pipe and stall
+fn collect<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            let mut bytes = Vec::new();
+            let _ = pipe.read_to_end(&mut bytes);
+            text = String::from_utf8_lossy(&bytes).to_string();
+        }
+        text
+    })
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> (Option<i32>, bool) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return (status.code(), false),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return (None, true);
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(_) => return (None, false),
+        }
+    }
+}
+
+/// Runs `command` through the platform shell with `values` in its
+/// environment, killing it after `timeout`. Never fails: problems are
+/// reported in the outcome.
+pub fn run_hook(command: &str, values: &HookValues, timeout: Duration) -> HookOutcome {
+    let mut outcome = HookOutcome {
+        command: command.to_string(),
+        status: None,
+        timed_out: false,
+        spawn_error: None,
+        output: Vec::new(),
+    };
+
+    let mut child = match shell(command)
+        .envs(hook_environment(values))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            outcome.spawn_error = Some(e.to_string());
+            return outcome;
+        }
+    };
+
+    let stdout = collect(child.stdout.take());
+    let stderr = collect(child.stderr.take());
+    (outcome.status, outcome.timed_out) = wait_with_timeout(&mut child, timeout);
+
+    // A killed shell may leave a grandchild holding the pipes open; only
+    // wait for the readers when the hook ended on its own
+    if !outcome.timed_out {
+        for text in [stdout.join(), stderr.join()].into_iter().flatten() {
+            outcome
+                .output
+                .extend(text.lines().map(|l| l.trim_end().to_string()).filter(|l| !l.is_empty()));
+        }
+        outcome.output.truncate(OUTPUT_LINES);
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn values<'a>(output: &'a str) -> HookValues<'a> {
+        HookValues {
+            output,
+            run: "RUN-01",
+            destination: "/data/runs",
+        }
+    }
+
+    #[test]
+    fn unknown_and_unclosed_placeholders_are_refused() {
+        assert!(validate_hook("copy {output} {destination}").is_ok());
+        assert!(validate_hook("copy {outptu}").unwrap_err().contains("{outptu}"));
+        assert!(validate_hook("copy {output").unwrap_err().contains("Unclosed"));
+    }
+
+    #[test]
+    fn placeholders_expand_to_variable_references() {
+        let command = expand_hook("upload {output} --run {run}").unwrap();
+        assert!(!command.contains('{'));
+        assert!(command.contains("MERGER_OUTPUT"));
+        assert!(command.contains("MERGER_RUN"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hook_sees_values_unchanged() {
+        let tricky = "/data/it's a \"run\" $(touch pwned) & `id`; 50%.csv";
+        let command = expand_hook("printf '%s\\n' {output} {run}").unwrap();
+        let outcome = run_hook(&command, &values(tricky), TIMEOUT);
+        assert!(outcome.succeeded(), "{:?}", outcome);
+        assert_eq!(outcome.output, vec![tricky.to_string(), "RUN-01".to_string()]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn hook_gets_paths_with_spaces_whole() {
+        let output = r"C:\Users\Lab User\runs\RUN 01 output.csv";
+        let command = expand_hook("for %a in ({output}) do @echo [%~a]").unwrap();
+        let outcome = run_hook(&command, &values(output), TIMEOUT);
+        assert!(outcome.succeeded(), "{:?}", outcome);
+        assert_eq!(outcome.output, vec![format!("[{}]", output)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_hook_reports_its_status_and_output() {
+        let command = expand_hook("echo 'no space left' >&2; exit 3").unwrap();
+        let outcome = run_hook(&command, &values("/data/out.csv"), TIMEOUT);
+        assert!(!outcome.succeeded());
+        assert_eq!(outcome.status, Some(3));
+        assert_eq!(outcome.status_text(TIMEOUT), "exit 3");
+        assert_eq!(outcome.output, vec!["no space left".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn slow_hook_is_stopped() {
+        let timeout = Duration::from_millis(200);
+        let outcome = run_hook("sleep 5", &values("/data/out.csv"), timeout);
+        assert!(outcome.timed_out);
+        assert!(!outcome.succeeded());
+        assert_eq!(outcome.status_text(Duration::from_secs(1)), "timed out after 1 s");
+    }
+}
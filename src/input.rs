@@ -0,0 +1,363 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::workspace::TempWorkspace;
+
+/// Opens an input for reading only. On Windows the file stays shared for
+/// reading, writing and deletion so other users of a network export are
+/// never locked out while we hold the handle.
+pub fn open_read_only(path: impl AsRef<Path>) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+        options.share_mode(0x1 | 0x2 | 0x4);
+    }
+    options.open(path)
+}
+
+pub fn read_input_bytes(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_read_only(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn read_input_text(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut text = String::new();
+    open_read_only(path)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+// Mount points network shares usually appear under on Linux and macOS
+const NETWORK_MOUNT_PREFIXES: &[&str] = &["/mnt/", "/media/", "/net/", "/Volumes/", "/smb/"];
+
+/// Heuristic: UNC paths (`\\server\share`, `//server/share`), Windows
+/// drive letters mapped to a share, and the usual Unix mount points
+pub fn is_network_path(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    if text.starts_with(r"\\?\UNC\") || text.starts_with(r"\\") || text.starts_with("//") {
+        return true;
+    }
+    if text.contains("/gvfs/") || NETWORK_MOUNT_PREFIXES.iter().any(|p| text.starts_with(p)) {
+        return true;
+    }
+    // A mapped drive letter resolves to its UNC form
+    if cfg!(windows) {
+        if let Ok(resolved) = path.canonicalize() {
+            return resolved.to_string_lossy().starts_with(r"\\?\UNC\");
+        }
+    }
+    false
+}
+
+/// A readable local path for an input. Network inputs are copied into a
+/// temp workspace first so the share's handle is released quickly; local
+/// files are read directly.
+pub struct LocalInput {
+    path: PathBuf,
+    // Keeps the copy alive until the input is dropped
+    _workspace: Option<TempWorkspace>,
+}
+
+impl LocalInput {
+    pub fn path_str(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+}
+
+/// `local_input` with the network classifier supplied by the caller
+pub fn local_input_with(path: &str, is_network: impl Fn(&Path) -> bool) -> Result<LocalInput, String> {
+    let source = Path::new(path);
+    if !is_network(source) {
+        return Ok(LocalInput {
+            path: source.to_path_buf(),
+            _workspace: None,
+        });
+    }
+
+    let mut workspace = TempWorkspace::new()?;
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "input".to_string());
+    let copy = workspace.file(&name);
+
+    let started = Instant::now();
+    let mut reader = open_read_only(source).map_err(|e| format!("Failed to open '{}': {e}", path))?;
+    let mut writer = File::create(&copy)
+        .map_err(|e| format!("Failed to create local copy '{}': {e}", copy.display()))?;
+    io::copy(&mut reader, &mut writer).map_err(|e| format!("Failed to copy '{}': {e}", path))?;
+    drop(reader);
+    let elapsed = started.elapsed();
+
+    let size = fs::metadata(&copy).map(|m| m.len()).unwrap_or(0);
+    eprintln!(
+        "Copied network input '{}' ({} bytes) to the temp workspace in {:.2}s",
+        path,
+        size,
+        elapsed.as_secs_f64()
+    );
+
+    Ok(LocalInput {
+        path: copy,
+        _workspace: Some(workspace),
+    })
+}
+
+pub fn local_input(path: &str) -> Result<LocalInput, String> {
+    local_input_with(path, is_network_path)
+}
+
+// Decodes %XX escapes of a file URL; anything malformed is kept as typed
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push(hi * 16 + lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// file:///C:/x -> C:/x, file:///home/x -> /home/x, file://server/share/x
+// -> //server/share/x (UNC); None when `text` is not a file URL
+fn file_url_path(text: &str) -> Option<String> {
+    let rest = text.get(..7).filter(|p| p.eq_ignore_ascii_case("file://")).map(|_| &text[7..])?;
+    let rest = rest.strip_prefix("localhost/").map(|r| format!("/{r}")).unwrap_or_else(|| rest.to_string());
+    let path = match rest.strip_prefix('/') {
+        // Drive letter: the leading slash belongs to the URL, not the path
+        Some(local) if local.as_bytes().get(1) == Some(&b':') => local.to_string(),
+        Some(local) => format!("/{local}"),
+        None => format!("//{rest}"),
+    };
+    Some(percent_decode(&path))
+}
+
+// Expands %NAME%, $NAME, ${NAME} and a leading ~; unknown names are kept
+// as typed so the "not found" status shows what was pasted
+fn expand_env_vars(text: &str, var: &impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        if let Some(home) = var("HOME").or_else(|| var("USERPROFILE")) {
+            out.push_str(&home);
+            rest = &rest[1..];
+        }
+    }
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '(' || c == ')';
+    while let Some(pos) = rest.find(['%', '$']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        let (name, len) = if let Some(body) = tail.strip_prefix('%') {
+            match body.find('%') {
+                Some(end) if end > 0 && body[..end].chars().all(is_name) => (&body[..end], end + 2),
+                _ => ("", 1),
+            }
+        } else if let Some(body) = tail.strip_prefix("${") {
+            match body.find('}') {
+                Some(end) if end > 0 => (&body[..end], end + 3),
+                _ => ("", 1),
+            }
+        } else {
+            let end = tail[1..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(tail.len() - 1);
+            (&tail[1..1 + end], end + 1)
+        };
+        match (name.is_empty(), var(name)) {
+            (false, Some(value)) => out.push_str(&value),
+            _ => out.push_str(&tail[..len.max(1)]),
+        }
+        rest = &tail[len.max(1)..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `normalize_pasted_path` with the platform and environment supplied by
+/// the caller
+pub fn normalize_pasted_path_with(text: &str, windows: bool, var: impl Fn(&str) -> Option<String>) -> String {
+    // Outlook and Teams wrap paths in quotes or angle brackets
+    let mut path = text.trim();
+    while let Some(inner) = ["\"\"", "''", "<>", "\u{201c}\u{201d}"].iter().find_map(|pair| {
+        let mut ends = pair.chars();
+        let (open, close) = (ends.next()?, ends.next()?);
+        path.strip_prefix(open)?.strip_suffix(close)
+    }) {
+        path = inner.trim();
+    }
+
+    let path = match file_url_path(path) {
+        Some(local) => local,
+        None => expand_env_vars(path, &var),
+    };
+
+    if windows {
+        path.replace('/', "\\")
+    } else if path.starts_with("\\\\") || path.as_bytes().get(1) == Some(&b':') {
+        // A Windows path pasted on Linux or macOS; only the separators can
+        // be fixed, the share or drive still has to be mounted
+        path.replace('\\', "/")
+    } else {
+        path
+    }
+}
+
+/// Cleans up a path pasted into a file field: surrounding quotes, file://
+/// URLs, environment variables (%USERPROFILE%, $HOME, ~) and separators
+/// for this platform. UNC paths are kept as UNC paths.
+pub fn normalize_pasted_path(text: &str) -> String {
+    normalize_pasted_path_with(text, cfg!(windows), |name| std::env::var(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::read_csv_normalized;
+
+    #[test]
+    fn network_paths_are_recognized() {
+        for path in [
+            r"\\labserver\epi\export.csv",
+            r"\\?\UNC\labserver\epi\export.csv",
+            "//labserver/epi/export.csv",
+            "/mnt/epi/export.csv",
+            "/Volumes/Shared/export.csv",
+            "/run/user/1000/gvfs/smb-share:server=lab,share=epi/export.csv",
+        ] {
+            assert!(is_network_path(Path::new(path)), "{}", path);
+        }
+        for path in ["/home/lab/export.csv", "export.csv", "/tmp/mnt/export.csv"] {
+            assert!(!is_network_path(Path::new(path)), "{}", path);
+        }
+    }
+
+    #[test]
+    fn local_inputs_are_read_in_place() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("samples.csv");
+        fs::write(&path, "sample,barcode\nS01,barcode01\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let input = local_input_with(&path, |_| false).unwrap();
+        assert_eq!(input.path_str(), path);
+        assert!(input._workspace.is_none());
+    }
+
+    #[test]
+    fn network_inputs_are_copied_then_parsed() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("epiinfo.csv");
+        fs::write(&path, "ICLabID,EpidNumber\nS01,NIE-KAN-24-001\nS02,NIE-KAN-24-002\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let input = local_input_with(&path, |p| p == Path::new(&path)).unwrap();
+        let copy = input.path_str();
+        assert_ne!(copy, path);
+        assert!(copy.ends_with("epiinfo.csv"));
+        assert_eq!(fs::read(&copy).unwrap(), fs::read(&path).unwrap());
+
+        let (copied, _) = read_csv_normalized(&copy).unwrap();
+        let (direct, _) = read_csv_normalized(&path).unwrap();
+        assert!(copied.equals_missing(&direct));
+
+        // The copy lives only as long as the input
+        drop(input);
+        assert!(!Path::new(&copy).exists());
+        assert!(Path::new(&path).exists());
+    }
+
+    #[test]
+    fn missing_network_input_is_an_error() {
+        let dir = TempWorkspace::new().unwrap();
+        let path = dir.path().join("gone.csv").to_string_lossy().to_string();
+        let err = local_input_with(&path, |_| true).err().unwrap();
+        assert!(err.starts_with("Failed to open"), "{}", err);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn read_only_handle_does_not_lock_the_file() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("export.csv");
+        fs::write(&path, "ICLabID\n").unwrap();
+
+        let handle = open_read_only(&path).unwrap();
+        fs::write(&path, "ICLabID\nS01\n").unwrap();
+        fs::remove_file(&path).unwrap();
+        drop(handle);
+    }
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "USERPROFILE" => Some(r"C:\Users\ana".to_string()),
+            "HOME" => Some("/home/ana".to_string()),
+            "DATA" => Some("/srv/data".to_string()),
+            _ => None,
+        }
+    }
+
+    fn on_windows(text: &str) -> String {
+        normalize_pasted_path_with(text, true, env)
+    }
+
+    fn on_unix(text: &str) -> String {
+        normalize_pasted_path_with(text, false, env)
+    }
+
+    #[test]
+    fn pasted_paths_lose_their_quotes() {
+        assert_eq!(on_windows(r#" "C:\Users\ana\samples.csv" "#), r"C:\Users\ana\samples.csv");
+        assert_eq!(on_unix(r#"<"/home/ana/samples.csv">"#), "/home/ana/samples.csv");
+        assert_eq!(on_unix("'/home/ana/run 1.csv'"), "/home/ana/run 1.csv");
+        assert_eq!(on_windows("\u{201c}D:/Runs/samples.csv\u{201d}"), r"D:\Runs\samples.csv");
+        // A quote inside the path is part of it
+        assert_eq!(on_unix("/home/ana/\"x\".csv"), "/home/ana/\"x\".csv");
+    }
+
+    #[test]
+    fn file_urls_become_paths() {
+        assert_eq!(on_windows("file:///C:/Runs/run%201/samples.csv"), r"C:\Runs\run 1\samples.csv");
+        assert_eq!(on_unix("file:///home/ana/run%201.csv"), "/home/ana/run 1.csv");
+        assert_eq!(on_unix("FILE://localhost/home/ana/a.csv"), "/home/ana/a.csv");
+        // A server in the URL is a UNC path
+        assert_eq!(on_windows("file://labserver/share/runs/a.csv"), r"\\labserver\share\runs\a.csv");
+        assert_eq!(on_unix("file://labserver/share/runs/a.csv"), "//labserver/share/runs/a.csv");
+        // Malformed escapes are kept as typed
+        assert_eq!(on_unix("file:///home/ana/100%zz.csv"), "/home/ana/100%zz.csv");
+    }
+
+    #[test]
+    fn environment_variables_are_expanded() {
+        assert_eq!(on_windows(r"%USERPROFILE%\Desktop\samples.csv"), r"C:\Users\ana\Desktop\samples.csv");
+        assert_eq!(on_unix("$HOME/runs/a.csv"), "/home/ana/runs/a.csv");
+        assert_eq!(on_unix("${DATA}/a.csv"), "/srv/data/a.csv");
+        assert_eq!(on_unix("~/runs/a.csv"), "/home/ana/runs/a.csv");
+        assert_eq!(on_unix("/tmp/~a.csv"), "/tmp/~a.csv");
+        // Unknown names stay, so the not-found message shows what was pasted
+        assert_eq!(on_windows(r"%NOPE%\a.csv"), r"%NOPE%\a.csv");
+        assert_eq!(on_unix("$NOPE/a.csv"), "$NOPE/a.csv");
+        assert_eq!(on_unix("/runs/50%_done.csv"), "/runs/50%_done.csv");
+    }
+
+    #[test]
+    fn separators_suit_the_platform() {
+        assert_eq!(on_windows("C:/Runs/a.csv"), r"C:\Runs\a.csv");
+        assert_eq!(on_windows(r"\\labserver\share\a.csv"), r"\\labserver\share\a.csv");
+        assert_eq!(on_unix(r"\\labserver\share\a.csv"), "//labserver/share/a.csv");
+        assert_eq!(on_unix(r"C:\Runs\a.csv"), "C:/Runs/a.csv");
+        // A backslash in a Linux file name is left alone
+        assert_eq!(on_unix(r"/home/ana/a\b.csv"), r"/home/ana/a\b.csv");
+    }
+}
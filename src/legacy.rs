@@ -0,0 +1,202 @@
+use polars::prelude::*;
+
+// 2023 samples layout -> current column names
+const LEGACY_RENAMES: &[(&str, &str)] = &[
+    ("CaseContactOrCommunity", "CaseOrContact"),
+    ("DateSampleCollected", "DateStoolCollected"),
+    ("Date_Fasta_generated", "DateFastaGenerated"),
+];
+
+// 2023 columns with no place in the current templates
+const LEGACY_DROPPED: &[&str] = &["ToReport"];
+
+// A sheet needs this many of the 2023-only columns to count as that layout,
+// so one stray column does not trigger an upgrade
+const SIGNATURE_MIN: usize = 2;
+
+/// What upgrading a 2023 sheet changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegacyUpgrade {
+    // (2023 name, current name)
+    pub renamed: Vec<(String, String)>,
+    pub dropped: Vec<String>,
+}
+
+/// Whether `columns` look like the 2023 samples layout
+pub fn is_legacy_layout(columns: &[String]) -> bool {
+    let signature = LEGACY_RENAMES.iter().map(|(old, _)| *old).chain(LEGACY_DROPPED.iter().copied());
+    signature.filter(|old| columns.iter().any(|c| c == old)).count() >= SIGNATURE_MIN
+}
+
+/// Brings a 2023 sheet to the current column names: renames what has a
+/// current equivalent and drops the rest. None when `df` is not in that
+/// layout. A 2023 column is kept as it is when its current name is
+/// already taken.
+pub fn upgrade_legacy_layout(df: DataFrame) -> Result<(DataFrame, Option<LegacyUpgrade>), String> {
+    let columns: Vec<String> = df.get_column_names().iter().map(|c| c.to_string()).collect();
+    if !is_legacy_layout(&columns) {
+        return Ok((df, None));
+    }
+
+    let mut df = df;
+    let mut upgrade = LegacyUpgrade::default();
+    for (old, new) in LEGACY_RENAMES {
+        if columns.iter().any(|c| c == old) && !columns.iter().any(|c| c == new) {
+            df.rename(old, PlSmallStr::from_str(new))
+                .map_err(|e| format!("Failed to rename '{}' to '{}': {e}", old, new))?;
+            upgrade.renamed.push((old.to_string(), new.to_string()));
+        }
+    }
+    let dropped: Vec<String> = LEGACY_DROPPED
+        .iter()
+        .filter(|old| columns.iter().any(|c| c == *old))
+        .map(|old| old.to_string())
+        .collect();
+    let df = df.drop_many(dropped.clone());
+    upgrade.dropped = dropped;
+    Ok((df, Some(upgrade)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::expected_columns_for_mode;
+
+    fn names(df: &DataFrame) -> Vec<String> {
+        df.get_column_names().iter().map(|c| c.to_string()).collect()
+    }
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    // A DDNS sheet made from the 2023 template: three columns under their
+    // old names and ToReport
+    fn legacy_sheet() -> String {
+        let header: Vec<&str> = expected_columns_for_mode("DDNS")
+            .into_iter()
+            .map(|c| LEGACY_RENAMES.iter().find(|(_, new)| *new == c).map(|(old, _)| *old).unwrap_or(c))
+            .chain(["ToReport"])
+            .collect();
+        let mut text = header.join(",") + "\n";
+        for (sample, barcode) in [("S01", "barcode01"), ("S02", "barcode02")] {
+            let row: Vec<&str> = header
+                .iter()
+                .map(|c| match *c {
+                    "sample" => sample,
+                    "barcode" => barcode,
+                    "CaseContactOrCommunity" => "1-Case",
+                    "DateSampleCollected" => "2024-02-02",
+                    "Date_Fasta_generated" => "2024-03-07",
+                    "ToReport" => "yes",
+                    _ => "",
+                })
+                .collect();
+            text += &(row.join(",") + "\n");
+        }
+        text
+    }
+
+    #[test]
+    fn legacy_layout_needs_two_signature_columns() {
+        assert!(is_legacy_layout(&columns(&["sample", "CaseContactOrCommunity", "ToReport"])));
+        assert!(is_legacy_layout(&columns(&["DateSampleCollected", "Date_Fasta_generated"])));
+        assert!(!is_legacy_layout(&columns(&["sample", "ToReport"])));
+        assert!(!is_legacy_layout(&columns(&expected_columns_for_mode("DDNS"))));
+        // Names are matched exactly, as the 2023 template wrote them
+        assert!(!is_legacy_layout(&columns(&["casecontactorcommunity", "toreport"])));
+    }
+
+    #[test]
+    fn legacy_columns_are_renamed_and_to_report_dropped() {
+        let df = df!(
+            "sample" => ["S01"],
+            "CaseContactOrCommunity" => ["1-Case"],
+            "DateSampleCollected" => ["2024-02-02"],
+            "ToReport" => ["yes"]
+        )
+        .unwrap();
+        let (df, upgrade) = upgrade_legacy_layout(df).unwrap();
+        assert_eq!(names(&df), ["sample", "CaseOrContact", "DateStoolCollected"]);
+        assert_eq!(
+            upgrade,
+            Some(LegacyUpgrade {
+                renamed: vec![
+                    ("CaseContactOrCommunity".to_string(), "CaseOrContact".to_string()),
+                    ("DateSampleCollected".to_string(), "DateStoolCollected".to_string()),
+                ],
+                dropped: vec!["ToReport".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn current_sheets_are_left_alone() {
+        let df = df!("sample" => ["S01"], "CaseOrContact" => ["1-Case"], "ToReport" => ["yes"]).unwrap();
+        let (out, upgrade) = upgrade_legacy_layout(df.clone()).unwrap();
+        assert!(upgrade.is_none());
+        assert!(out.equals_missing(&df));
+
+        // A sheet with both names keeps the current column and the old one
+        let df = df!(
+            "CaseOrContact" => ["1-Case"],
+            "CaseContactOrCommunity" => ["2-Contact"],
+            "ToReport" => ["yes"]
+        )
+        .unwrap();
+        let (df, upgrade) = upgrade_legacy_layout(df).unwrap();
+        assert_eq!(names(&df), ["CaseOrContact", "CaseContactOrCommunity"]);
+        assert!(upgrade.unwrap().renamed.is_empty());
+    }
+
+    #[test]
+    fn legacy_sheet_merges_into_a_current_output() {
+        use crate::matching::{match_samples, MatchOptions};
+        use crate::merge::{
+            add_optional_columns, build_output, default_comment_columns, merge_with_epiinfo, validate_columns,
+            DEFAULT_EPID_SOURCES,
+        };
+        use crate::model::{EpiData, SampleSheet};
+        use crate::run_constants::{FillPolicies, RunConstants};
+        use crate::warnings::{render_warnings, Lang, ValidationWarning};
+
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let path = dir.file("legacy.csv");
+        std::fs::write(&path, legacy_sheet()).unwrap();
+        let (sample_df, _) = crate::csv::read_csv_normalized(&path.to_string_lossy()).unwrap();
+
+        // Before the upgrade the sheet fails the column check
+        assert!(validate_columns(&sample_df, "DDNS").is_err());
+        let (sample_df, upgrade) = upgrade_legacy_layout(sample_df).unwrap();
+        let upgrade = upgrade.unwrap();
+        assert_eq!(upgrade.renamed.len(), 3);
+
+        let epi = df!("ICLabID" => ["S01", "S02"], "EpidNumber" => ["NIE-KAN-24-001", "NIE-KAN-24-002"]).unwrap();
+        let (sheet, epi) = (SampleSheet::new(sample_df).unwrap(), EpiData::new(epi).unwrap());
+        let matches = match_samples(&sheet.samples(), &epi, &MatchOptions::default());
+        let sources: Vec<String> = DEFAULT_EPID_SOURCES.iter().map(|s| s.to_string()).collect();
+        let (df, _) = merge_with_epiinfo(sheet, epi, &matches, &sources, &default_comment_columns()).unwrap().into_parts();
+        let (df, _) = add_optional_columns(df, "DDNS").unwrap();
+        validate_columns(&df, "DDNS").unwrap();
+        let params = RunConstants { run_num: "24-031".into(), fasta_date: "2024-03-08".into(), ..RunConstants::default() };
+        let out = build_output(df, "DDNS", &params, &FillPolicies::default()).unwrap();
+
+        assert_eq!(names(&out), expected_columns_for_mode("DDNS"));
+        let cell = |column: &str| out.column(column).unwrap().str().unwrap().get(1).map(str::to_string);
+        assert_eq!(cell("CaseOrContact").as_deref(), Some("1-Case"));
+        assert_eq!(cell("DateStoolCollected").as_deref(), Some("2024-02-02"));
+        // The sheet's own FASTA date is kept over the run's
+        assert_eq!(cell("DateFastaGenerated").as_deref(), Some("2024-03-07"));
+        assert_eq!(cell("EPID").as_deref(), Some("NIE-KAN-24-002"));
+        assert_eq!(cell("RunNumber").as_deref(), Some("24-031"));
+
+        let warning = ValidationWarning::LegacyLayoutUpgraded {
+            file: "legacy.csv".to_string(),
+            renamed: upgrade.renamed,
+            dropped: upgrade.dropped,
+        };
+        assert_eq!(warning.code(), "W045");
+        let text = render_warnings(&[warning], Lang::En);
+        assert!(text.contains("legacy.csv") && text.contains("ToReport"), "{text}");
+    }
+}
@@ -1,31 +1,127 @@
 #![windows_subsystem = "windows"]
 
-slint::slint!(export {AppWindow, PlateMapWindow} from "ui/app.slint";);
+slint::slint!(export {AppWindow, PlateMapWindow, Theme, FieldInfo, FieldInfos, FillRule, PaletteItem, EpiColumn, SlotStatus, ReportRun, ColumnPairing} from "ui/app.slint";);
 
+mod about;
+mod appearance;
+mod attempts;
+mod barcodes;
+mod bundle;
+mod capacity;
+mod cli;
+mod clock;
+mod cloud_sync;
+mod compliance;
+mod config;
+mod controls;
+mod corrections;
 mod csv;
+mod dialogs;
+mod domains;
+mod epi_cache;
+mod form;
 mod handlers;
+mod hooks;
+mod input;
+mod legacy;
+mod matching;
 mod merge;
+mod metrics;
 mod minknow;
+mod model;
+mod naming;
+mod ods;
+mod output;
+mod package;
+mod palette;
 mod plate_map;
+mod pseudonym;
+mod quick_check;
+mod reconcile;
+mod report_cache;
+mod report_fields;
+mod resolution;
+mod run_lock;
+mod run_constants;
+mod safe_mode;
+mod settings;
+mod setup_wizard;
+mod staging;
+mod staleness;
+mod summary;
 mod template;
 mod types;
+mod verify;
+mod warnings;
+mod workspace;
 
 use polars::prelude::*;
-use slint::SharedString;
+use rfd::FileDialog;
+use slint::{ComponentHandle, SharedString};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use update_checker::UpdateChecker;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use update_checker::slint_helpers::NOTES_PREVIEW_LINES;
+use update_checker::{UpdateChecker, UpdateError};
 
-use crate::csv::{read_csv_normalized, check_sample_barcode_status, SampleBarcodeStatus};
-use crate::handlers::{setup_clear_handler, setup_file_handlers, setup_plate_map_handlers, setup_standalone_plate_map_handler};
+use crate::about::build_info;
+use crate::capacity::{check_appended_capacity, check_plate_capacity};
+use crate::cloud_sync::{check_materialized, is_conflicted_copy, is_placeholder, with_sync_hint};
+use crate::cli::run_cli;
+use crate::compliance::{compare_headers, report_csv, report_text, Deviation};
+use crate::config::validate_all;
+use crate::dialogs::{dialog_spec, FilePicker, NativePicker};
+use crate::controls::ensure_control_rows;
+use crate::csv::{canonicalize_header_case, drop_rows, find_blank_rows, header_renames, read_csv_normalized, read_csv_projected, read_header_columns, FileProfile, check_sample_barcode_status, SampleBarcodeStatus};
+use crate::domains::{check_value_domains, line_of};
+use crate::epi_cache::EpiInfoCache;
+use crate::handlers::{refresh_slot_check, setup_clear_handler, setup_corrections_handler, setup_file_handlers, setup_plate_map_handlers, setup_standalone_plate_map_handler};
+use crate::legacy::upgrade_legacy_layout;
+use crate::matching::{match_samples, method_counts, weak_matches, MatchOptions, SampleMatch};
 use crate::merge::{
-    fill_run_constants, merge_with_epiinfo, rename_epiinfo_columns_for_minion,
-    select_expected_columns, validate_columns, validate_merge_inputs, MergeParams,
+    add_match_method_column, add_optional_columns, build_output, epi_match_rate, epiinfo_columns_for_mode, excluded_epiinfo_columns, expand_barcodes_only, fill_date_reported, ignored_fields, is_control_sample,
+    is_barcodes_only, match_rate_too_low, merge_with_epiinfo, rename_epiinfo_columns_for_minion, run_constant_columns, run_constant_conflicts, run_constant_values, select_expected_columns, sensitive_columns, ui_field_defs, unmatched_samples, validate_columns,
 };
-use crate::minknow::parse_minknow_html;
-use crate::template::create_template_for_mode;
+use crate::barcodes::{apply_barcodes, check_barcodes, clear_barcodes, has_empty_barcodes, BarcodeCheck};
+use crate::attempts::{with_attempt_count, MergeGuard, FAILURE_COOLDOWN};
+use crate::appearance::{
+    apply_scale_override, resolve_dark_mode, scale_from_label, scale_label, system_prefers_dark,
+};
+use crate::hooks::{expand_hook, run_hook, HookValues};
+use crate::metrics::{append_metrics, load_monthly, load_weekly, monthly_csv, sparkline, weekly_csv};
+use crate::minknow::{choose_run, is_supported_report, minknow_source, run_key, seq_date_mismatch, MinKnowData, MinKnowSource};
+use crate::model::{EpiData, MergedRun, SampleSheet};
+use crate::naming::{expand_output_name, is_windows_reserved, output_subfolder, NameValues};
+use crate::ods::is_ods;
+use crate::output::{
+    append_to_existing, backup_existing, excel_safe_copy, input_collision, normalize_decimals,
+    output_decimal_separator, save_with_fallback,
+};
+use crate::bundle::{apply_bundle, bundle_settings, export_bundle, import_bundle, list_bundles};
+use crate::package::{export_run_package, import_run_package, PackageContents};
+use crate::reconcile::{epi_without_samples, UnsampledEpi};
+use crate::report_cache::ReportCache;
+use crate::run_lock::{held_message, LockError, RunLock};
+use crate::run_constants::{ControlStatus, DateReportedPolicy, FillPolicies, FillPolicy, RunConstants};
+use crate::palette::{filter_actions, PaletteState, ACTIONS};
+use crate::safe_mode::{begin_startup, reset_settings, startup_succeeded};
+use crate::staging::{ReadBack, StagedOutputs};
+use crate::settings::{load_settings, settings_file_exists, settings_modified, update_settings, Settings, ThemePreference};
+use crate::staleness::stale_file_age;
+use crate::report_fields::{ReportFields, REPORT_FIELDS};
+use crate::resolution::{apply_plan, pairings, rename_plan};
+use crate::setup_wizard::{SetupWizard, WizardStep};
+use crate::summary::{column_fill, copy_to_clipboard, total_ms, warning_counts, InputFile, MergeSummary, StageClock, SUMMARY_SCHEMA_VERSION};
+use crate::template::{create_prefilled_template, create_template_for_mode, expected_columns_for_mode, PROVENANCE_COLUMNS};
 use crate::types::PendingMerge;
+use crate::verify::{verify_output, verify_written, VerifyOutcome, WrittenShape};
+use crate::warnings::{
+    render_warnings, render_warnings_capped, write_warnings, Lang, Severity, ValidationWarning, MAX_SHOWN_WARNINGS,
+};
+use crate::workspace::{is_temp_path, sweep_stale, TempWorkspace};
 
 /*
     Version: 1.2.1
@@ -38,6 +134,32 @@ use crate::types::PendingMerge;
 */
 
 fn main() {
+    // Command-line subcommands (e.g. `merger verify`) never open the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = run_cli(&args) {
+        std::process::exit(code);
+    }
+
+    // Startup log: which build and column templates this session runs
+    println!("{}", build_info().to_text());
+
+    // Two unfinished startups in a row: skip user settings and the update check
+    let startup = begin_startup();
+    let settings = Rc::new(RefCell::new(if startup.load_settings {
+        load_settings()
+    } else {
+        Settings::default()
+    }));
+
+    // Clear temp folders left behind by crashed sessions
+    let swept = sweep_stale();
+    if swept > 0 {
+        println!("Removed {swept} stale temp folder(s)");
+    }
+
+    // Scale has to be decided before the window exists
+    apply_scale_override(&settings.borrow());
+
     let ui = match AppWindow::new() {
         Ok(window) => window,
         Err(e) => {
@@ -47,6 +169,16 @@ fn main() {
     };
 
     let pending_merge: Rc<RefCell<Option<PendingMerge>>> = Rc::new(RefCell::new(None));
+    let last_summary: Arc<Mutex<Option<MergeSummary>>> = Arc::new(Mutex::new(None));
+    let epi_cache: Rc<RefCell<EpiInfoCache>> = Rc::new(RefCell::new(EpiInfoCache::default()));
+    let report_cache: Rc<RefCell<ReportCache>> = Rc::new(RefCell::new(ReportCache::default()));
+    let fill_rules: Rc<RefCell<HashMap<String, FillPolicy>>> = Rc::new(RefCell::new(HashMap::new()));
+    // EpiInfo column → include, as chosen in the import review
+    let epi_choices: Rc<RefCell<HashMap<String, bool>>> = Rc::new(RefCell::new(HashMap::new()));
+    // Run detail fields the user typed into, and report values held back for them
+    let report_fields: Rc<RefCell<ReportFields>> = Rc::new(RefCell::new(ReportFields::default()));
+    // Accept/skip per samples file column in the column resolution, kept across merges
+    let column_choices: Rc<RefCell<HashMap<String, bool>>> = Rc::new(RefCell::new(HashMap::new()));
     let plate_map_window: Rc<RefCell<Option<PlateMapWindow>>> = Rc::new(RefCell::new(None));
     let plate_entries: Rc<RefCell<HashMap<String, (String, String)>>> =
         Rc::new(RefCell::new(HashMap::new()));
@@ -56,12 +188,29 @@ fn main() {
     let standalone_plate_entries: Rc<RefCell<HashMap<String, (String, String)>>> =
         Rc::new(RefCell::new(HashMap::new()));
 
+    // Appearance (dark mode + scale)
+    setup_appearance(&ui, settings.clone());
+
+    // DateReported fill policy
+    setup_date_reported(&ui, settings.clone());
+
+    // Mode-dependent field enablement
+    setup_mode_fields(&ui);
+
+    // Language, lab and destination chosen in the first-run setup
+    apply_user_defaults(&ui, &settings.borrow());
+
     // Update checker
-    setup_update_checker(&ui);
+    if startup.check_updates && settings.borrow().check_updates {
+        setup_update_checker(&ui);
+    }
+
+    // Safe-mode banner and its reset button
+    setup_safe_mode(&ui, startup.safe_mode);
 
     // Setup handlers from modules
-    setup_file_handlers(&ui);
-    setup_clear_handler(&ui);
+    setup_file_handlers(&ui, settings.clone());
+    setup_clear_handler(&ui, report_fields.clone());
     setup_plate_map_handlers(
         &ui,
         pending_merge.clone(),
@@ -70,10 +219,77 @@ fn main() {
     );
 
     // Merge / Update handler
-    setup_merge_handler(&ui, pending_merge.clone());
+    setup_merge_handler(
+        &ui,
+        pending_merge.clone(),
+        settings.clone(),
+        last_summary.clone(),
+        epi_cache,
+        report_cache.clone(),
+        fill_rules.clone(),
+        epi_choices.clone(),
+        column_choices.clone(),
+        report_fields.clone(),
+    );
+
+    // Per-column fill rules and the force-overwrite confirmation
+    setup_fill_rules_handlers(&ui, fill_rules);
+
+    // Typed sequencing date that disagrees with the MinKNOW report
+    setup_seq_date_handler(&ui);
+    setup_constant_conflict_handler(&ui);
+
+    // Confirmation when few samples matched the EpiInfo file
+    setup_low_match_handler(&ui);
+
+    // Confirmation before removing empty rows inside the samples file
+    setup_blank_rows_handler(&ui);
+    setup_unclassified_handler(&ui);
+    setup_no_barcoding_handler(&ui);
+
+    // Confirmation before replacing a lock left by a crashed merge
+    setup_stale_lock_handler(&ui);
+
+    // EpiInfo column import review
+    setup_epi_review_handlers(&ui, epi_choices);
+    setup_column_resolution_handlers(&ui, column_choices);
+
+    // Copy summary button on the success dialog
+    setup_copy_summary_handler(&ui, last_summary);
 
     // Template handler
-    setup_template_handler(&ui);
+    setup_template_handler(&ui, settings.clone());
+
+    // Extract MinKNOW values without merging
+    setup_report_only_handlers(&ui, settings.clone(), report_cache.clone(), report_fields.clone());
+    setup_report_fields_handlers(&ui, report_fields);
+
+    // Position chooser for combined MinKNOW reports
+    setup_report_run_handler(&ui);
+
+    // Configuration diagnostics
+    setup_validate_config_handler(&ui);
+
+    // Version, commit and template fingerprints for bug reports
+    setup_about_handler(&ui);
+
+    // Re-check a finished output without writing anything
+    setup_verify_output_handler(&ui);
+
+    // Samples file header against the template, with a savable report
+    setup_compliance_handler(&ui);
+
+    // Run package export/import for inter-lab review
+    setup_package_handlers(&ui, settings.clone(), report_cache);
+
+    // Country configuration bundles: import, switch, export
+    setup_bundle_handlers(&ui, settings.clone());
+
+    // Local usage metrics (monthly summary + export)
+    setup_metrics_handlers(&ui);
+
+    // Apply a corrections sheet to an existing output
+    setup_corrections_handler(&ui);
 
     // Standalone Plate Map handler
     setup_standalone_plate_map_handler(
@@ -82,23 +298,247 @@ fn main() {
         standalone_plate_entries,
     );
 
+    // Ctrl+K quick action palette
+    setup_palette_handlers(&ui);
+
+    setup_organize_outputs_handler(&ui, settings.clone());
+
+    // First-run setup; also "Run setup again" from the palette
+    setup_wizard_handlers(&ui, settings.clone());
+    if startup.load_settings && !settings_file_exists() {
+        ui.invoke_open_setup();
+    }
+
+    // Pick up settings written by another instance or the CLI
+    let _settings_watch = startup
+        .watch_settings
+        .then(|| setup_settings_watch(&ui, settings.clone()));
+
+    // The window is up once the event loop runs; the startup counts as good
+    slint::Timer::single_shot(std::time::Duration::from_secs(1), startup_succeeded);
+
     let _ = ui.run();
+    startup_succeeded();
+}
+
+fn setup_safe_mode(ui: &AppWindow, safe_mode: bool) {
+    ui.set_safe_mode(safe_mode);
+
+    let ui_handle = ui.as_weak();
+    ui.on_reset_settings(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+        match reset_settings() {
+            Ok(backup) => {
+                ui.set_info_title(if fr { "Paramètres réinitialisés" } else { "Settings Reset" }.into());
+                ui.set_info_message(match (backup, fr) {
+                    (Some(path), true) => format!(
+                        "Les paramètres ont été mis de côté dans '{}'. Les valeurs par défaut seront utilisées au prochain démarrage.",
+                        path.display()
+                    ),
+                    (Some(path), false) => format!(
+                        "Your settings were moved to '{}'. Defaults will be used from the next start.",
+                        path.display()
+                    ),
+                    (None, true) => "Aucun fichier de paramètres à réinitialiser.".to_string(),
+                    (None, false) => "There was no settings file to reset.".to_string(),
+                }.into());
+                ui.set_show_info(1.0);
+            }
+            Err(e) => {
+                ui.set_error_title(if fr { "Erreur de réinitialisation" } else { "Reset Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+            }
+        }
+    });
+}
+
+// Polls settings.json and reloads it when another writer bumped the revision
+fn setup_settings_watch(ui: &AppWindow, settings: Rc<RefCell<Settings>>) -> slint::Timer {
+    let ui_handle = ui.as_weak();
+    let last_seen = Rc::new(RefCell::new(settings_modified()));
+    let timer = slint::Timer::default();
+
+    timer.start(slint::TimerMode::Repeated, std::time::Duration::from_secs(2), move || {
+        let modified = settings_modified();
+        if modified == *last_seen.borrow() {
+            return;
+        }
+        *last_seen.borrow_mut() = modified;
+
+        let loaded = load_settings();
+        if loaded.revision <= settings.borrow().revision {
+            return;
+        }
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_theme_pref(SharedString::from(loaded.theme.label()));
+            show_date_reported(&ui, &loaded.date_reported);
+            ui.set_template_max_rows(loaded.plate_capacity().max_rows as i32);
+            ui.set_active_bundle(SharedString::from(loaded.active_bundle.as_str()));
+            ui.global::<Theme>().set_dark(resolve_dark_mode(loaded.theme, system_prefers_dark()));
+        }
+        *settings.borrow_mut() = loaded;
+    });
+
+    timer
+}
+
+fn setup_appearance(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    let system_dark = system_prefers_dark();
+    {
+        let s = settings.borrow();
+        ui.set_theme_pref(SharedString::from(s.theme.label()));
+        ui.set_ui_scale(SharedString::from(scale_label(s.ui_scale)));
+        ui.global::<Theme>().set_dark(resolve_dark_mode(s.theme, system_dark));
+        ui.global::<Theme>().set_scale(ui.window().scale_factor());
+    }
+
+    let ui_handle = ui.as_weak();
+    ui.on_appearance_changed(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+
+        let theme = ThemePreference::from_label(ui.get_theme_pref().as_str());
+        let scale = scale_from_label(ui.get_ui_scale().as_str());
+        ui.global::<Theme>().set_dark(resolve_dark_mode(theme, system_dark));
+
+        let scale_changed = settings.borrow().ui_scale != scale;
+        match update_settings(|s| {
+            s.theme = theme;
+            s.ui_scale = scale;
+        }) {
+            Ok(saved) => *settings.borrow_mut() = saved,
+            Err(e) => {
+                eprintln!("{e}");
+                let mut s = settings.borrow_mut();
+                s.theme = theme;
+                s.ui_scale = scale;
+            }
+        }
+
+        if scale_changed {
+            ui.set_info_title(if fr { "Échelle de l'interface" } else { "UI scale" }.into());
+            ui.set_info_message(if fr {
+                "La nouvelle échelle sera appliquée au prochain démarrage de l'application.".into()
+            } else {
+                "The new scale will be applied the next time the app is started.".into()
+            });
+            ui.set_show_info(1.0);
+        }
+    });
+}
+
+fn show_date_reported(ui: &AppWindow, policy: &DateReportedPolicy) {
+    ui.set_date_reported_policy(SharedString::from(policy.ui_label()));
+    if let DateReportedPolicy::Fixed(date) = policy {
+        ui.set_date_reported_value(SharedString::from(date.as_str()));
+    }
+}
+
+fn setup_date_reported(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    show_date_reported(ui, &settings.borrow().date_reported);
+
+    let ui_handle = ui.as_weak();
+    ui.on_date_reported_changed(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let policy = DateReportedPolicy::from_ui(
+            ui.get_date_reported_policy().as_str(),
+            ui.get_date_reported_value().as_str(),
+        );
+        match update_settings(|s| s.date_reported = policy.clone()) {
+            Ok(saved) => *settings.borrow_mut() = saved,
+            Err(e) => {
+                eprintln!("{e}");
+                settings.borrow_mut().date_reported = policy;
+            }
+        }
+    });
+}
+
+// Labels, format hints and enablement of the run fields, from the mode's
+// column template
+fn field_info_for_mode(mode: &str) -> FieldInfos {
+    let defs = ui_field_defs(mode);
+    let info = |key: &str| {
+        defs.iter()
+            .find(|d| d.key == key)
+            .map(|d| FieldInfo {
+                enabled: d.enabled,
+                label_en: d.label_en.into(),
+                label_fr: d.label_fr.into(),
+                hint_en: d.hint_en.into(),
+                hint_fr: d.hint_fr.into(),
+            })
+            .unwrap_or_default()
+    };
+    FieldInfos {
+        lab: info("lab"),
+        run_num: info("run_num"),
+        pir_ver: info("pir_ver"),
+        rt_date: info("rt_date"),
+        vp1_date: info("vp1_date"),
+        pcr_machine: info("pcr_machine"),
+        vp1_pcr_machine: info("vp1_pcr_machine"),
+        rtpcr_primers: info("rtpcr_primers"),
+        vp1_primers: info("vp1_primers"),
+        fc_uses: info("fc_uses"),
+    }
+}
+
+fn setup_mode_fields(ui: &AppWindow) {
+    ui.set_field_info(field_info_for_mode(ui.get_mode().as_str()));
+
+    let ui_handle = ui.as_weak();
+    ui.on_mode_changed(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_field_info(field_info_for_mode(ui.get_mode().as_str()));
+            // Template columns differ per mode
+            ui.set_column_review_confirmed(false);
+            refresh_slot_check(&ui, "sample_file");
+        }
+    });
 }
 
 fn setup_update_checker(ui: &AppWindow) {
     let mut checker = UpdateChecker::new("Biosurv", "merger", env!("CARGO_PKG_VERSION"))
         .with_settings_namespace("Biosurv", "merger");
+    // Fetch again each launch so an update already seen is still announced;
+    // a rate-limit back-off is kept
     let _ = checker.clear_cache();
     checker.check_prereleases = false;
     checker.min_interval_minutes = 0;
     checker.github_token = std::env::var("GITHUB_TOKEN").ok();
 
+    // OS-specific hotfix tags (e.g. v1.2.1-win) only apply to that platform
+    let tag_pattern = if cfg!(target_os = "windows") {
+        r"^v?\d+\.\d+\.\d+(-win)?$"
+    } else {
+        r"^v?\d+\.\d+\.\d+$"
+    };
+    if let Ok(re) = regex::Regex::new(tag_pattern) {
+        checker = checker.with_tag_filter(re);
+    }
+    checker = checker.with_platform_asset_required(true);
+
     let ui_weak = ui.as_weak();
     std::thread::spawn(move || {
         match checker.check(false) {
             Ok(Some(info)) => {
                 let tag = info.tag.clone();
-                let url = info.html_url.clone();
+                // What changed, as written on the release page
+                let notes = info
+                    .notes_preview(NOTES_PREVIEW_LINES)
+                    .map(|n| format!("\n\n{}", n))
+                    .unwrap_or_default();
+                // Only a GitHub https link is offered; the notice still shows without one
+                let url = match checker.check_url(&info.html_url) {
+                    Ok(()) => info.html_url.clone(),
+                    Err(e) => {
+                        eprintln!("{e}");
+                        String::new()
+                    }
+                };
                 let _ = slint::invoke_from_event_loop(move || {
                     if let Some(ui) = ui_weak.upgrade() {
                         let fr = ui.get_is_french();
@@ -107,13 +547,19 @@ fn setup_update_checker(ui: &AppWindow) {
                         ));
                         ui.set_info_message(SharedString::from(if fr {
                             format!(
-                                "Une nouvelle version est disponible : v{}\nVous utilisez v{}.\nOuvrir la page de publication :\n{}",
-                                tag, env!("CARGO_PKG_VERSION"), url
+                                "Une nouvelle version est disponible : v{}\nVous utilisez v{}.{}{}",
+                                tag,
+                                env!("CARGO_PKG_VERSION"),
+                                notes,
+                                if url.is_empty() { String::new() } else { format!("\nOuvrir la page de publication :\n{}", url) }
                             )
                         } else {
                             format!(
-                                "A new version is available: v{}\nYou are on v{}.\nOpen the release page:\n{}",
-                                tag, env!("CARGO_PKG_VERSION"), url
+                                "A new version is available: v{}\nYou are on v{}.{}{}",
+                                tag,
+                                env!("CARGO_PKG_VERSION"),
+                                notes,
+                                if url.is_empty() { String::new() } else { format!("\nOpen the release page:\n{}", url) }
                             )
                         }));
                         ui.set_show_info(1.0);
@@ -121,6 +567,14 @@ fn setup_update_checker(ui: &AppWindow) {
                 });
             }
             Ok(None) => {}
+            Err(UpdateError::Offline(err)) => {
+                // No connection: skip the notice quietly
+                eprintln!("Update check skipped, offline: {err}");
+            }
+            Err(UpdateError::RateLimited { resets_at }) => {
+                // GitHub's anonymous limit: try again after it resets
+                eprintln!("Update check skipped, rate limited until {resets_at}");
+            }
             Err(err) => {
                 eprintln!("Update check failed: {err}");
                 let err_s = format!("{err}");
@@ -139,13 +593,77 @@ fn setup_update_checker(ui: &AppWindow) {
     });
 }
 
-fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingMerge>>>) {
+#[allow(clippy::too_many_arguments)]
+fn setup_merge_handler(
+    ui: &AppWindow,
+    pending_merge: Rc<RefCell<Option<PendingMerge>>>,
+    settings: Rc<RefCell<Settings>>,
+    last_summary: Arc<Mutex<Option<MergeSummary>>>,
+    epi_cache: Rc<RefCell<EpiInfoCache>>,
+    report_cache: Rc<RefCell<ReportCache>>,
+    fill_rules: Rc<RefCell<HashMap<String, FillPolicy>>>,
+    epi_choices: Rc<RefCell<HashMap<String, bool>>>,
+    column_choices: Rc<RefCell<HashMap<String, bool>>>,
+    report_fields: Rc<RefCell<ReportFields>>,
+) {
     let ui_handle = ui.as_weak();
+    let guard = Rc::new(RefCell::new(MergeGuard::default()));
 
     ui.on_merge(move |mode_action: SharedString| {
         if let Some(ui) = ui_handle.upgrade() {
             let fr = ui.get_is_french();
 
+            // Answers to this attempt's prompts are kept while a prompt's
+            // answer re-runs it; a new Merge/Update click starts over
+            let rerun = ui.get_merge_rerun();
+            ui.set_merge_rerun(false);
+
+            // Ignore clicks while an attempt runs or right after a failure
+            if !guard.borrow_mut().try_begin(Instant::now()) {
+                return;
+            }
+            if !rerun {
+                clear_confirmations(&ui);
+            }
+            ui.set_show_error(0.0);
+            ui.set_merge_busy(true);
+            let _attempt = MergeAttempt {
+                ui: ui.as_weak(),
+                guard: guard.clone(),
+            };
+            // Milliseconds per stage, for the merge summary
+            let mut stages = StageClock::start();
+
+            // Checkbox default plus any per-column rules
+            let policies = FillPolicies {
+                columns: fill_rules.borrow().clone(),
+                ..FillPolicies::from_checkbox(ui.get_overwrite_existing())
+            };
+
+            // Forcing an overwrite destroys per-sample values: ask first
+            if mode_action.as_str() == "merge" && policies.has_overwrite() && !ui.get_overwrite_confirmed() {
+                let columns: Vec<String> = policies
+                    .columns
+                    .iter()
+                    .filter(|(_, p)| **p == FillPolicy::Overwrite)
+                    .map(|(c, _)| c.clone())
+                    .collect();
+                ui.set_overwrite_pending_action(mode_action.clone());
+                ui.set_overwrite_prompt_message((if fr {
+                    format!(
+                        "Les colonnes suivantes seront écrasées pour chaque échantillon, y compris les valeurs déjà saisies :\n\n{}\n\nContinuer ?",
+                        columns.join(", ")
+                    )
+                } else {
+                    format!(
+                        "These columns will be overwritten for every sample, including values already entered:\n\n{}\n\nContinue?",
+                        columns.join(", ")
+                    )
+                }).into());
+                ui.set_show_overwrite_prompt(1.0);
+                return;
+            }
+
             // File presence check
             let mut files_present = true;
             let mut epiinfo_missing = false;
@@ -157,10 +675,12 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
             if ui.get_epiinfo_file().is_empty() {
                 epiinfo_missing = true;
             }
-            let minknow_missing = ui.get_minknow_file().is_empty();
+            let report_source = minknow_source(ui.get_no_minknow_report(), &ui.get_minknow_file());
+            let no_report = report_source == MinKnowSource::Manual;
+            let minknow_missing = report_source != MinKnowSource::Report;
 
-            // At least one optional file must be present
-            if epiinfo_missing && minknow_missing {
+            // At least one optional file (or manual MinKNOW values) must be present
+            if epiinfo_missing && report_source == MinKnowSource::Absent {
                 ui.set_error_title(if fr { "Fichiers d'entrée manquants" } else { "Missing Input Files" }.into());
                 ui.set_error_message(if fr {
                     "Au moins un fichier optionnel est requis.\n\n\
@@ -189,69 +709,193 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
             }
             println!("Passed File Check");
 
+            // Warnings and notes shown with the success message
+            let lang = Lang::from_french(fr);
+            let mut notes: Vec<ValidationWarning> = Vec::new();
+
             let piranha_path = ui.get_sample_file().to_string();
             let epiinfo_path = ui.get_epiinfo_file().to_string();
             let minknow_path = ui.get_minknow_file().to_string();
             let destination_path = ui.get_destination().to_string();
 
+            // Old EpiInfo exports are the main cause of missing case data
+            if !epiinfo_missing {
+                let threshold = settings.borrow().epiinfo_stale_days;
+                if let Some(age_days) = stale_file_age(Path::new(&epiinfo_path), threshold) {
+                    notes.push(ValidationWarning::StaleEpiInfo { age_days });
+                }
+            }
+
+            // Synced inputs that are online-only or a sync conflict's copy
+            // have been read as empty or outdated data
+            let mut synced_inputs = vec![piranha_path.as_str()];
+            if !epiinfo_missing {
+                synced_inputs.push(epiinfo_path.as_str());
+            }
+            if !minknow_missing {
+                synced_inputs.push(minknow_path.as_str());
+            }
+            for path in synced_inputs {
+                if is_placeholder(Path::new(path)) {
+                    notes.push(ValidationWarning::CloudPlaceholderInput { file: file_label(path) });
+                }
+                if is_conflicted_copy(Path::new(path)) {
+                    notes.push(ValidationWarning::ConflictedCopyInput { file: file_label(path) });
+                }
+            }
+
+            // Outputs must never end up in our own temp folders
+            if is_temp_path(Path::new(&destination_path)) {
+                ui.set_error_title(if fr { "Destination invalide" } else { "Invalid Destination" }.into());
+                ui.set_error_message(if fr {
+                    "La destination se trouve dans le dossier temporaire de Merger. Veuillez choisir un autre dossier.".into()
+                } else {
+                    "The destination is inside Merger's temporary folder. Please choose another folder.".into()
+                });
+                ui.set_show_error(1.0);
+                return;
+            }
+
+            // Scratch space for this merge, removed when the handler returns
+            let _workspace = match TempWorkspace::new() {
+                Ok(ws) => ws,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de dossier temporaire" } else { "Temp Folder Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
             // Extension validation
-            if !piranha_path.ends_with(".csv") {
+            if !piranha_path.ends_with(".csv") && !is_ods(&piranha_path) {
                 ui.set_error_title(if fr { "Entrée invalide" } else { "Invalid Input" }.into());
                 ui.set_error_message(if fr {
-                    "Le fichier d'échantillons sélectionné n'est pas un fichier CSV. Veuillez changer en CSV.".into()
+                    "Le fichier d'échantillons sélectionné n'est pas un fichier CSV ou ODS. Veuillez changer en CSV ou ODS.".into()
                 } else {
-                    "Sample file selected is not a CSV file. Please change to CSV.".into()
+                    "Sample file selected is not a CSV or ODS file. Please change to CSV or ODS.".into()
                 });
                 ui.set_show_error(1.0);
                 return;
             }
-            if !epiinfo_path.ends_with(".csv") && !epiinfo_missing {
+            if !epiinfo_path.ends_with(".csv") && !is_ods(&epiinfo_path) && !epiinfo_missing {
                 ui.set_error_title(if fr { "Entrée invalide" } else { "Invalid Input" }.into());
                 ui.set_error_message(if fr {
-                    "Le fichier Epi Info sélectionné n'est pas un fichier CSV. Veuillez changer en CSV.".into()
+                    "Le fichier Epi Info sélectionné n'est pas un fichier CSV ou ODS. Veuillez changer en CSV ou ODS.".into()
                 } else {
-                    "Epi Info file selected is not a CSV file. Please change to CSV.".into()
+                    "Epi Info file selected is not a CSV or ODS file. Please change to CSV or ODS.".into()
                 });
                 ui.set_show_error(1.0);
                 return;
             }
-            if !minknow_missing && !minknow_path.ends_with(".html") {
+            if !minknow_missing && !is_supported_report(&minknow_path) {
                 ui.set_error_title(if fr { "Entrée invalide" } else { "Invalid Input" }.into());
                 ui.set_error_message(if fr {
-                    "Le fichier sélectionné n'est pas un fichier HTML. Veuillez changer en HTML.".into()
+                    "Le fichier sélectionné n'est pas un rapport MinKNOW (HTML ou JSON). Veuillez changer de fichier.".into()
                 } else {
-                    "File selected is not a HTML file. Please change to HTML.".into()
+                    "File selected is not a MinKNOW report (HTML or JSON). Please change the file.".into()
                 });
                 ui.set_show_error(1.0);
                 return;
             }
 
-            // Parse MinKNOW HTML
+            // Parse MinKNOW report
             let minknow_data = if !minknow_missing {
-                match parse_minknow_html(&minknow_path) {
-                    Ok(data) => {
-                        ui.set_minknow_ver(SharedString::from(data.minknow_ver.clone()));
-                        ui.set_fc_id(SharedString::from(data.fc_id.clone()));
-                        ui.set_seq_kit(SharedString::from(data.seq_kit.clone()));
-                        ui.set_seq_hours(SharedString::from(data.seq_hours.clone()));
-                        ui.set_seq_date(SharedString::from(data.seq_date.clone()));
-                        ui.set_fc_pores(SharedString::from(data.fc_pores.clone()));
+                let read = report_cache.borrow_mut().read(&minknow_path, settings.borrow().date_zone);
+                match read {
+                    Ok((runs, cached)) => {
+                        let Some(mut data) = pick_report_run(&ui, &runs, &mode_action) else {
+                            return;
+                        };
+                        if cached {
+                            notes.push(ValidationWarning::ReportCacheUsed);
+                        }
+                        if runs.len() > 1 {
+                            notes.push(ValidationWarning::ReportPositionChosen {
+                                position: data.position.clone(),
+                                fc_id: data.fc_id.clone(),
+                                runs: runs.len(),
+                            });
+                        }
+                        // A typed sequencing date far from the report's may mean the wrong report
+                        let manual = ui.get_seq_date().trim().to_string();
+                        let decision = ui.get_seq_date_decision().to_string();
+                        if let Some(days) = seq_date_mismatch(&manual, &data.seq_date) {
+                            match decision.as_str() {
+                                "report" | "manual" => {
+                                    notes.push(ValidationWarning::SeqDateMismatch {
+                                        manual: manual.clone(),
+                                        report: data.seq_date.clone(),
+                                        used: decision.clone(),
+                                    });
+                                    if decision == "manual" {
+                                        data.seq_date = manual;
+                                    }
+                                }
+                                _ => {
+                                    ui.set_seq_date_pending_action(mode_action.clone());
+                                    ui.set_info_title(if fr { "Dates de séquençage différentes" } else { "Sequencing Dates Differ" }.into());
+                                    ui.set_info_message(if fr {
+                                        format!(
+                                            "La date de séquençage saisie ({}) est à {} jours de la fin du run dans le rapport MinKNOW ({}).\n\nLe rapport sélectionné est peut-être celui d'un autre run. Fermez pour vérifier le rapport.",
+                                            manual, days, data.seq_date
+                                        )
+                                    } else {
+                                        format!(
+                                            "The sequencing date entered ({}) is {} days from the run end in the MinKNOW report ({}).\n\nThe selected report may be from a different run. Close to re-check the report.",
+                                            manual, days, data.seq_date
+                                        )
+                                    }.into());
+                                    ui.set_info_action("seq_date".into());
+                                    ui.set_show_info(1.0);
+                                    return;
+                                }
+                            }
+                        }
+                        // Fields the user typed keep their text and get a hint
+                        if decision == "report" {
+                            report_fields.borrow_mut().forget("seq_date");
+                        }
+                        stage_minknow_data(&ui, &data, &mut report_fields.borrow_mut());
+                        report_fields.borrow().keep_edits(&mut data, |field| report_field_text(&ui, field));
                         Some(data)
                     }
                     Err(e) => {
-                        ui.set_error_title(if fr { "Erreur d'analyse HTML" } else { "HTML Parse Error" }.into());
+                        ui.set_error_title(if fr { "Erreur d'analyse du rapport" } else { "Report Parse Error" }.into());
                         ui.set_error_message(e.into());
                         ui.set_show_error(1.0);
                         return;
                     }
                 }
+            } else if no_report {
+                // No report available: the manual fields become required
+                let data = minknow_data_from_ui(&ui);
+                let missing = data.missing_fields();
+                if !missing.is_empty() {
+                    ui.set_error_title(if fr { "Valeurs MinKNOW manquantes" } else { "Missing MinKNOW Values" }.into());
+                    ui.set_error_message(if fr {
+                        format!(
+                            "Aucun rapport MinKNOW n'est disponible, veuillez donc remplir : {}",
+                            missing.join(", ")
+                        )
+                    } else {
+                        format!(
+                            "No MinKNOW report is available, so please fill in: {}",
+                            missing.join(", ")
+                        )
+                    }.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+                Some(data)
             } else {
                 None
             };
+            stages.end("minknow");
 
             // Read sample CSV
-            let (sample_df, mut delim) = match read_csv_normalized(&piranha_path) {
-                Ok((df, delim)) => (df, delim),
+            let (mut sample_df, sample_profile) = match read_csv_normalized(&piranha_path) {
+                Ok((df, profile)) => (df, profile),
                 Err(msg) => {
                     ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
                     ui.set_error_message(msg.into());
@@ -259,9 +903,247 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
                     return;
                 }
             };
+            // "Sample" / "BARCODE": only the join keys are matched without case
+            match canonicalize_header_case(&mut sample_df) {
+                Ok(renames) if !renames.is_empty() => {
+                    notes.push(ValidationWarning::HeaderCaseFixed { file: file_label(&piranha_path), renames });
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(format!("Failed to rename column headers: {e}").into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            }
+            // Sheets on the 2023 layout are brought to the current column names
+            let mut sample_df = match upgrade_legacy_layout(sample_df) {
+                Ok((df, upgrade)) => {
+                    if let Some(upgrade) = upgrade {
+                        notes.push(ValidationWarning::LegacyLayoutUpgraded {
+                            file: file_label(&piranha_path),
+                            renamed: upgrade.renamed,
+                            dropped: upgrade.dropped,
+                        });
+                    }
+                    df
+                }
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            // Columns that are probably template columns under another name
+            // ("Sampel", "date_received") are reviewed once per samples file
+            // and mode, then renamed before the columns are validated
+            let column_pairings = pairings(&sample_df, &expected_columns_for_mode(&ui.get_mode()));
+            if !column_pairings.is_empty() {
+                if !ui.get_column_review_confirmed() {
+                    let choices = column_choices.borrow();
+                    let rows: Vec<ColumnPairing> = column_pairings
+                        .iter()
+                        .map(|p| ColumnPairing {
+                            expected: p.expected.as_str().into(),
+                            actual: p.actual.as_str().into(),
+                            confidence: p.confidence as i32,
+                            preview: p.preview.join(", ").into(),
+                            accepted: choices.get(&p.actual).copied().unwrap_or_else(|| p.accepted_by_default()),
+                        })
+                        .collect();
+                    ui.set_column_pairings(Rc::new(slint::VecModel::from(rows)).into());
+                    ui.set_column_review_pending_action(mode_action.clone());
+                    ui.set_show_column_review(1.0);
+                    return;
+                }
+                let plan = rename_plan(&column_pairings, &column_choices.borrow());
+                match apply_plan(&mut sample_df, &plan) {
+                    Ok(renames) if !renames.is_empty() => {
+                        notes.push(ValidationWarning::ColumnsResolved { file: file_label(&piranha_path), renames });
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            }
+            // Each input (by path) keeps its own delimiter, encoding and date conventions
+            let mut input_profiles: Vec<(String, FileProfile)> = vec![(piranha_path.to_string(), sample_profile)];
+            // The output keeps the EpiInfo export's delimiter when there is one
+            let mut delim = sample_profile.delimiter;
+
+            // Runs sequenced without barcoding (single-sample validation runs)
+            // leave the barcode column empty. Suggested when the report says
+            // barcoding was off and the sheet has rows without one.
+            let no_barcoding = ui.get_no_barcoding();
+            let report_unbarcoded = minknow_data.as_ref().and_then(|d| d.barcoding) == Some(false);
+            if !no_barcoding && report_unbarcoded && has_empty_barcodes(&sample_df) {
+                ui.set_no_barcoding_pending_action(mode_action.clone());
+                ui.set_info_title(if fr { "Run sans codes-barres" } else { "Run Without Barcoding" }.into());
+                ui.set_info_message(if fr {
+                    "Le rapport MinKNOW indique que le code-barres était désactivé pour ce run, et le fichier d'échantillons a des lignes sans code-barres.\n\nActivez « Sans codes-barres » pour fusionner sans vérifier les codes-barres (la colonne barcode restera vide), ou fermez pour vérifier les fichiers."
+                } else {
+                    "The MinKNOW report says barcoding was disabled for this run, and the samples file has rows without a barcode.\n\nTurn on \"No barcoding\" to merge without barcode checks (the barcode column is left blank), or close to check the files."
+                }.into());
+                ui.set_info_action("no_barcoding".into());
+                ui.set_show_info(1.0);
+                return;
+            }
+
+            // Fully empty rows: trailing ones are dropped, interior ones only once confirmed
+            let remove_blank_rows = ui.get_blank_rows_confirmed();
+            let blank = match find_blank_rows(&sample_df) {
+                Ok(blank) => blank,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(format!("Failed to check for empty rows: {e}").into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            let lines: Vec<String> = blank.interior.iter().map(|&idx| line_of(idx).to_string()).collect();
+            if !blank.interior.is_empty() && !remove_blank_rows {
+                ui.set_blank_rows_pending_action(mode_action.clone());
+                ui.set_info_title(if fr { "Lignes vides dans les échantillons" } else { "Empty Rows in Samples" }.into());
+                ui.set_info_message(if fr {
+                    format!(
+                        "Le fichier d'échantillons contient des lignes entièrement vides entre des lignes remplies (lignes {}). Cela peut indiquer un collage incomplet.\n\nRetirez-les pour continuer, ou fermez pour vérifier le fichier.",
+                        lines.join(", ")
+                    )
+                } else {
+                    format!(
+                        "The samples file has fully empty rows between filled rows (lines {}). This can mean a paste went wrong.\n\nRemove them to continue, or close to check the file.",
+                        lines.join(", ")
+                    )
+                }.into());
+                ui.set_info_action("blank_rows".into());
+                ui.set_show_info(1.0);
+                return;
+            }
+            let dropped: Vec<usize> = blank.trailing.iter().chain(&blank.interior).copied().collect();
+            let sample_df = match drop_rows(&sample_df, &dropped) {
+                Ok(df) => df,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(format!("Failed to drop empty rows: {e}").into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            if !blank.trailing.is_empty() {
+                notes.push(ValidationWarning::TrailingBlankRowsDropped {
+                    file: file_label(&piranha_path),
+                    count: blank.trailing.len(),
+                });
+            }
+            if !blank.interior.is_empty() {
+                notes.push(ValidationWarning::InteriorBlankRowsRemoved {
+                    file: file_label(&piranha_path),
+                    lines,
+                });
+            }
+
+            // Repeated or blank headers were renamed while reading
+            let input_files = [(&piranha_path, true), (&epiinfo_path, !epiinfo_missing)];
+            for (path, present) in input_files {
+                if !present {
+                    continue;
+                }
+                if let Ok(renames) = header_renames(path) {
+                    if !renames.is_empty() {
+                        notes.push(ValidationWarning::HeadersRenamed { file: file_label(path), renames });
+                    }
+                }
+                if let Ok(columns) = read_header_columns(path) {
+                    let reserved: Vec<String> = columns.into_iter().filter(|c| is_windows_reserved(c)).collect();
+                    if !reserved.is_empty() {
+                        notes.push(ValidationWarning::ReservedColumnNames { file: file_label(path), columns: reserved });
+                    }
+                }
+            }
+
+            // Barcodes Piranha would drop: "unclassified" rows are removed once
+            // confirmed, other unknown names stop the merge
+            let remove_unclassified = ui.get_unclassified_confirmed();
+            let barcodes = if no_barcoding {
+                // Nothing to check: the column is left blank in the output
+                Ok(BarcodeCheck::default())
+            } else {
+                check_barcodes(&sample_df)
+            };
+            let barcodes = match barcodes {
+                Ok(check) => check,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de vérification des échantillons" } else { "Samples Check Error" }.into());
+                    ui.set_error_message(format!("Failed to check the barcode column: {e}").into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            if !barcodes.unmappable.is_empty() {
+                let rows: Vec<String> = barcodes.unmappable.iter().map(|(line, value)| format!("{} ({})", line, value)).collect();
+                ui.set_error_title(if fr { "Codes-barres non reconnus" } else { "Unrecognised Barcodes" }.into());
+                ui.set_error_message(if fr {
+                    format!(
+                        "Ces lignes n'ont pas de code-barres utilisable (attendu barcode01 à barcode96) : {}\n\nPiranha les ignorerait. Veuillez corriger le fichier CSV avant de fusionner.",
+                        rows.join(", ")
+                    )
+                } else {
+                    format!(
+                        "These lines have no usable barcode (expected barcode01 to barcode96): {}\n\nPiranha would skip them. Please correct the CSV file before merging.",
+                        rows.join(", ")
+                    )
+                }.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+            let unclassified_lines: Vec<String> = barcodes.unclassified.iter().map(|&idx| line_of(idx).to_string()).collect();
+            if !barcodes.unclassified.is_empty() && !remove_unclassified {
+                ui.set_unclassified_pending_action(mode_action.clone());
+                ui.set_info_title(if fr { "Lignes « unclassified »" } else { "Unclassified Rows" }.into());
+                ui.set_info_message(if fr {
+                    format!(
+                        "Le fichier d'échantillons contient des lignes avec le code-barres « unclassified » (lignes {}), probablement copiées depuis MinKNOW. Piranha les ignore.\n\nRetirez-les pour continuer, ou fermez pour vérifier le fichier.",
+                        unclassified_lines.join(", ")
+                    )
+                } else {
+                    format!(
+                        "The samples file has rows with barcode \"unclassified\" (lines {}), probably copied from MinKNOW. Piranha skips them.\n\nRemove them to continue, or close to check the file.",
+                        unclassified_lines.join(", ")
+                    )
+                }.into());
+                ui.set_info_action("unclassified".into());
+                ui.set_show_info(1.0);
+                return;
+            }
+            let sample_df = match apply_barcodes(sample_df, &barcodes).and_then(|df| drop_rows(&df, &barcodes.unclassified)) {
+                Ok(df) => df,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(format!("Failed to normalize the barcode column: {e}").into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            if !barcodes.renamed.is_empty() {
+                notes.push(ValidationWarning::BarcodesNormalized {
+                    changes: barcodes
+                        .renamed
+                        .iter()
+                        .map(|(line, from, to)| format!("{}: {} → {}", line, from.trim(), to))
+                        .collect(),
+                });
+            }
+            if !unclassified_lines.is_empty() {
+                notes.push(ValidationWarning::UnclassifiedRowsRemoved { lines: unclassified_lines });
+            }
 
             // Check sample/barcode status
-            let sample_status = match check_sample_barcode_status(&sample_df) {
+            let sample_status = match check_sample_barcode_status(&sample_df, !no_barcoding) {
                 Ok(status) => status,
                 Err(e) => {
                     ui.set_error_title(if fr { "Erreur de vérification des échantillons" } else { "Samples Check Error" }.into());
@@ -322,32 +1204,16 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
 
             // Merge with EpiInfo if present
             let current_mode = ui.get_mode().to_string();
-            let merged_df = if !epiinfo_missing {
-                let (mut epi_df, epi_delim) = match read_csv_normalized(&epiinfo_path) {
-                    Ok((df, d)) => (df, d),
-                    Err(msg) => {
-                        ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
-                        ui.set_error_message(msg.into());
-                        ui.set_show_error(1.0);
-                        return;
-                    }
-                };
-
-                delim = epi_delim;
 
-                if current_mode == "minION" {
-                    if let Err(e) = rename_epiinfo_columns_for_minion(&mut epi_df) {
-                        ui.set_error_title(if fr { "Erreur de renommage Epi Info" } else { "Epi Info rename error" }.into());
-                        ui.set_error_message(e.into());
-                        ui.set_show_error(1.0);
-                        return;
+            // A two-column barcodes.csv gets the rest of the template as empty columns
+            let sample_df = if is_barcodes_only(&sample_df) {
+                match expand_barcodes_only(sample_df, &current_mode) {
+                    Ok((df, added)) => {
+                        notes.push(ValidationWarning::BarcodesOnlyExpanded { added_columns: added });
+                        df
                     }
-                }
-
-                match merge_with_epiinfo(sample_df, epi_df) {
-                    Ok(df) => df,
                     Err(e) => {
-                        ui.set_error_title(if fr { "Erreur de fusion" } else { "Merge Error" }.into());
+                        ui.set_error_title(if fr { "Erreur de fichier d'échantillons" } else { "Sample File Error" }.into());
                         ui.set_error_message(e.into());
                         ui.set_show_error(1.0);
                         return;
@@ -356,6 +1222,240 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
             } else {
                 sample_df
             };
+            let sample_sheet = match SampleSheet::new(sample_df) {
+                Ok(sheet) => sheet,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de fichier d'échantillons" } else { "Sample File Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+            // (matched, unmatched) sample counts, only known with an EpiInfo file
+            let mut epi_match: Option<(usize, usize)> = None;
+            // (matched, non-control samples) and whether a low rate was accepted
+            let mut match_rate: Option<(usize, usize)> = None;
+            // How each sample was paired with an ICLabID, one per sheet row
+            let mut sample_matches: Vec<SampleMatch> = Vec::new();
+            let mut low_match_accepted = false;
+            // EpiInfo records without samples, when that report is enabled
+            let mut unsampled_epi: Option<Result<UnsampledEpi, String>> = None;
+            stages.end("read_sample");
+            let merged_run = if !epiinfo_missing {
+                // Only load the EpiInfo columns this mode can use, unless debugging
+                let epid_sources = settings.borrow().epid_sources.clone();
+                let mut epi_columns = epiinfo_columns_for_mode(&current_mode, &epid_sources);
+                // Columns only the EpiInfo-without-samples report reads; they
+                // are dropped again before the merge
+                let unsampled_report = settings.borrow().unsampled_epi_report.clone();
+                let mut report_only_columns: Vec<String> = Vec::new();
+                if unsampled_report.enabled {
+                    for column in unsampled_report.needed_columns() {
+                        if epi_columns.insert(column.clone()) {
+                            report_only_columns.push(column);
+                        }
+                    }
+                }
+                let projection = if settings.borrow().load_all_epiinfo_columns {
+                    None
+                } else {
+                    Some(&epi_columns)
+                };
+                let read = if settings.borrow().cache_epiinfo {
+                    epi_cache.borrow_mut().read(&epiinfo_path, projection)
+                } else {
+                    read_csv_projected(&epiinfo_path, projection).map(|(df, d, skipped)| (df, d, skipped, false))
+                };
+                let (mut epi_df, epi_profile) = match read {
+                    Ok((df, d, skipped, cached)) => {
+                        if cached {
+                            notes.push(ValidationWarning::EpiInfoCacheUsed);
+                        }
+                        if skipped > 0 {
+                            notes.push(ValidationWarning::EpiInfoColumnsSkipped { count: skipped });
+                        }
+                        (df, d)
+                    }
+                    Err(msg) => {
+                        ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                        ui.set_error_message(msg.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                };
+
+                delim = epi_profile.delimiter;
+                input_profiles.push((epiinfo_path.to_string(), epi_profile));
+
+                // Optional review of what gets imported; sensitive columns need an opt-in
+                let epi_columns: Vec<String> = epi_df.get_column_names().iter().map(|c| c.to_string()).collect();
+                let patterns = settings.borrow().sensitive_column_patterns.clone();
+                if settings.borrow().review_epiinfo_columns && !ui.get_epi_review_confirmed() {
+                    let sensitive = sensitive_columns(&epi_columns, &patterns);
+                    let choices = epi_choices.borrow();
+                    let rows: Vec<EpiColumn> = epi_columns
+                        .iter()
+                        .filter(|c| c.as_str() != "ICLabID")
+                        .map(|c| EpiColumn {
+                            name: c.as_str().into(),
+                            sensitive: sensitive.contains(c),
+                            include: choices.get(c).copied().unwrap_or(!sensitive.contains(c)),
+                        })
+                        .collect();
+                    ui.set_epi_columns(Rc::new(slint::VecModel::from(rows)).into());
+                    ui.set_epi_review_pending_action(mode_action.clone());
+                    ui.set_show_epi_review(1.0);
+                    return;
+                }
+
+                // Dropped before the join, so excluded data never enters the merged frame
+                let excluded = excluded_epiinfo_columns(&epi_columns, &patterns, &epi_choices.borrow());
+                if !excluded.is_empty() {
+                    epi_df = epi_df.drop_many(excluded.clone());
+                    notes.push(ValidationWarning::EpiInfoColumnsExcluded { columns: excluded });
+                }
+
+                if current_mode == "minION" {
+                    if let Err(e) = rename_epiinfo_columns_for_minion(&mut epi_df) {
+                        ui.set_error_title(if fr { "Erreur de renommage Epi Info" } else { "Epi Info rename error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+
+                let epi = match EpiData::new(epi_df) {
+                    Ok(epi) => epi,
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur de fichier Epi Info" } else { "Epi Info File Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                };
+
+                stages.end("read_epi");
+
+                let match_options = MatchOptions {
+                    fallbacks: settings.borrow().sample_match_fallbacks,
+                    prefixes: settings.borrow().sample_id_prefixes.clone(),
+                };
+                sample_matches = match_samples(&sample_sheet.samples(), &epi, &match_options);
+                let counts = method_counts(&sample_matches);
+                if counts.iter().any(|(method, _)| method != "exact" && method != "none") {
+                    notes.push(ValidationWarning::SampleMatchMethods { counts });
+                }
+                let weak = weak_matches(&sample_matches);
+                if !weak.is_empty() {
+                    notes.push(ValidationWarning::WeakSampleMatches { pairs: weak });
+                }
+
+                // Reporting only: EpiInfo records of the period no sample was paired with
+                if unsampled_report.enabled {
+                    let matched_ids: HashSet<String> =
+                        sample_matches.iter().filter_map(|m| m.iclab_id.clone()).collect();
+                    unsampled_epi = Some(epi_without_samples(epi.frame(), &matched_ids, &unsampled_report));
+                }
+                let epi = if report_only_columns.is_empty() || settings.borrow().load_all_epiinfo_columns {
+                    epi
+                } else {
+                    let present: Vec<String> = report_only_columns
+                        .iter()
+                        .filter(|c| epi.frame().column(c).is_ok())
+                        .cloned()
+                        .collect();
+                    match EpiData::new(epi.into_frame().drop_many(present)) {
+                        Ok(epi) => epi,
+                        Err(e) => {
+                            ui.set_error_title(if fr { "Erreur de fichier Epi Info" } else { "Epi Info File Error" }.into());
+                            ui.set_error_message(e.into());
+                            ui.set_show_error(1.0);
+                            return;
+                        }
+                    }
+                };
+
+                let samples = unmatched_samples(&sample_matches);
+                epi_match = Some((sample_sheet.height().saturating_sub(samples.len()), samples.len()));
+                if !samples.is_empty() {
+                    notes.push(ValidationWarning::UnmatchedSamples { samples });
+                }
+
+                // A very low match rate usually means the wrong export was selected
+                let low_match_confirmed = ui.get_low_match_confirmed();
+                match epi_match_rate(&sample_matches, &epi) {
+                    Ok((matched, samples)) => {
+                        match_rate = Some((matched, samples));
+                        let threshold = settings.borrow().min_epi_match_percent;
+                        if match_rate_too_low(matched, samples, threshold) {
+                            if !low_match_confirmed {
+                                ui.set_low_match_pending_action(mode_action.clone());
+                                ui.set_info_title(if fr { "Peu d'échantillons appariés" } else { "Few Samples Matched" }.into());
+                                ui.set_info_message(if fr {
+                                    format!(
+                                        "Seulement {} échantillons sur {} correspondent au fichier Epi Info. Cela signifie généralement que le mauvais export a été sélectionné.\n\nContinuer quand même ?",
+                                        matched, samples
+                                    )
+                                } else {
+                                    format!(
+                                        "Only {} of {} samples matched the EpiInfo file. This usually means the wrong export was selected.\n\nContinue anyway?",
+                                        matched, samples
+                                    )
+                                }.into());
+                                ui.set_info_action("low_match".into());
+                                ui.set_show_info(1.0);
+                                return;
+                            }
+                            low_match_accepted = true;
+                            notes.push(ValidationWarning::LowEpiMatchRate { matched, samples, threshold });
+                        }
+                    }
+                    Err(e) => eprintln!("{e}"),
+                }
+
+                let comment_columns = settings.borrow().comment_columns.clone();
+                match merge_with_epiinfo(sample_sheet, epi, &sample_matches, &epid_sources, &comment_columns) {
+                    Ok(run) => run,
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur de fusion" } else { "Merge Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            } else {
+                MergedRun::unmerged(sample_sheet)
+            };
+            let (merged_df, merge_notes) = merged_run.into_parts();
+            notes.extend(merge_notes);
+            stages.end("join");
+
+            let summary_inputs: Vec<InputFile> = input_profiles
+                .iter()
+                .map(|(path, profile)| InputFile::new(path, file_label(path), profile.describe()))
+                .collect();
+            notes.push(ValidationWarning::InputProfiles {
+                files: input_profiles
+                    .iter()
+                    .map(|(path, profile)| (file_label(path), profile.describe()))
+                    .collect(),
+            });
+
+            // Sheets from before an optional column was added to the template
+            let merged_df = match add_optional_columns(merged_df, &current_mode) {
+                Ok((df, added)) => {
+                    if !added.is_empty() {
+                        notes.push(ValidationWarning::OptionalColumnsAdded { columns: added });
+                    }
+                    df
+                }
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de colonne" } else { "Column Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
 
             // Validate columns
             if let Err(e) = validate_columns(&merged_df, &current_mode) {
@@ -368,50 +1468,117 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
             // Apply merge or update action
             let action = mode_action.as_str();
             let mut final_df = if action == "merge" {
-                let params = MergeParams {
-                    mode: current_mode.clone(),
-                    action: action.to_string(),
-                    overwrite_existing: ui.get_overwrite_existing(),
-                    run_num: ui.get_run_num().to_string(),
-                    minknow_ver: minknow_data.as_ref().map(|d| d.minknow_ver.clone()),
-                    pir_ver: ui.get_pir_ver().to_string(),
-                    seq_date: minknow_data.as_ref().map(|d| d.seq_date.clone()),
-                    fc_id: minknow_data.as_ref().map(|d| d.fc_id.clone()),
-                    fc_uses: ui.get_fc_uses().to_string(),
-                    fc_pores: minknow_data.as_ref().map(|d| d.fc_pores.clone()),
-                    seq_hours: minknow_data.as_ref().map(|d| d.seq_hours.clone()),
-                    fasta_date: ui.get_fasta_date().to_string(),
-                    seq_kit: minknow_data.as_ref().map(|d| d.seq_kit.clone()),
-                    rt_date: ui.get_rt_date().to_string(),
-                    lab: ui.get_lab().to_string(),
-                    pos_con: ui.get_pos_con().to_string(),
-                    neg_con: ui.get_neg_con().to_string(),
-                    vp1_date: ui.get_vp1_date().to_string(),
-                    pcr_machine: ui.get_pcr_machine().to_string(),
-                    vp1_pcr_machine: ui.get_vp1_pcr_machine().to_string(),
-                    rtpcr_primers: ui.get_rtpcr_primers().to_string(),
-                    vp1_primers: ui.get_vp1_primers().to_string(),
-                };
+                let params = run_constants_from_ui(&ui)
+                    .with_minknow(minknow_data.as_ref());
+
+                // Entries the user made that this mode has no column for
+                let ignored = ignored_fields(
+                    &current_mode,
+                    &[
+                        ("lab", params.lab.clone()),
+                        ("run_num", params.run_num.clone()),
+                        ("pir_ver", params.pir_ver.clone()),
+                        ("rt_date", params.rt_date.clone()),
+                        ("vp1_date", params.vp1_date.clone()),
+                        ("pcr_machine", params.pcr_machine.clone()),
+                        ("vp1_pcr_machine", params.vp1_pcr_machine.clone()),
+                        ("rtpcr_primers", params.rtpcr_primers.clone()),
+                        ("vp1_primers", params.vp1_primers.clone()),
+                        ("fc_uses", params.fc_uses.clone()),
+                    ],
+                );
+                if !ignored.is_empty() {
+                    notes.push(ValidationWarning::IgnoredFields {
+                        mode: current_mode.clone(),
+                        fields: ignored,
+                    });
+                }
+
+                // PCR controls are usually mandatory
+                let unrecorded = params.unrecorded_controls();
+                if !unrecorded.is_empty() {
+                    notes.push(ValidationWarning::ControlsNotRecorded { controls: unrecorded });
+                }
 
                 // Validate inputs
-                if let Err(e) = validate_merge_inputs(&params) {
+                if let Err(e) = params.validate(&current_mode) {
                     ui.set_error_title(if fr { "Erreur de format d'entrée" } else { "Input Format Error" }.into());
                     ui.set_error_message(e.into());
                     ui.set_show_error(1.0);
                     return;
                 }
 
-                // Fill run constants
-                match fill_run_constants(merged_df, &params) {
-                    Ok(df) => match select_expected_columns(df, &current_mode) {
-                        Ok(df) => df,
-                        Err(e) => {
-                            ui.set_error_title(if fr { "Erreur de sélection de colonnes" } else { "Select Columns Error" }.into());
-                            ui.set_error_message(e.into());
-                            ui.set_show_error(1.0);
+                // Typed constants that differ from values already in the
+                // samples file would leave two values in one column
+                let conflicts = match run_constant_conflicts(&merged_df, &current_mode, &params, &policies) {
+                    Ok(conflicts) => conflicts,
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur des constantes d'exécution" } else { "Run Constants Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                };
+                let decision = ui.get_constant_conflict_decision().to_string();
+                let mut policies = policies;
+                if !conflicts.is_empty() {
+                    let columns: Vec<String> = conflicts.iter().map(|c| c.column.clone()).collect();
+                    match decision.as_str() {
+                        "overwrite" => {
+                            for column in &columns {
+                                policies.columns.insert(column.clone(), FillPolicy::Overwrite);
+                            }
+                        }
+                        "keep" => {}
+                        _ => {
+                            ui.set_constant_conflict_pending_action(mode_action.clone());
+                            let lines: Vec<String> = conflicts
+                                .iter()
+                                .map(|c| {
+                                    let existing: Vec<String> =
+                                        c.existing.iter().map(|(v, n)| format!("{} ({})", v, n)).collect();
+                                    format!("{}: {} → {}", c.column, existing.join(", "), c.typed)
+                                })
+                                .collect();
+                            ui.set_info_title(if fr { "Constantes d'exécution différentes" } else { "Run Constants Differ" }.into());
+                            ui.set_info_message((if fr {
+                                format!(
+                                    "Le fichier d'échantillons contient déjà d'autres valeurs pour ces constantes (nombre de lignes entre parenthèses) :\n\n{}\n\nÉcraser remplace toutes les lignes par la valeur saisie ; Garder ne remplit que les cellules vides et laisse plusieurs valeurs dans la colonne. Fermez pour annuler.",
+                                    lines.join("\n")
+                                )
+                            } else {
+                                format!(
+                                    "The samples file already has other values for these constants (rows in brackets):\n\n{}\n\nOverwrite puts the typed value in every row; Keep only fills empty cells and leaves more than one value in the column. Close to cancel.",
+                                    lines.join("\n")
+                                )
+                            }).into());
+                            ui.set_info_action("constant_conflict".into());
+                            ui.set_show_info(1.0);
                             return;
                         }
-                    },
+                    }
+                    notes.push(ValidationWarning::RunConstantConflict { columns, decision });
+                }
+
+                // Which columns got which rule, when the user set any
+                if !policies.columns.is_empty() {
+                    let (mut filled, mut overwritten, mut skipped) = (Vec::new(), Vec::new(), Vec::new());
+                    for (column, value) in run_constant_values(&current_mode, &params) {
+                        if value.is_empty() {
+                            continue;
+                        }
+                        match policies.for_column(column) {
+                            FillPolicy::FillEmpty => filled.push(column.to_string()),
+                            FillPolicy::Overwrite => overwritten.push(column.to_string()),
+                            FillPolicy::Skip => skipped.push(column.to_string()),
+                        }
+                    }
+                    notes.push(ValidationWarning::FillRulesApplied { filled, overwritten, skipped });
+                }
+
+                // Fill run constants and select the template columns in one pass
+                match build_output(merged_df, &current_mode, &params, &policies) {
+                    Ok(df) => df,
                     Err(e) => {
                         ui.set_error_title(if fr { "Erreur des constantes d'exécution" } else { "Run Constants Error" }.into());
                         ui.set_error_message(e.into());
@@ -430,161 +1597,2189 @@ fn setup_merge_handler(ui: &AppWindow, pending_merge: Rc<RefCell<Option<PendingM
                     }
                 }
             };
+            stages.end("fills");
+
+            // Optional provenance columns after the template
+            if settings.borrow().provenance_columns {
+                let (basecaller, model, host) = minknow_data
+                    .as_ref()
+                    .map(|d| (d.basecaller.clone(), d.basecall_model.clone(), d.hostname.clone()))
+                    .unwrap_or_default();
+                let height = final_df.height();
+                for (column, value) in PROVENANCE_COLUMNS.iter().zip([basecaller, model, host]) {
+                    let series = Series::new((*column).into(), vec![value; height]);
+                    if let Err(e) = final_df.with_column(series) {
+                        ui.set_error_title(if fr { "Erreur de colonne" } else { "Column Error" }.into());
+                        ui.set_error_message(format!("Failed to add column '{}': {e}", column).into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            }
+
+            // How each row was paired with EpiInfo, for checking fallback matches
+            if settings.borrow().annotate_match_method && !sample_matches.is_empty() {
+                if let Err(e) = add_match_method_column(&mut final_df, &sample_matches) {
+                    ui.set_error_title(if fr { "Erreur de colonne" } else { "Column Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            }
+
+            // Controls the samples file left out, when the lab wants them listed
+            let capacity = settings.borrow().plate_capacity();
+            if action == "merge" && settings.borrow().ensure_control_rows {
+                let controls = settings.borrow().control_rows.clone();
+                let constants = run_constant_values(&current_mode, &run_constants_from_ui(&ui).with_minknow(minknow_data.as_ref()));
+                match ensure_control_rows(final_df, &controls, &constants, capacity.max_rows) {
+                    Ok((df, warnings)) => {
+                        final_df = df;
+                        notes.extend(warnings);
+                    }
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur de contrôles" } else { "Control Rows Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            }
+
+            // No barcoding: the output's barcode column stays blank, so the
+            // one-row-per-barcode check has nothing to compare
+            if no_barcoding {
+                match clear_barcodes(final_df) {
+                    Ok((df, cleared)) => {
+                        final_df = df;
+                        notes.push(ValidationWarning::NoBarcoding { cleared });
+                    }
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                        ui.set_error_message(format!("Failed to clear the barcode column: {e}").into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            }
+
+            // One row per barcode, no more rows than the plate holds
+            match check_plate_capacity(&final_df, &capacity) {
+                Ok(warning) => notes.extend(warning),
+                Err(e) => {
+                    ui.set_error_title(if fr { "Capacité de plaque dépassée" } else { "Plate Capacity Exceeded" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            }
+
+            // Value domains (Yes/No, run numbers, controls) and retest linkage
+            notes.extend(check_value_domains(&final_df, &current_mode));
 
             // Save output
-            let file_name = format!("{}_merger_output.csv", ui.get_run_num().as_str());
-            let file_path = format!("{}/{}", destination_path, file_name);
-            let mut file = match std::fs::File::create(&file_path) {
-                Ok(f) => f,
+            let name_values = NameValues {
+                run: ui.get_run_num().to_string(),
+                mode: current_mode.clone(),
+                lab: ui.get_lab().to_string(),
+                date: settings.borrow().date_zone.today(),
+                flowcell: minknow_data.as_ref().map(|d| d.fc_id.clone()).unwrap_or_default(),
+                timestamp: chrono::Local::now().format("%Y%m%d_%H%M%S").to_string(),
+            };
+            let pattern = settings.borrow().output_name_pattern.clone();
+            let output_name = match expand_output_name(&pattern, &name_values) {
+                Ok(name) => name,
                 Err(e) => {
-                    ui.set_error_title(if fr { "Erreur de création de fichier" } else { "File Create Error" }.into());
-                    ui.set_error_message(
-                        (if fr {
-                            format!("Impossible de créer le fichier de sortie à '{}' : {:?}", file_path, e)
-                        } else {
-                            format!("Failed to create output file at '{}': {:?}", file_path, e)
-                        }).into(),
-                    );
+                    ui.set_error_title(if fr { "Nom de sortie invalide" } else { "Invalid Output Name" }.into());
+                    ui.set_error_message(e.into());
                     ui.set_show_error(1.0);
                     return;
                 }
             };
+            if !output_name.empty.is_empty() {
+                notes.push(ValidationWarning::EmptyNamePlaceholders {
+                    placeholders: output_name.empty.clone(),
+                });
+            }
+            if output_name.run_added {
+                notes.push(ValidationWarning::RunAddedToOutputName { pattern: pattern.clone() });
+            }
+            let file_name = output_name.file_name("", "csv");
+
+            // Hub labs: destination/{mode}/{yyyy-mm}/, created on demand
+            let subfolder = output_subfolder(
+                settings.borrow().organize_outputs_by_mode,
+                &current_mode,
+                &ui.get_run_num(),
+                &name_values.date,
+            );
+            let relative_output = match &subfolder {
+                Some(sub) => sub.join(&file_name).to_string_lossy().to_string(),
+                None => file_name.clone(),
+            };
+            let destination_path = match &subfolder {
+                Some(sub) => {
+                    let dir = Path::new(&destination_path).join(sub);
+                    if let Err(e) = std::fs::create_dir_all(&dir) {
+                        ui.set_error_title(if fr { "Erreur de destination" } else { "Destination Error" }.into());
+                        ui.set_error_message((if fr {
+                            format!("Impossible de créer le dossier '{}' : {e}", dir.display())
+                        } else {
+                            format!("Cannot create the folder '{}': {e}", dir.display())
+                        }).into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                    dir.to_string_lossy().to_string()
+                }
+                None => destination_path,
+            };
+            let file_path = format!("{}/{}", destination_path, file_name);
 
-            if let Err(e) = CsvWriter::new(&mut file)
-                .with_separator(delim)
-                .finish(&mut final_df)
+            let excel_path = Path::new(&destination_path).join(output_name.file_name("_excel", "csv"));
+            let unsampled_path = Path::new(&destination_path).join(output_name.file_name("_epi_without_samples", "csv"));
+
+            // Never truncate a file we are reading from, with the output or
+            // any file written next to it
+            let mut artifacts = vec![PathBuf::from(&file_path)];
+            if ui.get_excel_safe_output() {
+                artifacts.push(excel_path.clone());
+            }
+            if matches!(unsampled_epi, Some(Ok(_))) {
+                artifacts.push(unsampled_path.clone());
+            }
+            let inputs = [piranha_path.as_str(), epiinfo_path.as_str(), minknow_path.as_str()];
+            if let Some((artifact, input)) =
+                artifacts.iter().find_map(|artifact| input_collision(artifact, &inputs).map(|input| (artifact, input)))
             {
-                ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
-                ui.set_error_message((if fr {
-                    format!("Échec de l'écriture du CSV de sortie : {:?}", e)
+                ui.set_error_title(if fr { "Conflit de chemin de sortie" } else { "Output Path Conflict" }.into());
+                ui.set_error_message(if fr {
+                    format!(
+                        "La sortie '{}' est aussi un fichier d'entrée ({}).\n\nVeuillez choisir un autre numéro d'exécution ou une autre destination.",
+                        artifact.display(), input
+                    )
                 } else {
-                    format!("Failed to write output CSV: {:?}", e)
-                }).into());
+                    format!(
+                        "The output '{}' is also one of the input files ({}).\n\nPlease choose a different run number or destination.",
+                        artifact.display(), input
+                    )
+                }.into());
                 ui.set_show_error(1.0);
                 return;
             }
 
-            // Success message
-            match mode_action.as_str() {
-                "merge" => {
-                    ui.set_info_title(if fr { "Fusion réussie" } else { "Merge Successful" }.into());
-                    ui.set_info_message(if fr {
+            // One merge per run and destination at a time; held until this
+            // attempt ends, written or not
+            let override_stale = ui.get_stale_lock_confirmed();
+            let _run_lock = match RunLock::acquire(Path::new(&destination_path), &ui.get_run_num(), override_stale) {
+                Ok(lock) => lock,
+                Err(LockError::Held(owner)) => {
+                    ui.set_error_title(if fr { "Run en cours de fusion" } else { "Run Already Being Merged" }.into());
+                    ui.set_error_message(held_message(&ui.get_run_num(), &owner, Lang::from_french(fr)).into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+                Err(LockError::Stale(owner, age)) => {
+                    ui.set_stale_lock_pending_action(mode_action.clone());
+                    ui.set_info_title(if fr { "Verrou ancien" } else { "Old Lock Found" }.into());
+                    ui.set_info_message((if fr {
                         format!(
-                            "Le rapport détaillé fusionné a été enregistré dans la destination sous {}.",
-                            file_name
+                            "Une fusion du run {} a commencé il y a {} minutes ({}) et ne s'est jamais terminée.\n\nRemplacez le verrou seulement si cette fusion n'est plus en cours.",
+                            ui.get_run_num(), age.as_secs() / 60, owner.describe()
                         )
                     } else {
                         format!(
-                            "Merged Detailed Run Report saved to destination as {}.",
-                            file_name
+                            "A merge of run {} started {} minutes ago ({}) and never finished.\n\nOnly replace the lock if that merge is no longer running.",
+                            ui.get_run_num(), age.as_secs() / 60, owner.describe()
                         )
-                    }.into());
+                    }).into());
+                    ui.set_info_action("stale_lock".into());
                     ui.set_show_info(1.0);
+                    return;
                 }
-                "update" => {
-                    ui.set_info_title(if fr { "Mise à jour réussie" } else { "Update Successful" }.into());
-                    ui.set_info_message(if fr {
-                        "Le rapport détaillé mis à jour a été enregistré dans la destination.".into()
-                    } else {
-                        "Updated Detailed Run Report saved to destination.".into()
-                    });
-                    ui.set_show_info(1.0);
+                Err(LockError::Io(e)) => {
+                    ui.set_error_title(if fr { "Erreur de verrou" } else { "Lock Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
                 }
-                _ => {}
-            }
-        }
-    });
-}
-
-fn setup_template_handler(ui: &AppWindow) {
-    let ui_handle = ui.as_weak();
+            };
 
-    ui.on_template(move || {
-        if let Some(ui) = ui_handle.upgrade() {
-            let fr = ui.get_is_french();
-            let current_mode = ui.get_mode().to_string();
+            // Append onto an existing output for the same run (multi-flowcell runs)
+            if ui.get_append_existing() && Path::new(&file_path).exists() {
+                // An output written before the optional columns existed gets them empty
+                let existing_df = match read_csv_normalized(&file_path)
+                    .and_then(|(df, _)| add_optional_columns(df, &current_mode))
+                {
+                    Ok((df, _)) => df,
+                    Err(msg) => {
+                        ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                        ui.set_error_message(msg.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                };
 
-            let file_name = if current_mode == "minION" {
-                "sample_template_minion.csv"
-            } else {
-                "sample_template_ddns.csv"
-            };
+                match append_to_existing(existing_df, final_df) {
+                    Ok(result) => {
+                        notes.push(ValidationWarning::RowsAppended {
+                            new_rows: result.new_rows,
+                            existing_rows: result.existing_rows,
+                            replaced_rows: result.replaced_rows,
+                        });
+                        notes.extend(result.warnings);
+                        final_df = result.df;
+                    }
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur d'ajout" } else { "Append Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
 
-            let file_path = match dirs::download_dir() {
-                Some(dir) => dir.join(file_name),
-                None => {
-                    ui.set_error_title(if fr { "Erreur de répertoire" } else { "Directory Error" }.into());
-                    ui.set_error_message(if fr {
-                        "Aucun dossier de téléchargements trouvé.".into()
-                    } else {
-                        "No Downloads folder found.".into()
-                    });
+                // The earlier runs count against their plates too
+                if let Err(e) = check_appended_capacity(&final_df, &capacity) {
+                    ui.set_error_title(if fr { "Capacité de plaque dépassée" } else { "Plate Capacity Exceeded" }.into());
+                    ui.set_error_message(e.into());
                     ui.set_show_error(1.0);
                     return;
                 }
-            };
+            }
 
-            let mut df = match create_template_for_mode(&current_mode) {
-                Ok(df) => df,
+            // DateReported goes in last, so it is the day the output is produced
+            let date_policy = DateReportedPolicy::from_ui(
+                ui.get_date_reported_policy().as_str(),
+                ui.get_date_reported_value().as_str(),
+            );
+            if let Err(e) = date_policy.validate() {
+                ui.set_error_title(if fr { "Erreur de format d'entrée" } else { "Input Format Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+            let today = settings.borrow().date_zone.today();
+            match fill_date_reported(final_df, &date_policy, &today) {
+                Ok((df, filled)) => {
+                    if let Some((date, rows)) = filled.filter(|(_, rows)| *rows > 0) {
+                        notes.push(ValidationWarning::DateReportedFilled { date, rows });
+                    }
+                    final_df = df;
+                }
                 Err(e) => {
-                    ui.set_error_title(if fr { "Erreur de modèle" } else { "Template Error" }.into());
-                    ui.set_error_message(if fr {
-                        format!("Échec de la création du modèle {} : {:?}", current_mode, e)
-                    } else {
-                        format!("Failed to create {} template DataFrame: {:?}", current_mode, e)
-                    }.into());
+                    ui.set_error_title(if fr { "Erreur de colonne" } else { "Column Error" }.into());
+                    ui.set_error_message(e.into());
                     ui.set_show_error(1.0);
                     return;
                 }
-            };
+            }
 
-            let file = match std::fs::File::create(&file_path) {
-                Ok(f) => f,
-                Err(e) => {
-                    ui.set_error_title(if fr { "Erreur de création de fichier" } else { "File Create Error" }.into());
+            // Strict mode: any outstanding warning blocks the write
+            if settings.borrow().strict_mode {
+                let blocking: Vec<ValidationWarning> = notes
+                    .iter()
+                    .filter(|w| w.severity() == Severity::Warning)
+                    .cloned()
+                    .collect();
+                if !blocking.is_empty() {
+                    ui.set_error_title(if fr { "Mode strict" } else { "Strict Mode" }.into());
                     ui.set_error_message(if fr {
                         format!(
-                            "Impossible de créer le fichier modèle à '{}' : {:?}",
-                            file_path.display(), e
+                            "La sortie n'a pas été écrite car le mode strict est activé :\n\n{}",
+                            render_warnings_capped(&blocking, lang)
                         )
                     } else {
                         format!(
-                            "Failed to create template file at '{}': {:?}",
-                            file_path.display(), e
+                            "The output was not written because strict mode is enabled:\n\n{}",
+                            render_warnings_capped(&blocking, lang)
                         )
                     }.into());
                     ui.set_show_error(1.0);
                     return;
                 }
-            };
+            }
 
-            if let Err(e) = CsvWriter::new(file).finish(&mut df) {
-                ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
-                ui.set_error_message((if fr {
-                    format!("Échec de l'écriture du modèle CSV : {:?}", e)
-                } else {
-                    format!("Failed to write template CSV: {:?}", e)
-                }).into());
+            // Keep a copy of any output we are about to replace
+            match backup_existing(Path::new(&file_path)) {
+                Ok(Some(backup)) => notes.push(ValidationWarning::PreviousOutputBackedUp {
+                    path: backup.display().to_string(),
+                }),
+                Ok(None) => {}
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de sauvegarde" } else { "Backup Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            }
+
+            // "66,5" would split a comma-separated row; points unless localized
+            let decimal = output_decimal_separator(delim, settings.borrow().localized_numbers);
+            let numeric_columns = settings.borrow().numeric_columns.clone();
+            if let Err(e) = normalize_decimals(&mut final_df, &numeric_columns, decimal) {
+                ui.set_error_title(if fr { "Erreur de colonne" } else { "Column Error" }.into());
+                ui.set_error_message(e.into());
                 ui.set_show_error(1.0);
                 return;
             }
 
-            let mode_label = if current_mode == "minION" {
-                "minION"
-            } else {
-                "DDNS"
+            stages.end("validation");
+
+            // Every artifact is staged first; the destination only changes
+            // once all of them were produced
+            let mut staged = match StagedOutputs::new() {
+                Ok(staged) => staged,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de création de fichier" } else { "File Create Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            // The output is read back once in place, as network drives have
+            // been seen to truncate files whose write succeeded, and synced
+            // folders to leave them empty
+            let expected = WrittenShape::of(&final_df);
+            let read_back: ReadBack = Box::new(move |path| {
+                check_materialized(path)
+                    .and_then(|_| verify_written(&path.to_string_lossy(), &expected))
+                    .map_err(|e| with_sync_hint(path, e))
+            });
+            if let Err(e) = staged.stage_verified(
+                "output CSV",
+                Path::new(&file_path),
+                |file| {
+                    CsvWriter::new(file)
+                        .with_separator(delim)
+                        .finish(&mut final_df)
+                        .map_err(|e| e.to_string())
+                },
+                read_back,
+            ) {
+                ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+
+            // Optional Excel-safe variant; the normal output above stays clean
+            if ui.get_excel_safe_output() {
+                let columns = settings.borrow().excel_safe_columns.clone();
+                let written = excel_safe_copy(&final_df, &columns).and_then(|mut df| {
+                    staged.stage("Excel-safe copy", &excel_path, |file| {
+                        CsvWriter::new(file)
+                            .with_separator(delim)
+                            .finish(&mut df)
+                            .map_err(|e| e.to_string())
+                    })
+                });
+                match written {
+                    Ok(()) => notes.push(ValidationWarning::ExcelSafeCopyWritten {
+                        path: excel_path.display().to_string(),
+                    }),
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            }
+
+            // Secondary report; a problem with it never blocks the main output
+            match unsampled_epi.take() {
+                Some(Ok(report)) => {
+                    let mut report_df = report.df;
+                    let records = report_df.height();
+                    let written = staged.stage("EpiInfo records without samples", &unsampled_path, |file| {
+                        CsvWriter::new(file)
+                            .with_separator(delim)
+                            .finish(&mut report_df)
+                            .map_err(|e| e.to_string())
+                    });
+                    match written {
+                        Ok(()) => notes.push(ValidationWarning::UnsampledEpiReportWritten {
+                            path: unsampled_path.display().to_string(),
+                            records,
+                            undated: report.undated,
+                            missing_columns: report.missing_columns,
+                        }),
+                        Err(e) => notes.push(ValidationWarning::UnsampledEpiReportSkipped { reason: e }),
+                    }
+                }
+                Some(Err(e)) => notes.push(ValidationWarning::UnsampledEpiReportSkipped { reason: e }),
+                None => {}
+            }
+
+            if let Err(e) = staged.commit() {
+                ui.set_error_title(if fr { "Erreur d'écriture CSV" } else { "CSV Write Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+            stages.end("write");
+
+            // Optional command from settings (upload, copy elsewhere). Runs
+            // only once the output is in place, on a worker thread so the
+            // window stays responsive; a failure is reported, the output stays.
+            let hook = settings.borrow().post_merge_hook.trim().to_string();
+            let timeout = std::time::Duration::from_secs(settings.borrow().post_merge_hook_timeout_secs);
+            let hook_run = ui.get_run_num().to_string();
+            let hook_destination = destination_path.clone();
+
+            // Keep a summary around for the "Copy summary" button
+            let run_qc = final_df
+                .column("RunQC")
+                .ok()
+                .and_then(|c| c.str().ok().map(|s| {
+                    let mut values: Vec<String> = Vec::new();
+                    for v in s.into_iter().flatten() {
+                        let v = v.trim();
+                        if !v.is_empty() && !values.iter().any(|x| x == v) {
+                            values.push(v.to_string());
+                        }
+                    }
+                    values.join(", ")
+                }))
+                .unwrap_or_default();
+            let controls = final_df
+                .column("sample")
+                .ok()
+                .and_then(|c| c.str().ok().map(|s| s.into_iter().flatten().filter(|v| is_control_sample(v)).count()))
+                .unwrap_or(0);
+            // For the EpiInfo completeness trend
+            let null_epid = epi_match.and_then(|_| {
+                final_df.column("EPID").ok().and_then(|c| {
+                    c.str().ok().map(|s| s.into_iter().filter(|v| v.is_none_or(|v| v.trim().is_empty())).count())
+                })
+            });
+            let column_fill = column_fill(&final_df);
+            let sample_count = final_df.height();
+            let match_methods = method_counts(&sample_matches);
+            let (flow_cell, pores) = minknow_data
+                .as_ref()
+                .map(|d| (d.fc_id.clone(), d.fc_pores.clone()))
+                .unwrap_or_default();
+            let run_number = ui.get_run_num().to_string();
+            let date = settings.borrow().date_zone.today();
+            let usage_metrics = settings.borrow().usage_metrics;
+            let summary_json = settings.borrow().summary_json;
+            let last_summary = last_summary.clone();
+
+            // The summary and the success message wait for the hook's status
+            let finish = move |ui: &AppWindow, stages: StageClock, notes: Vec<ValidationWarning>, hook_status: Option<String>| {
+                let stage_ms = stages.into_millis();
+                let summary = MergeSummary {
+                    schema_version: SUMMARY_SCHEMA_VERSION,
+                    app_version: env!("CARGO_PKG_VERSION").to_string(),
+                    run_number,
+                    date,
+                    mode: current_mode,
+                    sample_count,
+                    controls,
+                    matched: epi_match.map(|(m, _)| m),
+                    unmatched: epi_match.map(|(_, u)| u),
+                    match_rate,
+                    low_match_accepted,
+                    match_methods,
+                    flow_cell,
+                    pores,
+                    run_qc,
+                    output_file: relative_output.clone(),
+                    post_merge_hook: hook_status,
+                    inputs: summary_inputs,
+                    column_fill,
+                    warning_counts: warning_counts(&notes),
+                    stage_ms: stage_ms.clone(),
+                    total_ms: total_ms(&stage_ms),
+                    null_epid,
+                    no_barcoding,
+                };
+                eprintln!("Merge timings: {}", summary.timings_text(Lang::En));
+
+                // Local usage log; never blocks a merge
+                if usage_metrics {
+                    if let Err(e) = append_metrics(&summary.metrics_record()) {
+                        eprintln!("{e}");
+                    }
+                }
+
+                // The summary JSON and the full warning list record the hook's
+                // status, so they are staged and committed on their own once it ran
+                let mut side_outputs = if summary_json || notes.len() > MAX_SHOWN_WARNINGS {
+                    StagedOutputs::new().map_err(|e| eprintln!("{e}")).ok()
+                } else {
+                    None
+                };
+
+                // Machine-readable copy of the summary for downstream scripts
+                if let (true, Some(side_outputs)) = (summary_json, side_outputs.as_mut()) {
+                    let path = Path::new(&destination_path).join(output_name.file_name("_summary", "json"));
+                    let staged = summary.to_json().and_then(|json| {
+                        side_outputs.stage("summary JSON", &path, |file| {
+                            std::io::Write::write_all(file, json.as_bytes()).map_err(|e| e.to_string())
+                        })
+                    });
+                    if let Err(e) = staged {
+                        eprintln!("{e}");
+                    }
+                }
+                *last_summary.lock().unwrap() = Some(summary);
+
+                // Too many warnings for the dialog: the full list goes to a file
+                // next to the output, written line by line
+                let mut warnings_file = None;
+                if let (true, Some(side_outputs)) = (notes.len() > MAX_SHOWN_WARNINGS, side_outputs.as_mut()) {
+                    let path = Path::new(&destination_path).join(output_name.file_name("_warnings", "txt"));
+                    let staged = side_outputs.stage("warning list", &path, |file| {
+                        write_warnings(&mut std::io::BufWriter::new(file), &notes, lang).map_err(|e| e.to_string())
+                    });
+                    match staged {
+                        Ok(()) => warnings_file = Some(path),
+                        Err(e) => eprintln!("{e}"),
+                    }
+                }
+                if let Some(side_outputs) = side_outputs {
+                    if let Err(e) = side_outputs.commit() {
+                        eprintln!("{e}");
+                        warnings_file = None;
+                    }
+                }
+
+                // Success message
+                let notes_text = if notes.is_empty() {
+                    String::new()
+                } else {
+                    let file_note = match (&warnings_file, fr) {
+                        (Some(path), true) => format!("\n\nListe complète : {}", path.display()),
+                        (Some(path), false) => format!("\n\nFull list: {}", path.display()),
+                        (None, _) => String::new(),
+                    };
+                    format!("\n\n{}{}", render_warnings_capped(&notes, lang), file_note)
+                };
+                match mode_action.as_str() {
+                    "merge" => {
+                        ui.set_info_title(if fr { "Fusion réussie" } else { "Merge Successful" }.into());
+                        ui.set_info_message(if fr {
+                            format!(
+                                "Le rapport détaillé fusionné a été enregistré dans la destination sous {}.{}",
+                                relative_output, notes_text
+                            )
+                        } else {
+                            format!(
+                                "Merged Detailed Run Report saved to destination as {}.{}",
+                                relative_output, notes_text
+                            )
+                        }.into());
+                        ui.set_info_action("copy_summary".into());
+                        ui.set_show_info(1.0);
+                    }
+                    "update" => {
+                        ui.set_info_title(if fr { "Mise à jour réussie" } else { "Update Successful" }.into());
+                        ui.set_info_message(if fr {
+                            format!("Le rapport détaillé mis à jour a été enregistré dans la destination sous {}.{}", relative_output, notes_text)
+                        } else {
+                            format!("Updated Detailed Run Report saved to destination as {}.{}", relative_output, notes_text)
+                        }.into());
+                        ui.set_info_action("copy_summary".into());
+                        ui.set_show_info(1.0);
+                    }
+                    _ => {}
+                }
+            };
+
+            if hook.is_empty() {
+                finish(&ui, stages, notes, None);
+                return;
+            }
+            ui.set_hook_running(true);
+            let ui_weak = ui.as_weak();
+            std::thread::spawn(move || {
+                let (mut stages, mut notes) = (stages, notes);
+                let values = HookValues {
+                    output: &file_path,
+                    run: &hook_run,
+                    destination: &hook_destination,
+                };
+                let hook_status = match expand_hook(&hook) {
+                    Ok(command) => {
+                        let outcome = run_hook(&command, &values, timeout);
+                        let status = outcome.status_text(timeout);
+                        println!("Post-merge hook: {} ({})", outcome.command, status);
+                        for line in &outcome.output {
+                            println!("    {}", line);
+                        }
+                        notes.push(if outcome.succeeded() {
+                            ValidationWarning::PostMergeHookRan {
+                                command: outcome.command.clone(),
+                                status: status.clone(),
+                                output: outcome.output.clone(),
+                            }
+                        } else {
+                            ValidationWarning::PostMergeHookFailed {
+                                command: outcome.command.clone(),
+                                status: status.clone(),
+                                output: outcome.output.clone(),
+                            }
+                        });
+                        status
+                    }
+                    Err(e) => {
+                        notes.push(ValidationWarning::PostMergeHookFailed {
+                            command: hook.clone(),
+                            status: e.clone(),
+                            output: Vec::new(),
+                        });
+                        e
+                    }
+                };
+                stages.end("hook");
+                let _ = slint::invoke_from_event_loop(move || {
+                    if let Some(ui) = ui_weak.upgrade() {
+                        ui.set_hook_running(false);
+                        finish(&ui, stages, notes, Some(hook_status));
+                    }
+                });
+            });
+        }
+    });
+}
+
+fn setup_copy_summary_handler(ui: &AppWindow, last_summary: Arc<Mutex<Option<MergeSummary>>>) {
+    let ui_handle = ui.as_weak();
+
+    ui.on_copy_summary(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let Some(summary) = last_summary.lock().unwrap().clone() else {
+                return;
+            };
+
+            // Clipboard problems only get a toast, never an error dialog
+            let message = match copy_to_clipboard(&summary.to_clipboard_text(Lang::from_french(fr))) {
+                Ok(()) => {
+                    if fr { "Résumé copié dans le presse-papiers" } else { "Summary copied to clipboard" }.to_string()
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    if fr { "Impossible de copier le résumé" } else { "Could not copy the summary" }.to_string()
+                }
+            };
+            ui.set_toast_message(message.into());
+            ui.set_toast_visible(true);
+        }
+    });
+}
+
+fn setup_about_handler(ui: &AppWindow) {
+    ui.set_app_version(build_info().short().into());
+
+    let ui_handle = ui.as_weak();
+    ui.on_show_about(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            ui.set_info_title(if fr { "À propos de Merger" } else { "About Merger" }.into());
+            ui.set_info_message(build_info().to_text().into());
+            ui.set_info_action("about".into());
+            ui.set_show_info(1.0);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_copy_about(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let message = match copy_to_clipboard(&build_info().to_text()) {
+                Ok(()) => if fr { "Informations copiées dans le presse-papiers" } else { "Details copied to clipboard" }.to_string(),
+                Err(e) => {
+                    eprintln!("{e}");
+                    if fr { "Impossible de copier les informations" } else { "Could not copy the details" }.to_string()
+                }
+            };
+            ui.set_toast_message(message.into());
+            ui.set_toast_visible(true);
+        }
+    });
+}
+
+fn setup_validate_config_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+
+    ui.on_validate_config(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let reports = validate_all();
+
+            let mut lines: Vec<String> = Vec::new();
+            let mut problems = 0usize;
+            for report in &reports {
+                if !report.present {
+                    lines.push(if fr {
+                        format!("{} : absent, valeurs par défaut utilisées", report.file)
+                    } else {
+                        format!("{}: not present, defaults used", report.file)
+                    });
+                } else if report.issues.is_empty() {
+                    lines.push(format!("{}: OK", report.file));
+                } else {
+                    problems += report.issues.len();
+                    lines.extend(report.issues.iter().map(|i| {
+                        if fr {
+                            format!("{} ({}) : attendu {}", i.file, i.location, i.expected)
+                        } else {
+                            format!("{} ({}): expected {}", i.file, i.location, i.expected)
+                        }
+                    }));
+                }
+            }
+
+            if problems > 0 {
+                ui.set_error_title(if fr { "Configuration invalide" } else { "Invalid Configuration" }.into());
+                ui.set_error_message(lines.join("\n").into());
+                ui.set_show_error(1.0);
+            } else {
+                ui.set_info_title(if fr { "Configuration valide" } else { "Configuration Valid" }.into());
+                ui.set_info_message(lines.join("\n").into());
+                ui.set_show_info(1.0);
+            }
+        }
+    });
+}
+
+fn setup_metrics_handlers(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_monthly_summary(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let counts = match load_monthly() {
+                Ok(c) => c,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture" } else { "Read Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            ui.set_info_title(if fr { "Bilan mensuel" } else { "Monthly Summary" }.into());
+            if counts.is_empty() {
+                ui.set_info_message(if fr {
+                    "Aucune fusion enregistrée pour le moment."
+                } else {
+                    "No merges recorded yet."
+                }.into());
+                ui.set_info_action("".into());
+            } else {
+                let lines: Vec<String> = counts
+                    .iter()
+                    .map(|c| {
+                        if fr {
+                            format!("{} : {} exécution(s), {} échantillon(s), {} apparié(s)", c.month, c.runs, c.samples, c.matched)
+                        } else {
+                            format!("{}: {} run(s), {} sample(s), {} matched", c.month, c.runs, c.samples, c.matched)
+                        }
+                    })
+                    .collect();
+                ui.set_info_message(lines.join("\n").into());
+                ui.set_info_action("export_metrics".into());
+            }
+            ui.set_show_info(1.0);
+        }
+    });
+
+    // EpiInfo match rate per week, to see whether completeness improves
+    let ui_handle = ui.as_weak();
+    ui.on_match_trends(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let trend = match load_weekly(chrono::Local::now().date_naive()) {
+                Ok(trend) => trend,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de lecture" } else { "Read Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            ui.set_info_title(if fr { "Tendance d'appariement Epi Info" } else { "EpiInfo Match Trend" }.into());
+            if trend.iter().all(|w| w.runs == 0) {
+                ui.set_info_message(if fr {
+                    "Aucun taux d'appariement enregistré ces dernières semaines."
+                } else {
+                    "No match rates recorded in the last weeks."
+                }.into());
+                ui.set_info_action("".into());
+            } else {
+                let mut lines = vec![sparkline(&trend), String::new()];
+                lines.extend(trend.iter().map(|w| match (w.match_rate, fr) {
+                    (Some(rate), true) => format!("{} : {:.1} % sur {} fusion(s), {} sans EPID", w.week, rate, w.runs, w.null_epid),
+                    (Some(rate), false) => format!("{}: {:.1}% over {} merge(s), {} without EPID", w.week, rate, w.runs, w.null_epid),
+                    (None, true) => format!("{} : aucune fusion", w.week),
+                    (None, false) => format!("{}: no merges", w.week),
+                }));
+                ui.set_info_message(lines.join("\n").into());
+                ui.set_info_action("export_trends".into());
+            }
+            ui.set_show_info(1.0);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_export_trends(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let Some(path) = FileDialog::new()
+                .set_file_name("merger_match_trend.csv")
+                .add_filter("CSV", &["csv"])
+                .save_file()
+            else {
+                return;
+            };
+
+            let result = load_weekly(chrono::Local::now().date_naive()).and_then(|trend| {
+                std::fs::write(&path, weekly_csv(&trend)).map_err(|e| format!("Failed to write '{}': {e}", path.display()))
+            });
+            match result {
+                Ok(()) => {
+                    ui.set_toast_message((if fr {
+                        format!("Exporté vers {}", path.display())
+                    } else {
+                        format!("Exported to {}", path.display())
+                    }).into());
+                    ui.set_toast_visible(true);
+                }
+                Err(e) => {
+                    ui.set_show_info(0.0);
+                    ui.set_info_action("".into());
+                    ui.set_error_title(if fr { "Erreur d'export" } else { "Export Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_export_metrics(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let Some(path) = FileDialog::new()
+                .set_file_name("merger_monthly_summary.csv")
+                .add_filter("CSV", &["csv"])
+                .save_file()
+            else {
+                return;
+            };
+
+            let result = load_monthly()
+                .and_then(|counts| {
+                    std::fs::write(&path, monthly_csv(&counts))
+                        .map_err(|e| format!("Failed to write '{}': {e}", path.display()))
+                });
+            match result {
+                Ok(()) => {
+                    ui.set_toast_message((if fr {
+                        format!("Exporté vers {}", path.display())
+                    } else {
+                        format!("Exported to {}", path.display())
+                    }).into());
+                    ui.set_toast_visible(true);
+                }
+                Err(e) => {
+                    ui.set_show_info(0.0);
+                    ui.set_info_action("".into());
+                    ui.set_error_title(if fr { "Erreur d'export" } else { "Export Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+}
+
+// Run details as typed in the form; MinKNOW values are added by the caller
+fn run_constants_from_ui(ui: &AppWindow) -> RunConstants {
+    RunConstants {
+        run_num: ui.get_run_num().to_string(),
+        lab: ui.get_lab().to_string(),
+        pir_ver: ui.get_pir_ver().to_string(),
+        rt_date: ui.get_rt_date().to_string(),
+        fasta_date: ui.get_fasta_date().to_string(),
+        fc_uses: ui.get_fc_uses().to_string(),
+        pos_con: ControlStatus::from_ui(&ui.get_pos_con()),
+        neg_con: ControlStatus::from_ui(&ui.get_neg_con()),
+        vp1_date: ui.get_vp1_date().to_string(),
+        pcr_machine: ui.get_pcr_machine().to_string(),
+        vp1_pcr_machine: ui.get_vp1_pcr_machine().to_string(),
+        rtpcr_primers: ui.get_rtpcr_primers().to_string(),
+        vp1_primers: ui.get_vp1_primers().to_string(),
+        ..Default::default()
+    }
+}
+
+// Inverse of run_constants_from_ui, used when a run package is imported
+fn show_run_constants(ui: &AppWindow, constants: &RunConstants) {
+    ui.set_run_num(SharedString::from(constants.run_num.clone()));
+    ui.set_lab(SharedString::from(constants.lab.clone()));
+    ui.set_pir_ver(SharedString::from(constants.pir_ver.clone()));
+    ui.set_rt_date(SharedString::from(constants.rt_date.clone()));
+    ui.set_fasta_date(SharedString::from(constants.fasta_date.clone()));
+    ui.set_fc_uses(SharedString::from(constants.fc_uses.clone()));
+    ui.set_pos_con(SharedString::from(constants.pos_con.ui_label("Positive")));
+    ui.set_neg_con(SharedString::from(constants.neg_con.ui_label("Negative")));
+    ui.set_vp1_date(SharedString::from(constants.vp1_date.clone()));
+    ui.set_pcr_machine(SharedString::from(constants.pcr_machine.clone()));
+    ui.set_vp1_pcr_machine(SharedString::from(constants.vp1_pcr_machine.clone()));
+    ui.set_rtpcr_primers(SharedString::from(constants.rtpcr_primers.clone()));
+    ui.set_vp1_primers(SharedString::from(constants.vp1_primers.clone()));
+}
+
+// Re-runs the merge after a prompt was answered, keeping its earlier answers
+fn rerun_merge(ui: &AppWindow, action: SharedString) {
+    ui.set_merge_rerun(true);
+    ui.invoke_merge(action);
+}
+
+// Forgets every answer given to a merge prompt, so a new attempt asks again
+fn clear_confirmations(ui: &AppWindow) {
+    ui.set_overwrite_confirmed(false);
+    ui.set_seq_date_decision("".into());
+    ui.set_blank_rows_confirmed(false);
+    ui.set_unclassified_confirmed(false);
+    ui.set_epi_review_confirmed(false);
+    ui.set_low_match_confirmed(false);
+    ui.set_constant_conflict_decision("".into());
+    ui.set_stale_lock_confirmed(false);
+}
+
+// Ends a merge attempt however the handler returns. An error dialog left
+// open counts as a failure: repeats of the same error get a counter and
+// Merge stays disabled for the cool-down.
+struct MergeAttempt {
+    ui: slint::Weak<AppWindow>,
+    guard: Rc<RefCell<MergeGuard>>,
+}
+
+impl Drop for MergeAttempt {
+    fn drop(&mut self) {
+        let Some(ui) = self.ui.upgrade() else { return };
+        if ui.get_show_error() <= 0.0 {
+            self.guard.borrow_mut().finished();
+            ui.set_merge_busy(false);
+            return;
+        }
+
+        let message = ui.get_error_message().to_string();
+        let count = self.guard.borrow_mut().failed(&ui.get_error_title(), &message, Instant::now());
+        ui.set_error_message(with_attempt_count(&message, count, ui.get_is_french()).into());
+
+        let ui_handle = ui.as_weak();
+        slint::Timer::single_shot(FAILURE_COOLDOWN, move || {
+            if let Some(ui) = ui_handle.upgrade() {
+                ui.set_merge_busy(false);
+            }
+        });
+    }
+}
+
+// File name of an input for messages, or the whole path if it has none
+fn file_label(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+// Copies parsed MinKNOW values into the run detail fields
+fn show_minknow_data(ui: &AppWindow, data: &MinKnowData) {
+    ui.set_minknow_ver(SharedString::from(data.minknow_ver.clone()));
+    ui.set_fc_id(SharedString::from(data.fc_id.clone()));
+    ui.set_seq_kit(SharedString::from(data.seq_kit.clone()));
+    ui.set_seq_hours(SharedString::from(data.seq_hours.clone()));
+    ui.set_seq_date(SharedString::from(data.seq_date.clone()));
+    ui.set_fc_pores(SharedString::from(data.fc_pores.clone()));
+    ui.set_basecaller(SharedString::from(data.basecaller.clone()));
+    ui.set_basecall_model(SharedString::from(data.basecall_model.clone()));
+    ui.set_sequencer_host(SharedString::from(data.hostname.clone()));
+    ui.set_sequencer_position(SharedString::from(data.position.clone()));
+}
+
+// Text of a run detail field the report can fill
+fn report_field_text(ui: &AppWindow, field: &str) -> String {
+    match field {
+        "minknow_ver" => ui.get_minknow_ver(),
+        "fc_id" => ui.get_fc_id(),
+        "fc_pores" => ui.get_fc_pores(),
+        "seq_kit" => ui.get_seq_kit(),
+        "seq_hours" => ui.get_seq_hours(),
+        "seq_date" => ui.get_seq_date(),
+        _ => SharedString::default(),
+    }
+    .to_string()
+}
+
+fn set_report_field(ui: &AppWindow, field: &str, value: &str) {
+    let value = SharedString::from(value);
+    match field {
+        "minknow_ver" => ui.set_minknow_ver(value),
+        "fc_id" => ui.set_fc_id(value),
+        "fc_pores" => ui.set_fc_pores(value),
+        "seq_kit" => ui.set_seq_kit(value),
+        "seq_hours" => ui.set_seq_hours(value),
+        "seq_date" => ui.set_seq_date(value),
+        _ => {}
+    }
+}
+
+fn set_report_hint(ui: &AppWindow, field: &str, hint: &str) {
+    let hint = SharedString::from(hint);
+    match field {
+        "minknow_ver" => ui.set_minknow_ver_hint(hint),
+        "fc_id" => ui.set_fc_id_hint(hint),
+        "fc_pores" => ui.set_fc_pores_hint(hint),
+        "seq_kit" => ui.set_seq_kit_hint(hint),
+        "seq_hours" => ui.set_seq_hours_hint(hint),
+        "seq_date" => ui.set_seq_date_hint(hint),
+        _ => {}
+    }
+}
+
+// Like show_minknow_data, but fields the user typed into keep their text
+// and show the report's value as a hint to apply
+fn stage_minknow_data(ui: &AppWindow, data: &MinKnowData, fields: &mut ReportFields) {
+    let fill = fields.stage(data, |field| report_field_text(ui, field));
+    for (field, value) in &fill {
+        set_report_field(ui, field, value);
+    }
+    for field in REPORT_FIELDS {
+        set_report_hint(ui, field, fields.hint(field));
+    }
+    ui.set_basecaller(SharedString::from(data.basecaller.clone()));
+    ui.set_basecall_model(SharedString::from(data.basecall_model.clone()));
+    ui.set_sequencer_host(SharedString::from(data.hostname.clone()));
+    ui.set_sequencer_position(SharedString::from(data.position.clone()));
+}
+
+// Typing marks a field as the user's; clicking its hint applies the report's value
+fn setup_report_fields_handlers(ui: &AppWindow, report_fields: Rc<RefCell<ReportFields>>) {
+    let fields = report_fields.clone();
+    ui.on_report_field_edited(move |field, text| {
+        fields.borrow_mut().edited(&field, &text);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_report_hint_apply(move |field| {
+        if let Some(ui) = ui_handle.upgrade() {
+            if let Some(value) = report_fields.borrow_mut().apply(&field) {
+                set_report_field(&ui, &field, &value);
+            }
+            set_report_hint(&ui, &field, "");
+        }
+    });
+}
+
+fn minknow_data_from_ui(ui: &AppWindow) -> MinKnowData {
+    MinKnowData {
+        minknow_ver: ui.get_minknow_ver().trim().to_string(),
+        fc_id: ui.get_fc_id().trim().to_string(),
+        seq_kit: ui.get_seq_kit().trim().to_string(),
+        seq_hours: ui.get_seq_hours().trim().to_string(),
+        seq_date: ui.get_seq_date().trim().to_string(),
+        fc_pores: ui.get_fc_pores().trim().to_string(),
+        basecaller: ui.get_basecaller().to_string(),
+        basecall_model: ui.get_basecall_model().to_string(),
+        hostname: ui.get_sequencer_host().to_string(),
+        position: ui.get_sequencer_position().to_string(),
+        run_end_time: String::new(),
+        barcoding: None,
+    }
+}
+
+// The run of the MinKNOW report to use. A combined report with several
+// positions and none chosen yet opens the chooser, which re-runs
+// `pending_action` once a run is picked; None is returned then.
+fn pick_report_run(ui: &AppWindow, runs: &[MinKnowData], pending_action: &str) -> Option<MinKnowData> {
+    if let Some(data) = choose_run(runs, &ui.get_report_position()) {
+        return Some(data);
+    }
+    let rows: Vec<ReportRun> = runs
+        .iter()
+        .enumerate()
+        .map(|(idx, data)| ReportRun {
+            key: run_key(idx, data).into(),
+            position: data.position.as_str().into(),
+            fc_id: data.fc_id.as_str().into(),
+            end_time: data.run_end_time.as_str().into(),
+        })
+        .collect();
+    ui.set_report_runs(Rc::new(slint::VecModel::from(rows)).into());
+    ui.set_report_runs_pending_action(pending_action.into());
+    ui.set_show_report_runs(1.0);
+    None
+}
+
+// A run picked in the chooser is kept for the report until another is selected
+fn setup_report_run_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_report_run_chosen(move |key| {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_report_runs(0.0);
+            ui.set_report_position(key);
+            let action = ui.get_report_runs_pending_action();
+            match action.as_str() {
+                "extract_report" => ui.invoke_extract_report(),
+                "export_package" => ui.invoke_export_package(),
+                _ => rerun_merge(&ui, action),
+            }
+        }
+    });
+}
+
+fn setup_report_only_handlers(
+    ui: &AppWindow,
+    settings: Rc<RefCell<Settings>>,
+    report_cache: Rc<RefCell<ReportCache>>,
+    report_fields: Rc<RefCell<ReportFields>>,
+) {
+    // Parse the report only; samples and EpiInfo are not looked at
+    let ui_handle = ui.as_weak();
+    ui.on_extract_report(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let path = ui.get_minknow_file().to_string();
+
+            if path.is_empty() {
+                ui.set_error_title(if fr { "Rapport MinKNOW manquant" } else { "Missing MinKNOW Report" }.into());
+                ui.set_error_message(if fr {
+                    "Veuillez d'abord sélectionner un rapport MinKNOW."
+                } else {
+                    "Please select a MinKNOW report first."
+                }.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+
+            let read = report_cache.borrow_mut().read(&path, settings.borrow().date_zone);
+            match read {
+                Ok((runs, _)) => {
+                    let Some(data) = pick_report_run(&ui, &runs, "extract_report") else {
+                        return;
+                    };
+                    stage_minknow_data(&ui, &data, &mut report_fields.borrow_mut());
+                    ui.set_info_title(if fr { "Valeurs du rapport" } else { "Report Values" }.into());
+                    ui.set_info_message(data.to_text().into());
+                    ui.set_info_action("report_only".into());
+                    ui.set_show_info(1.0);
+                }
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur d'analyse du rapport" } else { "Report Parse Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_copy_report(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let message = match copy_to_clipboard(&minknow_data_from_ui(&ui).to_text()) {
+                Ok(()) => if fr { "Valeurs copiées dans le presse-papiers" } else { "Values copied to clipboard" }.to_string(),
+                Err(e) => {
+                    eprintln!("{e}");
+                    if fr { "Impossible de copier les valeurs" } else { "Could not copy the values" }.to_string()
+                }
+            };
+            ui.set_toast_message(message.into());
+            ui.set_toast_visible(true);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_save_report(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let mut dialog = FileDialog::new()
+                .set_file_name("report_metadata.csv")
+                .add_filter("CSV", &["csv"]);
+            if !ui.get_destination().is_empty() {
+                dialog = dialog.set_directory(ui.get_destination().as_str());
+            }
+            let Some(path) = dialog.save_file() else {
+                return;
+            };
+
+            match std::fs::write(&path, minknow_data_from_ui(&ui).to_csv()) {
+                Ok(()) => {
+                    ui.set_toast_message((if fr {
+                        format!("Enregistré sous {}", path.display())
+                    } else {
+                        format!("Saved as {}", path.display())
+                    }).into());
+                    ui.set_toast_visible(true);
+                }
+                Err(e) => {
+                    ui.set_show_info(0.0);
+                    ui.set_info_action("".into());
+                    ui.set_error_title(if fr { "Erreur d'écriture" } else { "Write Error" }.into());
+                    ui.set_error_message(format!("Failed to write '{}': {e}", path.display()).into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+}
+
+fn setup_compliance_handler(ui: &AppWindow) {
+    // Last report shown, kept for "Save report"
+    let last_report: Rc<RefCell<Vec<Deviation>>> = Rc::new(RefCell::new(Vec::new()));
+
+    let ui_handle = ui.as_weak();
+    let report = last_report.clone();
+    ui.on_check_compliance(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+        let mode = ui.get_mode().to_string();
+        let sample_file = ui.get_sample_file().to_string();
+
+        if sample_file.is_empty() {
+            ui.set_error_title(if fr { "Fichier manquant" } else { "Missing File" }.into());
+            ui.set_error_message(if fr {
+                "Veuillez d'abord sélectionner un fichier d'échantillons."
+            } else {
+                "Please select a samples file first."
+            }.into());
+            ui.set_show_error(1.0);
+            return;
+        }
+
+        let header = match read_header_columns(&sample_file) {
+            Ok(header) => header,
+            Err(msg) => {
+                ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                ui.set_error_message(msg.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+        };
+
+        let deviations = compare_headers(&expected_columns_for_mode(&mode), &header);
+        ui.set_info_title((if fr {
+            format!("Conformité au modèle {} : {} écart(s)", mode, deviations.len())
+        } else {
+            format!("{} template compliance: {} issue(s)", mode, deviations.len())
+        }).into());
+        ui.set_info_message(report_text(&deviations, Lang::from_french(fr)).into());
+        ui.set_info_action(if deviations.is_empty() { "" } else { "compliance" }.into());
+        ui.set_show_info(1.0);
+        *report.borrow_mut() = deviations;
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_save_compliance_report(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+        let mut dialog = FileDialog::new()
+            .set_file_name("template_compliance.csv")
+            .add_filter("CSV", &["csv"])
+            .add_filter(if fr { "Texte" } else { "Text" }, &["txt"]);
+        if !ui.get_destination().is_empty() {
+            dialog = dialog.set_directory(ui.get_destination().as_str());
+        }
+        let Some(path) = dialog.save_file() else {
+            return;
+        };
+
+        let deviations = last_report.borrow();
+        let is_text = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("txt"));
+        let contents = if is_text {
+            report_text(&deviations, Lang::from_french(fr)) + "\n"
+        } else {
+            report_csv(&deviations)
+        };
+        match std::fs::write(&path, contents) {
+            Ok(()) => {
+                ui.set_toast_message((if fr {
+                    format!("Enregistré sous {}", path.display())
+                } else {
+                    format!("Saved as {}", path.display())
+                }).into());
+                ui.set_toast_visible(true);
+            }
+            Err(e) => {
+                ui.set_show_info(0.0);
+                ui.set_info_action("".into());
+                ui.set_error_title(if fr { "Erreur d'écriture" } else { "Write Error" }.into());
+                ui.set_error_message(format!("Failed to write '{}': {e}", path.display()).into());
+                ui.set_show_error(1.0);
+            }
+        }
+    });
+}
+
+fn setup_verify_output_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+
+    ui.on_verify_output(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let mode = ui.get_mode().to_string();
+
+            let mut dialog = FileDialog::new().add_filter("CSV", &["csv"]);
+            if !ui.get_destination().is_empty() {
+                dialog = dialog.set_directory(ui.get_destination().as_str());
+            }
+            let Some(path) = dialog.pick_file() else {
+                return;
+            };
+
+            let df = match read_csv_normalized(&path.to_string_lossy()) {
+                Ok((df, _)) => df,
+                Err(msg) => {
+                    ui.set_error_title(if fr { "Erreur de lecture CSV" } else { "CSV Read Error" }.into());
+                    ui.set_error_message(msg.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            match verify_output(&df, &mode) {
+                VerifyOutcome::Clean => {
+                    ui.set_info_title(if fr { "Vérification réussie" } else { "Verification Passed" }.into());
+                    ui.set_info_message((if fr {
+                        format!("{} correspond au modèle {} actuel ({} lignes).", path.display(), mode, df.height())
+                    } else {
+                        format!("{} matches the current {} template ({} rows).", path.display(), mode, df.height())
+                    }).into());
+                    ui.set_show_info(1.0);
+                }
+                VerifyOutcome::Warnings(warnings) => {
+                    ui.set_info_title(if fr { "Vérification : avertissements" } else { "Verification Warnings" }.into());
+                    ui.set_info_message(render_warnings_capped(&warnings, Lang::from_french(fr)).into());
+                    ui.set_show_info(1.0);
+                }
+                VerifyOutcome::Structural(msg) => {
+                    ui.set_error_title(if fr { "Vérification échouée" } else { "Verification Failed" }.into());
+                    ui.set_error_message(msg.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+}
+
+fn fill_policy_index(policy: Option<FillPolicy>) -> i32 {
+    match policy {
+        None => 0,
+        Some(FillPolicy::FillEmpty) => 1,
+        Some(FillPolicy::Overwrite) => 2,
+        Some(FillPolicy::Skip) => 3,
+    }
+}
+
+fn palette_state(ui: &AppWindow) -> PaletteState {
+    PaletteState {
+        sample_file: !ui.get_sample_file().is_empty(),
+        epiinfo_file: !ui.get_epiinfo_file().is_empty(),
+        minknow_file: !ui.get_minknow_file().is_empty(),
+        no_minknow_report: ui.get_no_minknow_report(),
+        destination: !ui.get_destination().is_empty(),
+    }
+}
+
+fn show_palette_items(ui: &AppWindow, query: &str) {
+    let lang = Lang::from_french(ui.get_is_french());
+    let items: Vec<PaletteItem> = filter_actions(query, lang, &palette_state(ui))
+        .into_iter()
+        .map(|(action, enabled)| PaletteItem {
+            id: action.id.into(),
+            label: action.label(lang).into(),
+            enabled,
+        })
+        .collect();
+    ui.set_palette_items(Rc::new(slint::VecModel::from(items)).into());
+}
+
+// Runs a palette action through the same callbacks the buttons use
+fn run_palette_action(ui: &AppWindow, id: &str) {
+    match id {
+        "merge" | "update" => ui.invoke_merge(id.into()),
+        "verify_output" => ui.invoke_verify_output(),
+        "compliance" => ui.invoke_check_compliance(),
+        "validate_config" => ui.invoke_validate_config(),
+        "template" => ui.set_show_template_options(1.0),
+        "plate_map" => ui.invoke_plate_map(),
+        "fill_rules" => ui.invoke_open_fill_rules(),
+        "corrections" => ui.invoke_apply_corrections(),
+        "extract_report" => ui.invoke_extract_report(),
+        "monthly_summary" => ui.invoke_monthly_summary(),
+        "match_trends" => ui.invoke_match_trends(),
+        "export_package" => ui.invoke_export_package(),
+        "import_package" => ui.invoke_import_package(),
+        "import_bundle" => ui.invoke_import_bundle(),
+        "export_bundle" => ui.invoke_export_bundle(),
+        "setup" => ui.invoke_open_setup(),
+        "clear" => ui.invoke_clear(),
+        "about" => ui.invoke_show_about(),
+        "guide" => ui.set_show_guide(1.0),
+        _ => println!("Unknown palette action: {}", id),
+    }
+}
+
+fn setup_palette_handlers(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_open_palette(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            show_palette_items(&ui, "");
+            ui.set_show_palette(true);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_palette_query_changed(move |query| {
+        if let Some(ui) = ui_handle.upgrade() {
+            show_palette_items(&ui, query.as_str());
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_palette_best(move |query| {
+        let Some(ui) = ui_handle.upgrade() else { return SharedString::new() };
+        let lang = Lang::from_french(ui.get_is_french());
+        filter_actions(query.as_str(), lang, &palette_state(&ui))
+            .into_iter()
+            .find(|(_, enabled)| *enabled)
+            .map(|(action, _)| SharedString::from(action.id))
+            .unwrap_or_default()
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_palette_run(move |id| {
+        if let Some(ui) = ui_handle.upgrade() {
+            // A stale list could still offer something that became unavailable
+            let enabled = ACTIONS
+                .iter()
+                .any(|a| a.id == id.as_str() && a.is_enabled(&palette_state(&ui)));
+            if enabled {
+                run_palette_action(&ui, id.as_str());
+            }
+        }
+    });
+}
+
+fn setup_fill_rules_handlers(ui: &AppWindow, fill_rules: Rc<RefCell<HashMap<String, FillPolicy>>>) {
+    // Rules list for the current mode's run-constant columns
+    let ui_handle = ui.as_weak();
+    let rules = fill_rules.clone();
+    ui.on_open_fill_rules(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let rules = rules.borrow();
+            let model: Vec<FillRule> = run_constant_columns(ui.get_mode().as_str())
+                .into_iter()
+                .map(|column| FillRule {
+                    column: column.into(),
+                    policy: fill_policy_index(rules.get(column).copied()),
+                })
+                .collect();
+            ui.set_fill_rules(Rc::new(slint::VecModel::from(model)).into());
+            ui.set_show_fill_rules(1.0);
+        }
+    });
+
+    let rules = fill_rules;
+    ui.on_fill_rule_changed(move |column: SharedString, index: i32| {
+        let policy = match index {
+            1 => Some(FillPolicy::FillEmpty),
+            2 => Some(FillPolicy::Overwrite),
+            3 => Some(FillPolicy::Skip),
+            _ => None,
+        };
+        let mut rules = rules.borrow_mut();
+        match policy {
+            Some(p) => rules.insert(column.to_string(), p),
+            None => rules.remove(column.as_str()),
+        };
+    });
+
+    // Confirmation for forced overwrites; "Yes" re-runs the merge
+    let ui_handle = ui.as_weak();
+    ui.on_overwrite_yes(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_overwrite_prompt(0.0);
+            ui.set_overwrite_confirmed(true);
+            let action = ui.get_overwrite_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_overwrite_no(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_overwrite_prompt(0.0);
+            ui.set_overwrite_confirmed(false);
+        }
+    });
+}
+
+fn setup_epi_review_handlers(ui: &AppWindow, epi_choices: Rc<RefCell<HashMap<String, bool>>>) {
+    let choices = epi_choices.clone();
+    ui.on_epi_column_toggled(move |column, include| {
+        choices.borrow_mut().insert(column.to_string(), include);
+    });
+
+    // "Continue" re-runs the merge with the choices made
+    let ui_handle = ui.as_weak();
+    ui.on_epi_review_continue(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_epi_review(0.0);
+            ui.set_epi_review_confirmed(true);
+            let action = ui.get_epi_review_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Column resolution: accept/skip per pairing, then "Apply" re-runs the merge
+fn setup_column_resolution_handlers(ui: &AppWindow, column_choices: Rc<RefCell<HashMap<String, bool>>>) {
+    let choices = column_choices.clone();
+    ui.on_column_pairing_toggled(move |actual, accepted| {
+        choices.borrow_mut().insert(actual.to_string(), accepted);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_column_review_apply(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_column_review(0.0);
+            ui.set_column_review_confirmed(true);
+            let action = ui.get_column_review_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// "unclassified" barcode rows: removing them re-runs the merge
+fn setup_unclassified_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_unclassified_remove(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_unclassified_confirmed(true);
+            let action = ui.get_unclassified_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Report says barcoding was off: turning on "No barcoding" re-runs the merge
+fn setup_no_barcoding_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_no_barcoding_accept(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_no_barcoding(true);
+            let action = ui.get_no_barcoding_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Empty rows inside the samples file: removing them re-runs the merge
+fn setup_blank_rows_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_blank_rows_remove(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_blank_rows_confirmed(true);
+            let action = ui.get_blank_rows_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Lock left by an old merge of this run: replacing it re-runs the merge,
+// closing the dialog cancels it
+fn setup_stale_lock_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_stale_lock_replace(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_stale_lock_confirmed(true);
+            let action = ui.get_stale_lock_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Low EpiInfo match rate: continuing re-runs the merge, closing aborts it
+fn setup_low_match_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_low_match_continue(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_low_match_confirmed(true);
+            let action = ui.get_low_match_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Typed run constants vs the samples file's values: "overwrite" or
+// "keep" re-runs the merge; closing the dialog cancels it
+fn setup_constant_conflict_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_constant_conflict_choice(move |choice| {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_constant_conflict_decision(choice);
+            let action = ui.get_constant_conflict_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+// Typed vs report sequencing date: either choice re-runs the merge
+fn setup_seq_date_handler(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.on_seq_date_choice(move |choice| {
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_info(0.0);
+            ui.set_info_action("".into());
+            ui.set_seq_date_decision(choice);
+            let action = ui.get_seq_date_pending_action();
+            rerun_merge(&ui, action);
+        }
+    });
+}
+
+fn setup_package_handlers(ui: &AppWindow, settings: Rc<RefCell<Settings>>, report_cache: Rc<RefCell<ReportCache>>) {
+    // Export: sample file (EpiInfo columns blanked), MinKNOW values,
+    // run constants, settings and a validation report in one zip
+    let ui_handle = ui.as_weak();
+    ui.on_export_package(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let mode = ui.get_mode().to_string();
+            let sample_path = ui.get_sample_file().to_string();
+            let destination = ui.get_destination().to_string();
+
+            if sample_path.is_empty() || destination.is_empty() {
+                ui.set_error_title(if fr { "Fichiers manquants" } else { "Missing Files" }.into());
+                ui.set_error_message(if fr {
+                    "Sélectionnez le fichier d'échantillons et le dossier de destination avant d'exporter."
+                } else {
+                    "Select the samples file and the destination folder before exporting."
+                }.into());
+                ui.set_show_error(1.0);
+                return;
+            }
+
+            let minknow = if ui.get_no_minknow_report() {
+                Some(minknow_data_from_ui(&ui))
+            } else if !ui.get_minknow_file().is_empty() {
+                let read = report_cache.borrow_mut().read(&ui.get_minknow_file(), settings.borrow().date_zone);
+                match read {
+                    Ok((runs, _)) => match pick_report_run(&ui, &runs, "export_package") {
+                        Some(data) => Some(data),
+                        None => return,
+                    },
+                    Err(e) => {
+                        ui.set_error_title(if fr { "Erreur d'analyse du rapport" } else { "Report Parse Error" }.into());
+                        ui.set_error_message(e.into());
+                        ui.set_show_error(1.0);
+                        return;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let constants = run_constants_from_ui(&ui).with_minknow(minknow.as_ref());
+
+            // Validation report for the reviewer, always in English
+            let mut report = match constants.validate(&mode) {
+                Ok(()) => "Run constants: OK".to_string(),
+                Err(e) => format!("Run constants:\n{}", e),
+            };
+            let unrecorded = constants.unrecorded_controls();
+            if !unrecorded.is_empty() {
+                let notes = vec![ValidationWarning::ControlsNotRecorded { controls: unrecorded }];
+                report.push_str(&format!("\n\n{}", render_warnings(&notes, Lang::En)));
+            }
+
+            let epiinfo = ui.get_epiinfo_file().to_string();
+            let settings_snapshot = settings.borrow().clone();
+            let contents = PackageContents {
+                mode: &mode,
+                sample_path: &sample_path,
+                epiinfo_path: (!epiinfo.is_empty()).then_some(epiinfo.as_str()),
+                minknow: minknow.as_ref(),
+                constants: &constants,
+                settings: &settings_snapshot,
+                report: &report,
+            };
+
+            match export_run_package(Path::new(&destination), &contents) {
+                Ok(path) => {
+                    ui.set_info_title(if fr { "Dossier exporté" } else { "Package Exported" }.into());
+                    // Pseudonyms come from this computer's key: another install makes different ones
+                    let pseudonyms = match (settings_snapshot.pseudonymize_package_ids, fr) {
+                        (true, true) => "\n\nLes identifiants sample et EPID sont remplacés par des pseudonymes. Ils restent les mêmes d'un run à l'autre sur cet ordinateur, mais un autre ordinateur en produit d'autres.",
+                        (true, false) => "\n\nSample and EPID identifiers are replaced by pseudonyms. They stay the same from run to run on this computer, but another computer makes different ones.",
+                        (false, _) => "",
+                    };
+                    ui.set_info_message((if fr {
+                        format!("Dossier de run enregistré :\n{}\n\nLes données EpiInfo ne sont pas incluses.{}", path.display(), pseudonyms)
+                    } else {
+                        format!("Run package saved:\n{}\n\nEpiInfo data is not included.{}", path.display(), pseudonyms)
+                    }).into());
+                    ui.set_show_info(1.0);
+                }
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur d'exportation" } else { "Export Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+
+    // Import: restores the run constants and the sample file into the form
+    let ui_handle = ui.as_weak();
+    ui.on_import_package(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+
+            let Some(path) = FileDialog::new().add_filter("Run package", &["zip"]).pick_file() else {
+                return;
+            };
+
+            match import_run_package(&path) {
+                Ok(package) => {
+                    ui.set_mode(SharedString::from(package.manifest.mode.clone()));
+                    ui.set_field_info(field_info_for_mode(&package.manifest.mode));
+                    show_run_constants(&ui, &package.constants);
+                    if let Some(data) = &package.minknow {
+                        show_minknow_data(&ui, data);
+                    }
+                    ui.set_sample_file(SharedString::from(package.sample_path.to_string_lossy().to_string()));
+                    refresh_slot_check(&ui, "sample_file");
+
+                    ui.set_info_title(if fr { "Dossier importé" } else { "Package Imported" }.into());
+                    ui.set_info_message((if fr {
+                        format!(
+                            "Run {} ({}) importé, créé le {} avec Merger {}.\nFichier d'échantillons : {}\nColonnes EpiInfo vidées : {}",
+                            package.manifest.run_num, package.manifest.mode, package.manifest.created,
+                            package.manifest.app_version, package.sample_path.display(),
+                            package.manifest.cleared_columns.len()
+                        )
+                    } else {
+                        format!(
+                            "Imported run {} ({}), created {} with Merger {}.\nSamples file: {}\nEpiInfo columns blanked: {}",
+                            package.manifest.run_num, package.manifest.mode, package.manifest.created,
+                            package.manifest.app_version, package.sample_path.display(),
+                            package.manifest.cleared_columns.len()
+                        )
+                    }).into());
+                    ui.set_show_info(1.0);
+                }
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur d'importation" } else { "Import Error" }.into());
+                    ui.set_error_message(e.into());
+                    ui.set_show_error(1.0);
+                }
+            }
+        }
+    });
+}
+
+fn show_bundles(ui: &AppWindow, active: &str) {
+    let names: Vec<SharedString> = list_bundles().into_iter().map(SharedString::from).collect();
+    ui.set_bundle_names(Rc::new(slint::VecModel::from(names)).into());
+    ui.set_active_bundle(SharedString::from(active));
+}
+
+// "Organize outputs by mode/month", kept in the settings
+fn setup_organize_outputs_handler(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    ui.set_organize_outputs(settings.borrow().organize_outputs_by_mode);
+
+    let ui_handle = ui.as_weak();
+    ui.on_organize_outputs_changed(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let organize = ui.get_organize_outputs();
+        match update_settings(|s| s.organize_outputs_by_mode = organize) {
+            Ok(saved) => *settings.borrow_mut() = saved,
+            Err(e) => {
+                eprintln!("{e}");
+                settings.borrow_mut().organize_outputs_by_mode = organize;
+            }
+        }
+    });
+}
+
+// Fills the form from the per-user settings; fields already filled are kept
+fn apply_user_defaults(ui: &AppWindow, settings: &Settings) {
+    if !settings.language.is_empty() {
+        ui.set_is_french(settings.language == "fr");
+    }
+    if ui.get_lab().is_empty() && !settings.lab_name.is_empty() {
+        ui.set_lab(settings.lab_name.as_str().into());
+    }
+    if ui.get_destination().is_empty() && !settings.default_destination.is_empty() {
+        ui.set_destination(settings.default_destination.as_str().into());
+    }
+}
+
+// Shows the wizard's current step
+fn show_setup_step(ui: &AppWindow, wizard: &SetupWizard) {
+    ui.set_setup_step(wizard.step.id().into());
+    ui.set_setup_optional(wizard.step.optional());
+    ui.set_setup_error("".into());
+    if wizard.step == WizardStep::Review {
+        ui.set_setup_review(wizard.review_text().into());
+    }
+}
+
+// Copies what was typed on the wizard's pages into its state
+fn read_setup_inputs(ui: &AppWindow, wizard: &mut SetupWizard) {
+    wizard.french = ui.get_is_french();
+    wizard.lab = ui.get_setup_lab().to_string();
+    wizard.destination = ui.get_setup_destination().to_string();
+    let bundle = ui.get_setup_bundle().to_string();
+    wizard.bundle = (!bundle.trim().is_empty()).then(|| PathBuf::from(bundle.trim()));
+    wizard.check_updates = ui.get_setup_check_updates();
+}
+
+fn setup_wizard_handlers(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    // In progress, None when closed; nothing is saved before "Finish"
+    let wizard: Rc<RefCell<Option<SetupWizard>>> = Rc::new(RefCell::new(None));
+
+    let ui_handle = ui.as_weak();
+    let state = wizard.clone();
+    let current = settings.clone();
+    ui.on_open_setup(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fresh = SetupWizard {
+            french: ui.get_is_french(),
+            lab: current.borrow().lab_name.clone(),
+            destination: current.borrow().default_destination.clone(),
+            check_updates: current.borrow().check_updates,
+            ..SetupWizard::default()
+        };
+        ui.set_setup_lab(fresh.lab.as_str().into());
+        ui.set_setup_destination(fresh.destination.as_str().into());
+        ui.set_setup_bundle("".into());
+        ui.set_setup_check_updates(fresh.check_updates);
+        show_setup_step(&ui, &fresh);
+        *state.borrow_mut() = Some(fresh);
+        ui.set_show_setup(1.0);
+    });
+
+    let ui_handle = ui.as_weak();
+    let state = wizard.clone();
+    ui.on_setup_next(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let mut state = state.borrow_mut();
+        let Some(wizard) = state.as_mut() else { return };
+        read_setup_inputs(&ui, wizard);
+
+        if wizard.step != WizardStep::Review {
+            match wizard.next() {
+                Ok(()) => show_setup_step(&ui, wizard),
+                Err(e) => ui.set_setup_error(e.into()),
+            }
+            return;
+        }
+        match wizard.finish() {
+            Ok(written) => {
+                apply_user_defaults(&ui, &written);
+                show_bundles(&ui, &written.active_bundle);
+                show_date_reported(&ui, &written.date_reported);
+                ui.set_template_max_rows(written.plate_capacity().max_rows as i32);
+                *settings.borrow_mut() = written;
+                *state = None;
+                ui.set_show_setup(0.0);
+                let fr = ui.get_is_french();
+                ui.set_toast_message(if fr { "Configuration enregistrée" } else { "Setup saved" }.into());
+                ui.set_toast_visible(true);
+            }
+            Err(e) => ui.set_setup_error(e.into()),
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    let state = wizard.clone();
+    ui.on_setup_back(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        if let Some(wizard) = state.borrow_mut().as_mut() {
+            read_setup_inputs(&ui, wizard);
+            wizard.back();
+            show_setup_step(&ui, wizard);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    let state = wizard.clone();
+    ui.on_setup_skip(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        if let Some(wizard) = state.borrow_mut().as_mut() {
+            read_setup_inputs(&ui, wizard);
+            match wizard.skip() {
+                Ok(()) => {
+                    ui.set_setup_destination(wizard.destination.as_str().into());
+                    if wizard.bundle.is_none() {
+                        ui.set_setup_bundle("".into());
+                    }
+                    show_setup_step(&ui, wizard);
+                }
+                Err(e) => ui.set_setup_error(e.into()),
+            }
+        }
+    });
+
+    // Cancelling drops everything collected; no settings file is written
+    let ui_handle = ui.as_weak();
+    let state = wizard.clone();
+    ui.on_setup_cancel(move || {
+        *state.borrow_mut() = None;
+        if let Some(ui) = ui_handle.upgrade() {
+            ui.set_show_setup(0.0);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_setup_browse_destination(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        if let Some(dir) = FileDialog::new().pick_folder() {
+            ui.set_setup_destination(dir.to_string_lossy().to_string().into());
+        }
+    });
+
+    // A bundle zip, or the bundle.json of a bundle folder
+    let ui_handle = ui.as_weak();
+    ui.on_setup_browse_bundle(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let Some(path) = FileDialog::new()
+            .add_filter("Configuration bundle", &["zip", "json"])
+            .pick_file()
+        else {
+            return;
+        };
+        let is_manifest = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let source = match (is_manifest, path.parent()) {
+            (true, Some(dir)) => dir.to_path_buf(),
+            _ => path,
+        };
+        ui.set_setup_bundle(source.to_string_lossy().to_string().into());
+    });
+}
+
+fn setup_bundle_handlers(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    show_bundles(ui, &settings.borrow().active_bundle);
+
+    // Import: a bundle zip, or the bundle.json of a bundle folder
+    let ui_handle = ui.as_weak();
+    ui.on_import_bundle(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+
+        let Some(path) = FileDialog::new()
+            .add_filter("Configuration bundle", &["zip", "json"])
+            .pick_file()
+        else {
+            return;
+        };
+        let is_manifest = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let source = if is_manifest { path.parent().map(Path::to_path_buf).unwrap_or(path) } else { path };
+
+        match import_bundle(&source) {
+            Ok(manifest) => {
+                let active = ui.get_active_bundle().to_string();
+                show_bundles(&ui, &active);
+                ui.set_info_title(if fr { "Configuration importée" } else { "Bundle Imported" }.into());
+                let about = [manifest.country.trim(), manifest.description.trim()]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" – ");
+                ui.set_info_message((if fr {
+                    format!(
+                        "Configuration « {} » importée ({}).\nChoisissez-la dans la liste Configuration pour l'utiliser.",
+                        manifest.name, if about.is_empty() { manifest.files.join(", ") } else { about }
+                    )
+                } else {
+                    format!(
+                        "Imported bundle '{}' ({}).\nPick it from the Bundle list to use it.",
+                        manifest.name, if about.is_empty() { manifest.files.join(", ") } else { about }
+                    )
+                }).into());
+                ui.set_show_info(1.0);
+            }
+            Err(e) => {
+                ui.set_error_title(if fr { "Erreur d'importation" } else { "Import Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+            }
+        }
+    });
+
+    // Switch: the bundle's settings replace the current ones, except the
+    // per-user appearance and recent folders
+    let ui_handle = ui.as_weak();
+    let switch_settings = settings.clone();
+    ui.on_bundle_selected(move |name| {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+        let name = name.to_string();
+
+        let switched = bundle_settings(&name)
+            .and_then(|bundle| update_settings(|s| apply_bundle(s, bundle, &name)));
+        match switched {
+            Ok(saved) => {
+                show_date_reported(&ui, &saved.date_reported);
+                ui.set_template_max_rows(saved.plate_capacity().max_rows as i32);
+                *switch_settings.borrow_mut() = saved;
+            }
+            Err(e) => {
+                show_bundles(&ui, &switch_settings.borrow().active_bundle);
+                ui.set_error_title(if fr { "Erreur de configuration" } else { "Bundle Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+            }
+        }
+    });
+
+    // Export: the current settings as a bundle, for authoring
+    let ui_handle = ui.as_weak();
+    ui.on_export_bundle(move || {
+        let Some(ui) = ui_handle.upgrade() else { return };
+        let fr = ui.get_is_french();
+
+        let Some(dir) = FileDialog::new().pick_folder() else {
+            return;
+        };
+        let snapshot = settings.borrow().clone();
+        let name = if snapshot.active_bundle.trim().is_empty() { "merger" } else { snapshot.active_bundle.trim() };
+        match export_bundle(&dir, name, &snapshot) {
+            Ok(path) => {
+                ui.set_info_title(if fr { "Configuration exportée" } else { "Bundle Exported" }.into());
+                ui.set_info_message((if fr {
+                    format!("Configuration enregistrée :\n{}\n\nModifiez bundle.json pour la renommer.", path.display())
+                } else {
+                    format!("Bundle saved:\n{}\n\nEdit bundle.json to rename it.", path.display())
+                }).into());
+                ui.set_show_info(1.0);
+            }
+            Err(e) => {
+                ui.set_error_title(if fr { "Erreur d'exportation" } else { "Export Error" }.into());
+                ui.set_error_message(e.into());
+                ui.set_show_error(1.0);
+            }
+        }
+    });
+}
+
+fn setup_template_handler(ui: &AppWindow, settings: Rc<RefCell<Settings>>) {
+    ui.set_template_max_rows(settings.borrow().plate_capacity().max_rows as i32);
+    let ui_handle = ui.as_weak();
+
+    ui.on_template(move || {
+        if let Some(ui) = ui_handle.upgrade() {
+            let fr = ui.get_is_french();
+            let current_mode = ui.get_mode().to_string();
+
+            let file_name = if current_mode == "minION" {
+                "sample_template_minion.csv"
+            } else {
+                "sample_template_ddns.csv"
+            };
+
+            let prefill = ui.get_template_prefill();
+            let created = if prefill {
+                // Never more rows than one run may have
+                let max_rows = settings.borrow().plate_capacity().max_rows;
+                let rows = (ui.get_template_rows().max(1) as usize).min(max_rows);
+                create_prefilled_template(&current_mode, rows, &run_constants_from_ui(&ui))
+            } else {
+                create_template_for_mode(&current_mode).map_err(|e| format!("{:?}", e))
+            };
+            let mut df = match created {
+                Ok(df) => df,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de modèle" } else { "Template Error" }.into());
+                    ui.set_error_message(if fr {
+                        format!("Échec de la création du modèle {} : {}", current_mode, e)
+                    } else {
+                        format!("Failed to create {} template DataFrame: {}", current_mode, e)
+                    }.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            // Downloads first, then ask the user, then the destination folder
+            let destination = ui.get_destination().to_string();
+            let destination = if destination.is_empty() { None } else { Some(PathBuf::from(destination)) };
+            let saved = save_with_fallback(
+                file_name,
+                dirs::download_dir().as_deref(),
+                destination.as_deref(),
+                |name| {
+                    let last_dirs = settings.borrow().last_dirs.clone();
+                    NativePicker.save_file(&dialog_spec("template", &last_dirs, destination.as_deref(), Some(name)))
+                },
+                |path| {
+                    let file = std::fs::File::create(path)
+                        .map_err(|e| format!("Failed to create template file at '{}': {:?}", path.display(), e))?;
+                    CsvWriter::new(file)
+                        .finish(&mut df)
+                        .map_err(|e| format!("Failed to write template CSV at '{}': {:?}", path.display(), e))
+                },
+            );
+
+            let file_path = match saved {
+                Ok(path) => path,
+                Err(e) => {
+                    ui.set_error_title(if fr { "Erreur de création de fichier" } else { "File Create Error" }.into());
+                    ui.set_error_message(if fr {
+                        format!("Impossible d'enregistrer le modèle :\n{}", e)
+                    } else {
+                        format!("Could not save the template:\n{}", e)
+                    }.into());
+                    ui.set_show_error(1.0);
+                    return;
+                }
+            };
+
+            let mode_label = if current_mode == "minION" {
+                "minION"
+            } else {
+                "DDNS"
             };
 
             ui.set_info_title(if fr { "Modèle enregistré" } else { "Template saved" }.into());
+            let rows_note = match (prefill, fr) {
+                (false, _) => String::new(),
+                (true, true) => format!(" {} lignes pré-remplies avec les constantes d'exécution.", df.height()),
+                (true, false) => format!(" {} rows pre-filled with the run constants.", df.height()),
+            };
             ui.set_info_message(if fr {
                 format!(
-                    "Modèle {} enregistré dans le dossier de téléchargements sous {}.",
-                    mode_label, file_name
+                    "Modèle {} enregistré sous {}.{}",
+                    mode_label, file_path.display(), rows_note
                 )
             } else {
                 format!(
-                    "{} template saved to downloads folder as {}.",
-                    mode_label, file_name
+                    "{} template saved as {}.{}",
+                    mode_label, file_path.display(), rows_note
                 )
             }.into());
             ui.set_show_info(1.0);
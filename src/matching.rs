@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::merge::is_control_sample;
+use crate::model::EpiData;
+
+// Numeric tails shorter than this match too much to be trusted at all
+const MIN_NUMERIC_TAIL: usize = 3;
+
+/// How a sample name was paired with an ICLabID, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MatchMethod {
+    Exact,
+    // Same once case and spaces are ignored ("env 0012" = "ENV0012")
+    Normalized,
+    // Same once a configured lab prefix is removed ("CMR-ENV0012" = "ENV0012")
+    PrefixStripped,
+    // Only the trailing number agrees; needs a manual check
+    NumericTail,
+    None,
+}
+
+impl MatchMethod {
+    /// Value of the MatchMethod helper column
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchMethod::Exact => "exact",
+            MatchMethod::Normalized => "normalized",
+            MatchMethod::PrefixStripped => "prefix-stripped",
+            MatchMethod::NumericTail => "numeric-tail",
+            MatchMethod::None => "none",
+        }
+    }
+}
+
+/// One samples file row and the ICLabID it was paired with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleMatch {
+    pub sample: String,
+    pub method: MatchMethod,
+    pub iclab_id: Option<String>,
+}
+
+/// Which fallbacks may pair a sample that has no exact ICLabID
+#[derive(Debug, Clone, Default)]
+pub struct MatchOptions {
+    pub fallbacks: bool,
+    // Lab prefixes removed from both sides before comparing
+    pub prefixes: Vec<String>,
+}
+
+fn normalize(id: &str) -> String {
+    id.chars().filter(|c| !c.is_whitespace()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+fn strip_prefix(normalized: &str, prefixes: &[String]) -> Option<String> {
+    prefixes.iter().find_map(|prefix| {
+        let prefix = normalize(prefix);
+        normalized
+            .strip_prefix(prefix.as_str())
+            .filter(|_| !prefix.is_empty())
+            .map(|rest| rest.trim_start_matches(['-', '_', '.', '/']).to_string())
+            .filter(|rest| !rest.is_empty())
+    })
+}
+
+// Trailing digits without leading zeros: "ENV-000123" → "123"
+fn numeric_tail(id: &str) -> Option<String> {
+    let digits: String = id.chars().rev().take_while(|c| c.is_ascii_digit()).collect();
+    let digits: String = digits.chars().rev().collect();
+    let tail = digits.trim_start_matches('0');
+    (tail.len() >= MIN_NUMERIC_TAIL).then(|| tail.to_string())
+}
+
+// key → ICLabID, leaving out keys more than one ICLabID share
+fn unique_keys<'a>(ids: &[&'a str], key: impl Fn(&str) -> Option<String>) -> HashMap<String, &'a str> {
+    let mut seen: HashMap<String, Option<&'a str>> = HashMap::new();
+    for id in ids {
+        if let Some(k) = key(id) {
+            seen.entry(k)
+                .and_modify(|existing| {
+                    if *existing != Some(*id) {
+                        *existing = None;
+                    }
+                })
+                .or_insert(Some(*id));
+        }
+    }
+    seen.into_iter().filter_map(|(k, id)| id.map(|id| (k, id))).collect()
+}
+
+/// Pairs every sample with an ICLabID, trying the strategies in order.
+/// Fallbacks only apply to non-control samples, never pair two samples
+/// with the same ICLabID, and skip keys shared by several ICLabIDs.
+pub fn match_samples(samples: &[String], epi: &EpiData, options: &MatchOptions) -> Vec<SampleMatch> {
+    let ids: Vec<&str> = epi.ids().collect();
+    let mut claimed: HashSet<&str> = samples.iter().filter(|s| epi.contains(s)).map(|s| s.as_str()).collect();
+
+    let normalized = unique_keys(&ids, |id| Some(normalize(id)));
+    let stripped = unique_keys(&ids, |id| {
+        let id = normalize(id);
+        Some(strip_prefix(&id, &options.prefixes).unwrap_or(id))
+    });
+    let tails = unique_keys(&ids, numeric_tail);
+
+    samples
+        .iter()
+        .map(|sample| {
+            let unmatched = SampleMatch {
+                sample: sample.clone(),
+                method: MatchMethod::None,
+                iclab_id: None,
+            };
+            if epi.contains(sample) {
+                return SampleMatch {
+                    method: MatchMethod::Exact,
+                    iclab_id: Some(sample.clone()),
+                    ..unmatched
+                };
+            }
+            if !options.fallbacks || is_control_sample(sample) {
+                return unmatched;
+            }
+
+            let key = normalize(sample);
+            let candidates = [
+                (MatchMethod::Normalized, normalized.get(&key)),
+                (
+                    MatchMethod::PrefixStripped,
+                    strip_prefix(&key, &options.prefixes)
+                        .or_else(|| (!options.prefixes.is_empty()).then(|| key.clone()))
+                        .and_then(|k| stripped.get(&k)),
+                ),
+                (MatchMethod::NumericTail, numeric_tail(sample).and_then(|t| tails.get(&t))),
+            ];
+            for (method, id) in candidates {
+                if let Some(id) = id.filter(|id| !claimed.contains(**id)) {
+                    claimed.insert(*id);
+                    return SampleMatch {
+                        method,
+                        iclab_id: Some(id.to_string()),
+                        ..unmatched
+                    };
+                }
+            }
+            unmatched
+        })
+        .collect()
+}
+
+/// How many samples each strategy paired, strongest first, zeros left out
+pub fn method_counts(matches: &[SampleMatch]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(MatchMethod, usize)> = Vec::new();
+    for m in matches {
+        match counts.iter_mut().find(|(method, _)| *method == m.method) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((m.method, 1)),
+        }
+    }
+    counts.sort();
+    counts.into_iter().map(|(method, n)| (method.label().to_string(), n)).collect()
+}
+
+/// Samples paired only by their trailing number, as (sample, ICLabID)
+pub fn weak_matches(matches: &[SampleMatch]) -> Vec<(String, String)> {
+    matches
+        .iter()
+        .filter(|m| m.method == MatchMethod::NumericTail)
+        .filter_map(|m| m.iclab_id.clone().map(|id| (m.sample.clone(), id)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    fn epi(ids: &[&str]) -> EpiData {
+        EpiData::new(df!("ICLabID" => ids).unwrap()).unwrap()
+    }
+
+    fn names(samples: &[&str]) -> Vec<String> {
+        samples.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn with_fallbacks(prefixes: &[&str]) -> MatchOptions {
+        MatchOptions { fallbacks: true, prefixes: names(prefixes) }
+    }
+
+    fn methods(matches: &[SampleMatch]) -> Vec<(&str, MatchMethod, Option<&str>)> {
+        matches.iter().map(|m| (m.sample.as_str(), m.method, m.iclab_id.as_deref())).collect()
+    }
+
+    const IDS: &[&str] = &["S01", "ENV0012", "CMR-ENV0099", "LAB/000123", "X-00456", "Y-456", "ntc"];
+
+    #[test]
+    fn each_strategy_is_recorded_per_row() {
+        let samples = names(&["S01", "env 0012", "ENV0099", "Sample 123", "Z-456", "NTC", "S99"]);
+        let matches = match_samples(&samples, &epi(IDS), &with_fallbacks(&["CMR"]));
+        assert_eq!(
+            methods(&matches),
+            vec![
+                ("S01", MatchMethod::Exact, Some("S01")),
+                ("env 0012", MatchMethod::Normalized, Some("ENV0012")),
+                ("ENV0099", MatchMethod::PrefixStripped, Some("CMR-ENV0099")),
+                ("Sample 123", MatchMethod::NumericTail, Some("LAB/000123")),
+                // 456 is the tail of two ICLabIDs
+                ("Z-456", MatchMethod::None, None),
+                // Controls only ever match exactly
+                ("NTC", MatchMethod::None, None),
+                ("S99", MatchMethod::None, None),
+            ]
+        );
+
+        assert_eq!(
+            method_counts(&matches),
+            vec![
+                ("exact".to_string(), 1),
+                ("normalized".to_string(), 1),
+                ("prefix-stripped".to_string(), 1),
+                ("numeric-tail".to_string(), 1),
+                ("none".to_string(), 3),
+            ]
+        );
+        assert_eq!(weak_matches(&matches), vec![("Sample 123".to_string(), "LAB/000123".to_string())]);
+    }
+
+    #[test]
+    fn fallbacks_are_off_unless_asked_for() {
+        let samples = names(&["S01", "env 0012", "Sample 123"]);
+        let matches = match_samples(&samples, &epi(IDS), &MatchOptions::default());
+        let found: Vec<MatchMethod> = matches.iter().map(|m| m.method).collect();
+        assert_eq!(found, [MatchMethod::Exact, MatchMethod::None, MatchMethod::None]);
+        assert!(weak_matches(&matches).is_empty());
+    }
+
+    #[test]
+    fn an_iclab_id_is_paired_with_one_sample_only() {
+        // The exact match claims ENV0012 before the fallback sees it
+        let samples = names(&["env 0012", "ENV0012"]);
+        let matches = match_samples(&samples, &epi(IDS), &with_fallbacks(&[]));
+        assert_eq!(
+            methods(&matches),
+            vec![("env 0012", MatchMethod::None, None), ("ENV0012", MatchMethod::Exact, Some("ENV0012"))]
+        );
+
+        // Short numeric tails are never used
+        let matches = match_samples(&names(&["Z-12"]), &epi(IDS), &with_fallbacks(&[]));
+        assert_eq!(matches[0].method, MatchMethod::None);
+    }
+
+    #[test]
+    fn match_method_column_follows_the_rows() {
+        let samples = names(&["S01", "env 0012", "S99"]);
+        let matches = match_samples(&samples, &epi(IDS), &with_fallbacks(&[]));
+        let mut df = df!("sample" => [" S01", "env 0012", "S99", "NC"]).unwrap();
+        crate::merge::add_match_method_column(&mut df, &matches).unwrap();
+        let column = df.column(crate::merge::MATCH_METHOD_COLUMN).unwrap().str().unwrap().clone();
+        let values: Vec<Option<&str>> = column.into_iter().collect();
+        assert_eq!(values, [Some("exact"), Some("normalized"), Some("none"), Some("none")]);
+    }
+}
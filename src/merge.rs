@@ -1,35 +1,13 @@
 use polars::prelude::*;
 use regex::Regex;
-use std::collections::HashSet;
-
-use crate::template::expected_columns_for_mode;
-
-/// Input parameters for a merge op
-pub struct MergeParams {
-    pub mode: String,
-    pub action: String, 
-    pub overwrite_existing: bool,
-    pub run_num: String,
-    pub minknow_ver: Option<String>,
-    pub pir_ver: String,
-    pub seq_date: Option<String>,
-    pub fc_id: Option<String>,
-    pub fc_uses: String,
-    pub fc_pores: Option<String>,
-    pub seq_hours: Option<String>,
-    pub fasta_date: String,
-    pub seq_kit: Option<String>,
-    pub rt_date: String,
-    pub lab: String,
-    pub pos_con: String,
-    pub neg_con: String,
-    // DDNS-specific
-    pub vp1_date: String,
-    pub pcr_machine: String,
-    pub vp1_pcr_machine: String,
-    pub rtpcr_primers: String,
-    pub vp1_primers: String,
-}
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::matching::{MatchMethod, SampleMatch};
+use crate::model::{EpiData, MergedRun, SampleSheet};
+use crate::run_constants::{DateReportedPolicy, FillPolicies, FillPolicy, RunConstants};
+use crate::template::{expected_columns_for_mode, optional_columns_for_mode};
+use crate::warnings::ValidationWarning;
 
 /// Validates date format
 pub fn validate_date(val: &str, name: &str) -> Option<String> {
@@ -51,21 +29,159 @@ pub fn validate_run_number(run_num: &str) -> Option<String> {
     }
 }
 
+/// Free-text run fields of the UI: (key, English label, French label, output column).
+/// A field is only used in a mode whose template has its column.
+pub const UI_FIELDS: &[(&str, &str, &str, &str)] = &[
+    ("lab", "Laboratory", "Laboratoire", "institute"),
+    ("run_num", "Run Number", "Numéro d'exécution", "RunNumber"),
+    ("pir_ver", "Piranha Version", "Version Piranha", "AnalysisPipelineVersion"),
+    ("rt_date", "RT Date", "Date RT", "DateRTPCR"),
+    ("vp1_date", "VP1 Date", "Date VP1", "DateVP1PCR"),
+    ("pcr_machine", "RTPCR Machine", "Machine RTPCR", "RTPCRMachine"),
+    ("vp1_pcr_machine", "VP1 PCR Machine", "Machine VP1 PCR", "VP1PCRMachine"),
+    ("rtpcr_primers", "RTPCR Primers", "Amorces RTPCR", "RTPCRprimers"),
+    ("vp1_primers", "VP1 Primers", "Amorces VP1", "VP1primers"),
+    ("fc_uses", "FlowCell Prior Use", "Utilisations FlowCell", "FlowCellPriorUses"),
+];
+
+/// How one UI run field looks in a mode, derived from UI_FIELDS and the
+/// mode's template so the form and the validation cannot disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UiFieldDef {
+    pub key: &'static str,
+    pub label_en: &'static str,
+    pub label_fr: &'static str,
+    // Placeholder showing the format the validation expects, "" for free text
+    pub hint_en: &'static str,
+    pub hint_fr: &'static str,
+    // The mode's template has the field's column
+    pub enabled: bool,
+}
+
+// Format hint of an output column, matching validate_run_number/validate_date
+fn column_hint(column: &str) -> (&'static str, &'static str) {
+    if column == "RunNumber" {
+        ("YYYYMMDD_XXX", "AAAAMMJJ_XXX")
+    } else if column.starts_with("Date") {
+        ("YYYY-MM-DD", "AAAA-MM-JJ")
+    } else {
+        ("", "")
+    }
+}
+
+/// Every UI run field as the mode's template defines it, in UI_FIELDS order
+pub fn ui_field_defs(mode: &str) -> Vec<UiFieldDef> {
+    let expected = expected_columns_for_mode(mode);
+    UI_FIELDS
+        .iter()
+        .map(|(key, label_en, label_fr, column)| {
+            let (hint_en, hint_fr) = column_hint(column);
+            UiFieldDef {
+                key,
+                label_en,
+                label_fr,
+                hint_en,
+                hint_fr,
+                enabled: expected.contains(column),
+            }
+        })
+        .collect()
+}
+
+/// Keys of fields the user filled in that the mode does not use
+pub fn ignored_fields(mode: &str, values: &[(&str, String)]) -> Vec<String> {
+    let defs = ui_field_defs(mode);
+    values
+        .iter()
+        .filter(|(_, v)| !v.trim().is_empty())
+        .filter(|(key, _)| defs.iter().any(|d| d.key == *key && !d.enabled))
+        .map(|(key, _)| key.to_string())
+        .collect()
+}
+
+/// Display label of a UI field key
+pub fn ui_field_label(key: &str, french: bool) -> &str {
+    UI_FIELDS
+        .iter()
+        .find(|(k, _, _, _)| *k == key)
+        .map(|(_, en, fr, _)| if french { *fr } else { *en })
+        .unwrap_or(key)
+}
+
+/// EpiInfo → minION template column renames
+const MINION_EPI_RENAMES: &[(&str, &str)] = &[
+    ("DateFinalCellCultureResults", "DateFinalCultureResult"),
+    ("DateFinalrRTPCRResults", "DateFinalITDresult"),
+    ("FinalITDResult", "ITDResult"),
+    ("SequenceName", "SangerSequenceID"),
+    ("DateSeqResult", "DateSangerResultGenerated"),
+];
+
+/// EpiInfo spellings of the EPID, in priority order
+pub const DEFAULT_EPID_SOURCES: &[&str] = &["EpidNumber", "EPIDNumber", "EpidNo"];
+
+/// EpiInfo columns the merge can actually use for a mode: the join key,
+/// the EPID sources, the rename sources and the expected output columns
+pub fn epiinfo_columns_for_mode(mode: &str, epid_sources: &[String]) -> HashSet<String> {
+    let mut cols: HashSet<String> = expected_columns_for_mode(mode)
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    cols.insert("ICLabID".to_string());
+    cols.extend(epid_sources.iter().cloned());
+    if mode == "minION" {
+        for (old, _) in MINION_EPI_RENAMES {
+            cols.insert(old.to_string());
+        }
+    }
+    cols
+}
+
+/// EpiInfo column name fragments that suggest personal data
+pub const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &["Name", "Phone", "Address"];
+
+/// Columns whose name contains one of `patterns`, ignoring case. The join
+/// key is never treated as sensitive.
+pub fn sensitive_columns(columns: &[String], patterns: &[String]) -> Vec<String> {
+    let patterns: Vec<String> = patterns
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty())
+        .collect();
+    columns
+        .iter()
+        .filter(|c| c.as_str() != "ICLabID")
+        .filter(|c| {
+            let name = c.to_lowercase();
+            patterns.iter().any(|p| name.contains(p.as_str()))
+        })
+        .cloned()
+        .collect()
+}
+
+/// EpiInfo columns to leave out of the merge: sensitive ones unless the
+/// user opted in, and any other the user unticked in the review
+pub fn excluded_epiinfo_columns(
+    columns: &[String],
+    patterns: &[String],
+    choices: &HashMap<String, bool>,
+) -> Vec<String> {
+    let sensitive = sensitive_columns(columns, patterns);
+    columns
+        .iter()
+        .filter(|c| c.as_str() != "ICLabID")
+        .filter(|c| !choices.get(c.as_str()).copied().unwrap_or(!sensitive.contains(c)))
+        .cloned()
+        .collect()
+}
+
 /// Renames EpiInfo columns for minION mode
 pub fn rename_epiinfo_columns_for_minion(epi_df: &mut DataFrame) -> Result<(), String> {
     let has_col = |df: &DataFrame, name: &str| {
         df.get_column_names().iter().any(|n| n.as_str() == name)
     };
 
-    let rename_pairs = [
-        ("DateFinalCellCultureResults", "DateFinalCultureResult"),
-        ("DateFinalrRTPCRResults", "DateFinalITDresult"),
-        ("FinalITDResult", "ITDResult"),
-        ("SequenceName", "SangerSequenceID"),
-        ("DateSeqResult", "DateSangerResultGenerated"),
-    ];
-
-    for (old, new_) in rename_pairs {
+    for &(old, new_) in MINION_EPI_RENAMES {
         if has_col(epi_df, old) && !has_col(epi_df, new_) {
             epi_df
                 .rename(old, PlSmallStr::from_str(new_))
@@ -76,11 +192,230 @@ pub fn rename_epiinfo_columns_for_minion(epi_df: &mut DataFrame) -> Result<(), S
     Ok(())
 }
 
-// Merges sample_df with epi_df
+/// Samples no strategy paired with an ICLabID
+pub fn unmatched_samples(matches: &[SampleMatch]) -> Vec<String> {
+    matches
+        .iter()
+        .filter(|m| m.iclab_id.is_none() && !m.sample.is_empty())
+        .map(|m| m.sample.clone())
+        .collect()
+}
+
+// Ask before merging when fewer non-control samples than this matched
+pub const DEFAULT_MIN_EPI_MATCH_PERCENT: u8 = 50;
+
+// First word of a sample name that marks a PCR or sequencing control
+const CONTROL_SAMPLE_WORDS: &[&str] = &[
+    "NTC", "NC", "PC", "NEG", "POS", "NEGATIVE", "POSITIVE", "BLANK", "CONTROL", "CTRL",
+];
+
+/// True for control rows ("NTC", "PC-1", "Negative control", ...), which
+/// are never in the EpiInfo export
+pub fn is_control_sample(name: &str) -> bool {
+    let upper = name.trim().to_uppercase();
+    let first = upper.split(|c: char| !c.is_alphanumeric()).next().unwrap_or("");
+    CONTROL_SAMPLE_WORDS.contains(&first) || upper.contains("CONTROL")
+}
+
+/// How many non-control samples got EpiInfo data, out of how many
+/// non-control samples. A sample counts when the ICLabID it was paired
+/// with has at least one non-empty value besides the ICLabID itself.
+pub fn epi_match_rate(matches: &[SampleMatch], epi: &EpiData) -> Result<(usize, usize), String> {
+    let epi_df = epi.frame();
+    let mut with_data: HashSet<String> = HashSet::new();
+    if let Ok(ids) = epi_df.column("ICLabID") {
+        let ids = ids.str().map_err(|e| format!("ICLabID column is not text: {e}"))?;
+        let values: Vec<Column> = epi_df
+            .get_columns()
+            .iter()
+            .filter(|c| c.name().as_str() != "ICLabID")
+            .map(|c| c.cast(&DataType::String))
+            .collect::<PolarsResult<_>>()
+            .map_err(|e| format!("Failed to read the EpiInfo columns: {e}"))?;
+        for (idx, id) in ids.into_iter().enumerate() {
+            let Some(id) = id else { continue };
+            let has_value = values.iter().any(|c| {
+                c.str()
+                    .ok()
+                    .and_then(|ca| ca.get(idx))
+                    .is_some_and(|v| !v.trim().is_empty())
+            });
+            if has_value {
+                with_data.insert(id.to_string());
+            }
+        }
+    }
+
+    let (mut matched, mut total) = (0, 0);
+    for m in matches {
+        if m.sample.is_empty() || is_control_sample(&m.sample) {
+            continue;
+        }
+        total += 1;
+        if m.iclab_id.as_ref().is_some_and(|id| with_data.contains(id)) {
+            matched += 1;
+        }
+    }
+    Ok((matched, total))
+}
+
+/// Whether `matched` of `total` is below `threshold_percent`. 0 turns the
+/// check off, and a run without any non-control samples never trips it.
+pub fn match_rate_too_low(matched: usize, total: usize, threshold_percent: u8) -> bool {
+    threshold_percent > 0 && total > 0 && matched * 100 < total * threshold_percent as usize
+}
+
+/// True for Piranha's two-column barcodes.csv (sample and barcode, any order)
+pub fn is_barcodes_only(df: &DataFrame) -> bool {
+    let cols: HashSet<&str> = df.get_column_names().iter().map(|s| s.as_str()).collect();
+    cols.len() == 2 && cols.contains("sample") && cols.contains("barcode")
+}
+
+/// Adds the mode's remaining expected columns as empty so a barcodes.csv can
+/// go through the normal pipeline. Returns the frame and how many columns were added.
+pub fn expand_barcodes_only(df: DataFrame, mode: &str) -> Result<(DataFrame, usize), String> {
+    let mut df = df;
+    let mut added = 0;
+
+    for name in expected_columns_for_mode(mode) {
+        if df.column(name).is_ok() {
+            continue;
+        }
+        let empty = Series::full_null(PlSmallStr::from_str(name), df.height(), &DataType::String);
+        df.with_column(empty)
+            .map_err(|e| format!("Failed to add column '{}': {e}", name))?;
+        added += 1;
+    }
+
+    Ok((df, added))
+}
+
+/// Adds the mode's optional template columns a sheet from an older
+/// template lacks, as empty columns. Returns the frame and the added names.
+pub fn add_optional_columns(df: DataFrame, mode: &str) -> Result<(DataFrame, Vec<String>), String> {
+    let mut df = df;
+    let mut added = Vec::new();
+
+    for name in optional_columns_for_mode(mode) {
+        if df.column(name).is_ok() {
+            continue;
+        }
+        let empty = Series::full_null(PlSmallStr::from_str(name), df.height(), &DataType::String);
+        df.with_column(empty)
+            .map_err(|e| format!("Failed to add column '{}': {e}", name))?;
+        added.push(name.to_string());
+    }
+
+    Ok((df, added))
+}
+
+fn missing_value(v: &Option<String>) -> bool {
+    v.as_deref().is_none_or(|s| s.trim().is_empty())
+}
+
+/// Fills empty cells of `target` from `sources`, first non-empty source wins,
+/// then drops the sources. Returns how many cells each source supplied.
+pub fn coalesce_columns(
+    df: DataFrame,
+    target: &str,
+    sources: &[String],
+) -> Result<(DataFrame, Vec<(String, usize)>), String> {
+    let present: Vec<&String> = sources
+        .iter()
+        .filter(|s| s.as_str() != target && df.column(s).is_ok())
+        .collect();
+    if present.is_empty() {
+        return Ok((df, Vec::new()));
+    }
+
+    let str_values = |name: &str| -> Result<Vec<Option<String>>, String> {
+        let ca = df
+            .column(name)
+            .and_then(|c| c.str().cloned())
+            .map_err(|e| format!("Column '{}' is not text: {e}", name))?;
+        Ok(ca.into_iter().map(|v| v.map(|s| s.to_string())).collect())
+    };
+
+    let mut values = if df.column(target).is_ok() {
+        str_values(target)?
+    } else {
+        vec![None; df.height()]
+    };
+
+    let mut supplied = Vec::new();
+    for source in &present {
+        let mut count = 0;
+        for (value, candidate) in values.iter_mut().zip(str_values(source)?) {
+            if missing_value(value) && !missing_value(&candidate) {
+                *value = candidate;
+                count += 1;
+            }
+        }
+        supplied.push((source.to_string(), count));
+    }
+
+    let mut df = df;
+    df.with_column(Series::new(PlSmallStr::from_str(target), values))
+        .map_err(|e| format!("Failed to fill column '{}': {e}", target))?;
+    let df = df.drop_many(present.iter().map(|s| s.as_str()));
+
+    Ok((df, supplied))
+}
+
+/// How a free-text column both the samples file and EpiInfo have is merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CommentPolicy {
+    // The sample's text, or EpiInfo's when the sample has none
+    FirstNonEmpty,
+    // Both texts: "lab text; EpiInfo: epi text"
+    #[default]
+    Concatenate,
+    // The sample's text only, even when empty
+    PreferSample,
+    // EpiInfo's text only, even when empty
+    PreferEpi,
+}
+
+/// Comment columns merged per policy rather than taken from EpiInfo
+pub fn default_comment_columns() -> BTreeMap<String, CommentPolicy> {
+    BTreeMap::from([("QCComments".to_string(), CommentPolicy::Concatenate)])
+}
+
+/// One row's comment from the sample's and EpiInfo's text
+pub fn merge_comment(sample: Option<&str>, epi: Option<&str>, policy: CommentPolicy) -> Option<String> {
+    let text = |v: Option<&str>| v.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let (sample, epi) = (text(sample), text(epi));
+    match policy {
+        CommentPolicy::FirstNonEmpty => sample.or(epi),
+        CommentPolicy::PreferSample => sample,
+        CommentPolicy::PreferEpi => epi,
+        CommentPolicy::Concatenate => match (sample, epi) {
+            (Some(s), Some(e)) if s == e => Some(s),
+            (Some(s), Some(e)) => Some(format!("{}; EpiInfo: {}", s, e)),
+            (s, e) => s.or(e),
+        },
+    }
+}
+
+// The sample side of a comment column while the join runs
+fn sample_comment_column(column: &str) -> String {
+    format!("__merger_sample_{}", column)
+}
+
+/// Joins the sheet with the EpiInfo export, each sample on the ICLabID
+/// `matches` paired it with (one entry per sheet row, in order), filling
+/// EPID from the first populated source. Sources that supplied EPIDs are
+/// reported in the run's warnings. Other columns both sides have come from
+/// EpiInfo, except the `comment_columns`, merged per their policy.
 pub fn merge_with_epiinfo(
-    sample_df: DataFrame,
-    epi_df: DataFrame,
-) -> Result<DataFrame, String> {
+    sheet: SampleSheet,
+    epi: EpiData,
+    matches: &[SampleMatch],
+    epid_sources: &[String],
+    comment_columns: &BTreeMap<String, CommentPolicy>,
+) -> Result<MergedRun, String> {
+    let mut sample_df = sheet.into_frame();
+    let epi_df = epi.into_frame();
     let sample_cols: HashSet<String> = sample_df
         .get_column_names()
         .iter()
@@ -91,30 +426,100 @@ pub fn merge_with_epiinfo(
         .iter()
         .map(|&s| s.to_string())
         .collect();
-    let common_columns: Vec<String> = sample_cols.intersection(&epi_cols).cloned().collect();
+    let (comments, common_columns): (Vec<String>, Vec<String>) = sample_cols
+        .intersection(&epi_cols)
+        .cloned()
+        .partition(|c| comment_columns.contains_key(c));
 
+    // The join key is the paired ICLabID, which differs from the sample
+    // name when a fallback strategy matched
+    let keys: Vec<Option<String>> = matches.iter().map(|m| m.iclab_id.clone()).collect();
+    if keys.len() != sample_df.height() {
+        return Err(format!(
+            "Failed to merge dataframes: {} sample matches for {} rows",
+            keys.len(),
+            sample_df.height()
+        ));
+    }
+    sample_df
+        .with_column(Series::new(PlSmallStr::from_str(JOIN_KEY), keys))
+        .map_err(|e| format!("Failed to merge dataframes: {e}"))?;
+    // Comment columns keep the sample's text under a temporary name
+    for column in &comments {
+        sample_df
+            .rename(column, PlSmallStr::from_string(sample_comment_column(column)))
+            .map_err(|e| format!("Failed to merge dataframes: {e}"))?;
+    }
     let sample_df = sample_df.drop_many(common_columns);
 
-    let merged = sample_df
-        .left_join(&epi_df, ["sample"], ["ICLabID"])
+    let mut merged = sample_df
+        .left_join(&epi_df, [JOIN_KEY], ["ICLabID"])
+        .map_err(|e| format!("Failed to merge dataframes: {e}"))?
+        .drop(JOIN_KEY)
         .map_err(|e| format!("Failed to merge dataframes: {e}"))?;
 
+    for column in &comments {
+        let temp = sample_comment_column(column);
+        let policy = comment_columns.get(column).copied().unwrap_or_default();
+        let text = |name: &str| -> Result<Vec<Option<String>>, String> {
+            let values = merged
+                .column(name)
+                .and_then(|c| c.cast(&DataType::String))
+                .map_err(|e| format!("Failed to merge column '{}': {e}", column))?;
+            let values = values.str().map_err(|e| format!("Failed to merge column '{}': {e}", column))?;
+            Ok(values.into_iter().map(|v| v.map(str::to_string)).collect())
+        };
+        let combined: Vec<Option<String>> = text(&temp)?
+            .iter()
+            .zip(text(column)?.iter())
+            .map(|(sample, epi)| merge_comment(sample.as_deref(), epi.as_deref(), policy))
+            .collect();
+        merged
+            .with_column(Series::new(PlSmallStr::from_str(column), combined))
+            .map_err(|e| format!("Failed to merge column '{}': {e}", column))?;
+        merged = merged.drop(&temp).map_err(|e| format!("Failed to merge column '{}': {e}", column))?;
+    }
+
     // Normalize EPID column
-    let df = merged
-        .lazy()
-        .with_columns([when(col("EPID").is_null())
-            .then(col("EpidNumber"))
-            .otherwise(col("EPID"))
-            .alias("EPID")])
-        .collect()
+    let (df, supplied) = coalesce_columns(merged, "EPID", epid_sources)
         .map_err(|e| format!("Failed to normalize EPID column: {e}"))?;
 
-    match df.drop("EpidNumber") {
-        Ok(df2) => Ok(df2),
-        Err(_) => Ok(df),
+    let supplied: Vec<(String, usize)> = supplied.into_iter().filter(|(_, n)| *n > 0).collect();
+    let mut warnings = Vec::new();
+    if !supplied.is_empty() {
+        warnings.push(ValidationWarning::EpidFilled { sources: supplied });
     }
+    Ok(MergedRun::new(df, warnings))
+}
+
+// Temporary column holding each row's paired ICLabID during the join
+const JOIN_KEY: &str = "__merger_epi_key";
+
+/// Adds the MatchMethod helper column: how each row's sample was paired
+/// with EpiInfo. Rows the merge did not pair (added controls) get "none".
+pub fn add_match_method_column(df: &mut DataFrame, matches: &[SampleMatch]) -> Result<(), String> {
+    let methods: HashMap<&str, &str> = matches
+        .iter()
+        .rev()
+        .map(|m| (m.sample.as_str(), m.method.label()))
+        .collect();
+    let samples = df
+        .column("sample")
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(|e| format!("Failed to read sample column: {e}"))?;
+    let values: Vec<&str> = samples
+        .str()
+        .map_err(|e| format!("Failed to read sample column: {e}"))?
+        .into_iter()
+        .map(|s| s.and_then(|s| methods.get(s.trim()).copied()).unwrap_or(MatchMethod::None.label()))
+        .collect();
+    df.with_column(Series::new(PlSmallStr::from_str(MATCH_METHOD_COLUMN), values))
+        .map_err(|e| format!("Failed to add column '{}': {e}", MATCH_METHOD_COLUMN))?;
+    Ok(())
 }
 
+pub const MATCH_METHOD_COLUMN: &str = "MatchMethod";
+
 /// Validates that all expected columns are present
 pub fn validate_columns(df: &DataFrame, mode: &str) -> Result<(), String> {
     let expected_columns = expected_columns_for_mode(mode);
@@ -141,110 +546,197 @@ pub fn validate_columns(df: &DataFrame, mode: &str) -> Result<(), String> {
     }
 }
 
-// Converts PCR control selection to Pass/Fail string
-pub fn pcr_control_value(selection: &str) -> &'static str {
-    match selection {
-        "Positive Passed" | "Negative Passed" => "Pass",
-        "Positive Failed" | "Negative Failed" => "Fail",
-        "Unselected" => "",
-        _ => "unknown",
+// Writes `value` into `column` according to its fill policy.
+// An empty value never changes the column.
+fn fill_column(column: &str, value: &str, policy: FillPolicy) -> Expr {
+    let current = col(column).cast(DataType::String);
+    if value.is_empty() {
+        return current.alias(column);
+    }
+    match policy {
+        FillPolicy::Skip => current.alias(column),
+        FillPolicy::FillEmpty => when(current.clone().is_null().or(current.clone().eq(lit(""))))
+            .then(lit(value))
+            .otherwise(current)
+            .alias(column),
+        FillPolicy::Overwrite => lit(value.to_string()).alias(column),
     }
 }
 
-// Helper to fill a column if it's null or empty string
-fn fill_if_empty(column: &str, value: &str) -> Expr {
-    when(col(column).is_null().or(col(column).eq(lit(""))))
-        .then(lit(value))
-        .otherwise(col(column))
-        .alias(column)
-}
+/// Output column and value of every run constant the mode has
+pub fn run_constant_values(mode: &str, params: &RunConstants) -> Vec<(&'static str, String)> {
+    let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+    let mut values = vec![
+        ("RunNumber", params.run_num.clone()),
+        ("MinKNOWSoftwareVersion", opt(&params.minknow_ver)),
+        ("AnalysisPipelineVersion", params.pir_ver.clone()),
+        ("DateSeqRunLoaded", opt(&params.seq_date)),
+        ("FlowCellID", opt(&params.fc_id)),
+        ("FlowCellPriorUses", params.fc_uses.clone()),
+        ("PoresAvilableAtFlowCellCheck", opt(&params.fc_pores)),
+        ("RunHoursDuration", opt(&params.seq_hours)),
+        ("DateFastaGenerated", params.fasta_date.clone()),
+        ("LibraryPreparationKit", opt(&params.seq_kit)),
+        ("DateRTPCR", params.rt_date.clone()),
+        ("PositiveControlPCRCheck", params.pos_con.output_value().to_string()),
+    ];
 
-// Conditionally fills a column based on overwrite setting and value
-fn conditional_fill(column: &str, value: &str, overwrite: bool) -> Expr {
-    if !overwrite || value.is_empty() {
-        col(column).alias(column)
+    if mode == "minION" {
+        values.extend([
+            ("NegativeControlPCRheck", params.neg_con.output_value().to_string()),
+            ("institute", params.lab.clone()),
+        ]);
     } else {
-        fill_if_empty(column, value)
+        values.extend([
+            ("NegativeControlPCRCheck", params.neg_con.output_value().to_string()),
+            ("DateVP1PCR", params.vp1_date.clone()),
+            ("RTPCRMachine", params.pcr_machine.clone()),
+            ("VP1PCRMachine", params.vp1_pcr_machine.clone()),
+            ("RTPCRprimers", params.rtpcr_primers.clone()),
+            ("VP1primers", params.vp1_primers.clone()),
+        ]);
+    }
+    values
+}
+
+/// A run constant typed in the form that differs from values the samples
+/// file already has in its column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstantConflict {
+    pub column: String,
+    pub typed: String,
+    // Distinct values already in the column with their row counts, most
+    // frequent first
+    pub existing: Vec<(String, usize)>,
+    // Rows that would get the typed value when only empty cells are filled
+    pub empty_rows: usize,
+}
+
+/// Run constants that would leave a column with two different values:
+/// the typed value goes into empty cells while other rows keep what the
+/// samples file has. Only columns filled with `FillEmpty` can conflict;
+/// overwritten and skipped columns end up with a single value anyway.
+pub fn run_constant_conflicts(
+    df: &DataFrame,
+    mode: &str,
+    params: &RunConstants,
+    policies: &FillPolicies,
+) -> Result<Vec<ConstantConflict>, String> {
+    let mut conflicts = Vec::new();
+    for (column, typed) in run_constant_values(mode, params) {
+        let typed = typed.trim().to_string();
+        if typed.is_empty() || policies.for_column(column) != FillPolicy::FillEmpty {
+            continue;
+        }
+        let Ok(series) = df.column(column) else { continue };
+        let values = series
+            .cast(&DataType::String)
+            .map_err(|e| format!("Failed to read {}: {e}", column))?;
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        let mut empty_rows = 0;
+        for value in values.str().map_err(|e| format!("Failed to read {}: {e}", column))?.into_iter() {
+            match value.map(str::trim).filter(|v| !v.is_empty()) {
+                Some(v) => match counts.iter_mut().find(|(seen, _)| seen == v) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((v.to_string(), 1)),
+                },
+                None => empty_rows += 1,
+            }
+        }
+        if counts.iter().all(|(v, _)| *v == typed) {
+            continue;
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        conflicts.push(ConstantConflict {
+            column: column.to_string(),
+            typed,
+            existing: counts,
+            empty_rows,
+        });
     }
+    Ok(conflicts)
+}
+
+/// Run constant columns of a mode, in fill order
+pub fn run_constant_columns(mode: &str) -> Vec<&'static str> {
+    run_constant_values(mode, &RunConstants::default())
+        .into_iter()
+        .map(|(column, _)| column)
+        .collect()
+}
+
+/// Adds the run constant fills to a lazy query; nothing is computed until
+/// the caller collects, so the fills and the column selection after them
+/// run as one pass over the merged frame
+pub fn with_run_constants(
+    frame: LazyFrame,
+    mode: &str,
+    params: &RunConstants,
+    policies: &FillPolicies,
+) -> LazyFrame {
+    let exprs: Vec<Expr> = run_constant_values(mode, params)
+        .iter()
+        .map(|(column, value)| fill_column(column, value, policies.for_column(column)))
+        .collect();
+    frame.with_columns(exprs)
 }
 
 // Fills run constants into the merged DataFrame
 pub fn fill_run_constants(
     merged_df: DataFrame,
-    params: &MergeParams,
+    mode: &str,
+    params: &RunConstants,
+    policies: &FillPolicies,
 ) -> Result<DataFrame, String> {
-    let pos_con = pcr_control_value(&params.pos_con);
-    let neg_con = pcr_control_value(&params.neg_con);
-    let overwrite = params.overwrite_existing;
+    with_run_constants(merged_df.lazy(), mode, params, policies)
+        .collect()
+        .map_err(|e| format!("Failed to fill run constants: {:?}", e))
+}
 
-    let mut lazy = merged_df
-        .clone()
-        .lazy()
-        .with_columns([
-            conditional_fill("RunNumber", &params.run_num, overwrite),
-            conditional_fill("MinKNOWSoftwareVersion", params.minknow_ver.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("AnalysisPipelineVersion", &params.pir_ver, overwrite),
-            conditional_fill("DateSeqRunLoaded", params.seq_date.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("FlowCellID", params.fc_id.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("FlowCellPriorUses", &params.fc_uses, overwrite),
-            conditional_fill("PoresAvilableAtFlowCellCheck", params.fc_pores.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("RunHoursDuration", params.seq_hours.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("DateFastaGenerated", &params.fasta_date, overwrite),
-            conditional_fill("LibraryPreparationKit", params.seq_kit.as_deref().unwrap_or(""), overwrite),
-            conditional_fill("DateRTPCR", &params.rt_date, overwrite),
-        ]);
+/// Fills empty DateReported cells per the policy, leaving pre-filled
+/// dates alone. Returns the date used and how many cells got it.
+pub fn fill_date_reported(
+    df: DataFrame,
+    policy: &DateReportedPolicy,
+    today: &str,
+) -> Result<(DataFrame, Option<(String, usize)>), String> {
+    let Some(date) = policy.date(today) else {
+        return Ok((df, None));
+    };
+    let empty = match df.column("DateReported") {
+        Ok(column) => column
+            .cast(&DataType::String)
+            .map_err(|e| format!("Failed to read DateReported: {e}"))?
+            .str()
+            .map_err(|e| format!("Failed to read DateReported: {e}"))?
+            .into_iter()
+            .filter(|v| v.map(|s| s.trim().is_empty()).unwrap_or(true))
+            .count(),
+        // Both templates have it; nothing to do on anything else
+        Err(_) => return Ok((df, None)),
+    };
 
-    if params.mode == "minION" {
-        lazy = lazy.with_columns([
-            if overwrite && !pos_con.is_empty() {
-                when(col("PositiveControlPCRCheck").cast(DataType::String).is_null()
-                    .or(col("PositiveControlPCRCheck").cast(DataType::String).eq(lit(""))))
-                    .then(lit(pos_con))
-                    .otherwise(col("PositiveControlPCRCheck").cast(DataType::String))
-                    .alias("PositiveControlPCRCheck")
-            } else {
-                col("PositiveControlPCRCheck").cast(DataType::String).alias("PositiveControlPCRCheck")
-            },
-            if overwrite && !neg_con.is_empty() {
-                when(col("NegativeControlPCRheck").cast(DataType::String).is_null()
-                    .or(col("NegativeControlPCRheck").cast(DataType::String).eq(lit(""))))
-                    .then(lit(neg_con))
-                    .otherwise(col("NegativeControlPCRheck").cast(DataType::String))
-                    .alias("NegativeControlPCRheck")
-            } else {
-                col("NegativeControlPCRheck").cast(DataType::String).alias("NegativeControlPCRheck")
-            },
-            conditional_fill("institute", &params.lab, overwrite),
-        ]);
-    } else {
-        lazy = lazy.with_columns([
-            if overwrite && !pos_con.is_empty() {
-                when(col("PositiveControlPCRCheck").cast(DataType::String).is_null()
-                    .or(col("PositiveControlPCRCheck").cast(DataType::String).eq(lit(""))))
-                    .then(lit(pos_con))
-                    .otherwise(col("PositiveControlPCRCheck").cast(DataType::String))
-                    .alias("PositiveControlPCRCheck")
-            } else {
-                col("PositiveControlPCRCheck").cast(DataType::String).alias("PositiveControlPCRCheck")
-            },
-            if overwrite && !neg_con.is_empty() {
-                when(col("NegativeControlPCRCheck").cast(DataType::String).is_null()
-                    .or(col("NegativeControlPCRCheck").cast(DataType::String).eq(lit(""))))
-                    .then(lit(neg_con))
-                    .otherwise(col("NegativeControlPCRCheck").cast(DataType::String))
-                    .alias("NegativeControlPCRCheck")
-            } else {
-                col("NegativeControlPCRCheck").cast(DataType::String).alias("NegativeControlPCRCheck")
-            },
-            conditional_fill("DateVP1PCR", &params.vp1_date, overwrite),
-            conditional_fill("RTPCRMachine", &params.pcr_machine, overwrite),
-            conditional_fill("VP1PCRMachine", &params.vp1_pcr_machine, overwrite),
-            conditional_fill("RTPCRprimers", &params.rtpcr_primers, overwrite),
-            conditional_fill("VP1primers", &params.vp1_primers, overwrite),
-        ]);
-    }
+    let df = df
+        .lazy()
+        .with_column(fill_column("DateReported", &date, FillPolicy::FillEmpty))
+        .collect()
+        .map_err(|e| format!("Failed to fill DateReported: {:?}", e))?;
+    Ok((df, Some((date, empty))))
+}
 
-    lazy.collect()
+/// Fills the run constants and keeps only the mode's template columns,
+/// collecting once. `df` is moved in, so no copy of the merged frame is
+/// held while the output is built.
+pub fn build_output(
+    df: DataFrame,
+    mode: &str,
+    params: &RunConstants,
+    policies: &FillPolicies,
+) -> Result<DataFrame, String> {
+    let expected: Vec<Expr> = expected_columns_for_mode(mode).into_iter().map(col).collect();
+    with_run_constants(df.lazy(), mode, params, policies)
+        .select(expected)
+        .collect()
         .map_err(|e| format!("Failed to fill run constants: {:?}", e))
 }
 
@@ -255,44 +747,936 @@ pub fn select_expected_columns(df: DataFrame, mode: &str) -> Result<DataFrame, S
         .map_err(|e| format!("Failed to select expected columns: {:?}", e))
 }
 
-// Validates input formats for merge operation
-pub fn validate_merge_inputs(params: &MergeParams) -> Result<(), String> {
-    let mut errors = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::{canonicalize_header_case, read_csv_normalized, read_csv_projected};
+    use crate::matching::{match_samples, MatchOptions};
+    use crate::run_constants::ControlStatus;
+    use crate::warnings::Lang;
+    use crate::workspace::TempWorkspace;
 
-    // Validate run number
-    if let Some(err) = validate_run_number(&params.run_num) {
-        errors.push(err);
+    fn sources(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
     }
 
-    // Validate dates
-    let mut date_fields: Vec<(&str, &str)> = vec![
-        (&params.rt_date, "RT PCR Date"),
-        (&params.fasta_date, "Fasta Generation Date"),
-    ];
+    const EPIINFO: &str = "ICLabID,EpidNumber,DateOfOnset,Province,FinalITDResult,PatientName\n\
+                           S01,NIE-KAN-24-001,2024-02-01,Kano,NPEV,A\n\
+                           S02,NIE-KAN-24-002,2024-02-03,Kano,SL1,B\n";
+
+    fn cell(df: &DataFrame, column: &str, row: usize) -> Option<String> {
+        let column = df.column(column).unwrap().cast(&DataType::String).unwrap();
+        column.str().unwrap().get(row).map(str::to_string)
+    }
 
-    // Add optional seq_date if present
-    if let Some(ref seq_date) = params.seq_date {
-        date_fields.push((seq_date.as_str(), "Sequencing Date"));
+    // The merge as the window runs it, from the files' text to the output
+    fn merge_files(samples: &str, epiinfo: Option<&str>, mode: &str, params: &RunConstants) -> DataFrame {
+        merge_files_excluding(samples.as_bytes(), epiinfo.map(str::as_bytes), mode, params, &[], &HashMap::new())
     }
 
-    // Add DDNS-specific dates
-    if params.mode != "minION" {
-        date_fields.push((&params.vp1_date, "VP1 PCR Date"));
+    // Same, with the EpiInfo columns the review leaves out dropped before the join
+    fn merge_files_excluding(
+        samples: &[u8],
+        epiinfo: Option<&[u8]>,
+        mode: &str,
+        params: &RunConstants,
+        patterns: &[String],
+        choices: &HashMap<String, bool>,
+    ) -> DataFrame {
+        let mut dir = TempWorkspace::new().unwrap();
+        let mut write = |name: &str, text: &[u8]| {
+            let path = dir.file(name);
+            std::fs::write(&path, text).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let sample_path = write("samples.csv", samples);
+        let epi_path = epiinfo.map(|text| write("epiinfo.csv", text));
+        merge_paths(&sample_path, epi_path.as_deref(), mode, params, patterns, choices)
     }
 
-    for (val, name) in date_fields {
-        if let Some(err) = validate_date(val, name) {
-            errors.push(err);
+    // The same merge from files already on disk
+    fn merge_paths(
+        sample_path: &str,
+        epi_path: Option<&str>,
+        mode: &str,
+        params: &RunConstants,
+        patterns: &[String],
+        choices: &HashMap<String, bool>,
+    ) -> DataFrame {
+        let (mut sample_df, _) = read_csv_normalized(sample_path).unwrap();
+        canonicalize_header_case(&mut sample_df).unwrap();
+        if is_barcodes_only(&sample_df) {
+            sample_df = expand_barcodes_only(sample_df, mode).unwrap().0;
         }
+        let sheet = SampleSheet::new(sample_df).unwrap();
+        let run = match epi_path {
+            Some(path) => {
+                let keep = epiinfo_columns_for_mode(mode, &sources(DEFAULT_EPID_SOURCES));
+                let (mut epi_df, _, _) = read_csv_projected(path, Some(&keep)).unwrap();
+                let columns: Vec<String> = epi_df.get_column_names().iter().map(|c| c.to_string()).collect();
+                epi_df = epi_df.drop_many(excluded_epiinfo_columns(&columns, patterns, choices));
+                if mode == "minION" {
+                    rename_epiinfo_columns_for_minion(&mut epi_df).unwrap();
+                }
+                let epi = EpiData::new(epi_df).unwrap();
+                let matches = match_samples(&sheet.samples(), &epi, &MatchOptions::default());
+                merge_with_epiinfo(sheet, epi, &matches, &sources(DEFAULT_EPID_SOURCES), &default_comment_columns())
+                    .unwrap()
+            }
+            None => MergedRun::unmerged(sheet),
+        };
+        let (df, _) = run.into_parts();
+        let (df, _) = add_optional_columns(df, mode).unwrap();
+        validate_columns(&df, mode).unwrap();
+        build_output(df, mode, params, &FillPolicies::default()).unwrap()
     }
 
-    if !errors.is_empty() {
-        errors.push(String::from("Expected yyyy-mm-dd."));
-        Err(format!(
-            "{}\n\nRefer to the Guide for more information.",
-            errors.join("\n\n")
-        ))
-    } else {
-        Ok(())
+    #[test]
+    fn epiinfo_projection_keeps_the_columns_the_merge_reads() {
+        let ddns = epiinfo_columns_for_mode("DDNS", &sources(DEFAULT_EPID_SOURCES));
+        for column in ["ICLabID", "EpidNumber", "EPIDNumber", "EpidNo", "DateOfOnset", "Province"] {
+            assert!(ddns.contains(column), "{}", column);
+        }
+        assert!(!ddns.contains("SequenceName"));
+        assert!(!ddns.contains("PatientName"));
+
+        let minion = epiinfo_columns_for_mode("minION", &sources(&["EpidCustom"]));
+        for (old, new_) in MINION_EPI_RENAMES {
+            assert!(minion.contains(*old) && minion.contains(*new_), "{}", old);
+        }
+        assert!(minion.contains("EpidCustom"));
+        assert!(!minion.contains("EpidNo"));
+    }
+
+    const BARCODES_CSV: &str = "sample,barcode\nS01,barcode01\nS02,barcode02\nNTC,barcode96\n";
+
+    #[test]
+    fn barcodes_file_is_recognized_by_its_two_columns() {
+        let two = df!("barcode" => ["barcode01"], "sample" => ["S01"]).unwrap();
+        assert!(is_barcodes_only(&two));
+        let three = df!("sample" => ["S01"], "barcode" => ["barcode01"], "EPID" => [""]).unwrap();
+        assert!(!is_barcodes_only(&three));
+
+        let (expanded, added) = expand_barcodes_only(two, "DDNS").unwrap();
+        assert_eq!(added, expected_ddns_count() - 2);
+        assert_eq!(expanded.width(), expected_ddns_count());
+        assert_eq!(cell(&expanded, "Province", 0), None);
+    }
+
+    fn expected_ddns_count() -> usize {
+        expected_columns_for_mode("DDNS").len()
+    }
+
+    #[test]
+    fn barcodes_file_merges_end_to_end_in_both_modes() {
+        let params = RunConstants { run_num: "24-031".into(), ..RunConstants::default() };
+        for mode in ["DDNS", "minION"] {
+            let out = merge_files(BARCODES_CSV, Some(EPIINFO), mode, &params);
+            let names: Vec<String> = out.get_column_names().iter().map(|c| c.to_string()).collect();
+            assert_eq!(names, expected_columns_for_mode(mode), "{}", mode);
+            assert_eq!(out.height(), 3, "{}", mode);
+            assert_eq!(cell(&out, "barcode", 2).as_deref(), Some("barcode96"), "{}", mode);
+            assert_eq!(cell(&out, "EPID", 1).as_deref(), Some("NIE-KAN-24-002"), "{}", mode);
+            assert_eq!(cell(&out, "DateOfOnset", 0).as_deref(), Some("2024-02-01"), "{}", mode);
+            assert_eq!(cell(&out, "EPID", 2), None, "{}", mode);
+            assert_eq!(cell(&out, "RunNumber", 2).as_deref(), Some("24-031"), "{}", mode);
+        }
+        let minion = merge_files(BARCODES_CSV, Some(EPIINFO), "minION", &params);
+        assert_eq!(cell(&minion, "ITDResult", 1).as_deref(), Some("SL1"));
+    }
+
+    #[test]
+    fn barcodes_file_merges_without_epiinfo() {
+        let out = merge_files(BARCODES_CSV, None, "DDNS", &RunConstants::default());
+        assert_eq!(out.shape(), (3, expected_ddns_count()));
+        assert_eq!(cell(&out, "sample", 0).as_deref(), Some("S01"));
+        assert_eq!(cell(&out, "EPID", 0), None);
+    }
+
+    fn disabled(mode: &str) -> Vec<&'static str> {
+        ui_field_defs(mode).into_iter().filter(|d| !d.enabled).map(|d| d.key).collect()
+    }
+
+    #[test]
+    fn enabled_fields_follow_each_mode_template() {
+        assert_eq!(disabled("minION"), ["vp1_date", "pcr_machine", "vp1_pcr_machine", "rtpcr_primers", "vp1_primers"]);
+        // The DDNS template has no institute column
+        assert_eq!(disabled("DDNS"), ["lab"]);
+        // Every field is used by at least one mode
+        for (key, _, _, _) in UI_FIELDS {
+            assert!(!disabled("minION").contains(key) || !disabled("DDNS").contains(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn field_hints_match_the_validation_in_both_modes() {
+        for mode in ["DDNS", "minION"] {
+            let defs = ui_field_defs(mode);
+            let keys: Vec<&str> = defs.iter().map(|d| d.key).collect();
+            assert_eq!(keys, UI_FIELDS.iter().map(|(key, _, _, _)| *key).collect::<Vec<_>>(), "{}", mode);
+
+            for def in &defs {
+                assert!(!def.label_en.is_empty() && !def.label_fr.is_empty(), "{}", def.key);
+                assert_eq!(def.hint_en.is_empty(), def.hint_fr.is_empty(), "{}", def.key);
+                // A value typed the way the hint shows passes the checks
+                match def.hint_en {
+                    "YYYY-MM-DD" => assert_eq!(validate_date("2024-03-01", def.label_en), None, "{}", def.key),
+                    "YYYYMMDD_XXX" => assert_eq!(validate_run_number("20240301_001"), None),
+                    hint => assert_eq!(hint, "", "{}", def.key),
+                }
+            }
+            let hint = |key: &str| defs.iter().find(|d| d.key == key).map(|d| (d.hint_en, d.hint_fr)).unwrap();
+            assert_eq!(hint("run_num"), ("YYYYMMDD_XXX", "AAAAMMJJ_XXX"));
+            assert_eq!(hint("rt_date"), ("YYYY-MM-DD", "AAAA-MM-JJ"));
+            assert_eq!(hint("lab"), ("", ""));
+        }
+        // A mode without its own template gets the DDNS fields
+        assert_eq!(disabled("Sanger"), disabled("DDNS"));
+    }
+
+    #[test]
+    fn only_filled_fields_the_mode_skips_are_ignored() {
+        let values = vec![
+            ("run_num", "RUN042".to_string()),
+            ("vp1_date", "2024-03-01".to_string()),
+            ("pcr_machine", "  ".to_string()),
+            ("vp1_primers", "Q8/Cre".to_string()),
+            ("lab", "INRB".to_string()),
+        ];
+        assert_eq!(ignored_fields("minION", &values), ["vp1_date", "vp1_primers"]);
+        assert_eq!(ignored_fields("DDNS", &values), ["lab"]);
+    }
+
+    #[test]
+    fn ignored_fields_notice_uses_the_field_labels() {
+        let warning = ValidationWarning::IgnoredFields {
+            mode: "minION".to_string(),
+            fields: vec!["vp1_date".to_string(), "pcr_machine".to_string()],
+        };
+        assert_eq!(
+            warning.render(Lang::En),
+            "[W009] The following entries were ignored in minION mode: VP1 Date, RTPCR Machine"
+        );
+        assert_eq!(
+            warning.render(Lang::Fr),
+            "[W009] Les entrées suivantes ont été ignorées en mode minION : Date VP1, Machine RTPCR"
+        );
+        assert_eq!(ui_field_label("unknown", true), "unknown");
+    }
+
+    #[test]
+    fn each_epid_spelling_fills_the_epid() {
+        for spelling in DEFAULT_EPID_SOURCES {
+            let df = df!(
+                "sample" => ["S01", "S02"],
+                "EPID" => [None, Some("NIE-KAN-24-009")],
+                *spelling => [Some("NIE-KAN-24-001"), Some("NIE-KAN-24-002")]
+            )
+            .unwrap();
+            let (df, supplied) = coalesce_columns(df, "EPID", &sources(DEFAULT_EPID_SOURCES)).unwrap();
+            assert_eq!(cell(&df, "EPID", 0).as_deref(), Some("NIE-KAN-24-001"), "{}", spelling);
+            // An EPID the sheet already has is kept
+            assert_eq!(cell(&df, "EPID", 1).as_deref(), Some("NIE-KAN-24-009"), "{}", spelling);
+            assert_eq!(supplied, vec![(spelling.to_string(), 1)]);
+            assert!(df.column(spelling).is_err());
+        }
+    }
+
+    #[test]
+    fn first_populated_source_wins_and_every_source_is_dropped() {
+        let df = df!(
+            "sample" => ["S01", "S02", "S03", "S04"],
+            "EPID" => [None::<&str>, None, None, None],
+            "EpidNo" => [Some("no-1"), Some("no-2"), Some("no-3"), None],
+            "EpidNumber" => [Some("number-1"), Some(" "), None, None],
+            "EPIDNumber" => [Some("upper-1"), Some("upper-2"), None, None]
+        )
+        .unwrap();
+        let (df, supplied) = coalesce_columns(df, "EPID", &sources(DEFAULT_EPID_SOURCES)).unwrap();
+
+        let epid: Vec<Option<String>> = (0..4).map(|row| cell(&df, "EPID", row)).collect();
+        assert_eq!(
+            epid,
+            [Some("number-1".to_string()), Some("upper-2".to_string()), Some("no-3".to_string()), None]
+        );
+        assert_eq!(
+            supplied,
+            vec![("EpidNumber".to_string(), 1), ("EPIDNumber".to_string(), 1), ("EpidNo".to_string(), 1)]
+        );
+        let columns = df.get_column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        assert_eq!(columns, ["sample", "EPID"]);
+    }
+
+    #[test]
+    fn coalesce_works_for_other_targets() {
+        // No target column yet: it is created from the sources
+        let df = df!("sample" => ["S01", "S02"], "Prov" => [Some("Kano"), None], "Region" => [Some("North"), Some("Lagos")])
+            .unwrap();
+        let (df, supplied) = coalesce_columns(df, "Province", &sources(&["Prov", "Region", "Absent"])).unwrap();
+        assert_eq!(cell(&df, "Province", 0).as_deref(), Some("Kano"));
+        assert_eq!(cell(&df, "Province", 1).as_deref(), Some("Lagos"));
+        assert_eq!(supplied, vec![("Prov".to_string(), 1), ("Region".to_string(), 1)]);
+
+        // No source present leaves the frame alone
+        let df = df!("sample" => ["S01"]).unwrap();
+        let (df, supplied) = coalesce_columns(df, "EPID", &sources(DEFAULT_EPID_SOURCES)).unwrap();
+        assert!(supplied.is_empty());
+        assert!(df.column("EPID").is_err());
+    }
+
+    #[test]
+    fn join_key_headers_in_another_case_merge() {
+        let out = merge_files("Sample,BARCODE\nS01,barcode01\nS02,barcode02\n", Some(EPIINFO), "DDNS", &RunConstants::default());
+        assert_eq!(out.height(), 2);
+        assert_eq!(cell(&out, "barcode", 1).as_deref(), Some("barcode02"));
+        assert_eq!(cell(&out, "EPID", 0).as_deref(), Some("NIE-KAN-24-001"));
+
+        let sheet = full_sheet("minION", &[], &[("EPID", "NIE-KAN-24-009")]).replacen("sample,barcode", "SAMPLE,Barcode", 1).replacen(",EPID,", ",epid,", 1);
+        let out = merge_files(&sheet, Some(EPIINFO), "minION", &RunConstants::default());
+        assert_eq!(cell(&out, "sample", 0).as_deref(), Some("S01"));
+        assert_eq!(cell(&out, "EPID", 0).as_deref(), Some("NIE-KAN-24-009"));
+    }
+
+    #[test]
+    fn epiinfo_with_another_epid_spelling_merges_end_to_end() {
+        let samples = full_sheet("DDNS", &[], &[]);
+        let epiinfo = EPIINFO.replace("EpidNumber", "EpidNo");
+        let df = merge_files(&samples, Some(&epiinfo), "DDNS", &RunConstants::default());
+        assert_eq!(cell(&df, "EPID", 0).as_deref(), Some("NIE-KAN-24-001"));
+        assert_eq!(cell(&df, "EPID", 1).as_deref(), Some("NIE-KAN-24-002"));
+    }
+
+    const CONTROL_STATUSES: [ControlStatus; 4] =
+        [ControlStatus::Pass, ControlStatus::Fail, ControlStatus::NotRecorded, ControlStatus::NotApplicable];
+
+    fn negative_control_column(mode: &str) -> &'static str {
+        if mode == "minION" {
+            "NegativeControlPCRheck"
+        } else {
+            "NegativeControlPCRCheck"
+        }
+    }
+
+    #[test]
+    fn every_control_status_fills_the_agreed_token_in_both_modes() {
+        for mode in ["DDNS", "minION"] {
+            for (status, token) in CONTROL_STATUSES.into_iter().zip(["Pass", "Fail", "", "NA"]) {
+                let params = RunConstants { pos_con: status, neg_con: status, ..RunConstants::default() };
+                let values = run_constant_values(mode, &params);
+                let value = |column: &str| values.iter().find(|(c, _)| *c == column).map(|(_, v)| v.as_str());
+                assert_eq!(value("PositiveControlPCRCheck"), Some(token), "{} {:?}", mode, status);
+                assert_eq!(value(negative_control_column(mode)), Some(token), "{} {:?}", mode, status);
+
+                let out = merge_files(BARCODES_CSV, None, mode, &params);
+                let expected = Some(token.to_string()).filter(|t| !t.is_empty());
+                assert_eq!(cell(&out, "PositiveControlPCRCheck", 0).filter(|v| !v.is_empty()), expected, "{} {:?}", mode, status);
+                assert_eq!(cell(&out, negative_control_column(mode), 0).filter(|v| !v.is_empty()), expected, "{} {:?}", mode, status);
+            }
+        }
+    }
+
+    #[test]
+    fn only_unrecorded_controls_are_warned_about() {
+        for pos_con in CONTROL_STATUSES {
+            for neg_con in CONTROL_STATUSES {
+                let params = RunConstants { pos_con, neg_con, ..RunConstants::default() };
+                let mut expected = Vec::new();
+                if pos_con == ControlStatus::NotRecorded {
+                    expected.push("Positive");
+                }
+                if neg_con == ControlStatus::NotRecorded {
+                    expected.push("Negative");
+                }
+                assert_eq!(params.unrecorded_controls(), expected, "{:?} {:?}", pos_con, neg_con);
+            }
+        }
+
+        let warning = ValidationWarning::ControlsNotRecorded {
+            controls: vec!["Positive".to_string(), "Negative".to_string()],
+        };
+        assert_eq!(
+            warning.render(Lang::En),
+            "[W017] PCR control check not recorded: Positive, Negative. Select Passed, Failed or Not applicable."
+        );
+        assert_eq!(
+            warning.render(Lang::Fr),
+            "[W017] Contrôle PCR non renseigné : positif, négatif. Choisissez Passed, Failed ou Not applicable."
+        );
+    }
+
+    fn reported(df: &DataFrame) -> Vec<Option<String>> {
+        (0..df.height()).map(|row| cell(df, "DateReported", row).filter(|v| !v.is_empty())).collect()
+    }
+
+    #[test]
+    fn date_reported_policies_fill_only_empty_cells() {
+        let df = df!("sample" => ["S01", "S02", "S03"], "DateReported" => [Some("2024-03-01"), Some(""), None]).unwrap();
+        let kept = Some("2024-03-01".to_string());
+
+        let (out, filled) = fill_date_reported(df.clone(), &DateReportedPolicy::LeaveEmpty, "2024-03-10").unwrap();
+        assert_eq!(filled, None);
+        assert_eq!(reported(&out), [kept.clone(), None, None]);
+
+        let (out, filled) = fill_date_reported(df.clone(), &DateReportedPolicy::Today, "2024-03-10").unwrap();
+        assert_eq!(filled, Some(("2024-03-10".to_string(), 2)));
+        assert_eq!(reported(&out), [kept.clone(), Some("2024-03-10".to_string()), Some("2024-03-10".to_string())]);
+
+        let fixed = DateReportedPolicy::Fixed("2024-03-08".to_string());
+        let (out, filled) = fill_date_reported(df, &fixed, "2024-03-10").unwrap();
+        assert_eq!(filled, Some(("2024-03-08".to_string(), 2)));
+        assert_eq!(reported(&out), [kept, Some("2024-03-08".to_string()), Some("2024-03-08".to_string())]);
+    }
+
+    #[test]
+    fn date_reported_fills_the_same_in_both_modes() {
+        for mode in ["DDNS", "minION"] {
+            let out = merge_files(BARCODES_CSV, None, mode, &RunConstants::default());
+            let (out, filled) = fill_date_reported(out, &DateReportedPolicy::Today, "2024-03-10").unwrap();
+            assert_eq!(filled, Some(("2024-03-10".to_string(), out.height())), "{}", mode);
+            assert!(reported(&out).iter().all(|d| d.as_deref() == Some("2024-03-10")), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn date_reported_choice_maps_from_the_form() {
+        assert_eq!(DateReportedPolicy::from_ui("Leave empty", ""), DateReportedPolicy::LeaveEmpty);
+        assert_eq!(DateReportedPolicy::from_ui("Today", "2024-01-01"), DateReportedPolicy::Today);
+        let fixed = DateReportedPolicy::from_ui("Date", " 2024-03-08 ");
+        assert_eq!(fixed, DateReportedPolicy::Fixed("2024-03-08".to_string()));
+        assert_eq!(fixed.ui_label(), "Date");
+        assert_eq!(fixed.validate(), Ok(()));
+        assert!(DateReportedPolicy::Fixed(String::new()).validate().is_err());
+        assert!(DateReportedPolicy::Fixed("08/03/2024".to_string()).validate().is_err());
+        // A chosen date with nothing typed fills nothing
+        assert_eq!(DateReportedPolicy::Fixed(String::new()).date("2024-03-10"), None);
+    }
+
+    #[test]
+    fn sensitive_columns_match_patterns_ignoring_case() {
+        let columns = sources(&["ICLabID", "PatientName", "PHONE_NUMBER", "HomeAddress", "Province", "DateOfOnset"]);
+        assert_eq!(
+            sensitive_columns(&columns, &sources(DEFAULT_SENSITIVE_PATTERNS)),
+            sources(&["PatientName", "PHONE_NUMBER", "HomeAddress"])
+        );
+        // Blank patterns match nothing, and the join key is never sensitive
+        assert!(sensitive_columns(&columns, &sources(&["", "  "])).is_empty());
+        assert!(sensitive_columns(&columns, &sources(&["iclab"])).is_empty());
+        assert_eq!(sensitive_columns(&columns, &sources(&[" province "])), sources(&["Province"]));
+    }
+
+    #[test]
+    fn sensitive_columns_are_excluded_until_opted_in() {
+        let columns = sources(&["ICLabID", "PatientName", "HomeAddress", "Province"]);
+        let patterns = sources(DEFAULT_SENSITIVE_PATTERNS);
+
+        let none = HashMap::new();
+        assert_eq!(excluded_epiinfo_columns(&columns, &patterns, &none), sources(&["PatientName", "HomeAddress"]));
+
+        // Opting in to one keeps it; unticking an ordinary column drops it
+        let choices = HashMap::from([("PatientName".to_string(), true), ("Province".to_string(), false)]);
+        assert_eq!(excluded_epiinfo_columns(&columns, &patterns, &choices), sources(&["HomeAddress", "Province"]));
+
+        // The join key stays even when unticked
+        let key = HashMap::from([("ICLabID".to_string(), false)]);
+        assert!(!excluded_epiinfo_columns(&columns, &patterns, &key).contains(&"ICLabID".to_string()));
+    }
+
+    #[test]
+    fn excluded_columns_never_reach_the_output() {
+        let params = RunConstants::default();
+        let patterns = sources(&["Province"]);
+        let samples = b"sample,barcode\nS01,barcode01\nS02,barcode02\n";
+
+        let out = merge_files_excluding(samples, Some(EPIINFO.as_bytes()), "DDNS", &params, &patterns, &HashMap::new());
+        let mut text = Vec::new();
+        CsvWriter::new(&mut text).finish(&mut out.clone()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(!text.contains("Kano"), "{}", text);
+        // The rest of the EpiInfo row still comes through
+        assert_eq!(cell(&out, "DateOfOnset", 0).as_deref(), Some("2024-02-01"));
+
+        let warning = ValidationWarning::EpiInfoColumnsExcluded { columns: patterns.clone() };
+        assert!(!warning.render(Lang::En).contains("Kano"));
+
+        let opted_in = HashMap::from([("Province".to_string(), true)]);
+        let out = merge_files_excluding(samples, Some(EPIINFO.as_bytes()), "DDNS", &params, &patterns, &opted_in);
+        assert_eq!(cell(&out, "Province", 0).as_deref(), Some("Kano"));
+    }
+
+    #[test]
+    fn inputs_with_different_profiles_merge_correctly() {
+        // French Excel: semicolons, UTF-8, French month names
+        let dates = ["DateRTPCR", "12-mars-2024", "3-févr.-2024"];
+        let samples: String = full_sheet("DDNS", &["DateRTPCR"], &[])
+            .lines()
+            .zip(dates)
+            .map(|(line, date)| format!("{};{}\n", line.replace(',', ";"), date))
+            .collect();
+        // EpiInfo export: commas, Windows-1252 (0xC9 is É)
+        let mut epiinfo = b"ICLabID,EpidNumber,DateOfOnset,Province\n".to_vec();
+        epiinfo.extend_from_slice(b"S01,DRC-EQT-24-001,2024-02-01,\xC9quateur\n");
+        epiinfo.extend_from_slice(b"S02,DRC-EQT-24-002,2024-02-03,Kinshasa\n");
+
+        let out = merge_files_excluding(
+            samples.as_bytes(),
+            Some(&epiinfo),
+            "DDNS",
+            &RunConstants::default(),
+            &[],
+            &HashMap::new(),
+        );
+        assert_eq!(cell(&out, "EPID", 0).as_deref(), Some("DRC-EQT-24-001"));
+        assert_eq!(cell(&out, "Province", 0).as_deref(), Some("Équateur"));
+        assert_eq!(cell(&out, "Province", 1).as_deref(), Some("Kinshasa"));
+        assert_eq!(cell(&out, "DateOfOnset", 1).as_deref(), Some("2024-02-03"));
+        assert_eq!(cell(&out, "DateRTPCR", 0).as_deref(), Some("12-Mar-2024"));
+        assert_eq!(cell(&out, "DateRTPCR", 1).as_deref(), Some("3-Feb-2024"));
+    }
+
+    #[test]
+    fn control_rows_are_recognized_by_name() {
+        for name in ["NTC", "ntc", "PC-1", "NC", "Neg", "Positive control", "BLANK_01", "Extraction control", "ctrl"] {
+            assert!(is_control_sample(name), "{}", name);
+        }
+        for name in ["S01", "INRB-24-001", "NCD001", "POSTE12", "PCR-17"] {
+            assert!(!is_control_sample(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn match_rate_threshold_is_a_strict_lower_bound() {
+        assert!(match_rate_too_low(7, 90, 50));
+        assert!(match_rate_too_low(44, 90, 50));
+        assert!(!match_rate_too_low(45, 90, 50));
+        assert!(!match_rate_too_low(90, 90, 50));
+        assert!(match_rate_too_low(0, 1, 1));
+        assert!(!match_rate_too_low(99, 100, 99));
+        assert!(match_rate_too_low(99, 100, 100));
+        // 0 turns the check off; no non-control samples never trips it
+        assert!(!match_rate_too_low(0, 90, 0));
+        assert!(!match_rate_too_low(0, 0, 50));
+    }
+
+    fn paired(sample: &str, iclab_id: Option<&str>) -> SampleMatch {
+        SampleMatch {
+            sample: sample.to_string(),
+            method: if iclab_id.is_some() { MatchMethod::Exact } else { MatchMethod::None },
+            iclab_id: iclab_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn match_rate_leaves_controls_out() {
+        let epi = EpiData::new(
+            df!(
+                "ICLabID" => ["S01", "S02", "S03", "NTC"],
+                "EpidNumber" => [Some("NIE-KAN-24-001"), None, Some(" "), Some("x")],
+                "Province" => [Some("Kano"), None, None, Some("Kano")]
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let matches = vec![
+            paired("S01", Some("S01")),
+            // Paired, but the EpiInfo row is empty
+            paired("S02", Some("S02")),
+            paired("S03", Some("S03")),
+            paired("S04", None),
+            // Controls count in neither number, even when they pair
+            paired("NTC", Some("NTC")),
+            paired("PC", None),
+            paired("", None),
+        ];
+        assert_eq!(epi_match_rate(&matches, &epi).unwrap(), (1, 4));
+        assert!(match_rate_too_low(1, 4, DEFAULT_MIN_EPI_MATCH_PERCENT));
+
+        let controls_only = vec![paired("NTC", Some("NTC")), paired("Negative control", None)];
+        assert_eq!(epi_match_rate(&controls_only, &epi).unwrap(), (0, 0));
+        assert!(!match_rate_too_low(0, 0, DEFAULT_MIN_EPI_MATCH_PERCENT));
+    }
+
+    // A samples sheet with every template column of `mode` except `without`
+    fn full_sheet(mode: &str, without: &[&str], values: &[(&str, &str)]) -> String {
+        let columns: Vec<&str> =
+            expected_columns_for_mode(mode).into_iter().filter(|c| !without.contains(c)).collect();
+        let mut text = columns.join(",") + "\n";
+        for (sample, barcode) in [("S01", "barcode01"), ("S02", "barcode02")] {
+            let row: Vec<&str> = columns
+                .iter()
+                .map(|c| match *c {
+                    "sample" => sample,
+                    "barcode" => barcode,
+                    _ => values.iter().find(|(name, _)| name == c).map(|(_, v)| *v).unwrap_or(""),
+                })
+                .collect();
+            text += &(row.join(",") + "\n");
+        }
+        text
+    }
+
+    #[test]
+    fn positive_control_columns_survive_to_the_minion_output() {
+        let sheet = full_sheet(
+            "minION",
+            &[],
+            &[("TypeofPositiveControl", "Sabin 1"), ("DatePositiveControlreconstituted", "2024-02-20")],
+        );
+        let out = merge_files(&sheet, Some(EPIINFO), "minION", &RunConstants::default());
+        for row in 0..2 {
+            assert_eq!(cell(&out, "TypeofPositiveControl", row).as_deref(), Some("Sabin 1"));
+            assert_eq!(cell(&out, "DatePositiveControlreconstituted", row).as_deref(), Some("2024-02-20"));
+        }
+        // In template order, after the stool dates
+        let columns = out.get_column_names().iter().map(|c| c.to_string()).collect::<Vec<String>>();
+        let at = |name: &str| columns.iter().position(|c| c == name).unwrap();
+        assert_eq!(at("TypeofPositiveControl"), at("DateStoolsuspension") + 1);
+        assert_eq!(at("DatePositiveControlreconstituted"), at("TypeofPositiveControl") + 1);
+    }
+
+    #[test]
+    fn older_minion_sheets_get_the_optional_columns_added_empty() {
+        let optional = optional_columns_for_mode("minION");
+        let sheet = full_sheet("minION", optional, &[]);
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("samples.csv");
+        std::fs::write(&path, &sheet).unwrap();
+        let (df, _) = read_csv_normalized(&path.to_string_lossy()).unwrap();
+        assert!(validate_columns(&df, "minION").is_err());
+
+        let (df, added) = add_optional_columns(df, "minION").unwrap();
+        assert_eq!(added, sources(optional));
+        validate_columns(&df, "minION").unwrap();
+        // Adding again is a no-op
+        assert!(add_optional_columns(df, "minION").unwrap().1.is_empty());
+
+        let out = merge_files(&sheet, Some(EPIINFO), "minION", &RunConstants::default());
+        assert_eq!(out.height(), 2);
+        assert_eq!(cell(&out, "TypeofPositiveControl", 0), None);
+        assert_eq!(cell(&out, "EPID", 0).as_deref(), Some("NIE-KAN-24-001"));
+
+        let note = ValidationWarning::OptionalColumnsAdded { columns: added };
+        assert_eq!(note.severity(), crate::warnings::Severity::Info);
+        assert!(note.render(Lang::En).contains("TypeofPositiveControl, DatePositiveControlreconstituted"));
+
+        // DDNS has no optional columns
+        assert!(optional_columns_for_mode("DDNS").is_empty());
+    }
+
+    // Counts the bytes held by the test binary, so a test can see the most
+    // a step had allocated at once
+    struct CountingAlloc;
+
+    static HELD: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    static PEAK: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            use std::sync::atomic::Ordering::Relaxed;
+            let ptr = std::alloc::System.alloc(layout);
+            if !ptr.is_null() {
+                let held = HELD.fetch_add(layout.size(), Relaxed) + layout.size();
+                PEAK.fetch_max(held, Relaxed);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout);
+            HELD.fetch_sub(layout.size(), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    // Bytes held now, and the most held at once since the last call
+    fn held_and_reset_peak() -> (usize, usize) {
+        use std::sync::atomic::Ordering::Relaxed;
+        let held = HELD.load(Relaxed);
+        (held, PEAK.swap(held, Relaxed))
+    }
+
+    // A merged frame with every template column of `mode` plus two the
+    // output drops. Run constant columns are half filled; the others hold
+    // `text_len` characters per cell.
+    fn merged_frame(mode: &str, rows: usize, text_len: usize) -> DataFrame {
+        let constants = run_constant_columns(mode);
+        let mut names = expected_columns_for_mode(mode);
+        names.extend(["PatientName", MATCH_METHOD_COLUMN]);
+        let columns: Vec<Column> = names
+            .into_iter()
+            .map(|name| {
+                let values: Vec<String> = (0..rows)
+                    .map(|row| {
+                        if constants.contains(&name) {
+                            if row % 2 == 0 { String::new() } else { format!("kept{row}") }
+                        } else {
+                            format!("{name}-{row}-").chars().cycle().take(text_len).collect()
+                        }
+                    })
+                    .collect();
+                Series::new(PlSmallStr::from_str(name), values).into_column()
+            })
+            .collect();
+        DataFrame::new(columns).unwrap()
+    }
+
+    fn constants() -> RunConstants {
+        RunConstants {
+            run_num: "24-031".into(),
+            pir_ver: "piranha 1.2".into(),
+            fc_id: Some("FAX12345".into()),
+            seq_kit: Some("SQK-RBK114.96".into()),
+            seq_date: Some("2024-03-05".into()),
+            ..RunConstants::default()
+        }
+    }
+
+    #[test]
+    fn one_pass_output_matches_filling_then_selecting() {
+        let mut per_column = FillPolicies::default();
+        per_column.columns.insert("RunNumber".to_string(), FillPolicy::Overwrite);
+        per_column.columns.insert("FlowCellID".to_string(), FillPolicy::Skip);
+        for mode in ["DDNS", "minION"] {
+            for policies in [FillPolicies::default(), FillPolicies::from_checkbox(false), per_column.clone()] {
+                let df = merged_frame(mode, 6, 12);
+                let old = fill_run_constants(df.clone(), mode, &constants(), &policies)
+                    .and_then(|df| select_expected_columns(df, mode))
+                    .unwrap();
+                let new = build_output(df, mode, &constants(), &policies).unwrap();
+                assert!(new.equals_missing(&old), "{mode} {policies:?}");
+            }
+        }
+
+        // A missing template column fails the same way, without an output
+        let df = merged_frame("DDNS", 3, 12).drop("sample").unwrap();
+        assert!(build_output(df, "DDNS", &constants(), &FillPolicies::default()).is_err());
+    }
+
+    #[test]
+    fn building_the_output_does_not_copy_the_merged_frame() {
+        let (before, _) = held_and_reset_peak();
+        let df = merged_frame("DDNS", 20_000, 64);
+        let (after, _) = held_and_reset_peak();
+        let frame_bytes = after - before;
+
+        let out = build_output(df, "DDNS", &constants(), &FillPolicies::default()).unwrap();
+        let (_, peak) = held_and_reset_peak();
+        assert_eq!(out.height(), 20_000);
+
+        // Only the run constant columns are new; the template's other
+        // columns are shared with the merged frame, which was moved in
+        let extra = peak.saturating_sub(after);
+        assert!(extra < frame_bytes / 2, "built with {extra} extra bytes for a {frame_bytes} byte frame");
+    }
+
+    // A 96-row DDNS sheet whose RunNumber column holds `run_number(row)`;
+    // every other column is empty apart from sample and barcode
+    fn sheet_with_run_numbers(run_number: impl Fn(usize) -> Option<&'static str>) -> DataFrame {
+        let columns: Vec<Column> = expected_columns_for_mode("DDNS")
+            .into_iter()
+            .map(|name| {
+                let values: Vec<Option<String>> = (0..96)
+                    .map(|row| match name {
+                        "sample" => Some(format!("S{:02}", row + 1)),
+                        "barcode" => Some(format!("barcode{:02}", row + 1)),
+                        "RunNumber" => run_number(row).map(str::to_string),
+                        _ => None,
+                    })
+                    .collect();
+                Series::new(PlSmallStr::from_str(name), values).into_column()
+            })
+            .collect();
+        DataFrame::new(columns).unwrap()
+    }
+
+    fn typed_run(run_num: &str) -> RunConstants {
+        RunConstants { run_num: run_num.into(), ..RunConstants::default() }
+    }
+
+    fn run_number_counts(df: &DataFrame) -> Vec<(Option<String>, usize)> {
+        let mut counts: BTreeMap<Option<String>, usize> = BTreeMap::new();
+        for row in 0..df.height() {
+            *counts.entry(cell(df, "RunNumber", row)).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    #[test]
+    fn typed_constant_conflicting_with_most_rows_is_found() {
+        // 90 rows already name the run, 6 are empty
+        let df = sheet_with_run_numbers(|row| (row < 90).then_some("20250110_002"));
+        let conflicts = run_constant_conflicts(&df, "DDNS", &typed_run("20250110_003"), &FillPolicies::default()).unwrap();
+        assert_eq!(
+            conflicts,
+            vec![ConstantConflict {
+                column: "RunNumber".to_string(),
+                typed: "20250110_003".to_string(),
+                existing: vec![("20250110_002".to_string(), 90)],
+                empty_rows: 6,
+            }]
+        );
+
+        // Keeping the existing values fills the empty rows only: two run numbers
+        let kept = build_output(df.clone(), "DDNS", &typed_run("20250110_003"), &FillPolicies::default()).unwrap();
+        assert_eq!(
+            run_number_counts(&kept),
+            [(Some("20250110_002".to_string()), 90), (Some("20250110_003".to_string()), 6)]
+        );
+
+        // Overwrite all replaces every row, not just the empty ones
+        let mut overwrite = FillPolicies::default();
+        for conflict in &conflicts {
+            overwrite.columns.insert(conflict.column.clone(), FillPolicy::Overwrite);
+        }
+        let overwritten = build_output(df.clone(), "DDNS", &typed_run("20250110_003"), &overwrite).unwrap();
+        assert_eq!(run_number_counts(&overwritten), [(Some("20250110_003".to_string()), 96)]);
+        // After that choice there is nothing left to ask about
+        assert!(run_constant_conflicts(&df, "DDNS", &typed_run("20250110_003"), &overwrite).unwrap().is_empty());
+    }
+
+    #[test]
+    fn matching_or_absent_values_do_not_conflict() {
+        let policies = FillPolicies::default();
+        let same = sheet_with_run_numbers(|row| (row % 2 == 0).then_some(" 20250110_002 "));
+        assert!(run_constant_conflicts(&same, "DDNS", &typed_run("20250110_002"), &policies).unwrap().is_empty());
+
+        let empty = sheet_with_run_numbers(|_| None);
+        assert!(run_constant_conflicts(&empty, "DDNS", &typed_run("20250110_003"), &policies).unwrap().is_empty());
+
+        // Nothing typed, a skipped column or a column the sheet lacks
+        let mixed = sheet_with_run_numbers(|row| Some(if row < 50 { "A" } else { "B" }));
+        assert!(run_constant_conflicts(&mixed, "DDNS", &typed_run(" "), &policies).unwrap().is_empty());
+        let skipped = FillPolicies::from_checkbox(false);
+        assert!(run_constant_conflicts(&mixed, "DDNS", &typed_run("C"), &skipped).unwrap().is_empty());
+        let without = mixed.drop("RunNumber").unwrap();
+        assert!(run_constant_conflicts(&without, "DDNS", &typed_run("C"), &policies).unwrap().is_empty());
+    }
+
+    #[test]
+    fn existing_values_are_listed_most_frequent_first() {
+        let df = sheet_with_run_numbers(|row| match row % 4 {
+            0 => Some("B"),
+            1 | 2 => Some("A"),
+            _ => Some("C"),
+        });
+        let conflicts = run_constant_conflicts(&df, "DDNS", &typed_run("A"), &FillPolicies::default()).unwrap();
+        // The typed value is among them, but other values still conflict
+        let existing: Vec<(&str, usize)> = conflicts[0].existing.iter().map(|(v, n)| (v.as_str(), *n)).collect();
+        assert_eq!(existing, [("A", 48), ("B", 24), ("C", 24)]);
+        assert_eq!(conflicts[0].empty_rows, 0);
+
+        let warning =
+            ValidationWarning::RunConstantConflict { columns: vec!["RunNumber".to_string()], decision: "keep".to_string() };
+        assert_eq!(warning.code(), "W046");
+        assert!(warning.render(Lang::En).contains("more than one value"));
+    }
+
+    const POLICIES: [CommentPolicy; 4] = [
+        CommentPolicy::FirstNonEmpty,
+        CommentPolicy::Concatenate,
+        CommentPolicy::PreferSample,
+        CommentPolicy::PreferEpi,
+    ];
+
+    #[test]
+    fn comment_policies_on_one_row() {
+        let merged = |sample, epi| POLICIES.map(|policy| merge_comment(sample, epi, policy));
+        let text = |s: &str| Some(s.to_string());
+
+        // Policies in order: first non-empty, concatenate, prefer sample, prefer EpiInfo
+        assert_eq!(merged(Some("re-extracted"), None), [text("re-extracted"), text("re-extracted"), text("re-extracted"), None]);
+        assert_eq!(merged(None, Some("late arrival")), [text("late arrival"), text("late arrival"), None, text("late arrival")]);
+        assert_eq!(
+            merged(Some(" re-extracted "), Some("late arrival")),
+            [text("re-extracted"), text("re-extracted; EpiInfo: late arrival"), text("re-extracted"), text("late arrival")]
+        );
+        assert_eq!(merged(Some(" "), Some("")), [None, None, None, None]);
+        // The same text on both sides is written once
+        assert_eq!(merge_comment(Some("ok"), Some(" ok "), CommentPolicy::Concatenate), text("ok"));
+    }
+
+    #[test]
+    fn comment_columns_are_merged_per_policy() {
+        // S01 only the lab wrote a comment, S02 only EpiInfo, S03 both,
+        // S04 neither; S05 is not in EpiInfo
+        let sheet = || {
+            let df = df!(
+                "sample" => ["S01", "S02", "S03", "S04", "S05"],
+                "barcode" => ["barcode01", "barcode02", "barcode03", "barcode04", "barcode05"],
+                "QCComments" => [Some("re-extracted"), None, Some("low yield"), None, Some("repeat")],
+                "Province" => [Some("Kano"), Some("Kano"), None, None, None]
+            )
+            .unwrap();
+            SampleSheet::new(df).unwrap()
+        };
+        let epi = || {
+            let df = df!(
+                "ICLabID" => ["S01", "S02", "S03", "S04"],
+                "QCComments" => [None, Some("late arrival"), Some("leaking tube"), Some("")],
+                "Province" => [Some("Lagos"), None, Some("Kano"), None]
+            )
+            .unwrap();
+            EpiData::new(df).unwrap()
+        };
+        let comments = |policy: Option<CommentPolicy>| -> Vec<Option<String>> {
+            let (sheet, epi) = (sheet(), epi());
+            let matches = match_samples(&sheet.samples(), &epi, &MatchOptions::default());
+            let columns: BTreeMap<String, CommentPolicy> =
+                policy.map(|p| ("QCComments".to_string(), p)).into_iter().collect();
+            let (df, _) = merge_with_epiinfo(sheet, epi, &matches, &sources(DEFAULT_EPID_SOURCES), &columns)
+                .unwrap()
+                .into_parts();
+            // Other shared columns still come from EpiInfo
+            assert_eq!(cell(&df, "Province", 0).as_deref(), Some("Lagos"));
+            (0..5).map(|row| cell(&df, "QCComments", row)).collect()
+        };
+        let text = |s: &str| Some(s.to_string());
+
+        assert_eq!(
+            comments(Some(CommentPolicy::FirstNonEmpty)),
+            [text("re-extracted"), text("late arrival"), text("low yield"), None, text("repeat")]
+        );
+        assert_eq!(
+            comments(Some(CommentPolicy::Concatenate)),
+            [text("re-extracted"), text("late arrival"), text("low yield; EpiInfo: leaking tube"), None, text("repeat")]
+        );
+        assert_eq!(
+            comments(Some(CommentPolicy::PreferSample)),
+            [text("re-extracted"), None, text("low yield"), None, text("repeat")]
+        );
+        assert_eq!(comments(Some(CommentPolicy::PreferEpi)), [None, text("late arrival"), text("leaking tube"), None, None]);
+
+        // Without a policy the column is EpiInfo's as it is, as for any shared column
+        assert_eq!(comments(None), [None, text("late arrival"), text("leaking tube"), text(""), None]);
+        assert_eq!(default_comment_columns().get("QCComments"), Some(&CommentPolicy::Concatenate));
+    }
+
+    #[test]
+    fn comment_policies_read_from_settings() {
+        let columns: BTreeMap<String, CommentPolicy> =
+            serde_json::from_str(r#"{"QCComments": "PreferEpi", "RTPCRcomments": "FirstNonEmpty"}"#).unwrap();
+        assert_eq!(columns["QCComments"], CommentPolicy::PreferEpi);
+        assert_eq!(columns["RTPCRcomments"], CommentPolicy::FirstNonEmpty);
+        assert!(serde_json::from_str::<CommentPolicy>(r#""Merge""#).is_err());
+    }
+
+    // Fixture files kept next to the templates
+    fn example_data(name: &str) -> String {
+        format!("{}/example_data/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn ods_sheets_merge_end_to_end_in_both_modes() {
+        let epiinfo = example_data("EPIINFO_export.ods");
+        for (mode, samples) in [("DDNS", "DDNS_samples.ods"), ("minION", "isolate_samples.ods")] {
+            let out = merge_paths(&example_data(samples), Some(&epiinfo), mode, &RunConstants::default(), &[], &HashMap::new());
+            assert_eq!(out.height(), 2, "{}", mode);
+            assert_eq!(cell(&out, "EPID", 0).as_deref(), Some("NIE-KAN-24-001"), "{}", mode);
+            assert_eq!(cell(&out, "DateOfOnset", 1).as_deref(), Some("2024-02-03"), "{}", mode);
+            // Date and number cells come through as the text a CSV would hold
+            assert_eq!(cell(&out, "DateRTPCR", 0).as_deref(), Some("2024-03-12"), "{}", mode);
+            assert_eq!(cell(&out, "SpecimenNumber", 1).as_deref(), Some("2"), "{}", mode);
+        }
     }
 }
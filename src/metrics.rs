@@ -0,0 +1,384 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+// Local-only usage log: one line per successful merge, one file per year.
+// match_rate and null_epid were added later: older lines stop at app_version.
+const HEADER: &str = "date,mode,sample_count,matched_count,app_version,match_rate,null_epid";
+
+/// Weeks shown in the match-rate trend
+pub const TREND_WEEKS: usize = 12;
+
+pub struct MetricsRecord {
+    // YYYY-MM-DD
+    pub date: String,
+    pub mode: String,
+    pub sample_count: usize,
+    pub matched_count: Option<usize>,
+    // Percentage of non-control samples paired with EpiInfo
+    pub match_rate: Option<f64>,
+    // Output rows left without an EPID
+    pub null_epid: Option<usize>,
+}
+
+/// Merges per month, aggregated from the metrics files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthlyCount {
+    // YYYY-MM
+    pub month: String,
+    pub runs: usize,
+    pub samples: usize,
+    pub matched: usize,
+}
+
+pub fn metrics_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("merger"))
+}
+
+fn metrics_path(year: &str) -> Option<PathBuf> {
+    metrics_dir().map(|d| d.join(format!("metrics_{}.csv", year)))
+}
+
+/// Appends one merge to this year's metrics file
+pub fn append_metrics(record: &MetricsRecord) -> Result<(), String> {
+    let year = record.date.get(..4).unwrap_or("unknown");
+    let path = metrics_path(year).ok_or_else(|| "Cannot determine data directory".to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory '{}': {e}", dir.display()))?;
+    }
+
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open metrics file '{}': {e}", path.display()))?;
+
+    let mut line = String::new();
+    if is_new {
+        line.push_str(HEADER);
+        line.push('\n');
+    }
+    line.push_str(&format!(
+        "{},{},{},{},{},{},{}\n",
+        record.date,
+        record.mode.replace(',', " "),
+        record.sample_count,
+        record.matched_count.map(|m| m.to_string()).unwrap_or_default(),
+        env!("CARGO_PKG_VERSION"),
+        record.match_rate.map(|r| format!("{:.1}", r)).unwrap_or_default(),
+        record.null_epid.map(|n| n.to_string()).unwrap_or_default()
+    ));
+
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write metrics file '{}': {e}", path.display()))
+}
+
+/// Aggregates metrics CSV text into per-month counts, oldest first.
+/// Malformed lines are skipped.
+pub fn aggregate_monthly(text: &str) -> Vec<MonthlyCount> {
+    let mut months: BTreeMap<String, MonthlyCount> = BTreeMap::new();
+
+    for line in text.lines().filter(|l| !l.trim().is_empty() && *l != HEADER) {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let Some(month) = fields[0].get(..7) else {
+            continue;
+        };
+        let Ok(samples) = fields[2].trim().parse::<usize>() else {
+            continue;
+        };
+        let matched = fields[3].trim().parse::<usize>().unwrap_or(0);
+
+        let entry = months.entry(month.to_string()).or_insert_with(|| MonthlyCount {
+            month: month.to_string(),
+            runs: 0,
+            samples: 0,
+            matched: 0,
+        });
+        entry.runs += 1;
+        entry.samples += samples;
+        entry.matched += matched;
+    }
+
+    months.into_values().collect()
+}
+
+/// Match quality of one ISO week
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyRate {
+    // 2026-W07
+    pub week: String,
+    // Merges that recorded a match rate
+    pub runs: usize,
+    // Mean match rate of those merges, None for a week without any
+    pub match_rate: Option<f64>,
+    pub null_epid: usize,
+}
+
+fn iso_week_label(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// Match rate per ISO week for the `weeks` weeks ending with the one
+/// holding `today`, oldest first. Weeks without merges are kept, empty.
+/// Lines without a match rate (older files, merges without EpiInfo) only
+/// count towards null_epid.
+pub fn aggregate_weekly(text: &str, today: NaiveDate, weeks: usize) -> Vec<WeeklyRate> {
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let mut trend: Vec<WeeklyRate> = (0..weeks)
+        .rev()
+        .map(|back| WeeklyRate {
+            week: iso_week_label(monday - Duration::weeks(back as i64)),
+            runs: 0,
+            match_rate: None,
+            null_epid: 0,
+        })
+        .collect();
+
+    let mut rate_sums = vec![0.0; trend.len()];
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let Some(date) = fields.first().and_then(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok()) else {
+            continue;
+        };
+        let label = iso_week_label(date);
+        let Some(idx) = trend.iter().position(|w| w.week == label) else {
+            continue;
+        };
+        if let Some(null_epid) = fields.get(6).and_then(|v| v.trim().parse::<usize>().ok()) {
+            trend[idx].null_epid += null_epid;
+        }
+        if let Some(rate) = fields.get(5).and_then(|v| v.trim().parse::<f64>().ok()) {
+            trend[idx].runs += 1;
+            rate_sums[idx] += rate;
+        }
+    }
+    for (week, sum) in trend.iter_mut().zip(rate_sums) {
+        if week.runs > 0 {
+            week.match_rate = Some(sum / week.runs as f64);
+        }
+    }
+    trend
+}
+
+/// One block character per week, higher for a better match rate; a dot
+/// for a week without merges
+pub fn sparkline(trend: &[WeeklyRate]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    trend
+        .iter()
+        .map(|w| match w.match_rate {
+            Some(rate) => BARS[((rate.clamp(0.0, 100.0) / 100.0) * 7.0).round() as usize],
+            None => '·',
+        })
+        .collect()
+}
+
+/// CSV export of the weekly trend
+pub fn weekly_csv(trend: &[WeeklyRate]) -> String {
+    let mut out = String::from("week,runs,match_rate,null_epid\n");
+    for w in trend {
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            w.week,
+            w.runs,
+            w.match_rate.map(|r| format!("{:.1}", r)).unwrap_or_default(),
+            w.null_epid
+        ));
+    }
+    out
+}
+
+// Every yearly metrics file, one after the other
+fn load_metrics_text() -> Result<String, String> {
+    let Some(dir) = metrics_dir() else {
+        return Ok(String::new());
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(String::new());
+    };
+
+    let mut text = String::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("metrics_") && name.ends_with(".csv") {
+            let content = fs::read_to_string(entry.path())
+                .map_err(|e| format!("Failed to read '{}': {e}", entry.path().display()))?;
+            text.push_str(&content);
+            text.push('\n');
+        }
+    }
+    Ok(text)
+}
+
+/// Reads every yearly metrics file and aggregates them
+pub fn load_monthly() -> Result<Vec<MonthlyCount>, String> {
+    load_metrics_text().map(|text| aggregate_monthly(&text))
+}
+
+/// The match-rate trend of the last TREND_WEEKS weeks up to `today`
+pub fn load_weekly(today: NaiveDate) -> Result<Vec<WeeklyRate>, String> {
+    load_metrics_text().map(|text| aggregate_weekly(&text, today, TREND_WEEKS))
+}
+
+/// CSV export of the monthly counts
+pub fn monthly_csv(counts: &[MonthlyCount]) -> String {
+    let mut out = String::from("month,runs,samples,matched\n");
+    for c in counts {
+        out.push_str(&format!("{},{},{},{}\n", c.month, c.runs, c.samples, c.matched));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two yearly files back to back: the old header and the current one
+    const METRICS: &str = "date,mode,sample_count,matched_count,app_version
+2025-12-30,DDNS,24,22,1.4.0
+2026-01-05,DDNS,24,20,1.4.0
+2026-01-19,minION,12,,1.4.0
+
+date,mode,sample_count,matched_count,app_version,match_rate,null_epid
+2026-01-28,minION,10,9,1.5.0,90.0,1
+not a metrics line
+2026-02,DDNS,many,3,1.5.0
+2026-03-02,DDNS,30,30,1.5.0,100.0,0
+";
+
+    fn month(month: &str, runs: usize, samples: usize, matched: usize) -> MonthlyCount {
+        MonthlyCount {
+            month: month.to_string(),
+            runs,
+            samples,
+            matched,
+        }
+    }
+
+    #[test]
+    fn merges_are_counted_per_month_across_files() {
+        assert_eq!(
+            aggregate_monthly(METRICS),
+            vec![
+                month("2025-12", 1, 24, 22),
+                month("2026-01", 3, 46, 29),
+                month("2026-03", 1, 30, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_export_has_one_line_per_month() {
+        let csv = monthly_csv(&aggregate_monthly(METRICS));
+        assert_eq!(csv, "month,runs,samples,matched\n2025-12,1,24,22\n2026-01,3,46,29\n2026-03,1,30,30\n");
+        assert_eq!(monthly_csv(&aggregate_monthly("")), "month,runs,samples,matched\n");
+    }
+
+    // A year of merges up to Friday 2026-10-16 (2026-W42), with weeks
+    // without merges, an old-format line and one from after today
+    const HISTORY: &str = "date,mode,sample_count,matched_count,app_version,match_rate,null_epid
+2025-10-14,DDNS,24,20,1.5.0,83.3,4
+2025-12-30,DDNS,24,22,1.5.0,91.7,2
+2026-01-02,minION,12,6,1.5.0,50.0,6
+2026-01-05,DDNS,24,24,1.5.0,100.0,0
+2026-03-10,DDNS,24,18,1.5.0,75.0,1
+2026-07-28,DDNS,20,10,1.5.0,50.0,3
+2026-07-31,DDNS,20,20,1.5.0,100.0,0
+2026-08-05,minION,10,8,1.5.0,80.0,1
+2026-08-12,DDNS,24,,1.4.0
+2026-08-13,DDNS,24,,1.5.0,,5
+2026-09-14,DDNS,20,19,1.5.0,95.0,0
+2026-10-02,DDNS,10,6,1.5.0,60.0,2
+2026-10-16,DDNS,24,24,1.5.0,100.0,0
+2026-10-19,DDNS,24,12,1.5.0,50.0,9
+";
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn week(week: &str, runs: usize, match_rate: Option<f64>, null_epid: usize) -> WeeklyRate {
+        WeeklyRate { week: week.to_string(), runs, match_rate, null_epid }
+    }
+
+    #[test]
+    fn match_rate_is_averaged_per_iso_week_with_gaps_kept() {
+        let trend = aggregate_weekly(HISTORY, date("2026-10-16"), TREND_WEEKS);
+        let empty = |label: &str| week(label, 0, None, 0);
+        assert_eq!(
+            trend,
+            vec![
+                week("2026-W31", 2, Some(75.0), 3),
+                week("2026-W32", 1, Some(80.0), 1),
+                // Merges without a match rate only add their null EPIDs
+                week("2026-W33", 0, None, 5),
+                empty("2026-W34"),
+                empty("2026-W35"),
+                empty("2026-W36"),
+                empty("2026-W37"),
+                week("2026-W38", 1, Some(95.0), 0),
+                empty("2026-W39"),
+                week("2026-W40", 1, Some(60.0), 2),
+                empty("2026-W41"),
+                week("2026-W42", 1, Some(100.0), 0),
+            ]
+        );
+
+        // Any day of the week gives the same weeks
+        assert_eq!(aggregate_weekly(HISTORY, date("2026-10-12"), TREND_WEEKS), trend);
+        assert_eq!(aggregate_weekly(HISTORY, date("2026-10-18"), TREND_WEEKS), trend);
+    }
+
+    #[test]
+    fn weeks_span_the_year_boundary() {
+        let trend = aggregate_weekly(HISTORY, date("2026-01-07"), 3);
+        let labels: Vec<&str> = trend.iter().map(|w| w.week.as_str()).collect();
+        // 2025-12-30 falls in the first ISO week of 2026
+        assert_eq!(labels, ["2025-W52", "2026-W01", "2026-W02"]);
+        assert_eq!((trend[0].runs, trend[0].match_rate), (0, None));
+        assert_eq!((trend[1].runs, trend[1].null_epid), (2, 8));
+        assert!((trend[1].match_rate.unwrap() - 70.85).abs() < 1e-9);
+        assert_eq!(trend[2].match_rate, Some(100.0));
+    }
+
+    #[test]
+    fn a_whole_year_of_weeks() {
+        let trend = aggregate_weekly(HISTORY, date("2026-10-16"), 52);
+        assert_eq!(trend.len(), 52);
+        assert_eq!((trend[0].week.as_str(), trend[51].week.as_str()), ("2025-W43", "2026-W42"));
+        let labels: std::collections::HashSet<&str> = trend.iter().map(|w| w.week.as_str()).collect();
+        assert_eq!(labels.len(), 52);
+
+        // The same week number a year earlier and merges after today are not counted
+        assert_eq!(trend.iter().map(|w| w.runs).sum::<usize>(), 10);
+        assert_eq!(trend.iter().map(|w| w.null_epid).sum::<usize>(), 20);
+        assert_eq!(trend.iter().filter(|w| w.match_rate.is_none()).count(), 52 - 8);
+    }
+
+    #[test]
+    fn trend_renders_as_sparkline_and_csv() {
+        let trend = aggregate_weekly(HISTORY, date("2026-10-16"), TREND_WEEKS);
+        assert_eq!(sparkline(&trend), "▆▇·····█·▅·█");
+
+        let csv = weekly_csv(&trend);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), TREND_WEEKS + 1);
+        assert_eq!(lines[0], "week,runs,match_rate,null_epid");
+        assert_eq!(lines[1], "2026-W31,2,75.0,3");
+        assert_eq!(lines[3], "2026-W33,0,,5");
+        assert_eq!(lines[12], "2026-W42,1,100.0,0");
+
+        // No history at all: every week empty
+        let none = aggregate_weekly("", date("2026-10-16"), TREND_WEEKS);
+        assert_eq!(sparkline(&none), "·".repeat(TREND_WEEKS));
+        assert!(none.iter().all(|w| w.runs == 0 && w.null_epid == 0));
+    }
+}
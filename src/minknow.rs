@@ -1,9 +1,11 @@
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::fs::File;
-use std::io::Read;
 
-#[derive(Default)]
+use crate::clock::{report_date, DateZone};
+use crate::input::{local_input, read_input_text};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MinKnowData {
     pub minknow_ver: String,
     pub fc_id: String,
@@ -11,118 +13,762 @@ pub struct MinKnowData {
     pub seq_hours: String,
     pub seq_date: String,
     pub fc_pores: String,
+    // Basecaller name and version (e.g. "Dorado 7.4.12") and model.
+    // Optional: older reports and runs without live basecalling have neither.
+    #[serde(default)]
+    pub basecaller: String,
+    #[serde(default)]
+    pub basecall_model: String,
+    // Computer and device position the run was on (e.g. "GXB02001", "X3").
+    // Optional: only some reports list them.
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub position: String,
+    // run_end_time as the report wrote it (UTC); seq_date is derived from it
+    #[serde(default)]
+    pub run_end_time: String,
+    // Whether the run was set up with barcoding; None when the report
+    // does not say
+    #[serde(default)]
+    pub barcoding: Option<bool>,
+}
+
+impl MinKnowData {
+    /// Re-derives the sequencing date from the report's run end in `zone`.
+    /// Dates entered by hand (no run end time) are left alone.
+    pub fn set_date_zone(&mut self, zone: DateZone) {
+        if let Some(date) = report_date(&self.run_end_time, zone) {
+            self.seq_date = date;
+        }
+    }
+
+    /// Labels of values that are still empty, used when the user enters
+    /// MinKNOW values by hand because no report is available
+    pub fn missing_fields(&self) -> Vec<&'static str> {
+        let fields = [
+            (&self.minknow_ver, "MinKNOW Version"),
+            (&self.fc_id, "Flow Cell ID"),
+            (&self.fc_pores, "Pores Available"),
+            (&self.seq_kit, "Sequencing Kit"),
+            (&self.seq_hours, "Run Hours"),
+            (&self.seq_date, "Sequencing Date"),
+        ];
+        fields
+            .iter()
+            .filter(|(val, _)| val.trim().is_empty())
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// (label, value) pairs in display order
+    pub fn labelled_values(&self) -> [(&'static str, &str); 10] {
+        [
+            ("MinKNOW Version", &self.minknow_ver),
+            ("Flow Cell ID", &self.fc_id),
+            ("Pores Available", &self.fc_pores),
+            ("Sequencing Kit", &self.seq_kit),
+            ("Run Hours", &self.seq_hours),
+            ("Sequencing Date", &self.seq_date),
+            ("Basecaller", &self.basecaller),
+            ("Basecall Model", &self.basecall_model),
+            ("Hostname", &self.hostname),
+            ("Position", &self.position),
+        ]
+    }
+
+    /// Plain-text block, one "label: value" per line
+    pub fn to_text(&self) -> String {
+        self.labelled_values()
+            .iter()
+            .map(|(label, value)| format!("{}: {}", label, value))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Two-row CSV (header + values) for report_metadata.csv
+    pub fn to_csv(&self) -> String {
+        let values = self.labelled_values();
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        format!(
+            "{}\n{}\n",
+            values.iter().map(|(l, _)| quote(l)).collect::<Vec<_>>().join(","),
+            values.iter().map(|(_, v)| quote(v)).collect::<Vec<_>>().join(",")
+        )
+    }
+}
+
+/// Where a merge takes its MinKNOW values from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinKnowSource {
+    // The selected report file
+    Report,
+    // Typed into the panel because the run has no report; all required
+    Manual,
+    // Neither; the EpiInfo file must then be given
+    Absent,
 }
 
-pub fn parse_minknow_html(path: &str) -> Result<MinKnowData, String> {
-    let mut html_content = String::new();
-    let mut file = File::open(path)
-        .map_err(|e| format!("Failed to open HTML file at '{}': {:?}", path, e))?;
-    file.read_to_string(&mut html_content)
+/// "No report" wins over a selected file, so typed values are never mixed
+/// with a report left selected from an earlier run
+pub fn minknow_source(no_report: bool, report_path: &str) -> MinKnowSource {
+    if no_report {
+        MinKnowSource::Manual
+    } else if report_path.trim().is_empty() {
+        MinKnowSource::Absent
+    } else {
+        MinKnowSource::Report
+    }
+}
+
+// Runs often end the day after they were started, which is the date some
+// users write down
+const SEQ_DATE_TOLERANCE_DAYS: i64 = 1;
+
+/// Days between a hand-entered sequencing date and the report's run end
+/// date when they are more than a day apart. None if they agree or either
+/// is not a YYYY-MM-DD date.
+pub fn seq_date_mismatch(manual: &str, report: &str) -> Option<i64> {
+    let parse = |s: &str| chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok();
+    let days = (parse(manual)? - parse(report)?).num_days().abs();
+    (days > SEQ_DATE_TOLERANCE_DAYS).then_some(days)
+}
+
+/// Report formats the parser understands, by file extension
+pub const SUPPORTED_REPORT_EXTENSIONS: &[&str] = &["html", "htm", "json"];
+
+const REPORT_DATA_MARKER: &str = "const reportData=";
+
+fn report_extension(path: &str) -> String {
+    std::path::Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+pub fn is_supported_report(path: &str) -> bool {
+    SUPPORTED_REPORT_EXTENSIONS.contains(&report_extension(path).as_str())
+}
+
+/// Parses a MinKNOW run report, picking the parser from the file extension.
+/// Combined reports of multi-position devices give one run per position.
+pub fn parse_minknow_report(path: &str) -> Result<Vec<MinKnowData>, String> {
+    match report_extension(path).as_str() {
+        "html" | "htm" => parse_minknow_html(&local_input(path)?.path_str()),
+        "json" => parse_minknow_json(&local_input(path)?.path_str()),
+        _ => Err(format!(
+            "Unsupported MinKNOW report format '{}'. Supported: {}",
+            path,
+            SUPPORTED_REPORT_EXTENSIONS.join(", ")
+        )),
+    }
+}
+
+/// Parses the report data exported as JSON (same content as the HTML's
+/// reportData); an array holds one report per position
+pub fn parse_minknow_json(path: &str) -> Result<Vec<MinKnowData>, String> {
+    let content = read_input_text(path)
+        .map_err(|e| format!("Failed to read JSON report at '{}': {:?}", path, e))?;
+    let report_data: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse JSON report at '{}': {e}", path))?;
+    match report_data.as_array() {
+        Some(reports) if !reports.is_empty() => Ok(reports.iter().map(data_from_report).collect()),
+        _ => Ok(vec![data_from_report(&report_data)]),
+    }
+}
+
+// Every reportData object assigned in a script, in order. Each object is
+// read as one JSON value, so semicolons inside strings do not cut it short.
+// Blocks that are not valid JSON are skipped.
+fn report_data_blocks(script_text: &str) -> Vec<Value> {
+    script_text
+        .split(REPORT_DATA_MARKER)
+        .skip(1)
+        .filter_map(|rest| {
+            serde_json::Deserializer::from_str(rest.trim_start())
+                .into_iter::<Value>()
+                .next()
+                .and_then(|v| v.ok())
+        })
+        .filter(|v| v.is_object())
+        .collect()
+}
+
+/// Parses every reportData block of an HTML report. A report without one
+/// gives a single empty run, as before combined reports were handled.
+pub fn parse_minknow_html(path: &str) -> Result<Vec<MinKnowData>, String> {
+    let html_content = read_input_text(path)
         .map_err(|e| format!("Failed to read HTML file at '{}': {:?}", path, e))?;
 
     let document = Html::parse_document(&html_content);
     let script_selector = Selector::parse("script")
         .map_err(|e| format!("Failed to parse script selector: {:?}", e))?;
 
-    let mut script_tag = None;
-    for script in document.select(&script_selector) {
-        let text = script.text().collect::<String>();
-        if text.contains("const reportData=") {
-            script_tag = Some(script);
-            break;
-        }
+    let runs: Vec<MinKnowData> = document
+        .select(&script_selector)
+        .map(|script| script.text().collect::<String>())
+        .filter(|text| text.contains(REPORT_DATA_MARKER))
+        .flat_map(|text| report_data_blocks(&text))
+        .map(|report_data| data_from_report(&report_data))
+        .collect();
+
+    if runs.is_empty() {
+        return Ok(vec![MinKnowData::default()]);
     }
+    Ok(runs)
+}
 
+/// How a run of a combined report is picked: its position, or its number
+/// in the report ("2") when the report does not name positions
+pub fn run_key(index: usize, data: &MinKnowData) -> String {
+    if data.position.trim().is_empty() {
+        (index + 1).to_string()
+    } else {
+        data.position.trim().to_string()
+    }
+}
+
+/// The run to use from a parsed report. A single run is used as is;
+/// otherwise `position` must name one of them (case-insensitively), and
+/// None means the user has to choose.
+pub fn choose_run(runs: &[MinKnowData], position: &str) -> Option<MinKnowData> {
+    if let [only] = runs {
+        return Some(only.clone());
+    }
+    let position = position.trim();
+    if position.is_empty() {
+        return None;
+    }
+    runs.iter()
+        .enumerate()
+        .find(|(idx, data)| run_key(*idx, data).eq_ignore_ascii_case(position))
+        .map(|(_, data)| data.clone())
+}
+
+// Pulls the fields we use out of MinKNOW's reportData object
+// "Guppy", "Dorado", "Guppy basecaller", ...
+fn is_basecaller_title(title: &str) -> bool {
+    let title = title.to_lowercase();
+    title.contains("guppy") || title.contains("dorado")
+}
+
+// "Basecall model", "Basecalling model", "Basecall config", "Model"
+fn is_basecall_model_title(title: &str) -> bool {
+    let title = title.to_lowercase();
+    title == "model" || (title.contains("basecall") && (title.contains("model") || title.contains("config")))
+}
+
+// "Barcoding disabled", "Off", "None" -> false; "Enabled" or a barcoding
+// kit name -> true
+fn barcoding_from_setting(value: &str) -> Option<bool> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() {
+        return None;
+    }
+    let off = ["disabled", "off", "none", "no", "false", "n/a"];
+    Some(!off.iter().any(|w| value == *w || value.ends_with(&format!(" {}", w))))
+}
+
+// First non-empty string among `keys` of `object`
+fn first_string(object: Option<&Value>, keys: &[&str]) -> Option<String> {
+    let object = object?;
+    keys.iter()
+        .filter_map(|k| object.get(*k).and_then(|v| v.as_str()))
+        .map(|s| s.trim().to_string())
+        .find(|s| !s.is_empty())
+}
+
+// Value of the first run_setup entry whose title is one of `titles`
+fn setup_value(report_data: &Value, titles: &[&str]) -> Option<String> {
+    report_data
+        .get("run_setup")
+        .and_then(|v| v.as_array())?
+        .iter()
+        .find(|e| {
+            e.get("title")
+                .and_then(|t| t.as_str())
+                .is_some_and(|t| titles.iter().any(|w| t.eq_ignore_ascii_case(w)))
+        })
+        .and_then(|e| e.get("value").and_then(|v| v.as_str()))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// Hostname and position, from the host/device blocks or the run setup
+// list depending on the MinKNOW release. Left empty when absent.
+fn device_from_report(report_data: &Value) -> (String, String) {
+    let hostname = first_string(report_data.get("host"), &["hostname", "name", "serial"])
+        .or_else(|| setup_value(report_data, &["Hostname", "Host name", "Host"]))
+        .unwrap_or_default();
+    let position = first_string(report_data.get("device"), &["device_id", "position", "name"])
+        .or_else(|| first_string(report_data.get("position"), &["name", "device_id"]))
+        .or_else(|| setup_value(report_data, &["Position", "Device position", "Device ID"]))
+        .unwrap_or_default();
+    (hostname, position)
+}
+
+fn data_from_report(report_data: &Value) -> MinKnowData {
     let mut data = MinKnowData::default();
 
-    if let Some(script) = script_tag {
-        let script_text = script.text().collect::<String>();
-        if let Some(json_str) = script_text
-            .split("const reportData=")
-            .nth(1)
-            .and_then(|s| s.split(';').next())
-            .map(|s| s.trim())
-        {
-            if let Ok(report_data) = serde_json::from_str::<Value>(json_str) {
-                // Software versions
-                if let Some(software_versions) = report_data.get("software_versions").and_then(|v| v.as_array()) {
-                    for version in software_versions {
-                        if version.get("title").and_then(|t| t.as_str()) == Some("MinKNOW") {
-                            data.minknow_ver = version
-                                .get("value")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("Unknown")
-                                .to_string();
-                        }
-                    }
-                }
+    // Software versions
+    if let Some(software_versions) = report_data.get("software_versions").and_then(|v| v.as_array()) {
+        for version in software_versions {
+            let title = version.get("title").and_then(|t| t.as_str()).unwrap_or("");
+            if title == "MinKNOW" {
+                data.minknow_ver = version
+                    .get("value")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Unknown")
+                    .to_string();
+            } else if is_basecaller_title(title) {
+                let value = version.get("value").and_then(|v| v.as_str()).unwrap_or("");
+                data.basecaller = format!("{} {}", title, value).trim().to_string();
+            }
+        }
+    }
 
-                // Run setup
-                if let Some(run_config) = report_data.get("run_setup").and_then(|v| v.as_array()) {
-                    for config in run_config {
-                        match config.get("title").and_then(|t| t.as_str()) {
-                            Some("Flow cell ID") => {
-                                data.fc_id = config
-                                    .get("value")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("Unknown")
-                                    .to_string();
-                            }
-                            Some("Kit type") => {
-                                data.seq_kit = config
-                                    .get("value")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("Unknown")
-                                    .to_string();
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+    // Basecall model, listed under run settings or run setup depending on the MinKNOW release
+    for section in ["run_settings", "run_setup"] {
+        if !data.basecall_model.is_empty() {
+            break;
+        }
+        if let Some(entries) = report_data.get(section).and_then(|v| v.as_array()) {
+            if let Some(model) = entries.iter().find_map(|e| {
+                let title = e.get("title").and_then(|t| t.as_str())?;
+                is_basecall_model_title(title)
+                    .then(|| e.get("value").and_then(|v| v.as_str()))
+                    .flatten()
+            }) {
+                data.basecall_model = model.to_string();
+            }
+        }
+    }
 
-                // Run settings
-                if let Some(run_settings) = report_data.get("run_settings").and_then(|v| v.as_array()) {
-                    for config in run_settings {
-                        if config.get("title").and_then(|t| t.as_str()) == Some("Run limit") {
-                            data.seq_hours = config
-                                .get("value")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("Unknown")
-                                .to_string();
-                        }
-                    }
+    // Run setup
+    if let Some(run_config) = report_data.get("run_setup").and_then(|v| v.as_array()) {
+        for config in run_config {
+            match config.get("title").and_then(|t| t.as_str()) {
+                Some("Flow cell ID") => {
+                    data.fc_id = config
+                        .get("value")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("Unknown")
+                        .to_string();
+                }
+                Some("Kit type") => {
+                    data.seq_kit = config
+                        .get("value")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("Unknown")
+                        .to_string();
                 }
+                _ => {}
+            }
+        }
+    }
 
-                // Run end time
-                data.seq_date = report_data
-                    .get("run_end_time")
+    // Run settings
+    if let Some(run_settings) = report_data.get("run_settings").and_then(|v| v.as_array()) {
+        for config in run_settings {
+            if config.get("title").and_then(|t| t.as_str()) == Some("Run limit") {
+                data.seq_hours = config
+                    .get("value")
                     .and_then(|v| v.as_str())
-                    .and_then(|s| s.split('T').next())
                     .unwrap_or("Unknown")
                     .to_string();
+            }
+        }
+    }
+
+    // Device, for labs with more than one sequencer
+    (data.hostname, data.position) = device_from_report(report_data);
+
+    data.barcoding = setup_value(report_data, &["Barcoding", "Barcoding kit", "Barcoding kits", "Barcode kits"])
+        .and_then(|v| barcoding_from_setting(&v));
 
-                // Pore scan
-                if let Some(series_data) = report_data
-                    .get("pore_scan")
-                    .and_then(|v| v.get("series_data"))
-                    .and_then(|v| v.as_array())
-                {
-                    if let Some(pore_available) = series_data
-                        .iter()
-                        .find(|&s| s.get("name").and_then(|n| n.as_str()) == Some("Pore available"))
-                    {
-                        if let Some(data_arr) = pore_available.get("data").and_then(|v| v.as_array()) {
-                            if let Some(first_data_pair) = data_arr.get(0) {
-                                if let Some(value) = first_data_pair.get(1).and_then(|v| v.as_i64()) {
-                                    data.fc_pores = value.to_string();
-                                }
-                            }
-                        }
+    // Run end time, in UTC; the date is the local day it ended
+    data.run_end_time = report_data
+        .get("run_end_time")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    data.seq_date = report_date(&data.run_end_time, DateZone::Local).unwrap_or_else(|| "Unknown".to_string());
+
+    // Pore scan
+    if let Some(series_data) = report_data
+        .get("pore_scan")
+        .and_then(|v| v.get("series_data"))
+        .and_then(|v| v.as_array())
+    {
+        if let Some(pore_available) = series_data
+            .iter()
+            .find(|&s| s.get("name").and_then(|n| n.as_str()) == Some("Pore available"))
+        {
+            if let Some(data_arr) = pore_available.get("data").and_then(|v| v.as_array()) {
+                if let Some(first_data_pair) = data_arr.first() {
+                    if let Some(value) = first_data_pair.get(1).and_then(|v| v.as_i64()) {
+                        data.fc_pores = value.to_string();
                     }
                 }
             }
         }
     }
 
-    Ok(data)
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn barcoding_settings_are_read() {
+        let cases = [
+            ("Barcoding disabled", Some(false)),
+            ("Disabled", Some(false)),
+            ("off", Some(false)),
+            ("None", Some(false)),
+            ("No", Some(false)),
+            ("Enabled", Some(true)),
+            ("Barcoding enabled", Some(true)),
+            ("SQK-NBD114-96", Some(true)),
+            ("", None),
+            ("   ", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(barcoding_from_setting(value), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn report_without_barcoding_says_so() {
+        let report = json!({
+            "run_setup": [
+                { "title": "Flow cell ID", "value": "FAX12345" },
+                { "title": "Barcoding", "value": "Barcoding disabled" }
+            ]
+        });
+        let data = data_from_report(&report);
+        assert_eq!(data.fc_id, "FAX12345");
+        assert_eq!(data.barcoding, Some(false));
+    }
+
+    #[test]
+    fn report_without_a_barcoding_entry_leaves_it_unknown() {
+        let data = data_from_report(&json!({ "run_setup": [] }));
+        assert_eq!(data.barcoding, None);
+    }
+
+    fn filled() -> MinKnowData {
+        MinKnowData {
+            minknow_ver: "24.02.6".into(),
+            fc_id: "FAX12345".into(),
+            seq_kit: "SQK-NBD114-96".into(),
+            seq_hours: "72".into(),
+            seq_date: "2026-03-01".into(),
+            fc_pores: "1450".into(),
+            ..MinKnowData::default()
+        }
+    }
+
+    #[test]
+    fn no_report_toggle_wins_over_a_selected_file() {
+        assert_eq!(minknow_source(true, "old_run/report.html"), MinKnowSource::Manual);
+        assert_eq!(minknow_source(true, ""), MinKnowSource::Manual);
+        assert_eq!(minknow_source(false, "report.html"), MinKnowSource::Report);
+        assert_eq!(minknow_source(false, "  "), MinKnowSource::Absent);
+    }
+
+    #[test]
+    fn manual_values_are_required_without_a_report() {
+        assert_eq!(
+            MinKnowData::default().missing_fields(),
+            ["MinKNOW Version", "Flow Cell ID", "Pores Available", "Sequencing Kit", "Run Hours", "Sequencing Date"]
+        );
+        let data = MinKnowData { fc_pores: " ".into(), seq_date: String::new(), ..filled() };
+        assert_eq!(data.missing_fields(), ["Pores Available", "Sequencing Date"]);
+        assert!(filled().missing_fields().is_empty());
+    }
+
+    // reportData as MinKNOW writes it, trimmed to the fields we read
+    const REPORT_DATA: &str = r#"{
+        "software_versions": [{"title": "MinKNOW", "value": "24.02.8"}, {"title": "Dorado", "value": "7.3.11"}],
+        "run_setup": [
+            {"title": "Flow cell ID", "value": "FAX12345"},
+            {"title": "Kit type", "value": "SQK-RBK114.96"},
+            {"title": "Position", "value": "X2"}
+        ],
+        "run_settings": [{"title": "Run limit", "value": "72 hrs"}],
+        "run_end_time": "2024-03-05T12:00:00Z",
+        "pore_scan": {"series_data": [{"name": "Pore available", "data": [[0, 1432]]}]}
+    }"#;
+
+    fn report_file(dir: &mut crate::workspace::TempWorkspace, name: &str, text: &str) -> String {
+        let path = dir.file(name);
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn assert_report_values(runs: &[MinKnowData]) {
+        assert_eq!(runs.len(), 1);
+        let data = &runs[0];
+        assert_eq!(data.minknow_ver, "24.02.8");
+        assert_eq!(data.fc_id, "FAX12345");
+        assert_eq!(data.seq_kit, "SQK-RBK114.96");
+        assert_eq!(data.seq_hours, "72 hrs");
+        assert_eq!(data.fc_pores, "1432");
+        assert_eq!(data.basecaller, "Dorado 7.3.11");
+        assert_eq!(data.position, "X2");
+        assert_eq!(data.run_end_time, "2024-03-05T12:00:00Z");
+    }
+
+    #[test]
+    fn every_supported_report_format_is_extracted() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let html = format!(
+            "<html><head><script>var x = 1;</script><script>const reportData={};\nrender(reportData);</script></head></html>",
+            REPORT_DATA
+        );
+        for (name, text) in [
+            ("report.html", html.clone()),
+            ("REPORT.HTM", html),
+            ("report.json", REPORT_DATA.to_string()),
+        ] {
+            let path = report_file(&mut dir, name, &text);
+            assert!(is_supported_report(&path), "{}", name);
+            assert_report_values(&parse_minknow_report(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn report_parse_failures_are_errors() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+
+        let broken = report_file(&mut dir, "report.json", "{\"run_setup\": [");
+        let err = parse_minknow_report(&broken).unwrap_err();
+        assert!(err.starts_with("Failed to parse JSON report"), "{}", err);
+
+        // Compressed reports are not read
+        let gz = report_file(&mut dir, "report.json.gz", "\u{1f}\u{8b}");
+        assert!(!is_supported_report(&gz));
+        let err = parse_minknow_report(&gz).unwrap_err();
+        assert!(err.starts_with("Unsupported MinKNOW report format"), "{}", err);
+
+        let missing = dir.path().join("missing.html").to_string_lossy().to_string();
+        assert!(parse_minknow_report(&missing).is_err());
+    }
+
+    #[test]
+    fn extracted_values_copy_and_save_as_text_and_csv() {
+        let mut data = MinKnowData {
+            minknow_ver: "24.02.8".to_string(),
+            fc_id: "FAX12345".to_string(),
+            fc_pores: "1432".to_string(),
+            seq_kit: "SQK-RBK114.96".to_string(),
+            seq_hours: "72 hrs".to_string(),
+            seq_date: "2024-03-05".to_string(),
+            ..MinKnowData::default()
+        };
+        data.hostname = "GXB \"lab\" 2".to_string();
+
+        let text = data.to_text();
+        assert!(text.starts_with("MinKNOW Version: 24.02.8\nFlow Cell ID: FAX12345\nPores Available: 1432\n"));
+        assert_eq!(text.lines().count(), 10);
+
+        let csv = data.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("\"MinKNOW Version\",\"Flow Cell ID\","));
+        assert!(lines[1].contains(",\"GXB \"\"lab\"\" 2\","));
+    }
+
+    #[test]
+    fn guppy_basecaller_and_model_are_read() {
+        let report = json!({
+            "software_versions": [
+                { "title": "MinKNOW", "value": "22.12.7" },
+                { "title": "Guppy", "value": "6.4.6" }
+            ],
+            "run_settings": [{ "title": "Basecall model", "value": "dna_r9.4.1_450bps_hac" }]
+        });
+        let data = data_from_report(&report);
+        assert_eq!(data.minknow_ver, "22.12.7");
+        assert_eq!(data.basecaller, "Guppy 6.4.6");
+        assert_eq!(data.basecall_model, "dna_r9.4.1_450bps_hac");
+    }
+
+    #[test]
+    fn dorado_basecaller_and_model_are_read() {
+        let report = json!({
+            "software_versions": [
+                { "title": "MinKNOW", "value": "24.02.8" },
+                { "title": "Dorado", "value": "7.3.11" }
+            ],
+            "run_setup": [{ "title": "Basecalling config", "value": "dna_r10.4.1_e8.2_400bps_sup@v4.3.0" }]
+        });
+        let data = data_from_report(&report);
+        assert_eq!(data.basecaller, "Dorado 7.3.11");
+        assert_eq!(data.basecall_model, "dna_r10.4.1_e8.2_400bps_sup@v4.3.0");
+    }
+
+    #[test]
+    fn report_without_basecalling_leaves_it_empty() {
+        let report = json!({
+            "software_versions": [{ "title": "MinKNOW", "value": "21.10.4" }],
+            "run_setup": [{ "title": "Flow cell ID", "value": "FAX12345" }]
+        });
+        let data = data_from_report(&report);
+        assert_eq!(data.basecaller, "");
+        assert_eq!(data.basecall_model, "");
+        // Only the required values are reported missing
+        assert!(!data.missing_fields().iter().any(|f| f.starts_with("Basecall")));
+
+        // Saved values from before basecalling was read still load
+        let old: MinKnowData = serde_json::from_str(
+            r#"{"minknow_ver":"21.10.4","fc_id":"FAX12345","seq_kit":"","seq_hours":"","seq_date":"","fc_pores":""}"#,
+        )
+        .unwrap();
+        assert_eq!(old.basecaller, "");
+    }
+
+    #[test]
+    fn same_day_sequencing_dates_agree() {
+        assert_eq!(seq_date_mismatch("2024-03-05", "2024-03-05"), None);
+        assert_eq!(seq_date_mismatch(" 2024-03-05 ", "2024-03-05"), None);
+    }
+
+    #[test]
+    fn a_day_apart_across_midnight_is_tolerated() {
+        // Run ended at 23:30 UTC: the next day for a lab east of UTC
+        let east = chrono::FixedOffset::east_opt(3600).unwrap();
+        let report = crate::clock::report_date_in("2024-03-05T23:30:00Z", &east).unwrap();
+        assert_eq!(report, "2024-03-06");
+        assert_eq!(seq_date_mismatch("2024-03-05", &report), None);
+        // Started the evening before, written down as the start date
+        assert_eq!(seq_date_mismatch("2024-03-04", "2024-03-05"), None);
+        assert_eq!(seq_date_mismatch("2024-02-29", "2024-03-01"), None);
+        assert_eq!(seq_date_mismatch("2023-12-31", "2024-01-01"), None);
+    }
+
+    #[test]
+    fn gross_mismatch_gives_the_gap_in_days() {
+        assert_eq!(seq_date_mismatch("2024-03-05", "2024-03-07"), Some(2));
+        assert_eq!(seq_date_mismatch("2024-03-07", "2024-03-05"), Some(2));
+        assert_eq!(seq_date_mismatch("2024-01-05", "2024-03-05"), Some(60));
+        // Nothing to compare against
+        assert_eq!(seq_date_mismatch("2024-03-05", "Unknown"), None);
+        assert_eq!(seq_date_mismatch("", "2024-03-05"), None);
+        assert_eq!(seq_date_mismatch("05/03/2024", "2024-03-09"), None);
+    }
+
+    #[test]
+    fn host_block_gives_hostname_and_position() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let mut report: Value = serde_json::from_str(REPORT_DATA).unwrap();
+        report["host"] = json!({ "serial": "", "hostname": " GXB02001 " });
+        report["device"] = json!({ "device_id": "X3" });
+        let path = report_file(&mut dir, "report.json", &report.to_string());
+        let data = &parse_minknow_report(&path).unwrap()[0];
+        assert_eq!(data.hostname, "GXB02001");
+        // The device block wins over the run setup's "Position"
+        assert_eq!(data.position, "X3");
+        assert!(data.labelled_values().contains(&("Hostname", "GXB02001")));
+    }
+
+    #[test]
+    fn older_reports_list_the_device_in_the_run_setup() {
+        let report = json!({
+            "run_setup": [
+                { "title": "Host name", "value": "lab-gridion-2" },
+                { "title": "Device position", "value": "GA20000" }
+            ]
+        });
+        let data = data_from_report(&report);
+        assert_eq!((data.hostname.as_str(), data.position.as_str()), ("lab-gridion-2", "GA20000"));
+    }
+
+    #[test]
+    fn reports_without_a_host_block_leave_it_empty() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let path = report_file(&mut dir, "report.json", REPORT_DATA);
+        let data = &parse_minknow_report(&path).unwrap()[0];
+        assert_eq!(data.hostname, "");
+        // The rest of the report is read as usual
+        assert_report_values(std::slice::from_ref(data));
+
+        let data = data_from_report(&json!({ "host": {}, "device": { "device_id": "  " } }));
+        assert_eq!((data.hostname.as_str(), data.position.as_str()), ("", ""));
+
+        // Saved before the device fields existed
+        let saved: MinKnowData = serde_json::from_str(
+            r#"{"minknow_ver": "23.04.5", "fc_id": "FAX1", "seq_kit": "", "seq_hours": "", "seq_date": "", "fc_pores": ""}"#,
+        )
+        .unwrap();
+        assert_eq!((saved.hostname.as_str(), saved.position.as_str()), ("", ""));
+    }
+
+    // Combined report of a two-position device: one reportData per position
+    fn two_position_report() -> String {
+        let run = |position: &str, fc_id: &str, end: &str| {
+            format!(
+                r#"{{"run_setup": [{{"title": "Flow cell ID", "value": "{fc_id}"}}, {{"title": "Position", "value": "{position}"}}, {{"title": "Notes", "value": "a; b"}}], "run_end_time": "{end}"}}"#
+            )
+        };
+        format!(
+            "<html><script>const reportData={};\nrender(reportData);</script><script>var chart = 1;</script><script>const reportData={}; render(reportData);</script></html>",
+            run("X1", "FAX11111", "2024-03-05T10:00:00Z"),
+            run("X2", "FAX22222", "2024-03-06T11:00:00Z"),
+        )
+    }
+
+    #[test]
+    fn every_position_of_a_combined_report_is_extracted() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let path = report_file(&mut dir, "combined.html", &two_position_report());
+        let runs = parse_minknow_report(&path).unwrap();
+        let found: Vec<(&str, &str, &str)> =
+            runs.iter().map(|r| (r.position.as_str(), r.fc_id.as_str(), r.run_end_time.as_str())).collect();
+        assert_eq!(
+            found,
+            [("X1", "FAX11111", "2024-03-05T10:00:00Z"), ("X2", "FAX22222", "2024-03-06T11:00:00Z")]
+        );
+
+        // Both blocks in one script, and the JSON export's array, give the same runs
+        let one_script = two_position_report().replace("</script><script>var chart = 1;</script><script>", "\n");
+        let path = report_file(&mut dir, "one_script.html", &one_script);
+        assert_eq!(parse_minknow_report(&path).unwrap().len(), 2);
+
+        let blocks = report_data_blocks(&two_position_report());
+        let json = report_file(&mut dir, "combined.json", &Value::Array(blocks).to_string());
+        let runs = parse_minknow_report(&json).unwrap();
+        assert_eq!(runs[1].fc_id, "FAX22222");
+    }
+
+    #[test]
+    fn position_picks_the_run_of_a_combined_report() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let path = report_file(&mut dir, "combined.html", &two_position_report());
+        let runs = parse_minknow_report(&path).unwrap();
+
+        assert_eq!(choose_run(&runs, "X2").map(|r| r.fc_id), Some("FAX22222".to_string()));
+        assert_eq!(choose_run(&runs, " x1 ").map(|r| r.fc_id), Some("FAX11111".to_string()));
+        // More than one run: the user has to choose
+        assert!(choose_run(&runs, "").is_none());
+        assert!(choose_run(&runs, "X3").is_none());
+
+        // A single run is used without asking
+        assert_eq!(choose_run(&runs[..1], "").map(|r| r.fc_id), Some("FAX11111".to_string()));
+
+        // Without positions, runs are numbered
+        let unnamed = [MinKnowData::default(), MinKnowData::default()];
+        assert_eq!((run_key(0, &unnamed[0]), run_key(1, &unnamed[1])), ("1".to_string(), "2".to_string()));
+        assert!(choose_run(&unnamed, "2").is_some());
+
+        let cli = |args: &[&str]| crate::cli::run_cli(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(cli(&["report", &path, "--position", "X2"]), Some(0));
+        assert_eq!(cli(&["report", &path]), Some(1));
+        assert_eq!(cli(&["report", &path, "--position", "X9"]), Some(1));
+        assert_eq!(cli(&["report", &path, "--pos", "X2"]), Some(2));
+    }
 }
@@ -0,0 +1,179 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::warnings::ValidationWarning;
+
+// Trims every value of a text key column, so " S01" and "S01" join
+fn trim_key_column(df: &mut DataFrame, name: &str) -> Result<(), String> {
+    let values: Vec<Option<String>> = df
+        .column(name)
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(|e| format!("Failed to read the {} column: {e}", name))?
+        .str()
+        .map_err(|e| format!("The {} column is not text: {e}", name))?
+        .into_iter()
+        .map(|v| v.map(|s| s.trim().to_string()))
+        .collect();
+    df.with_column(Series::new(PlSmallStr::from_str(name), values))
+        .map_err(|e| format!("Failed to normalize the {} column: {e}", name))?;
+    Ok(())
+}
+
+fn require_columns(df: &DataFrame, file: &str, columns: &[&str]) -> Result<(), String> {
+    let missing: Vec<&str> = columns
+        .iter()
+        .copied()
+        .filter(|c| df.column(c).is_err())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("The {} has no {} column.", file, missing.join(" or ")))
+    }
+}
+
+/// The samples file of one run, ready to merge: it has `sample` and
+/// `barcode` columns and its sample names are trimmed
+#[derive(Debug, Clone)]
+pub struct SampleSheet {
+    df: DataFrame,
+}
+
+impl SampleSheet {
+    pub fn new(mut df: DataFrame) -> Result<Self, String> {
+        require_columns(&df, "samples file", &["sample", "barcode"])?;
+        trim_key_column(&mut df, "sample")?;
+        Ok(Self { df })
+    }
+
+    pub fn height(&self) -> usize {
+        self.df.height()
+    }
+
+    /// Sample name of every row in file order, "" for an empty cell
+    pub fn samples(&self) -> Vec<String> {
+        self.df
+            .column("sample")
+            .ok()
+            .and_then(|c| c.str().ok().cloned())
+            .map(|ca| ca.into_iter().map(|s| s.unwrap_or("").to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn into_frame(self) -> DataFrame {
+        self.df
+    }
+}
+
+/// An EpiInfo export after projection, exclusions and renames: it has a
+/// trimmed `ICLabID` column and an index from each ICLabID to its first row
+#[derive(Debug, Clone)]
+pub struct EpiData {
+    df: DataFrame,
+    index: HashMap<String, usize>,
+}
+
+impl EpiData {
+    pub fn new(mut df: DataFrame) -> Result<Self, String> {
+        require_columns(&df, "EpiInfo file", &["ICLabID"])?;
+        trim_key_column(&mut df, "ICLabID")?;
+
+        let mut index = HashMap::new();
+        if let Ok(ids) = df.column("ICLabID").and_then(|c| c.str().cloned()) {
+            for (row, id) in ids.into_iter().enumerate() {
+                if let Some(id) = id.filter(|id| !id.is_empty()) {
+                    index.entry(id.to_string()).or_insert(row);
+                }
+            }
+        }
+        Ok(Self { df, index })
+    }
+
+    pub fn frame(&self) -> &DataFrame {
+        &self.df
+    }
+
+    /// Every ICLabID once, in no particular order
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(|id| id.as_str())
+    }
+
+    pub fn contains(&self, iclab_id: &str) -> bool {
+        self.index.contains_key(iclab_id)
+    }
+
+    pub fn into_frame(self) -> DataFrame {
+        self.df
+    }
+}
+
+/// A samples file merged with EpiInfo (or on its own when there is no
+/// export), with what the merge had to report
+#[derive(Debug, Clone)]
+pub struct MergedRun {
+    df: DataFrame,
+    warnings: Vec<ValidationWarning>,
+}
+
+impl MergedRun {
+    pub fn new(df: DataFrame, warnings: Vec<ValidationWarning>) -> Self {
+        Self { df, warnings }
+    }
+
+    /// A run without EpiInfo data: the sheet as it is
+    pub fn unmerged(sheet: SampleSheet) -> Self {
+        Self::new(sheet.into_frame(), Vec::new())
+    }
+
+    pub fn into_parts(self) -> (DataFrame, Vec<ValidationWarning>) {
+        (self.df, self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_sheet_needs_sample_and_barcode_and_trims_names() {
+        let err = SampleSheet::new(df!("sample" => ["S01"]).unwrap()).unwrap_err();
+        assert_eq!(err, "The samples file has no barcode column.");
+        let err = SampleSheet::new(df!("name" => ["S01"]).unwrap()).unwrap_err();
+        assert_eq!(err, "The samples file has no sample or barcode column.");
+
+        let sheet =
+            SampleSheet::new(df!("sample" => [Some(" S01 "), None, Some("S03")], "barcode" => ["b1", "b2", "b3"]).unwrap())
+                .unwrap();
+        assert_eq!(sheet.height(), 3);
+        assert_eq!(sheet.samples(), ["S01", "", "S03"]);
+    }
+
+    #[test]
+    fn epi_data_indexes_each_iclab_id_once() {
+        let epi = EpiData::new(
+            df!(
+                "ICLabID" => [Some("S01 "), Some("S02"), Some("S01"), Some(""), None],
+                "Province" => ["Kano", "Kano", "Lagos", "Oyo", "Oyo"]
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let mut ids: Vec<&str> = epi.ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["S01", "S02"]);
+        assert!(epi.contains("S01") && !epi.contains("S01 ") && !epi.contains(""));
+        // Trimmed in the frame too, so the join sees the same key
+        assert_eq!(epi.frame().column("ICLabID").unwrap().str().unwrap().get(0), Some("S01"));
+
+        let err = EpiData::new(df!("EpidNumber" => ["x"]).unwrap()).unwrap_err();
+        assert_eq!(err, "The EpiInfo file has no ICLabID column.");
+    }
+
+    #[test]
+    fn unmerged_run_is_the_sheet_as_it_is() {
+        let df = df!("sample" => ["S01"], "barcode" => ["barcode01"]).unwrap();
+        let (out, warnings) = MergedRun::unmerged(SampleSheet::new(df.clone()).unwrap()).into_parts();
+        assert!(out.equals_missing(&df));
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use std::path::{Path, PathBuf};
+
+// Output file naming pattern, e.g. "{lab}_{run}_{mode}.csv".
+// The pattern gives the stem shared by every output of a merge; each
+// output adds its own suffix/extension so they stay side by side.
+
+/// Keeps the historical `[Run Number]_merger_output.csv`
+pub const DEFAULT_OUTPUT_PATTERN: &str = "{run}_merger_output.csv";
+
+pub const PLACEHOLDERS: &[&str] = &["run", "mode", "lab", "date", "flowcell", "timestamp"];
+
+/// Values the placeholders expand to
+pub struct NameValues {
+    pub run: String,
+    pub mode: String,
+    pub lab: String,
+    // YYYY-MM-DD
+    pub date: String,
+    pub flowcell: String,
+    // YYYYMMDD_HHMMSS
+    pub timestamp: String,
+}
+
+impl NameValues {
+    fn get(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "run" => Some(&self.run),
+            "mode" => Some(&self.mode),
+            "lab" => Some(&self.lab),
+            "date" => Some(&self.date),
+            "flowcell" => Some(&self.flowcell),
+            "timestamp" => Some(&self.timestamp),
+            _ => None,
+        }
+    }
+}
+
+/// An expanded pattern
+pub struct OutputName {
+    pub stem: String,
+    // Placeholders that expanded to nothing
+    pub empty: Vec<String>,
+    // The pattern had no {run} and the run number was put in front
+    pub run_added: bool,
+}
+
+impl OutputName {
+    /// File name for one output, e.g. `file_name("", "csv")` or
+    /// `file_name("_excel", "csv")`
+    pub fn file_name(&self, suffix: &str, extension: &str) -> String {
+        format!("{}{}.{}", self.stem, suffix, extension)
+    }
+}
+
+// Characters not allowed in file names on any of the supported platforms
+fn replace_reserved(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+// Device names Windows reserves, with or without an extension ("CON",
+// "nul.txt"). Fine in a CSV, but rejected by tools that turn column
+// names into file or sheet names.
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether `name` is a Windows-reserved device name, ignoring case,
+/// surrounding spaces and any extension
+pub fn is_windows_reserved(name: &str) -> bool {
+    let stem = name.trim().split('.').next().unwrap_or("").trim_end();
+    WINDOWS_RESERVED_NAMES.iter().any(|r| stem.eq_ignore_ascii_case(r))
+}
+
+/// Makes one placeholder value safe to use inside a file name
+pub fn sanitize_file_component(value: &str) -> String {
+    // Windows drops trailing dots and spaces
+    replace_reserved(value.trim()).trim_end_matches(['.', ' ']).to_string()
+}
+
+/// Names used in the pattern, in order; errors on an unclosed brace
+pub fn pattern_placeholders(pattern: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            return Err(format!("Unclosed '{{' in output name pattern '{}'.", pattern));
+        };
+        names.push(after[..end].to_string());
+        rest = &after[end + 1..];
+    }
+    Ok(names)
+}
+
+/// Checks the pattern without expanding it
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    let unknown: Vec<String> = pattern_placeholders(pattern)?
+        .into_iter()
+        .filter(|p| !PLACEHOLDERS.contains(&p.as_str()))
+        .map(|p| format!("{{{}}}", p))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown placeholder {} in output name pattern '{}'. Known placeholders: {}.",
+            unknown.join(", "),
+            pattern,
+            PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Folder under the destination an output goes to when outputs are
+/// organized by mode and month: `{mode}/{yyyy-mm}`, the month taken from
+/// the run number (`yyyymmdd_xxx`) or else from `today` (YYYY-MM-DD).
+/// None when the option is off, so outputs stay flat in the destination.
+pub fn output_subfolder(organize: bool, mode: &str, run: &str, today: &str) -> Option<PathBuf> {
+    if !organize {
+        return None;
+    }
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let from_run = run
+        .trim()
+        .split_once('_')
+        .filter(|(date, serial)| date.len() == 8 && digits(date) && serial.len() == 3 && digits(serial))
+        .map(|(date, _)| (date[..4].to_string(), date[4..6].to_string()));
+    let (year, month) = from_run
+        .filter(|(_, month)| matches!(month.parse::<u8>(), Ok(1..=12)))
+        .unwrap_or_else(|| (today.get(..4).unwrap_or("").to_string(), today.get(5..7).unwrap_or("").to_string()));
+
+    let mode = sanitize_file_component(mode);
+    let mode = if mode.is_empty() { "other".to_string() } else { mode };
+    Some(Path::new(&mode).join(format!("{}-{}", year, month)))
+}
+
+/// Expands the pattern. Unknown placeholders are an error; empty values
+/// are reported in `empty` so the caller can warn. A pattern without
+/// {run} gets the run number in front, so two runs merged into the same
+/// folder never share output names; `run_added` says so for a warning.
+pub fn expand_output_name(pattern: &str, values: &NameValues) -> Result<OutputName, String> {
+    validate_pattern(pattern)?;
+    let scoped;
+    let run_added = !pattern_placeholders(pattern)?.iter().any(|p| p == "run");
+    let pattern = if !run_added {
+        pattern
+    } else {
+        scoped = format!("{{run}}_{}", pattern);
+        scoped.as_str()
+    };
+
+    // The extension belongs to each output, not to the shared stem
+    let pattern = pattern.strip_suffix(".csv").unwrap_or(pattern);
+
+    let mut stem = String::new();
+    let mut empty = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        stem.push_str(&replace_reserved(&rest[..start]));
+        let after = &rest[start + 1..];
+        let end = after.find('}').unwrap_or(after.len());
+        let name = &after[..end];
+        let value = sanitize_file_component(values.get(name).unwrap_or(""));
+        if value.is_empty() {
+            empty.push(name.to_string());
+        }
+        stem.push_str(&value);
+        rest = after.get(end + 1..).unwrap_or("");
+    }
+    stem.push_str(&replace_reserved(rest));
+
+    let stem = stem.trim_matches(['_', '-', ' ', '.']).to_string();
+    if stem.is_empty() {
+        return Err(format!("The output name pattern '{}' expanded to an empty file name.", pattern));
+    }
+    Ok(OutputName { stem, empty, run_added })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::backup_existing;
+    use crate::workspace::TempWorkspace;
+
+    fn values() -> NameValues {
+        NameValues {
+            run: "20240305_001".to_string(),
+            mode: "DDNS".to_string(),
+            lab: "INRB".to_string(),
+            date: "2024-03-06".to_string(),
+            flowcell: "FAX12345".to_string(),
+            timestamp: "20240306_101500".to_string(),
+        }
+    }
+
+    fn stem(pattern: &str) -> String {
+        expand_output_name(pattern, &values()).unwrap().stem
+    }
+
+    #[test]
+    fn default_pattern_keeps_the_historical_name() {
+        let name = expand_output_name(DEFAULT_OUTPUT_PATTERN, &values()).unwrap();
+        assert_eq!(name.file_name("", "csv"), "20240305_001_merger_output.csv");
+        assert_eq!(name.file_name("_excel", "csv"), "20240305_001_merger_output_excel.csv");
+        assert_eq!(name.file_name("_epi_without_samples", "csv"), "20240305_001_merger_output_epi_without_samples.csv");
+        assert!(name.empty.is_empty());
+    }
+
+    #[test]
+    fn each_placeholder_expands() {
+        assert_eq!(stem("{run}"), "20240305_001");
+        assert_eq!(stem("{run}_{mode}"), "20240305_001_DDNS");
+        assert_eq!(stem("{lab}_{run}"), "INRB_20240305_001");
+        assert_eq!(stem("{run}_{date}"), "20240305_001_2024-03-06");
+        assert_eq!(stem("{run}_{flowcell}"), "20240305_001_FAX12345");
+        assert_eq!(stem("{run}_{timestamp}.csv"), "20240305_001_20240306_101500");
+        // Without {run} the run number goes in front, and the caller is told
+        assert_eq!(stem("{date}_{flowcell}.csv"), "20240305_001_2024-03-06_FAX12345");
+        assert!(expand_output_name("{date}_{flowcell}.csv", &values()).unwrap().run_added);
+        assert!(!expand_output_name("{lab}_{run}.csv", &values()).unwrap().run_added);
+    }
+
+    #[test]
+    fn unknown_or_unclosed_placeholders_are_errors() {
+        let err = expand_output_name("{run}_{site}.csv", &values()).err().unwrap();
+        assert!(err.starts_with("Unknown placeholder {site}"), "{}", err);
+        assert!(validate_pattern("{run}_{Lab}").is_err());
+        assert!(validate_pattern("{run}_{lab").err().unwrap().starts_with("Unclosed '{'"));
+        assert_eq!(validate_pattern("{lab}_{run}_{mode}.csv"), Ok(()));
+    }
+
+    #[test]
+    fn empty_expansions_are_reported() {
+        let values = NameValues {
+            lab: "  ".to_string(),
+            flowcell: String::new(),
+            ..values()
+        };
+        let name = expand_output_name("{lab}_{run}_{flowcell}.csv", &values).unwrap();
+        assert_eq!(name.stem, "20240305_001");
+        assert_eq!(name.empty, ["lab", "flowcell"]);
+
+        let nothing = NameValues { run: String::new(), ..values };
+        assert!(expand_output_name("{run}", &nothing).is_err());
+    }
+
+    #[test]
+    fn expansions_are_made_file_name_safe() {
+        let values = NameValues {
+            lab: "INRB/Kinshasa: \"virology\"".to_string(),
+            run: "run*1?. ".to_string(),
+            ..values()
+        };
+        let name = expand_output_name("{lab}_{run}.csv", &values).unwrap();
+        assert_eq!(name.stem, "INRB_Kinshasa_ _virology__run_1");
+        assert_eq!(sanitize_file_component(" lab.. "), "lab");
+        assert!(is_windows_reserved("nul.csv") && is_windows_reserved(" Com1 "));
+        assert!(!is_windows_reserved("CONSOLE"));
+    }
+
+    #[test]
+    fn existing_output_with_the_same_name_is_kept_aside() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let name = expand_output_name("{lab}_{mode}.csv", &values()).unwrap().file_name("", "csv");
+        let path = dir.file(&name);
+        std::fs::write(&path, "previous merge").unwrap();
+
+        let backup = backup_existing(&path).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "previous merge");
+        assert!(backup.file_name().unwrap().to_string_lossy().starts_with("20240305_001_INRB_DDNS."));
+
+        // Another run merged into the same folder gets its own name
+        let other = NameValues { run: "20240305_002".to_string(), ..values() };
+        assert_ne!(expand_output_name("{lab}_{mode}.csv", &other).unwrap().file_name("", "csv"), name);
+    }
+
+    #[test]
+    fn windows_device_names_are_recognised() {
+        for name in ["CON", "con", " Nul ", "AUX", "PRN.csv", "COM1", "com9.txt", "LPT3"] {
+            assert!(is_windows_reserved(name), "{name}");
+        }
+        for name in ["CONSENT", "Console", "COM0", "COM10", "LPT", "sample", ""] {
+            assert!(!is_windows_reserved(name), "{name}");
+        }
+    }
+
+    fn subfolder(mode: &str, run: &str) -> Option<PathBuf> {
+        output_subfolder(true, mode, run, "2026-10-16")
+    }
+
+    #[test]
+    fn subfolder_month_comes_from_the_run_number() {
+        assert_eq!(subfolder("DDNS", "20250110_002"), Some(Path::new("DDNS").join("2025-01")));
+        assert_eq!(subfolder("minION", " 20241231_017 "), Some(Path::new("minION").join("2024-12")));
+    }
+
+    #[test]
+    fn subfolder_falls_back_to_today() {
+        let today = Some(Path::new("DDNS").join("2026-10"));
+        for run in ["", "  ", "RUN-12", "2025011_002", "20250110", "20250110_02", "20251310_002", "2025a110_002"] {
+            assert_eq!(subfolder("DDNS", run), today, "{run:?}");
+        }
+        // A mode that is not a safe folder name still gets a folder
+        assert_eq!(subfolder(" ", "20250110_002"), Some(Path::new("other").join("2025-01")));
+        assert_eq!(
+            output_subfolder(true, "DDNS", "", "2026-03-01T09:00"),
+            Some(Path::new("DDNS").join("2026-03"))
+        );
+    }
+
+    #[test]
+    fn outputs_stay_flat_when_not_organized() {
+        assert_eq!(output_subfolder(false, "DDNS", "20250110_002", "2026-10-16"), None);
+        assert_eq!(output_subfolder(false, "minION", "", "2026-10-16"), None);
+
+        // Organized or not, the file name itself is the same
+        let destination = Path::new("runs");
+        let name = "20250110_002_merger_output.csv";
+        let flat = destination.join(name);
+        let organized = destination.join(subfolder("DDNS", "20250110_002").unwrap()).join(name);
+        assert_eq!(flat.file_name(), organized.file_name());
+        assert_eq!(organized.strip_prefix(destination).unwrap(), Path::new("DDNS").join("2025-01").join(name));
+    }
+}
@@ -0,0 +1,160 @@
+use calamine::{open_workbook, Data, Ods, Reader};
+use polars::prelude::*;
+use std::collections::HashSet;
+
+use crate::csv::clean_headers;
+
+/// Whether `path` is an OpenDocument spreadsheet (LibreOffice's .ods)
+pub fn is_ods(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".ods")
+}
+
+/// One cell as text, as it would read in a CSV saved from the sheet.
+/// Dates stay ISO (YYYY-MM-DD), with the time only when there is one.
+fn cell_text(cell: &Data) -> Option<String> {
+    match cell {
+        Data::Empty => None,
+        Data::String(s) if s.is_empty() => None,
+        Data::String(s) => Some(s.clone()),
+        Data::DateTimeIso(s) => Some(s.strip_suffix("T00:00:00").unwrap_or(s).to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Rows of the first sheet that has any cell filled, from its first
+/// non-blank row on
+fn read_rows(path: &str) -> Result<Vec<Vec<Option<String>>>, String> {
+    let mut workbook: Ods<_> =
+        open_workbook(path).map_err(|e| format!("Failed to read spreadsheet '{}': {e}", path))?;
+    for sheet in workbook.sheet_names() {
+        let range = workbook
+            .worksheet_range(&sheet)
+            .map_err(|e| format!("Failed to read sheet '{}' of '{}': {e}", sheet, path))?;
+        let rows: Vec<Vec<Option<String>>> = range
+            .rows()
+            .map(|row| row.iter().map(cell_text).collect())
+            .skip_while(|row: &Vec<Option<String>>| row.iter().all(Option::is_none))
+            .collect();
+        if !rows.is_empty() {
+            return Ok(rows);
+        }
+    }
+    Err(format!("Spreadsheet '{}' appears to be empty", path))
+}
+
+/// Header cells of the sheet `read_ods_projected` reads, unsanitized
+pub fn read_ods_header(path: &str) -> Result<Vec<String>, String> {
+    let rows = read_rows(path)?;
+    Ok(rows[0].iter().map(|cell| cell.clone().unwrap_or_default()).collect())
+}
+
+/// Reads an ODS file the way `read_csv_projected` reads a CSV: the first
+/// row is the header, every column is text and only the columns in
+/// `keep` are loaded (all when None). Also returns how many columns were
+/// skipped.
+pub fn read_ods_projected(path: &str, keep: Option<&HashSet<String>>) -> Result<(DataFrame, usize), String> {
+    let rows = read_rows(path)?;
+    let raw: Vec<String> = rows[0].iter().map(|cell| cell.clone().unwrap_or_default()).collect();
+    let raw: Vec<&str> = raw.iter().map(String::as_str).collect();
+    let (headers, _) = clean_headers(&raw);
+
+    let columns: Vec<Column> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| keep.is_none_or(|keep| keep.contains(name.as_str())))
+        .map(|(idx, name)| {
+            let values: Vec<Option<&str>> = rows[1..]
+                .iter()
+                .map(|row| row.get(idx).and_then(|cell| cell.as_deref()))
+                .collect();
+            Series::new(PlSmallStr::from_str(name), values).into_column()
+        })
+        .collect();
+    let skipped = headers.len() - columns.len();
+
+    let df = DataFrame::new(columns).map_err(|e| format!("Failed to read spreadsheet '{}': {e}", path))?;
+    Ok((df, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv::{header_renames, read_csv_normalized, read_header_columns};
+
+    fn example_data(name: &str) -> String {
+        format!("{}/example_data/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn values(df: &DataFrame, column: &str) -> Vec<Option<String>> {
+        let column = df.column(column).unwrap().str().unwrap().clone();
+        column.into_iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn only_ods_paths_are_spreadsheets() {
+        assert!(is_ods("/data/samples.ods"));
+        assert!(is_ods(r"C:\Runs\SAMPLES.ODS"));
+        assert!(!is_ods("/data/samples.csv"));
+        assert!(!is_ods("/data/samples.ods.csv"));
+    }
+
+    #[test]
+    fn cells_read_as_csv_text() {
+        assert_eq!(cell_text(&Data::Empty), None);
+        assert_eq!(cell_text(&Data::String(String::new())), None);
+        assert_eq!(cell_text(&Data::String("S01".into())).as_deref(), Some("S01"));
+        assert_eq!(cell_text(&Data::Float(2.0)).as_deref(), Some("2"));
+        assert_eq!(cell_text(&Data::Float(12.5)).as_deref(), Some("12.5"));
+        assert_eq!(cell_text(&Data::Int(7)).as_deref(), Some("7"));
+        assert_eq!(cell_text(&Data::DateTimeIso("2024-03-12".into())).as_deref(), Some("2024-03-12"));
+        assert_eq!(cell_text(&Data::DateTimeIso("2024-03-12T00:00:00".into())).as_deref(), Some("2024-03-12"));
+        assert_eq!(cell_text(&Data::DateTimeIso("2024-03-12T14:30:00".into())).as_deref(), Some("2024-03-12T14:30:00"));
+    }
+
+    #[test]
+    fn first_filled_sheet_is_read_as_text() {
+        // The fixture's first sheet is empty
+        let path = example_data("DDNS_samples.ods");
+        let (df, skipped) = read_ods_projected(&path, None).unwrap();
+        assert_eq!(skipped, 0);
+        assert_eq!(df.height(), 2);
+        assert!(df.get_columns().iter().all(|c| c.dtype() == &DataType::String));
+        assert_eq!(values(&df, "sample"), [Some("S01".to_string()), Some("S02".to_string())]);
+        assert_eq!(values(&df, "SpecimenNumber"), [Some("1".to_string()), Some("2".to_string())]);
+        assert_eq!(values(&df, "DateRTPCR"), [Some("2024-03-12".to_string()), Some("2024-03-13".to_string())]);
+        assert_eq!(values(&df, "EPID"), [None, None]);
+    }
+
+    #[test]
+    fn only_kept_columns_are_loaded() {
+        let path = example_data("EPIINFO_export.ods");
+        let keep: HashSet<String> = ["ICLabID", "EpidNumber"].iter().map(|c| c.to_string()).collect();
+        let (df, skipped) = read_ods_projected(&path, Some(&keep)).unwrap();
+        assert_eq!(df.get_column_names(), ["ICLabID", "EpidNumber"]);
+        assert_eq!(skipped, 4);
+    }
+
+    #[test]
+    fn csv_readers_hand_ods_files_over() {
+        let path = example_data("EPIINFO_export.ods");
+        let (df, profile) = read_csv_normalized(&path).unwrap();
+        assert!(profile.spreadsheet);
+        assert_eq!(profile.describe(), "ODS spreadsheet");
+        assert_eq!(values(&df, "DateOfOnset"), [Some("2024-02-01".to_string()), Some("2024-02-03".to_string())]);
+
+        assert_eq!(
+            read_header_columns(&path).unwrap(),
+            ["ICLabID", "EpidNumber", "DateOfOnset", "Province", "District", "FinalITDResult"]
+        );
+        assert!(header_renames(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unreadable_spreadsheets_are_errors() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let path = dir.file("broken.ods");
+        std::fs::write(&path, b"not a zip").unwrap();
+        let err = read_ods_projected(&path.to_string_lossy(), None).unwrap_err();
+        assert!(err.starts_with("Failed to read spreadsheet"), "{}", err);
+    }
+}
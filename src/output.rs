@@ -0,0 +1,687 @@
+use polars::prelude::*;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::warnings::ValidationWarning;
+
+/// Result of appending a new batch onto an existing output
+pub struct AppendResult {
+    pub df: DataFrame,
+    pub existing_rows: usize,
+    pub new_rows: usize,
+    pub replaced_rows: usize,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+// Copies an existing output aside before it gets replaced
+pub fn backup_existing(path: &Path) -> Result<Option<PathBuf>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "merger_output".to_string());
+    // Timestamp and process id keep two merges in the same second (or
+    // from two computers on a shared folder) from sharing a backup name
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let pid = std::process::id();
+    let backup = (0..)
+        .map(|n| match n {
+            0 => format!("{}.{}_{}.bak.csv", stem, stamp, pid),
+            n => format!("{}.{}_{}_{}.bak.csv", stem, stamp, pid, n),
+        })
+        .map(|name| path.with_file_name(name))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_default();
+
+    std::fs::copy(path, &backup).map_err(|e| {
+        format!(
+            "Failed to back up '{}' to '{}': {e}",
+            path.display(),
+            backup.display()
+        )
+    })?;
+
+    Ok(Some(backup))
+}
+
+fn string_cell(df: &DataFrame, col_name: &str, idx: usize) -> Option<String> {
+    df.column(col_name)
+        .ok()
+        .and_then(|c| c.str().ok().and_then(|s| s.get(idx).map(|v| v.to_string())))
+}
+
+fn row_values(df: &DataFrame, idx: usize) -> Vec<Option<String>> {
+    df.get_column_names()
+        .iter()
+        .map(|name| string_cell(df, name.as_str(), idx))
+        .collect()
+}
+
+/// Stacks `new_df` under `existing`, de-duplicating on sample.
+/// Rows of the new batch win; collisions with differing data are reported.
+pub fn append_to_existing(existing: DataFrame, new_df: DataFrame) -> Result<AppendResult, String> {
+    let new_cols: Vec<String> = new_df.get_column_names().iter().map(|s| s.to_string()).collect();
+    let existing_cols: HashSet<String> = existing
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let missing: Vec<&String> = new_cols.iter().filter(|c| !existing_cols.contains(*c)).collect();
+    let extra: Vec<&String> = existing_cols
+        .iter()
+        .filter(|c| !new_cols.contains(c))
+        .collect();
+    if !missing.is_empty() || !extra.is_empty() {
+        let mut msg = String::from("The existing output does not have the same columns as the new batch.");
+        if !missing.is_empty() {
+            msg.push_str(&format!(
+                "\nMissing from existing output: {}",
+                missing.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !extra.is_empty() {
+            msg.push_str(&format!(
+                "\nNot in the new batch: {}",
+                extra.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        return Err(msg);
+    }
+
+    // same column order as the new batch so vstack lines up
+    let existing = existing
+        .select(new_cols.iter().map(|s| s.as_str()))
+        .map_err(|e| format!("Failed to align existing output columns: {e}"))?;
+
+    let new_samples: Vec<Option<String>> = (0..new_df.height())
+        .map(|i| string_cell(&new_df, "sample", i))
+        .collect();
+    let new_index: std::collections::HashMap<String, usize> = new_samples
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| s.clone().map(|s| (s, i)))
+        .collect();
+
+    let mut keep: Vec<bool> = Vec::with_capacity(existing.height());
+    let mut warnings = Vec::new();
+    let mut replaced_rows = 0usize;
+
+    for idx in 0..existing.height() {
+        let sample = string_cell(&existing, "sample", idx);
+        match sample.as_ref().and_then(|s| new_index.get(s)) {
+            Some(&new_idx) => {
+                replaced_rows += 1;
+                keep.push(false);
+                if row_values(&existing, idx) != row_values(&new_df, new_idx) {
+                    warnings.push(ValidationWarning::DuplicateSampleReplaced {
+                        sample: sample.unwrap_or_default(),
+                    });
+                }
+            }
+            None => keep.push(true),
+        }
+    }
+
+    let mask = BooleanChunked::from_slice(PlSmallStr::from_str("keep"), &keep);
+    let kept = existing
+        .filter(&mask)
+        .map_err(|e| format!("Failed to de-duplicate existing output: {e}"))?;
+
+    let existing_rows = kept.height();
+    let new_rows = new_df.height();
+    let df = kept
+        .vstack(&new_df)
+        .map_err(|e| format!("Failed to append rows to existing output: {e}"))?;
+
+    Ok(AppendResult {
+        df,
+        existing_rows,
+        new_rows,
+        replaced_rows,
+        warnings,
+    })
+}
+
+/// Saves `file_name` into the first writable location: the `downloads`
+/// folder (normally `dirs::download_dir()`), then a location picked by the
+/// user, then the destination folder. Returns the path actually written so
+/// callers can report it.
+pub fn save_with_fallback(
+    file_name: &str,
+    downloads: Option<&Path>,
+    destination: Option<&Path>,
+    ask_user: impl FnOnce(&str) -> Option<PathBuf>,
+    mut write: impl FnMut(&Path) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let mut failures: Vec<String> = Vec::new();
+
+    match downloads {
+        Some(dir) => {
+            let path = dir.join(file_name);
+            match write(&path) {
+                Ok(()) => return Ok(path),
+                Err(e) => failures.push(e),
+            }
+        }
+        None => failures.push("No Downloads folder found.".to_string()),
+    }
+
+    if let Some(path) = ask_user(file_name) {
+        match write(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) => failures.push(e),
+        }
+    }
+
+    if let Some(dir) = destination {
+        let path = dir.join(file_name);
+        match write(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) => failures.push(e),
+        }
+    }
+
+    Err(failures.join("\n"))
+}
+
+// A leading tab keeps Excel from re-typing the cell (e.g. "01" -> 1)
+pub const EXCEL_SAFE_PREFIX: char = '\t';
+
+/// Default columns protected in the Excel-safe variant
+pub fn default_excel_safe_columns() -> Vec<String> {
+    ["SpecimenNumber", "FlaskNumber", "barcode"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Copy of `df` where non-empty cells of `columns` carry the Excel-safe
+/// prefix. Columns missing from `df` are ignored; `df` itself is untouched.
+pub fn excel_safe_copy(df: &DataFrame, columns: &[String]) -> Result<DataFrame, String> {
+    let mut out = df.clone();
+
+    for name in columns {
+        let Ok(col) = df.column(name) else {
+            continue;
+        };
+        let ca = col
+            .str()
+            .map_err(|e| format!("Column '{}' is not text: {e}", name))?;
+        let values: Vec<Option<String>> = ca
+            .into_iter()
+            .map(|v| {
+                v.map(|s| {
+                    if s.is_empty() || s.starts_with(EXCEL_SAFE_PREFIX) {
+                        s.to_string()
+                    } else {
+                        format!("{}{}", EXCEL_SAFE_PREFIX, s)
+                    }
+                })
+            })
+            .collect();
+        out.with_column(Series::new(PlSmallStr::from_str(name), values))
+            .map_err(|e| format!("Failed to protect column '{}': {e}", name))?;
+    }
+
+    Ok(out)
+}
+
+/// Default columns holding decimal numbers typed by hand
+pub fn default_numeric_columns() -> Vec<String> {
+    ["RunHoursDuration", "PoresAvilableAtFlowCellCheck", "FlowCellPriorUses"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Decimal separator for numeric columns: a point, unless localized
+/// numbers are on and the output is semicolon separated
+pub fn output_decimal_separator(delimiter: u8, localized: bool) -> char {
+    if localized && delimiter == b';' {
+        ','
+    } else {
+        '.'
+    }
+}
+
+/// Rewrites decimal numbers ("66,5", "66.5") in `columns` with `decimal`
+/// as separator. Other text is left alone, as are columns missing from
+/// `df`. Returns how many cells changed.
+pub fn normalize_decimals(df: &mut DataFrame, columns: &[String], decimal: char) -> Result<usize, String> {
+    let number = Regex::new(r"^(-?\d+)([.,])(\d+)$").unwrap();
+    let mut changed = 0;
+
+    for name in columns {
+        let Ok(col) = df.column(name) else {
+            continue;
+        };
+        let Ok(ca) = col.str() else {
+            // Parsed as a number already; the writer uses a point
+            continue;
+        };
+        let values: Vec<Option<String>> = ca
+            .into_iter()
+            .map(|v| {
+                v.map(|s| match number.captures(s.trim()) {
+                    // Rewritten only when the separator changes; padding alone is kept
+                    Some(caps) if !caps[2].starts_with(decimal) => {
+                        changed += 1;
+                        format!("{}{}{}", &caps[1], decimal, &caps[3])
+                    }
+                    _ => s.to_string(),
+                })
+            })
+            .collect();
+        df.with_column(Series::new(PlSmallStr::from_str(name), values))
+            .map_err(|e| format!("Failed to rewrite numbers in column '{}': {e}", name))?;
+    }
+
+    Ok(changed)
+}
+
+// Resolves symlinks and `..`; for a file that does not exist yet only the
+// parent folder is resolved. Case-folded on Windows, where paths are case-insensitive.
+fn comparable_path(path: &Path) -> PathBuf {
+    let resolved = path.canonicalize().unwrap_or_else(|_| {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => parent
+                .canonicalize()
+                .map(|p| p.join(name))
+                .unwrap_or_else(|_| path.to_path_buf()),
+            _ => path.to_path_buf(),
+        }
+    });
+
+    if cfg!(windows) {
+        PathBuf::from(resolved.to_string_lossy().to_lowercase())
+    } else {
+        resolved
+    }
+}
+
+/// Returns the first input that resolves to the same file as `output`
+pub fn input_collision<'a>(output: &Path, inputs: &[&'a str]) -> Option<&'a str> {
+    let output = comparable_path(output);
+    inputs
+        .iter()
+        .copied()
+        .filter(|input| !input.is_empty())
+        .find(|input| comparable_path(Path::new(input)) == output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    fn batch(samples: &[&str], values: &[&str]) -> DataFrame {
+        df!("sample" => samples, "value" => values).unwrap()
+    }
+
+    // Writer that fails in the folders listed in `failing`, recording every try
+    fn writer<'a>(tried: &'a mut Vec<PathBuf>, failing: &'a [&'a str]) -> impl FnMut(&Path) -> Result<(), String> + 'a {
+        move |path: &Path| {
+            tried.push(path.to_path_buf());
+            if failing.iter().any(|dir| path.starts_with(dir)) {
+                Err(format!("Cannot write {}", path.display()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn downloads_folder_is_tried_first() {
+        let mut tried = Vec::new();
+        let saved = save_with_fallback(
+            "template.csv",
+            Some(Path::new("/home/lab/Downloads")),
+            Some(Path::new("/data/runs")),
+            |_| panic!("the user is only asked when Downloads fails"),
+            writer(&mut tried, &[]),
+        );
+        assert_eq!(saved.unwrap(), PathBuf::from("/home/lab/Downloads/template.csv"));
+        assert_eq!(tried.len(), 1);
+    }
+
+    #[test]
+    fn failing_locations_fall_back_in_order() {
+        let mut tried = Vec::new();
+        let saved = save_with_fallback(
+            "template.csv",
+            Some(Path::new("/home/lab/Downloads")),
+            Some(Path::new("/data/runs")),
+            |name| Some(PathBuf::from("/picked").join(name)),
+            writer(&mut tried, &["/home/lab/Downloads", "/picked"]),
+        );
+        assert_eq!(saved.unwrap(), PathBuf::from("/data/runs/template.csv"));
+        assert_eq!(
+            tried,
+            vec![
+                PathBuf::from("/home/lab/Downloads/template.csv"),
+                PathBuf::from("/picked/template.csv"),
+                PathBuf::from("/data/runs/template.csv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn picked_location_is_used_when_downloads_fails() {
+        let mut tried = Vec::new();
+        let saved = save_with_fallback(
+            "template.csv",
+            Some(Path::new("/home/lab/Downloads")),
+            Some(Path::new("/data/runs")),
+            |name| Some(PathBuf::from("/picked").join(name)),
+            writer(&mut tried, &["/home/lab/Downloads"]),
+        );
+        assert_eq!(saved.unwrap(), PathBuf::from("/picked/template.csv"));
+        assert_eq!(tried.len(), 2);
+    }
+
+    #[test]
+    fn every_failure_is_reported_when_nothing_works() {
+        let mut tried = Vec::new();
+        let saved = save_with_fallback(
+            "template.csv",
+            None,
+            Some(Path::new("/data/runs")),
+            |_| None,
+            writer(&mut tried, &["/data/runs"]),
+        );
+        let error = saved.unwrap_err();
+        assert!(error.starts_with("No Downloads folder found."));
+        assert!(error.contains("Cannot write /data/runs/template.csv"));
+        assert_eq!(tried, vec![PathBuf::from("/data/runs/template.csv")]);
+    }
+
+    #[test]
+    fn append_keeps_old_rows_and_adds_the_new_batch() {
+        let result = append_to_existing(batch(&["S1", "S2"], &["a", "b"]), batch(&["S3"], &["c"])).unwrap();
+        assert_eq!(result.existing_rows, 2);
+        assert_eq!(result.new_rows, 1);
+        assert_eq!(result.replaced_rows, 0);
+        assert_eq!(result.df.height(), 3);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn append_replaces_repeated_samples_with_the_new_row() {
+        let result = append_to_existing(batch(&["S1", "S2"], &["a", "b"]), batch(&["S2", "S3"], &["b2", "c"])).unwrap();
+        assert_eq!(result.existing_rows, 1);
+        assert_eq!(result.new_rows, 2);
+        assert_eq!(result.replaced_rows, 1);
+        assert_eq!(result.df.height(), 3);
+        assert_eq!(
+            result.warnings,
+            vec![ValidationWarning::DuplicateSampleReplaced { sample: "S2".to_string() }]
+        );
+        assert_eq!(string_cell(&result.df, "value", 2), Some("c".to_string()));
+        assert_eq!(string_cell(&result.df, "value", 1), Some("b2".to_string()));
+    }
+
+    #[test]
+    fn append_of_an_identical_row_is_silent() {
+        let result = append_to_existing(batch(&["S1"], &["a"]), batch(&["S1"], &["a"])).unwrap();
+        assert_eq!(result.replaced_rows, 1);
+        assert_eq!(result.df.height(), 1);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn append_lines_up_columns_in_another_order() {
+        // e.g. optional columns added at the end of an older output
+        let existing = df!("value" => ["a", "b"], "sample" => ["S1", "S2"]).unwrap();
+        let result = append_to_existing(existing, batch(&["S2", "S3"], &["b", "c"])).unwrap();
+        assert!(result.warnings.is_empty());
+        assert_eq!(result.replaced_rows, 1);
+        let names: Vec<String> = result.df.get_column_names().iter().map(|s| s.to_string()).collect();
+        assert_eq!(names, ["sample", "value"]);
+        assert_eq!(string_cell(&result.df, "sample", 0), Some("S1".to_string()));
+        assert_eq!(string_cell(&result.df, "value", 0), Some("a".to_string()));
+    }
+
+    #[test]
+    fn append_refuses_different_columns() {
+        let existing = df!("sample" => ["S1"], "other" => ["x"]).unwrap();
+        let err = append_to_existing(existing, batch(&["S2"], &["b"])).err().unwrap();
+        assert!(err.contains("Missing from existing output: value"));
+        assert!(err.contains("Not in the new batch: other"));
+    }
+
+    #[test]
+    fn backup_copies_the_existing_output_under_a_new_name() {
+        let dir = TempWorkspace::new().unwrap();
+        let output = dir.path().join("run.csv");
+        assert_eq!(backup_existing(&output).unwrap(), None);
+
+        std::fs::write(&output, "sample,value\nS1,a\n").unwrap();
+        let first = backup_existing(&output).unwrap().unwrap();
+        let second = backup_existing(&output).unwrap().unwrap();
+        assert_ne!(first, second);
+        for backup in [&first, &second] {
+            assert!(backup.to_string_lossy().ends_with(".bak.csv"));
+            assert_eq!(std::fs::read_to_string(backup).unwrap(), "sample,value\nS1,a\n");
+        }
+        assert!(output.exists());
+    }
+
+    fn leading_zeros() -> DataFrame {
+        df!(
+            "sample" => ["S1", "S2", "S3"],
+            "SpecimenNumber" => [Some("01"), Some("002"), None],
+            "barcode" => ["barcode01", "barcode02", "barcode03"],
+            "RunHoursDuration" => ["072", "48", "24"]
+        )
+        .unwrap()
+    }
+
+    // Writes `df` as the merge does and returns the file's text and its read-back
+    fn written(dir: &mut TempWorkspace, name: &str, df: &mut DataFrame) -> (String, DataFrame) {
+        let path = dir.file(name);
+        CsvWriter::new(std::fs::File::create(&path).unwrap()).finish(df).unwrap();
+        let (read, _) = crate::csv::read_csv_normalized(&path.to_string_lossy()).unwrap();
+        (std::fs::read_to_string(&path).unwrap(), read)
+    }
+
+    #[test]
+    fn excel_safe_copy_prefixes_only_the_chosen_columns() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let columns = vec!["SpecimenNumber".to_string(), "barcode".to_string(), "NotInOutput".to_string()];
+        let mut plain = leading_zeros();
+        let mut safe = excel_safe_copy(&plain, &columns).unwrap();
+        assert!(plain.equals_missing(&leading_zeros()));
+
+        let (plain_text, _) = written(&mut dir, "run.csv", &mut plain);
+        assert_eq!(
+            plain_text,
+            "sample,SpecimenNumber,barcode,RunHoursDuration\nS1,01,barcode01,072\nS2,002,barcode02,48\nS3,,barcode03,24\n"
+        );
+        let (safe_text, _) = written(&mut dir, "run_excel.csv", &mut safe);
+        assert_eq!(
+            safe_text,
+            "sample,SpecimenNumber,barcode,RunHoursDuration\nS1,\t01,\tbarcode01,072\nS2,\t002,\tbarcode02,48\nS3,,\tbarcode03,24\n"
+        );
+    }
+
+    #[test]
+    fn stripping_the_protection_gives_back_the_original() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let columns = default_excel_safe_columns();
+        let mut safe = excel_safe_copy(&leading_zeros(), &columns).unwrap();
+        // Protecting twice adds nothing
+        assert!(excel_safe_copy(&safe, &columns).unwrap().equals_missing(&safe));
+
+        let (_, read) = written(&mut dir, "run_excel.csv", &mut safe);
+        let stripped: Vec<Column> = read
+            .get_columns()
+            .iter()
+            .map(|c| {
+                let values: Vec<Option<String>> = c
+                    .str()
+                    .unwrap()
+                    .into_iter()
+                    .map(|v| v.map(|s| s.strip_prefix(EXCEL_SAFE_PREFIX).unwrap_or(s).to_string()))
+                    .collect();
+                Series::new(c.name().clone(), values).into_column()
+            })
+            .collect();
+        assert!(DataFrame::new(stripped).unwrap().equals_missing(&leading_zeros()));
+    }
+
+    #[test]
+    fn output_on_an_input_is_caught() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let samples = dir.file("samples.csv");
+        std::fs::write(&samples, "sample,barcode\n").unwrap();
+        let samples = samples.to_string_lossy().to_string();
+        let samples = samples.as_str();
+        let epiinfo = dir.path().join("epi.csv").to_string_lossy().to_string();
+        let epiinfo = epiinfo.as_str();
+
+        assert_eq!(input_collision(Path::new(samples), &["", epiinfo, samples]), Some(samples));
+        // The same file through `..`
+        let roundabout = dir.path().join("sub").join("..").join("samples.csv");
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        assert_eq!(input_collision(&roundabout, &[samples]), Some(samples));
+        // An output not written yet, next to an input
+        assert_eq!(input_collision(&dir.path().join("run1_merger_output.csv"), &[samples, epiinfo, ""]), None);
+    }
+
+    #[test]
+    fn case_differing_names_collide_only_where_paths_ignore_case() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let samples = dir.file("samples.csv");
+        std::fs::write(&samples, "sample,barcode\n").unwrap();
+        let samples = samples.to_string_lossy().to_string();
+        let shouted = dir.path().join("SAMPLES.CSV");
+
+        let collision = input_collision(&shouted, &[samples.as_str()]);
+        // macOS volumes may ignore case too, but paths are not folded there
+        if cfg!(windows) {
+            assert_eq!(collision, Some(samples.as_str()));
+        } else if cfg!(target_os = "linux") {
+            assert_eq!(collision, None);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_through_a_symlink_is_caught() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let samples = dir.file("samples.csv");
+        std::fs::write(&samples, "sample,barcode\n").unwrap();
+        let link = dir.path().join("linked");
+        std::os::unix::fs::symlink(dir.path(), &link).unwrap();
+        let samples = samples.to_string_lossy().to_string();
+        assert_eq!(input_collision(&link.join("samples.csv"), &[samples.as_str()]), Some(samples.as_str()));
+    }
+
+    fn hours_batch() -> DataFrame {
+        df!(
+            "sample" => ["S01", "S02", "S03", "S04"],
+            "RunHoursDuration" => [Some("66,5"), Some("12.25"), Some("n/a"), None],
+            "FlowCellPriorUses" => [Some("2"), Some("3,0"), None, Some("-1,5")],
+            "QCComments" => [Some("ok, rerun; later"), None, Some("66,5"), Some("fine")]
+        )
+        .unwrap()
+    }
+
+    // Fields per line, splitting on `delimiter` outside double quotes
+    fn field_counts(text: &str, delimiter: char) -> Vec<usize> {
+        text.lines()
+            .map(|line| {
+                let mut quoted = false;
+                1 + line
+                    .chars()
+                    .filter(|c| {
+                        if *c == '"' {
+                            quoted = !quoted;
+                        }
+                        *c == delimiter && !quoted
+                    })
+                    .count()
+            })
+            .collect()
+    }
+
+    fn write_and_read(df: &mut DataFrame, delimiter: u8) -> (String, DataFrame) {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("output.csv");
+        CsvWriter::new(std::fs::File::create(&path).unwrap()).with_separator(delimiter).finish(df).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (read, _) = crate::csv::read_csv_normalized(&path.to_string_lossy()).unwrap();
+        (text, read)
+    }
+
+    fn strings(df: &DataFrame, column: &str) -> Vec<Option<String>> {
+        df.column(column).unwrap().str().unwrap().into_iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn decimal_separator_follows_the_delimiter_and_toggle() {
+        assert_eq!(output_decimal_separator(b',', false), '.');
+        assert_eq!(output_decimal_separator(b',', true), '.');
+        assert_eq!(output_decimal_separator(b';', false), '.');
+        assert_eq!(output_decimal_separator(b';', true), ',');
+    }
+
+    #[test]
+    fn decimal_commas_become_points_for_comma_output() {
+        let mut df = hours_batch();
+        let changed = normalize_decimals(&mut df, &default_numeric_columns(), '.').unwrap();
+        assert_eq!(changed, 3);
+        assert_eq!(
+            strings(&df, "RunHoursDuration"),
+            vec![Some("66.5".to_string()), Some("12.25".to_string()), Some("n/a".to_string()), None]
+        );
+        assert_eq!(strings(&df, "FlowCellPriorUses")[3].as_deref(), Some("-1.5"));
+        // Columns not configured as numeric keep their text
+        assert_eq!(strings(&df, "QCComments")[2].as_deref(), Some("66,5"));
+
+        let (text, read) = write_and_read(&mut df, b',');
+        assert_eq!(field_counts(&text, ','), vec![4; 5], "{}", text);
+        assert!(text.contains("S01,66.5,2,"), "{}", text);
+        for column in ["RunHoursDuration", "FlowCellPriorUses", "QCComments"] {
+            assert_eq!(strings(&read, column), strings(&df, column), "{}", column);
+        }
+    }
+
+    #[test]
+    fn localized_semicolon_output_gets_decimal_commas() {
+        let mut df = hours_batch();
+        let changed = normalize_decimals(&mut df, &default_numeric_columns(), ',').unwrap();
+        // "66,5" and "3,0" already use commas
+        assert_eq!(changed, 1);
+        assert_eq!(strings(&df, "RunHoursDuration")[1].as_deref(), Some("12,25"));
+
+        let (text, read) = write_and_read(&mut df, b';');
+        assert_eq!(field_counts(&text, ';'), vec![4; 5], "{}", text);
+        assert!(text.contains("S02;12,25;3,0;"), "{}", text);
+        for column in ["RunHoursDuration", "FlowCellPriorUses", "QCComments"] {
+            assert_eq!(strings(&read, column), strings(&df, column), "{}", column);
+        }
+    }
+
+    #[test]
+    fn numeric_columns_parsed_as_numbers_are_left_alone() {
+        let mut df = df!("RunHoursDuration" => [66.5, 12.0]).unwrap();
+        assert_eq!(normalize_decimals(&mut df, &default_numeric_columns(), ',').unwrap(), 0);
+        assert_eq!(df.column("RunHoursDuration").unwrap().dtype(), &DataType::Float64);
+    }
+
+    #[test]
+    fn padding_alone_is_not_a_change() {
+        let mut df = df!("RunHoursDuration" => [" 66,5 ", "12.25 ", "3,0"]).unwrap();
+        assert_eq!(normalize_decimals(&mut df, &default_numeric_columns(), ',').unwrap(), 1);
+        assert_eq!(
+            strings(&df, "RunHoursDuration"),
+            vec![Some(" 66,5 ".to_string()), Some("12,25".to_string()), Some("3,0".to_string())]
+        );
+    }
+}
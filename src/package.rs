@@ -0,0 +1,349 @@
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use ::zip::write::SimpleFileOptions;
+use ::zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::about::{build_info, BuildInfo};
+use crate::csv::{read_csv_normalized, read_header_columns};
+use crate::input::open_read_only;
+use crate::minknow::MinKnowData;
+use crate::pseudonym::{load_or_create_secret, pseudonymize_columns, PSEUDONYMIZED_COLUMNS};
+use crate::run_constants::RunConstants;
+use crate::settings::Settings;
+
+// Bump when the package layout changes; older apps refuse newer packages
+pub const MANIFEST_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const SAMPLES_FILE: &str = "samples.csv";
+const MINKNOW_FILE: &str = "minknow.json";
+const CONSTANTS_FILE: &str = "run_constants.json";
+const SETTINGS_FILE: &str = "settings.json";
+const REPORT_FILE: &str = "validation_report.txt";
+
+/// Describes a run package for inter-lab review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub manifest_version: u32,
+    pub app_version: String,
+    // YYYY-MM-DD HH:MM
+    pub created: String,
+    pub mode: String,
+    pub run_num: String,
+    // Sample file columns blanked because EpiInfo supplies them
+    pub cleared_columns: Vec<String>,
+    // Commit, build date and column templates of the exporting app;
+    // absent in packages from before it was recorded
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+    // Post-merge hook configured in the exporting lab's settings, if any
+    #[serde(default)]
+    pub post_merge_hook: Option<String>,
+    // Sample file columns holding pseudonyms instead of IDs; they only
+    // match pseudonyms made on the exporting computer
+    #[serde(default)]
+    pub pseudonymized_columns: Vec<String>,
+}
+
+/// What the reviewer gets back from a package
+pub struct ImportedPackage {
+    pub manifest: PackageManifest,
+    pub constants: RunConstants,
+    pub minknow: Option<MinKnowData>,
+    // Extracted copy of the sample file, next to the package
+    pub sample_path: PathBuf,
+}
+
+pub struct PackageContents<'a> {
+    pub mode: &'a str,
+    pub sample_path: &'a str,
+    // Only its header is read, to know which columns to blank
+    pub epiinfo_path: Option<&'a str>,
+    pub minknow: Option<&'a MinKnowData>,
+    pub constants: &'a RunConstants,
+    pub settings: &'a Settings,
+    pub report: &'a str,
+}
+
+pub fn package_file_name(run_num: &str) -> String {
+    let run = if run_num.trim().is_empty() { "run" } else { run_num.trim() };
+    format!("{}_run_package.zip", run)
+}
+
+/// Blanks every sample column that EpiInfo also provides (except the
+/// `sample` key), so no epidemiological data leaves the lab.
+/// Returns the blanked column names.
+pub fn strip_epiinfo_columns(df: &mut DataFrame, epi_columns: &[String]) -> Result<Vec<String>, String> {
+    let height = df.height();
+    let present: Vec<String> = df
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .filter(|c| c != "sample" && epi_columns.contains(c))
+        .collect();
+
+    for column in &present {
+        let blank = Series::new(column.as_str().into(), vec![""; height]);
+        df.with_column(blank)
+            .map_err(|e| format!("Failed to clear column '{}': {e}", column))?;
+    }
+    Ok(present)
+}
+
+fn to_json<T: Serialize>(value: &T, what: &str) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize {}: {e}", what))
+}
+
+/// Writes the run package zip into `dest_dir` and returns its path.
+/// The EpiInfo file itself is never included.
+pub fn export_run_package(dest_dir: &Path, contents: &PackageContents) -> Result<PathBuf, String> {
+    let (mut samples, _) = read_csv_normalized(contents.sample_path)?;
+    let cleared_columns = match contents.epiinfo_path {
+        Some(epi) => strip_epiinfo_columns(&mut samples, &read_header_columns(epi)?)?,
+        None => Vec::new(),
+    };
+    let pseudonymized_columns = if contents.settings.pseudonymize_package_ids {
+        pseudonymize_columns(&mut samples, PSEUDONYMIZED_COLUMNS, &load_or_create_secret()?)?
+    } else {
+        Vec::new()
+    };
+
+    let mut samples_csv = Vec::new();
+    CsvWriter::new(&mut samples_csv)
+        .finish(&mut samples)
+        .map_err(|e| format!("Failed to write samples: {e}"))?;
+
+    let manifest = PackageManifest {
+        manifest_version: MANIFEST_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        mode: contents.mode.to_string(),
+        run_num: contents.constants.run_num.clone(),
+        cleared_columns,
+        build: Some(build_info()),
+        post_merge_hook: Some(contents.settings.post_merge_hook.trim().to_string()).filter(|h| !h.is_empty()),
+        pseudonymized_columns,
+    };
+
+    let mut entries: Vec<(&str, Vec<u8>)> = vec![
+        (MANIFEST_FILE, to_json(&manifest, "manifest")?),
+        (SAMPLES_FILE, samples_csv),
+        (CONSTANTS_FILE, to_json(contents.constants, "run constants")?),
+        (SETTINGS_FILE, to_json(contents.settings, "settings")?),
+        (REPORT_FILE, contents.report.as_bytes().to_vec()),
+    ];
+    if let Some(data) = contents.minknow {
+        entries.push((MINKNOW_FILE, to_json(data, "MinKNOW values")?));
+    }
+
+    let path = dest_dir.join(package_file_name(&contents.constants.run_num));
+    let file = File::create(&path)
+        .map_err(|e| format!("Failed to create '{}': {e}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, bytes) in entries {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(&bytes).map_err(Into::into))
+            .map_err(|e| format!("Failed to write '{}' to the package: {e}", name))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to finish '{}': {e}", path.display()))?;
+
+    Ok(path)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<String>, String> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(::zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read '{}' from the package: {e}", name)),
+    };
+    let mut text = String::new();
+    entry
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read '{}' from the package: {e}", name))?;
+    Ok(Some(text))
+}
+
+/// Opens a run package and extracts its sample file next to it.
+/// Packages from a newer manifest version are rejected.
+pub fn import_run_package(path: &Path) -> Result<ImportedPackage, String> {
+    let file = open_read_only(path)
+        .map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| format!("'{}' is not a run package: {e}", path.display()))?;
+
+    let manifest_text = read_entry(&mut archive, MANIFEST_FILE)?
+        .ok_or_else(|| format!("'{}' is not a run package: no {}.", path.display(), MANIFEST_FILE))?;
+    let manifest: PackageManifest = serde_json::from_str(&manifest_text)
+        .map_err(|e| format!("Invalid package manifest: {e}"))?;
+    if manifest.manifest_version > MANIFEST_VERSION {
+        return Err(format!(
+            "This run package was made by a newer version of Merger ({}, package format {}). \
+             This version reads package format {} or older; please update Merger to open it.",
+            manifest.app_version, manifest.manifest_version, MANIFEST_VERSION
+        ));
+    }
+
+    let constants = match read_entry(&mut archive, CONSTANTS_FILE)? {
+        Some(text) => RunConstants::from_json(&text)?,
+        None => return Err(format!("The package has no {}.", CONSTANTS_FILE)),
+    };
+    let minknow = match read_entry(&mut archive, MINKNOW_FILE)? {
+        Some(text) => Some(
+            serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {e}", MINKNOW_FILE))?,
+        ),
+        None => None,
+    };
+    let samples = read_entry(&mut archive, SAMPLES_FILE)?
+        .ok_or_else(|| format!("The package has no {}.", SAMPLES_FILE))?;
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("run_package");
+    let sample_path = path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(format!("{}_samples.csv", stem));
+    std::fs::write(&sample_path, samples)
+        .map_err(|e| format!("Failed to write '{}': {e}", sample_path.display()))?;
+
+    Ok(ImportedPackage {
+        manifest,
+        constants,
+        minknow,
+        sample_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    const SAMPLES: &str = "sample,barcode,EPID,DateOfOnset,Province\nS01,barcode01,NIE-KAN-24-001,2024-02-01,Kano\n";
+    const EPIINFO: &str = "ICLabID,EpidNumber,DateOfOnset,Province,PatientName\nS01,NIE-KAN-24-001,2024-02-01,Kano,A\n";
+
+    fn write(dir: &mut TempWorkspace, name: &str, text: &str) -> String {
+        let path = dir.file(name);
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn constants() -> RunConstants {
+        RunConstants {
+            run_num: "20240305_001".to_string(),
+            lab: "INRB".to_string(),
+            ..RunConstants::default()
+        }
+    }
+
+    fn export(dir: &mut TempWorkspace, with_epiinfo: bool) -> PathBuf {
+        let sample_path = write(dir, "samples.csv", SAMPLES);
+        let epiinfo_path = write(dir, "epiinfo.csv", EPIINFO);
+        let minknow = MinKnowData {
+            fc_id: "FAX12345".to_string(),
+            ..MinKnowData::default()
+        };
+        let contents = PackageContents {
+            mode: "DDNS",
+            sample_path: &sample_path,
+            epiinfo_path: with_epiinfo.then_some(epiinfo_path.as_str()),
+            minknow: Some(&minknow),
+            constants: &constants(),
+            settings: &Settings::default(),
+            report: "No warnings.",
+        };
+        export_run_package(dir.path(), &contents).unwrap()
+    }
+
+    fn zip_with_manifest(path: &Path, manifest: &str) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(MANIFEST_FILE, SimpleFileOptions::default()).unwrap();
+        zip.write_all(manifest.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn package_holds_the_run_but_no_epiinfo_data() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = export(&mut dir, true);
+        assert_eq!(path.file_name().unwrap(), "20240305_001_run_package.zip");
+
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [MANIFEST_FILE, MINKNOW_FILE, CONSTANTS_FILE, SAMPLES_FILE, SETTINGS_FILE, REPORT_FILE]
+        );
+
+        let samples = read_entry(&mut archive, SAMPLES_FILE).unwrap().unwrap();
+        assert!(!samples.contains("Kano") && !samples.contains("2024-02-01"));
+        // The sample's own EPID is not an EpiInfo column
+        assert!(samples.contains("S01,barcode01,NIE-KAN-24-001,"));
+
+        let manifest: PackageManifest =
+            serde_json::from_str(&read_entry(&mut archive, MANIFEST_FILE).unwrap().unwrap()).unwrap();
+        assert_eq!(manifest.manifest_version, MANIFEST_VERSION);
+        assert_eq!(manifest.cleared_columns, ["DateOfOnset", "Province"]);
+        assert!(manifest.pseudonymized_columns.is_empty());
+    }
+
+    #[test]
+    fn imported_package_restores_constants_and_samples() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = export(&mut dir, false);
+
+        let imported = import_run_package(&path).unwrap();
+        assert_eq!(imported.constants, constants());
+        assert_eq!(imported.minknow.map(|m| m.fc_id).as_deref(), Some("FAX12345"));
+        assert_eq!(imported.manifest.mode, "DDNS");
+        assert!(imported.manifest.cleared_columns.is_empty());
+        assert_eq!(imported.sample_path, dir.path().join("20240305_001_run_package_samples.csv"));
+        let (samples, _) = read_csv_normalized(&imported.sample_path.to_string_lossy()).unwrap();
+        assert_eq!(samples.height(), 1);
+        assert_eq!(samples.column("Province").unwrap().str().unwrap().get(0), Some("Kano"));
+    }
+
+    #[test]
+    fn newer_manifest_version_is_refused() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("future_run_package.zip");
+        let manifest = PackageManifest {
+            manifest_version: MANIFEST_VERSION + 1,
+            app_version: "9.0.0".to_string(),
+            created: "2030-01-01 09:00".to_string(),
+            mode: "DDNS".to_string(),
+            run_num: "20300101_001".to_string(),
+            cleared_columns: Vec::new(),
+            build: None,
+            post_merge_hook: None,
+            pseudonymized_columns: Vec::new(),
+        };
+        zip_with_manifest(&path, &serde_json::to_string(&manifest).unwrap());
+
+        let err = import_run_package(&path).err().unwrap();
+        assert!(err.contains("made by a newer version of Merger (9.0.0, package format 2)"), "{}", err);
+        assert!(err.contains("please update Merger"), "{}", err);
+    }
+
+    #[test]
+    fn files_that_are_not_packages_are_refused() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let not_zip = dir.file("notes.zip");
+        std::fs::write(&not_zip, "not a zip").unwrap();
+        assert!(import_run_package(&not_zip).err().unwrap().contains("is not a run package"));
+
+        let no_manifest = dir.file("other.zip");
+        let mut zip = ZipWriter::new(File::create(&no_manifest).unwrap());
+        zip.start_file("readme.txt", SimpleFileOptions::default()).unwrap();
+        zip.finish().unwrap();
+        assert!(import_run_package(&no_manifest).err().unwrap().contains("no manifest.json"));
+
+        let broken = dir.file("broken.zip");
+        zip_with_manifest(&broken, "{\"manifest_version\": \"one\"}");
+        assert!(import_run_package(&broken).err().unwrap().starts_with("Invalid package manifest"));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::warnings::Lang;
+
+/// What the enablement predicates look at, read from the UI whenever the
+/// palette is filtered
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PaletteState {
+    pub sample_file: bool,
+    pub epiinfo_file: bool,
+    pub minknow_file: bool,
+    pub no_minknow_report: bool,
+    pub destination: bool,
+}
+
+/// One quick action. `id` is what the dispatcher matches on.
+pub struct PaletteAction {
+    pub id: &'static str,
+    label_en: &'static str,
+    label_fr: &'static str,
+    enabled: fn(&PaletteState) -> bool,
+}
+
+impl PaletteAction {
+    pub fn label(&self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::En => self.label_en,
+            Lang::Fr => self.label_fr,
+        }
+    }
+
+    pub fn is_enabled(&self, state: &PaletteState) -> bool {
+        (self.enabled)(state)
+    }
+}
+
+fn always(_: &PaletteState) -> bool {
+    true
+}
+
+// Same file check the merge handler does before anything is read
+fn can_merge(s: &PaletteState) -> bool {
+    s.sample_file && s.destination && (s.epiinfo_file || s.minknow_file || s.no_minknow_report)
+}
+
+fn can_export_package(s: &PaletteState) -> bool {
+    s.sample_file && s.destination
+}
+
+fn has_sample_file(s: &PaletteState) -> bool {
+    s.sample_file
+}
+
+fn has_minknow_file(s: &PaletteState) -> bool {
+    s.minknow_file
+}
+
+/// Every action the palette offers, in the order shown for an empty query
+pub static ACTIONS: &[PaletteAction] = &[
+    PaletteAction { id: "merge", label_en: "Merge", label_fr: "Fusionner", enabled: can_merge },
+    PaletteAction { id: "update", label_en: "Update detailed run report", label_fr: "Mettre à jour le rapport détaillé", enabled: can_merge },
+    PaletteAction { id: "verify_output", label_en: "Verify output", label_fr: "Vérifier la sortie", enabled: always },
+    PaletteAction { id: "compliance", label_en: "Check samples file against template", label_fr: "Comparer le fichier d'échantillons au modèle", enabled: has_sample_file },
+    PaletteAction { id: "validate_config", label_en: "Check configuration", label_fr: "Vérifier la configuration", enabled: always },
+    PaletteAction { id: "template", label_en: "Generate template", label_fr: "Générer un modèle", enabled: always },
+    PaletteAction { id: "plate_map", label_en: "Open plate map", label_fr: "Ouvrir la carte de plaque", enabled: always },
+    PaletteAction { id: "fill_rules", label_en: "Fill rules", label_fr: "Règles de remplissage", enabled: always },
+    PaletteAction { id: "corrections", label_en: "Apply corrections", label_fr: "Appliquer des corrections", enabled: always },
+    PaletteAction { id: "extract_report", label_en: "Extract MinKNOW values", label_fr: "Extraire les valeurs MinKNOW", enabled: has_minknow_file },
+    PaletteAction { id: "monthly_summary", label_en: "Monthly summary", label_fr: "Bilan mensuel", enabled: always },
+    PaletteAction { id: "match_trends", label_en: "EpiInfo match trend (12 weeks)", label_fr: "Tendance d'appariement Epi Info (12 semaines)", enabled: always },
+    PaletteAction { id: "export_package", label_en: "Export run package", label_fr: "Exporter le dossier de run", enabled: can_export_package },
+    PaletteAction { id: "import_package", label_en: "Import run package", label_fr: "Importer un dossier de run", enabled: always },
+    PaletteAction { id: "import_bundle", label_en: "Import configuration bundle", label_fr: "Importer une configuration pays", enabled: always },
+    PaletteAction { id: "export_bundle", label_en: "Export configuration bundle", label_fr: "Exporter la configuration pays", enabled: always },
+    PaletteAction { id: "setup", label_en: "Run setup again", label_fr: "Relancer la configuration", enabled: always },
+    PaletteAction { id: "clear", label_en: "Clear all fields", label_fr: "Effacer tous les champs", enabled: always },
+    PaletteAction { id: "about", label_en: "About Merger", label_fr: "À propos de Merger", enabled: always },
+    PaletteAction { id: "guide", label_en: "Open guide", label_fr: "Ouvrir le guide", enabled: always },
+];
+
+/// Scores `label` against `query` as a case-insensitive subsequence match.
+/// Consecutive characters and word starts score higher; None if the
+/// query's characters do not all appear in order.
+pub fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let query: Vec<char> = query.trim().to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for (idx, c) in label.iter().enumerate() {
+        if next == query.len() {
+            break;
+        }
+        if *c != query[next] {
+            continue;
+        }
+        score += 1;
+        if previous == Some(idx.wrapping_sub(1)) {
+            score += 3;
+        }
+        if idx == 0 || !label[idx - 1].is_alphanumeric() {
+            score += 5;
+        }
+        previous = Some(idx);
+        next += 1;
+    }
+
+    (next == query.len()).then_some(score)
+}
+
+/// Actions matching `query`, best match first (registry order on ties),
+/// each with whether it can run in `state`
+pub fn filter_actions(query: &str, lang: Lang, state: &PaletteState) -> Vec<(&'static PaletteAction, bool)> {
+    let mut matches: Vec<(i32, usize, &'static PaletteAction)> = ACTIONS
+        .iter()
+        .enumerate()
+        .filter_map(|(order, action)| {
+            // The id matches too, so "export" works in both languages
+            let score = fuzzy_score(query, action.label(lang)).max(fuzzy_score(query, action.id));
+            score.map(|s| (s, order, action))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches
+        .into_iter()
+        .map(|(_, _, action)| (action, action.is_enabled(state)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(id: &str) -> &'static PaletteAction {
+        ACTIONS.iter().find(|a| a.id == id).unwrap()
+    }
+
+    fn ids(query: &str, lang: Lang) -> Vec<&'static str> {
+        filter_actions(query, lang, &PaletteState::default()).into_iter().map(|(a, _)| a.id).collect()
+    }
+
+    #[test]
+    fn registry_ids_are_unique_and_labelled() {
+        let mut seen = std::collections::HashSet::new();
+        for action in ACTIONS {
+            assert!(seen.insert(action.id), "{} is listed twice", action.id);
+            assert!(!action.label(Lang::En).is_empty() && !action.label(Lang::Fr).is_empty(), "{}", action.id);
+        }
+    }
+
+    #[test]
+    fn fuzzy_score_needs_every_character_in_order() {
+        assert_eq!(fuzzy_score("", "Merge"), Some(0));
+        assert!(fuzzy_score("mrg", "Merge").is_some());
+        assert!(fuzzy_score("MERGE", "Merge").is_some());
+        assert_eq!(fuzzy_score("gem", "Merge"), None);
+        assert_eq!(fuzzy_score("merger", "Merge"), None);
+        // Runs of characters and word starts rank higher
+        assert!(fuzzy_score("temp", "Generate template") > fuzzy_score("gnrt", "Generate template"));
+        assert!(fuzzy_score("ep", "Export run package") > fuzzy_score("ep", "Update detailed run report"));
+    }
+
+    #[test]
+    fn empty_query_lists_every_action_in_registry_order() {
+        let all: Vec<&str> = ACTIONS.iter().map(|a| a.id).collect();
+        assert_eq!(ids("", Lang::En), all);
+        assert_eq!(ids("   ", Lang::Fr), all);
+    }
+
+    #[test]
+    fn best_match_comes_first_in_either_language() {
+        assert_eq!(ids("merge", Lang::En)[0], "merge");
+        assert_eq!(ids("fusion", Lang::Fr)[0], "merge");
+        assert_eq!(ids("modèle", Lang::Fr)[0], "template");
+        // Ids match too, so English words work in French
+        assert!(ids("export_package", Lang::Fr).starts_with(&["export_package"]));
+        assert!(ids("zzz", Lang::En).is_empty());
+    }
+
+    #[test]
+    fn enablement_follows_the_selected_files() {
+        let none = PaletteState::default();
+        let samples_only = PaletteState { sample_file: true, destination: true, ..none };
+        let with_epiinfo = PaletteState { epiinfo_file: true, ..samples_only };
+        let manual_minknow = PaletteState { no_minknow_report: true, ..samples_only };
+        let no_destination = PaletteState { destination: false, ..with_epiinfo };
+
+        assert!(!action("merge").is_enabled(&none));
+        assert!(!action("merge").is_enabled(&samples_only));
+        assert!(action("merge").is_enabled(&with_epiinfo));
+        assert!(action("merge").is_enabled(&manual_minknow));
+        assert!(!action("merge").is_enabled(&no_destination));
+        assert!(action("update").is_enabled(&with_epiinfo));
+
+        assert!(action("export_package").is_enabled(&samples_only));
+        assert!(!action("export_package").is_enabled(&no_destination));
+        assert!(action("compliance").is_enabled(&no_destination));
+        assert!(!action("compliance").is_enabled(&none));
+        assert!(!action("extract_report").is_enabled(&with_epiinfo));
+        assert!(action("extract_report").is_enabled(&PaletteState { minknow_file: true, ..none }));
+        assert!(action("about").is_enabled(&none));
+
+        let listed = filter_actions("merge", Lang::En, &with_epiinfo);
+        assert_eq!(listed[0].0.id, "merge");
+        assert!(listed[0].1);
+    }
+}
@@ -0,0 +1,217 @@
+use hmac::{Hmac, Mac};
+use polars::prelude::*;
+use sha2::Sha256;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::settings::settings_dir;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Characters in a pseudonym: 16 base32 characters carry 80 bits
+pub const PSEUDONYM_LENGTH: usize = 16;
+
+// Sample file columns replaced by pseudonyms when sharing
+pub const PSEUDONYMIZED_COLUMNS: &[&str] = &["sample", "EPID"];
+
+const SECRET_FILE: &str = "pseudonym.key";
+const SECRET_BYTES: usize = 32;
+
+// RFC 4648 base32, no padding
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The per-installation secret. Only this computer has it, so the same ID
+/// gives the same pseudonym here and a different one anywhere else.
+pub fn secret_path() -> Option<PathBuf> {
+    settings_dir().map(|d| d.join(SECRET_FILE))
+}
+
+fn base32(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+// Owner-only where the platform has Unix permissions; elsewhere the
+// config folder's own access rules apply
+fn create_secret_file(path: &Path) -> std::io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Reads the secret at `path`, creating it with random bytes on first use
+pub fn load_or_create_secret_at(path: &Path) -> Result<Vec<u8>, String> {
+    match fs::read(path) {
+        Ok(secret) if secret.len() >= SECRET_BYTES => return Ok(secret),
+        Ok(_) => return Err(format!("The pseudonym key '{}' is damaged; restore it from a backup.", path.display())),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("Failed to read the pseudonym key '{}': {e}", path.display()))
+        }
+        Err(_) => {}
+    }
+
+    let mut secret = vec![0u8; SECRET_BYTES];
+    getrandom::getrandom(&mut secret).map_err(|e| format!("Failed to generate a pseudonym key: {e}"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create '{}': {e}", dir.display()))?;
+    }
+    create_secret_file(path)
+        .and_then(|mut file| file.write_all(&secret))
+        .map_err(|e| format!("Failed to write the pseudonym key '{}': {e}", path.display()))?;
+    Ok(secret)
+}
+
+pub fn load_or_create_secret() -> Result<Vec<u8>, String> {
+    let path = secret_path().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    load_or_create_secret_at(&path)
+}
+
+/// HMAC-SHA256 of the trimmed ID, base32, cut to PSEUDONYM_LENGTH. Cannot
+/// be turned back into the ID without the secret.
+pub fn pseudonym(secret: &[u8], id: &str) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC key");
+    mac.update(id.trim().as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let mut text = base32(&digest[..PSEUDONYM_LENGTH * 5 / 8]);
+    text.truncate(PSEUDONYM_LENGTH);
+    text
+}
+
+/// Replaces the values of `columns` in `df` with their pseudonyms; empty
+/// cells stay empty. Returns the columns replaced.
+pub fn pseudonymize_columns(df: &mut DataFrame, columns: &[&str], secret: &[u8]) -> Result<Vec<String>, String> {
+    let mut replaced = Vec::new();
+    for &column in columns {
+        let Ok(values) = df.column(column) else {
+            continue;
+        };
+        let values = values
+            .cast(&DataType::String)
+            .map_err(|e| format!("Failed to read column '{}': {e}", column))?;
+        let pseudonyms: Vec<String> = values
+            .str()
+            .map_err(|e| format!("Column '{}' is not text: {e}", column))?
+            .into_iter()
+            .map(|v| match v.map(str::trim) {
+                Some(v) if !v.is_empty() => pseudonym(secret, v),
+                _ => String::new(),
+            })
+            .collect();
+        df.with_column(Series::new(column.into(), pseudonyms))
+            .map_err(|e| format!("Failed to pseudonymize column '{}': {e}", column))?;
+        replaced.push(column.to_string());
+    }
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use std::collections::HashSet;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn same_id_gives_same_pseudonym() {
+        let first = pseudonym(SECRET, "ENV-NIE-ABC-23-001");
+        assert_eq!(first, pseudonym(SECRET, "ENV-NIE-ABC-23-001"));
+        assert_eq!(first, pseudonym(SECRET, "  ENV-NIE-ABC-23-001 "));
+        assert_eq!(first.len(), PSEUDONYM_LENGTH);
+        assert!(first.bytes().all(|b| BASE32.contains(&b)), "{}", first);
+    }
+
+    #[test]
+    fn other_secret_gives_other_pseudonym() {
+        let other = b"fedcba9876543210fedcba9876543210";
+        assert_ne!(pseudonym(SECRET, "S001"), pseudonym(other, "S001"));
+    }
+
+    #[test]
+    fn similar_ids_do_not_collide() {
+        let pseudonyms: HashSet<String> = (0..20_000).map(|i| pseudonym(SECRET, &format!("S{:05}", i))).collect();
+        assert_eq!(pseudonyms.len(), 20_000);
+    }
+
+    #[test]
+    fn base32_matches_rfc_4648() {
+        assert_eq!(base32(b""), "");
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base32(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn secret_is_created_once_and_reused() {
+        let dir = TempWorkspace::new().unwrap();
+        let path = dir.path().join("config").join(SECRET_FILE);
+        let created = load_or_create_secret_at(&path).unwrap();
+        assert_eq!(created.len(), SECRET_BYTES);
+        assert_eq!(load_or_create_secret_at(&path).unwrap(), created);
+    }
+
+    #[test]
+    fn short_secret_is_refused() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file(SECRET_FILE);
+        fs::write(&path, b"too short").unwrap();
+        let error = load_or_create_secret_at(&path).unwrap_err();
+        assert!(error.contains("damaged"), "{}", error);
+        assert_eq!(fs::read(&path).unwrap(), b"too short");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file(SECRET_FILE);
+        load_or_create_secret_at(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn columns_are_replaced_and_empty_cells_kept() {
+        let mut df = df!(
+            "sample" => ["S001", "", "S001"],
+            "EPID" => [Some("E1"), None, Some(" E2 ")],
+            "barcode" => ["barcode01", "barcode02", "barcode03"]
+        )
+        .unwrap();
+        let replaced = pseudonymize_columns(&mut df, &["sample", "EPID", "missing"], SECRET).unwrap();
+        assert_eq!(replaced, ["sample", "EPID"]);
+
+        let text = |column: &str| -> Vec<String> {
+            df.column(column)
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_iter()
+                .map(|v| v.unwrap_or_default().to_string())
+                .collect()
+        };
+        assert_eq!(text("sample"), [pseudonym(SECRET, "S001"), String::new(), pseudonym(SECRET, "S001")]);
+        assert_eq!(text("EPID"), [pseudonym(SECRET, "E1"), String::new(), pseudonym(SECRET, "E2")]);
+        assert_eq!(text("barcode"), ["barcode01", "barcode02", "barcode03"]);
+    }
+}
@@ -0,0 +1,414 @@
+use std::io::{self, Read};
+
+use crate::csv::{canonical_column, read_header_columns};
+use crate::input::open_read_only;
+use crate::minknow::is_supported_report;
+use crate::template::expected_columns_for_mode;
+use crate::warnings::Lang;
+
+// Quick checks never read past this much of a file; a bigger file reports
+// "at least" its row count
+const QUICK_READ_BYTES: u64 = 4 * 1024 * 1024;
+
+// HTML reports carry reportData in a script after the page's styles and
+// chart code, so they get a larger budget
+const REPORT_SCAN_BYTES: u64 = 32 * 1024 * 1024;
+
+const REPORT_DATA_MARKER: &[u8] = b"const reportData=";
+
+/// Outcome of a quick check, shown next to the file slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotLevel {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl SlotLevel {
+    /// Value of the slot status property: 1 tick, 2 amber, 3 red
+    pub fn ui_value(self) -> i32 {
+        match self {
+            SlotLevel::Ok => 1,
+            SlotLevel::Warning => 2,
+            SlotLevel::Error => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotCheck {
+    pub level: SlotLevel,
+    pub message: String,
+}
+
+impl SlotCheck {
+    fn new(level: SlotLevel, message: String) -> Self {
+        Self { level, message }
+    }
+}
+
+// Data rows in the first QUICK_READ_BYTES (lines minus the header), and
+// whether the file goes on past that
+fn count_rows(path: &str) -> io::Result<(usize, bool)> {
+    let mut file = open_read_only(path)?.take(QUICK_READ_BYTES + 1);
+    let mut buffer = [0u8; 64 * 1024];
+    let (mut lines, mut read, mut last) = (0usize, 0u64, b'\n');
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        lines += buffer[..n].iter().filter(|&&b| b == b'\n').count();
+        last = buffer[n - 1];
+    }
+    // A last line without a newline still counts
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok((lines.saturating_sub(1), read > QUICK_READ_BYTES))
+}
+
+// Whether `needle` occurs in the first `limit` bytes, read in chunks
+fn contains_within(path: &str, needle: &[u8], limit: u64) -> io::Result<bool> {
+    let mut file = open_read_only(path)?.take(limit);
+    let mut buffer = vec![0u8; 256 * 1024];
+    let mut carry: Vec<u8> = Vec::new();
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(false);
+        }
+        carry.extend_from_slice(&buffer[..n]);
+        if carry.windows(needle.len()).any(|w| w == needle) {
+            return Ok(true);
+        }
+        // Keep the tail so a marker split across two reads is still found
+        let keep = carry.len().saturating_sub(needle.len());
+        carry.drain(..keep);
+    }
+}
+
+fn rows_text(rows: usize, more: bool, lang: Lang) -> String {
+    match (lang, more) {
+        (Lang::En, false) => format!("{} rows", rows),
+        (Lang::En, true) => format!("at least {} rows", rows),
+        (Lang::Fr, false) => format!("{} lignes", rows),
+        (Lang::Fr, true) => format!("au moins {} lignes", rows),
+    }
+}
+
+fn read_error(e: impl std::fmt::Display, lang: Lang) -> SlotCheck {
+    SlotCheck::new(
+        SlotLevel::Error,
+        match lang {
+            Lang::En => format!("Cannot read the file: {e}"),
+            Lang::Fr => format!("Lecture du fichier impossible : {e}"),
+        },
+    )
+}
+
+/// Samples file: sample and barcode columns, template columns, row count
+pub fn check_sample_file(path: &str, mode: &str, lang: Lang) -> SlotCheck {
+    let columns = match read_header_columns(path) {
+        Ok(columns) => columns,
+        Err(e) => return read_error(e, lang),
+    };
+    let (rows, more) = match count_rows(path) {
+        Ok(counted) => counted,
+        Err(e) => return read_error(e, lang),
+    };
+    let rows = rows_text(rows, more, lang);
+
+    // The join keys are renamed to the template's case when merging
+    let columns: Vec<String> = columns
+        .into_iter()
+        .map(|h| canonical_column(&h).map(str::to_string).unwrap_or(h))
+        .collect();
+    let required: Vec<&str> = ["sample", "barcode"]
+        .into_iter()
+        .filter(|c| !columns.iter().any(|h| h == c))
+        .collect();
+    if !required.is_empty() {
+        return SlotCheck::new(
+            SlotLevel::Error,
+            match lang {
+                Lang::En => format!("No {} column", required.join(" or ")),
+                Lang::Fr => format!("Pas de colonne {}", required.join(" ni ")),
+            },
+        );
+    }
+
+    // A two-column barcodes.csv is filled out from the template at merge time
+    if columns.len() == 2 {
+        return SlotCheck::new(
+            SlotLevel::Ok,
+            match lang {
+                Lang::En => format!("Barcodes only, {}", rows),
+                Lang::Fr => format!("Codes-barres uniquement, {}", rows),
+            },
+        );
+    }
+
+    let missing: Vec<&str> = expected_columns_for_mode(mode)
+        .into_iter()
+        .filter(|c| !columns.iter().any(|h| h == c))
+        .collect();
+    if missing.is_empty() {
+        return SlotCheck::new(SlotLevel::Ok, rows);
+    }
+    SlotCheck::new(
+        SlotLevel::Warning,
+        match lang {
+            Lang::En => format!("{}; {} {} columns missing: {}", rows, missing.len(), mode, missing.join(", ")),
+            Lang::Fr => format!("{} ; {} colonnes {} manquantes : {}", rows, missing.len(), mode, missing.join(", ")),
+        },
+    )
+}
+
+/// EpiInfo export: ICLabID column and row count
+pub fn check_epiinfo_file(path: &str, lang: Lang) -> SlotCheck {
+    let columns = match read_header_columns(path) {
+        Ok(columns) => columns,
+        Err(e) => return read_error(e, lang),
+    };
+    if !columns.iter().any(|c| c == "ICLabID") {
+        return SlotCheck::new(
+            SlotLevel::Error,
+            match lang {
+                Lang::En => "No ICLabID column".to_string(),
+                Lang::Fr => "Pas de colonne ICLabID".to_string(),
+            },
+        );
+    }
+    match count_rows(path) {
+        Ok((0, _)) => SlotCheck::new(
+            SlotLevel::Warning,
+            match lang {
+                Lang::En => "No rows".to_string(),
+                Lang::Fr => "Aucune ligne".to_string(),
+            },
+        ),
+        Ok((rows, more)) => SlotCheck::new(SlotLevel::Ok, rows_text(rows, more, lang)),
+        Err(e) => read_error(e, lang),
+    }
+}
+
+/// MinKNOW report: a supported format with report data in it
+pub fn check_minknow_file(path: &str, lang: Lang) -> SlotCheck {
+    if !is_supported_report(path) {
+        return SlotCheck::new(
+            SlotLevel::Error,
+            match lang {
+                Lang::En => "Not an HTML or JSON report".to_string(),
+                Lang::Fr => "Ce n'est pas un rapport HTML ou JSON".to_string(),
+            },
+        );
+    }
+    let found = if path.to_lowercase().ends_with(".json") {
+        // The JSON export is the report data itself, or a list of them
+        let mut start = Vec::new();
+        open_read_only(path)
+            .and_then(|f| f.take(1024).read_to_end(&mut start))
+            .map(|_| String::from_utf8_lossy(&start).trim_start_matches('\u{feff}').trim_start().starts_with(['{', '[']))
+    } else {
+        contains_within(path, REPORT_DATA_MARKER, REPORT_SCAN_BYTES)
+    };
+    match found {
+        Ok(true) => SlotCheck::new(
+            SlotLevel::Ok,
+            match lang {
+                Lang::En => "Report data found".to_string(),
+                Lang::Fr => "Données du rapport trouvées".to_string(),
+            },
+        ),
+        Ok(false) => SlotCheck::new(
+            SlotLevel::Error,
+            match lang {
+                Lang::En => "No report data found; is this a MinKNOW run report?".to_string(),
+                Lang::Fr => "Aucune donnée de rapport ; est-ce bien un rapport MinKNOW ?".to_string(),
+            },
+        ),
+        Err(e) => read_error(e, lang),
+    }
+}
+
+/// A typed or pasted path that is not an existing file; None when it is
+/// one, so the content checks can run
+pub fn check_path_exists(path: &str, lang: Lang) -> Option<SlotCheck> {
+    let target = std::path::Path::new(path);
+    let message = if target.is_file() {
+        return None;
+    } else if target.is_dir() {
+        match lang {
+            Lang::En => format!("'{}' is a folder, not a file", path),
+            Lang::Fr => format!("« {} » est un dossier, pas un fichier", path),
+        }
+    } else {
+        match lang {
+            Lang::En => format!("File not found: {}", path),
+            Lang::Fr => format!("Fichier introuvable : {}", path),
+        }
+    };
+    Some(SlotCheck::new(SlotLevel::Error, message))
+}
+
+/// Quick check of a file slot ("sample_file", "epiinfo_file",
+/// "minknow_file"); None for other slots
+pub fn quick_check(slot: &str, path: &str, mode: &str, lang: Lang) -> Option<SlotCheck> {
+    match slot {
+        "sample_file" => Some(check_sample_file(path, mode, lang)),
+        "epiinfo_file" => Some(check_epiinfo_file(path, lang)),
+        "minknow_file" => Some(check_minknow_file(path, lang)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    fn file(dir: &mut TempWorkspace, name: &str, bytes: &[u8]) -> String {
+        let path = dir.file(name);
+        std::fs::write(&path, bytes).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn full_sheet(mode: &str, rows: usize) -> String {
+        let mut text = expected_columns_for_mode(mode).join(",") + "\n";
+        for row in 1..=rows {
+            text += &format!("S{:02},barcode{:02}\n", row, row);
+        }
+        text
+    }
+
+    #[test]
+    fn sample_file_checks() {
+        let mut dir = TempWorkspace::new().unwrap();
+
+        let full = file(&mut dir, "full.csv", full_sheet("DDNS", 3).as_bytes());
+        assert_eq!(check_sample_file(&full, "DDNS", Lang::En), SlotCheck::new(SlotLevel::Ok, "3 rows".to_string()));
+
+        // The DDNS sheet lacks the minION-only columns
+        let check = check_sample_file(&full, "minION", Lang::En);
+        assert_eq!(check.level, SlotLevel::Warning);
+        assert!(check.message.starts_with("3 rows; "), "{}", check.message);
+        assert!(check.message.contains("institute"), "{}", check.message);
+
+        let barcodes = file(&mut dir, "barcodes.csv", b"Sample,barcode\nS01,barcode01\nS02,barcode02");
+        let check = check_sample_file(&barcodes, "DDNS", Lang::Fr);
+        assert_eq!(check, SlotCheck::new(SlotLevel::Ok, "Codes-barres uniquement, 2 lignes".to_string()));
+
+        let no_barcode = file(&mut dir, "names.csv", b"sample,EPID\nS01,x\n");
+        assert_eq!(check_sample_file(&no_barcode, "DDNS", Lang::En).message, "No barcode column");
+
+        let missing = dir.path().join("missing.csv").to_string_lossy().to_string();
+        assert_eq!(check_sample_file(&missing, "DDNS", Lang::En).level, SlotLevel::Error);
+    }
+
+    #[test]
+    fn row_counts_stop_at_the_read_budget() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let line = "S0001,barcode01\n";
+        let rows = (QUICK_READ_BYTES as usize / line.len()) + 100;
+        let text = "sample,barcode\n".to_string() + &line.repeat(rows);
+        let big = file(&mut dir, "big.csv", text.as_bytes());
+
+        let (counted, more) = count_rows(&big).unwrap();
+        assert!(more);
+        assert!(counted < rows);
+        assert!(check_sample_file(&big, "DDNS", Lang::En).message.starts_with("Barcodes only, at least "));
+
+        let small = file(&mut dir, "small.csv", b"sample,barcode\nS01,barcode01\n");
+        assert_eq!(count_rows(&small).unwrap(), (1, false));
+        let header_only = file(&mut dir, "header.csv", b"sample,barcode");
+        assert_eq!(count_rows(&header_only).unwrap(), (0, false));
+    }
+
+    #[test]
+    fn epiinfo_file_checks() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let export = file(&mut dir, "epi.csv", b"ICLabID,EpidNumber\nS01,x\nS02,y\n");
+        assert_eq!(check_epiinfo_file(&export, Lang::En), SlotCheck::new(SlotLevel::Ok, "2 rows".to_string()));
+
+        let empty = file(&mut dir, "empty.csv", b"ICLabID,EpidNumber\n");
+        assert_eq!(check_epiinfo_file(&empty, Lang::Fr), SlotCheck::new(SlotLevel::Warning, "Aucune ligne".to_string()));
+
+        let wrong = file(&mut dir, "wrong.csv", b"sample,barcode\nS01,barcode01\n");
+        assert_eq!(check_epiinfo_file(&wrong, Lang::En), SlotCheck::new(SlotLevel::Error, "No ICLabID column".to_string()));
+    }
+
+    #[test]
+    fn minknow_file_checks() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let html = file(&mut dir, "report.html", b"<html><script>const reportData={\"run_setup\": []};</script></html>");
+        assert_eq!(check_minknow_file(&html, Lang::En).level, SlotLevel::Ok);
+
+        let page = file(&mut dir, "page.html", b"<html><body>Not a report</body></html>");
+        assert_eq!(check_minknow_file(&page, Lang::En).level, SlotLevel::Error);
+
+        let json = file(&mut dir, "report.json", "\u{feff}  {\"run_setup\": []}".as_bytes());
+        assert_eq!(check_minknow_file(&json, Lang::En).level, SlotLevel::Ok);
+        // One report per position
+        let list = file(&mut dir, "positions.json", b"[{\"run_setup\": []}, {\"run_setup\": []}]");
+        assert_eq!(check_minknow_file(&list, Lang::En).level, SlotLevel::Ok);
+        let text = file(&mut dir, "notes.json", b"run notes");
+        assert_eq!(check_minknow_file(&text, Lang::En).level, SlotLevel::Error);
+
+        let pdf = file(&mut dir, "report.pdf", b"%PDF");
+        assert_eq!(check_minknow_file(&pdf, Lang::En).message, "Not an HTML or JSON report");
+    }
+
+    #[test]
+    fn report_marker_is_found_across_reads_and_only_within_the_limit() {
+        let mut dir = TempWorkspace::new().unwrap();
+        // The marker straddles the first 256 KiB read
+        let mut bytes = vec![b' '; 256 * 1024 - 5];
+        bytes.extend_from_slice(REPORT_DATA_MARKER);
+        bytes.extend_from_slice(b"{}");
+        let path = file(&mut dir, "report.html", &bytes);
+        assert!(contains_within(&path, REPORT_DATA_MARKER, REPORT_SCAN_BYTES).unwrap());
+        assert!(!contains_within(&path, REPORT_DATA_MARKER, 1024).unwrap());
+    }
+
+    #[test]
+    fn paths_and_slots() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let export = file(&mut dir, "epi.csv", b"ICLabID\nS01\n");
+        assert_eq!(check_path_exists(&export, Lang::En), None);
+        let folder = dir.path().to_string_lossy().to_string();
+        assert!(check_path_exists(&folder, Lang::En).unwrap().message.contains("is a folder"));
+        let missing = dir.path().join("gone.csv").to_string_lossy().to_string();
+        assert!(check_path_exists(&missing, Lang::En).unwrap().message.starts_with("File not found"));
+
+        assert_eq!(quick_check("epiinfo_file", &export, "DDNS", Lang::En).unwrap().level, SlotLevel::Ok);
+        assert_eq!(quick_check("destination", &folder, "DDNS", Lang::En), None);
+        assert_eq!(
+            [SlotLevel::Ok, SlotLevel::Warning, SlotLevel::Error].map(SlotLevel::ui_value),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn pasted_paths_are_checked_before_reading() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let existing = file(&mut dir, "samples.csv", b"sample,barcode\nS01,barcode01\n");
+
+        // A quoted copy of an existing file is fine once normalized
+        let pasted = crate::input::normalize_pasted_path(&format!("\"{}\"", existing));
+        assert_eq!(pasted, existing);
+        assert!(check_path_exists(&pasted, Lang::En).is_none());
+
+        let missing = dir.file("missing.csv").to_string_lossy().to_string();
+        let check = check_path_exists(&missing, Lang::En).unwrap();
+        assert_eq!(check.level, SlotLevel::Error);
+        assert_eq!(check.message, format!("File not found: {}", missing));
+        assert!(check_path_exists(&missing, Lang::Fr).unwrap().message.starts_with("Fichier introuvable"));
+
+        let folder = dir.path().to_string_lossy().to_string();
+        let check = check_path_exists(&folder, Lang::En).unwrap();
+        assert_eq!(check.level, SlotLevel::Error);
+        assert!(check.message.contains("is a folder"), "{}", check.message);
+    }
+}
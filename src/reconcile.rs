@@ -0,0 +1,291 @@
+use chrono::NaiveDate;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// EpiInfo records with no sequencing sample, written next to the output
+/// for environmental surveillance reconciliation. Reporting only: the main
+/// output never changes because of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnsampledEpiReport {
+    pub enabled: bool,
+    // EpiInfo column the date window applies to
+    pub date_column: String,
+    // YYYY-MM-DD, inclusive; empty = open-ended
+    pub from: String,
+    pub to: String,
+    // EpiInfo columns written, in this order; ICLabID is always first
+    pub columns: Vec<String>,
+}
+
+impl Default for UnsampledEpiReport {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            date_column: "DateStoolCollected".to_string(),
+            from: String::new(),
+            to: String::new(),
+            columns: ["EPID", "DateStoolCollected", "DateStoolReceivedinLab"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl UnsampledEpiReport {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.date_column.trim().is_empty() && !(self.from.trim().is_empty() && self.to.trim().is_empty()) {
+            return Err("a date column is needed for a date window".to_string());
+        }
+        for (name, value) in [("from", &self.from), ("to", &self.to)] {
+            if !value.trim().is_empty() && NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").is_err() {
+                return Err(format!("{} must be a YYYY-MM-DD date (found '{}')", name, value));
+            }
+        }
+        if let (Some(from), Some(to)) = (self.bound(&self.from), self.bound(&self.to)) {
+            if from > to {
+                return Err(format!("from ({}) is after to ({})", self.from, self.to));
+            }
+        }
+        Ok(())
+    }
+
+    fn bound(&self, value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+    }
+
+    fn has_window(&self) -> bool {
+        !self.from.trim().is_empty() || !self.to.trim().is_empty()
+    }
+
+    /// EpiInfo columns the report reads, so they are loaded with the export
+    pub fn needed_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone();
+        if !self.date_column.trim().is_empty() {
+            columns.push(self.date_column.trim().to_string());
+        }
+        columns
+    }
+}
+
+// EpiInfo exports write dates as ISO, day/month/year or with a month
+// name; a time after the date is ignored
+fn parse_epi_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    let date = value.split(['T', ' ']).next().unwrap_or(value);
+    ["%Y-%m-%d", "%d/%m/%Y", "%d-%b-%Y", "%d-%b-%y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%d %b %Y").ok())
+}
+
+/// The anti-join and what it left out
+pub struct UnsampledEpi {
+    pub df: DataFrame,
+    // Requested columns the export does not have
+    pub missing_columns: Vec<String>,
+    // Records without a readable date, left out when a window is set
+    pub undated: usize,
+}
+
+/// EpiInfo records whose ICLabID no sample was paired with, inside the
+/// report's date window, with ICLabID and the report's columns
+pub fn epi_without_samples(
+    epi: &DataFrame,
+    matched_ids: &HashSet<String>,
+    report: &UnsampledEpiReport,
+) -> Result<UnsampledEpi, String> {
+    let ids = epi
+        .column("ICLabID")
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(|e| format!("Failed to read the ICLabID column: {e}"))?;
+    let ids = ids
+        .str()
+        .map_err(|e| format!("The ICLabID column is not text: {e}"))?;
+
+    let window = report.has_window();
+    let dates: Option<StringChunked> = if window {
+        let column = epi
+            .column(report.date_column.trim())
+            .map_err(|_| format!("The EpiInfo file has no {} column for the date window.", report.date_column))?
+            .cast(&DataType::String)
+            .map_err(|e| format!("Failed to read the {} column: {e}", report.date_column))?;
+        Some(
+            column
+                .str()
+                .map_err(|e| format!("The {} column is not text: {e}", report.date_column))?
+                .clone(),
+        )
+    } else {
+        None
+    };
+    let (from, to) = (report.bound(&report.from), report.bound(&report.to));
+
+    let mut undated = 0;
+    let mut keep = Vec::with_capacity(epi.height());
+    for (row, id) in ids.into_iter().enumerate() {
+        let id = id.map(str::trim).unwrap_or("");
+        if id.is_empty() || matched_ids.contains(id) {
+            keep.push(false);
+            continue;
+        }
+        let in_window = match &dates {
+            None => true,
+            Some(dates) => match dates.get(row).and_then(parse_epi_date) {
+                Some(date) => from.is_none_or(|f| date >= f) && to.is_none_or(|t| date <= t),
+                None => {
+                    undated += 1;
+                    false
+                }
+            },
+        };
+        keep.push(in_window);
+    }
+
+    let mut columns = vec!["ICLabID".to_string()];
+    let mut missing_columns = Vec::new();
+    for column in &report.columns {
+        let column = column.trim();
+        if column.is_empty() || columns.iter().any(|c| c == column) {
+            continue;
+        }
+        if epi.column(column).is_ok() {
+            columns.push(column.to_string());
+        } else {
+            missing_columns.push(column.to_string());
+        }
+    }
+
+    let mask = BooleanChunked::from_slice(PlSmallStr::from_str("keep"), &keep);
+    let df = epi
+        .select(columns)
+        .and_then(|df| df.filter(&mask))
+        .map_err(|e| format!("Failed to select the EpiInfo records without samples: {e}"))?;
+    Ok(UnsampledEpi { df, missing_columns, undated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records of March 2024 and around it; ENV003 is the only one sequenced
+    fn epi() -> DataFrame {
+        df!(
+            "ICLabID" => ["ENV001", "ENV002", "ENV003", "ENV004", "ENV005", "ENV006", "", "ENV008"],
+            "EPID" => ["E-1", "E-2", "E-3", "E-4", "E-5", "E-6", "E-7", "E-8"],
+            "SiteName" => ["Kano", "Lagos", "Kano", "Abuja", "Lagos", "Kano", "Kano", "Abuja"],
+            "DateStoolCollected" => [
+                "2024-02-29",
+                "2024-03-01",
+                "2024-03-10",
+                "15/03/2024",
+                "31-Mar-2024 08:00",
+                "2024-04-01",
+                "2024-03-12",
+                "",
+            ]
+        )
+        .unwrap()
+    }
+
+    fn march(columns: &[&str]) -> UnsampledEpiReport {
+        UnsampledEpiReport {
+            enabled: true,
+            from: "2024-03-01".to_string(),
+            to: "2024-03-31".to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn matched() -> HashSet<String> {
+        ["ENV003".to_string()].into_iter().collect()
+    }
+
+    fn ids(df: &DataFrame) -> Vec<String> {
+        df.column("ICLabID")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.unwrap_or("").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unsampled_records_inside_the_window_are_kept() {
+        let epi = epi();
+        let result = epi_without_samples(&epi, &matched(), &march(&["EPID", "SiteName"])).unwrap();
+
+        // Both bounds included, every date format read; sampled, blank and
+        // out-of-window records left out
+        assert_eq!(ids(&result.df), ["ENV002", "ENV004", "ENV005"]);
+        assert_eq!(result.undated, 1);
+        assert!(result.missing_columns.is_empty());
+        let columns: Vec<&str> = result.df.get_column_names().iter().map(|c| c.as_str()).collect();
+        assert_eq!(columns, ["ICLabID", "EPID", "SiteName"]);
+        let sites: Vec<Option<&str>> = result.df.column("SiteName").unwrap().str().unwrap().into_iter().collect();
+        assert_eq!(sites, [Some("Lagos"), Some("Abuja"), Some("Lagos")]);
+
+        // The EpiInfo frame the merge uses is left as it was
+        assert!(epi.equals_missing(&self::epi()));
+    }
+
+    #[test]
+    fn open_ended_and_missing_windows() {
+        let epi = epi();
+
+        let mut from_only = march(&["EPID"]);
+        from_only.to.clear();
+        let result = epi_without_samples(&epi, &matched(), &from_only).unwrap();
+        assert_eq!(ids(&result.df), ["ENV002", "ENV004", "ENV005", "ENV006"]);
+
+        // Without a window every unsampled record counts, dated or not
+        let mut no_window = march(&["EPID"]);
+        no_window.from.clear();
+        no_window.to.clear();
+        let result = epi_without_samples(&epi, &matched(), &no_window).unwrap();
+        assert_eq!(ids(&result.df), ["ENV001", "ENV002", "ENV004", "ENV005", "ENV006", "ENV008"]);
+        assert_eq!(result.undated, 0);
+
+        // Everything sequenced: an empty report
+        let all: HashSet<String> = ids(&epi).into_iter().collect();
+        assert_eq!(epi_without_samples(&epi, &all, &march(&["EPID"])).unwrap().df.height(), 0);
+    }
+
+    #[test]
+    fn requested_columns_the_export_lacks_are_reported() {
+        let result =
+            epi_without_samples(&epi(), &matched(), &march(&["ICLabID", "EPID", "Region", " EPID ", ""])).unwrap();
+        let columns: Vec<&str> = result.df.get_column_names().iter().map(|c| c.as_str()).collect();
+        assert_eq!(columns, ["ICLabID", "EPID"]);
+        assert_eq!(result.missing_columns, ["Region"]);
+
+        let mut other_date = march(&["EPID"]);
+        other_date.date_column = "DateReceived".to_string();
+        let err = epi_without_samples(&epi(), &matched(), &other_date).err().unwrap();
+        assert!(err.contains("DateReceived"), "{err}");
+    }
+
+    #[test]
+    fn report_settings_are_validated() {
+        assert!(UnsampledEpiReport::default().validate().is_ok());
+        assert!(march(&[]).validate().is_ok());
+
+        let mut reversed = march(&[]);
+        std::mem::swap(&mut reversed.from, &mut reversed.to);
+        assert!(reversed.validate().is_err());
+
+        let mut bad_date = march(&[]);
+        bad_date.to = "31/03/2024".to_string();
+        assert!(bad_date.validate().unwrap_err().contains("to"));
+
+        let mut no_column = march(&[]);
+        no_column.date_column.clear();
+        assert!(no_column.validate().is_err());
+
+        assert_eq!(march(&["EPID"]).needed_columns(), ["EPID", "DateStoolCollected"]);
+    }
+}
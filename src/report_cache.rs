@@ -0,0 +1,138 @@
+use std::time::SystemTime;
+
+use crate::clock::DateZone;
+use crate::minknow::{parse_minknow_report, MinKnowData};
+
+/// Identifies one version of a MinKNOW report. Only file metadata is read:
+/// the point is not to touch the (often multi-megabyte, often remote)
+/// report again when it has not changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReportKey {
+    path: String,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl ReportKey {
+    fn for_file(path: &str) -> Result<Self, String> {
+        let meta = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read file info for '{}': {e}", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+}
+
+/// Session cache of the last parsed MinKNOW report, shared by merge and
+/// "Extract MinKNOW values". Merge is often clicked several times for the
+/// same run while validation errors are fixed.
+#[derive(Default)]
+pub struct ReportCache {
+    entry: Option<(ReportKey, Vec<MinKnowData>)>,
+}
+
+impl ReportCache {
+    /// Same contract as `parse_minknow_report`, plus whether the cache was
+    /// used. A different path, size or mtime invalidates the entry. The
+    /// sequencing dates are given in `zone`.
+    pub fn read(&mut self, path: &str, zone: DateZone) -> Result<(Vec<MinKnowData>, bool), String> {
+        let key = ReportKey::for_file(path)?;
+
+        if let Some((cached_key, runs)) = &self.entry {
+            if *cached_key == key {
+                return Ok((in_zone(runs.clone(), zone), true));
+            }
+        }
+
+        // A failed parse must not leave the previous report answering for this path
+        self.entry = None;
+        let runs = parse_minknow_report(path)?;
+        self.entry = Some((key, runs.clone()));
+        Ok((in_zone(runs, zone), false))
+    }
+}
+
+fn in_zone(mut runs: Vec<MinKnowData>, zone: DateZone) -> Vec<MinKnowData> {
+    for data in &mut runs {
+        data.set_date_zone(zone);
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use std::time::Duration;
+
+    const REPORT: &str = r#"{
+        "software_versions": [{"title": "MinKNOW", "value": "24.02.8"}],
+        "run_setup": [{"title": "Flow cell ID", "value": "FAX12345"}],
+        "run_end_time": "2024-03-05T12:00:00Z"
+    }"#;
+
+    fn report(dir: &mut TempWorkspace, name: &str, text: &str) -> String {
+        let path = dir.file(name);
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn second_read_of_the_same_report_skips_the_parse() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = report(&mut dir, "report.json", REPORT);
+        let mut cache = ReportCache::default();
+
+        let (first, cached) = cache.read(&path, DateZone::Utc).unwrap();
+        assert!(!cached);
+        let (second, cached) = cache.read(&path, DateZone::Utc).unwrap();
+        assert!(cached);
+        assert_eq!(second[0].fc_id, first[0].fc_id);
+        assert_eq!(second[0].seq_date, "2024-03-05");
+
+        // The zone is applied to the cached runs too
+        let (local, cached) = cache.read(&path, DateZone::Local).unwrap();
+        assert!(cached);
+        assert_eq!(local[0].run_end_time, "2024-03-05T12:00:00Z");
+    }
+
+    #[test]
+    fn touching_or_replacing_the_report_invalidates_the_cache() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = report(&mut dir, "report.json", REPORT);
+        let mut cache = ReportCache::default();
+        cache.read(&path, DateZone::Utc).unwrap();
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        drop(file);
+        assert!(!cache.read(&path, DateZone::Utc).unwrap().1);
+
+        std::fs::write(&path, REPORT.replace("FAX12345", "FAX67890")).unwrap();
+        let (runs, cached) = cache.read(&path, DateZone::Utc).unwrap();
+        assert!(!cached);
+        assert_eq!(runs[0].fc_id, "FAX67890");
+
+        // Another report is another entry
+        let other = report(&mut dir, "other.json", REPORT);
+        assert!(!cache.read(&other, DateZone::Utc).unwrap().1);
+        assert!(!cache.read(&path, DateZone::Utc).unwrap().1);
+    }
+
+    #[test]
+    fn a_failed_parse_clears_the_entry() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = report(&mut dir, "report.json", REPORT);
+        let mut cache = ReportCache::default();
+        cache.read(&path, DateZone::Utc).unwrap();
+
+        std::fs::write(&path, "{\"run_setup\": [").unwrap();
+        assert!(cache.read(&path, DateZone::Utc).is_err());
+        assert!(cache.entry.is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(cache.read(&path, DateZone::Utc).unwrap_err().starts_with("Failed to read file info"));
+    }
+}
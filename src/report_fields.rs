@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::minknow::MinKnowData;
+
+/// Run detail fields that both the MinKNOW report and the user can fill
+pub const REPORT_FIELDS: &[&str] = &["minknow_ver", "fc_id", "fc_pores", "seq_kit", "seq_hours", "seq_date"];
+
+pub fn report_value<'a>(data: &'a MinKnowData, field: &str) -> Option<&'a str> {
+    match field {
+        "minknow_ver" => Some(&data.minknow_ver),
+        "fc_id" => Some(&data.fc_id),
+        "fc_pores" => Some(&data.fc_pores),
+        "seq_kit" => Some(&data.seq_kit),
+        "seq_hours" => Some(&data.seq_hours),
+        "seq_date" => Some(&data.seq_date),
+        _ => None,
+    }
+}
+
+fn report_value_mut<'a>(data: &'a mut MinKnowData, field: &str) -> Option<&'a mut String> {
+    match field {
+        "minknow_ver" => Some(&mut data.minknow_ver),
+        "fc_id" => Some(&mut data.fc_id),
+        "fc_pores" => Some(&mut data.fc_pores),
+        "seq_kit" => Some(&mut data.seq_kit),
+        "seq_hours" => Some(&mut data.seq_hours),
+        "seq_date" => Some(&mut data.seq_date),
+        _ => None,
+    }
+}
+
+/// Which report fields the user typed into, and report values held back
+/// because they differ from what was typed. A field the user left alone
+/// (or emptied) is filled from the report; a typed one keeps its text and
+/// gets a "report says X" hint instead.
+#[derive(Debug, Default)]
+pub struct ReportFields {
+    touched: HashSet<String>,
+    // field -> report value not applied
+    hints: BTreeMap<String, String>,
+}
+
+impl ReportFields {
+    /// The user changed `field` to `text`
+    pub fn edited(&mut self, field: &str, text: &str) {
+        if text.trim().is_empty() {
+            self.touched.remove(field);
+        } else {
+            self.touched.insert(field.to_string());
+        }
+    }
+
+    /// Lets the report fill `field` again, e.g. once the user chose the
+    /// report's value in a conflict
+    pub fn forget(&mut self, field: &str) {
+        self.touched.remove(field);
+        self.hints.remove(field);
+    }
+
+    pub fn is_touched(&self, field: &str) -> bool {
+        self.touched.contains(field)
+    }
+
+    pub fn hint(&self, field: &str) -> &str {
+        self.hints.get(field).map(String::as_str).unwrap_or("")
+    }
+
+    /// Sorts the report's values into fields to fill and hints, given
+    /// each field's current text. Returns (field, value) to write.
+    pub fn stage(&mut self, data: &MinKnowData, current: impl Fn(&str) -> String) -> Vec<(&'static str, String)> {
+        self.hints.clear();
+        let mut fill = Vec::new();
+        for &field in REPORT_FIELDS {
+            let value = report_value(data, field).unwrap_or("").trim().to_string();
+            let typed = current(field).trim().to_string();
+            if !self.is_touched(field) || typed.is_empty() {
+                fill.push((field, value));
+            } else if !value.is_empty() && value != typed {
+                self.hints.insert(field.to_string(), value);
+            }
+        }
+        fill
+    }
+
+    /// Takes the held-back report value of `field`; the field is no longer
+    /// the user's once the report's value is applied
+    pub fn apply(&mut self, field: &str) -> Option<String> {
+        let value = self.hints.remove(field)?;
+        self.touched.remove(field);
+        Some(value)
+    }
+
+    /// Puts the user's text into `data` for every field with a hint, so a
+    /// correction typed before the report was read is the one used
+    pub fn keep_edits(&self, data: &mut MinKnowData, current: impl Fn(&str) -> String) {
+        for field in self.hints.keys() {
+            if let Some(value) = report_value_mut(data, field) {
+                *value = current(field).trim().to_string();
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.touched.clear();
+        self.hints.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn report() -> MinKnowData {
+        MinKnowData {
+            minknow_ver: "24.02.8".to_string(),
+            fc_id: "FAX12345".to_string(),
+            fc_pores: "1432".to_string(),
+            seq_kit: "SQK-RBK114.96".to_string(),
+            seq_hours: "72".to_string(),
+            seq_date: "2024-03-05".to_string(),
+            ..MinKnowData::default()
+        }
+    }
+
+    // The run detail fields of the window, as the user left them
+    struct Form {
+        text: HashMap<String, String>,
+        fields: ReportFields,
+    }
+
+    impl Form {
+        fn new() -> Self {
+            Self { text: HashMap::new(), fields: ReportFields::default() }
+        }
+
+        fn type_in(&mut self, field: &str, text: &str) {
+            self.text.insert(field.to_string(), text.to_string());
+            self.fields.edited(field, text);
+        }
+
+        fn read_report(&mut self, data: &MinKnowData) {
+            let text = self.text.clone();
+            let fill = self.fields.stage(data, |f| text.get(f).cloned().unwrap_or_default());
+            for (field, value) in fill {
+                self.text.insert(field.to_string(), value);
+            }
+        }
+
+        fn get(&self, field: &str) -> &str {
+            self.text.get(field).map(String::as_str).unwrap_or("")
+        }
+    }
+
+    #[test]
+    fn untouched_fields_are_filled_from_the_report() {
+        let mut form = Form::new();
+        form.read_report(&report());
+        for &field in REPORT_FIELDS {
+            assert_eq!(form.get(field), report_value(&report(), field).unwrap(), "{field}");
+            assert_eq!(form.fields.hint(field), "");
+        }
+
+        // A second report replaces values the user never touched
+        let other = MinKnowData { fc_id: "FAY99999".to_string(), ..report() };
+        form.read_report(&other);
+        assert_eq!(form.get("fc_id"), "FAY99999");
+    }
+
+    #[test]
+    fn typed_values_are_kept_with_a_hint() {
+        let mut form = Form::new();
+        form.type_in("seq_kit", "SQK-NBD114.96");
+        form.type_in("fc_id", " FAX12345 ");
+        form.read_report(&report());
+
+        assert_eq!(form.get("seq_kit"), "SQK-NBD114.96");
+        assert_eq!(form.fields.hint("seq_kit"), "SQK-RBK114.96");
+        // The same value typed by hand needs no hint
+        assert_eq!(form.get("fc_id"), " FAX12345 ");
+        assert_eq!(form.fields.hint("fc_id"), "");
+        assert_eq!(form.get("fc_pores"), "1432");
+
+        // The merge uses what the user typed
+        let mut data = report();
+        let text = form.text.clone();
+        form.fields.keep_edits(&mut data, |f| text.get(f).cloned().unwrap_or_default());
+        assert_eq!(data.seq_kit, "SQK-NBD114.96");
+        assert_eq!(data.fc_pores, "1432");
+    }
+
+    #[test]
+    fn emptied_fields_belong_to_the_report_again() {
+        let mut form = Form::new();
+        form.type_in("seq_hours", "48");
+        form.type_in("seq_hours", "  ");
+        assert!(!form.fields.is_touched("seq_hours"));
+        form.read_report(&report());
+        assert_eq!(form.get("seq_hours"), "72");
+
+        // A report without a value leaves a typed field and its hint alone
+        form.type_in("seq_date", "2024-03-06");
+        form.read_report(&MinKnowData { seq_date: String::new(), ..report() });
+        assert_eq!(form.get("seq_date"), "2024-03-06");
+        assert_eq!(form.fields.hint("seq_date"), "");
+    }
+
+    #[test]
+    fn applying_a_hint_hands_the_field_back_to_the_report() {
+        let mut form = Form::new();
+        form.type_in("seq_kit", "SQK-NBD114.96");
+        form.read_report(&report());
+
+        let value = form.fields.apply("seq_kit").unwrap();
+        form.text.insert("seq_kit".to_string(), value);
+        assert_eq!(form.get("seq_kit"), "SQK-RBK114.96");
+        assert_eq!(form.fields.hint("seq_kit"), "");
+        assert!(!form.fields.is_touched("seq_kit"));
+        assert!(form.fields.apply("seq_kit").is_none());
+
+        // The next report fills it like any untouched field
+        form.read_report(&MinKnowData { seq_kit: "SQK-LSK114".to_string(), ..report() });
+        assert_eq!(form.get("seq_kit"), "SQK-LSK114");
+    }
+
+    #[test]
+    fn forget_and_reset_clear_the_state() {
+        let mut form = Form::new();
+        form.type_in("seq_kit", "SQK-NBD114.96");
+        form.type_in("fc_pores", "1200");
+        form.read_report(&report());
+
+        form.fields.forget("seq_kit");
+        assert!(!form.fields.is_touched("seq_kit"));
+        assert_eq!(form.fields.hint("seq_kit"), "");
+        assert_eq!(form.fields.hint("fc_pores"), "1432");
+
+        form.fields.reset();
+        assert!(!form.fields.is_touched("fc_pores"));
+        assert_eq!(form.fields.hint("fc_pores"), "");
+        assert_eq!(report_value(&report(), "lab"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+
+use crate::compliance::{compare_headers, match_confidence, Deviation};
+
+// Pairings at least this sure start out accepted; the reviewer can still skip them
+pub const ACCEPTED_BY_DEFAULT: u8 = 70;
+
+// Values of the file's column shown next to each pairing
+const PREVIEW_VALUES: usize = 3;
+
+/// A template column and the column of the file that is probably it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pairing {
+    pub expected: String,
+    pub actual: String,
+    // 0-100; 100 for a difference in case only
+    pub confidence: u8,
+    // First non-empty values of `actual`
+    pub preview: Vec<String>,
+}
+
+impl Pairing {
+    pub fn accepted_by_default(&self) -> bool {
+        self.confidence >= ACCEPTED_BY_DEFAULT
+    }
+}
+
+// First non-empty values of a column, as text
+fn preview_values(df: &DataFrame, column: &str) -> Vec<String> {
+    let Ok(values) = df.column(column).and_then(|c| c.cast(&DataType::String)) else {
+        return Vec::new();
+    };
+    let Ok(values) = values.str() else {
+        return Vec::new();
+    };
+    values
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .take(PREVIEW_VALUES)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Columns of `df` that look like a template column they are not named
+/// as: case differences and near matches, most certain first
+pub fn pairings(df: &DataFrame, expected: &[&str]) -> Vec<Pairing> {
+    let header: Vec<String> = df.get_column_names().iter().map(|c| c.to_string()).collect();
+    let mut pairings: Vec<Pairing> = compare_headers(expected, &header)
+        .into_iter()
+        .filter_map(|deviation| match deviation {
+            Deviation::CaseMismatch { expected, actual } => Some((expected, actual, 100)),
+            Deviation::NearMatch { expected, actual } => {
+                let confidence = match_confidence(&expected, &actual);
+                Some((expected, actual, confidence))
+            }
+            _ => None,
+        })
+        .map(|(expected, actual, confidence)| Pairing {
+            preview: preview_values(df, &actual),
+            expected,
+            actual,
+            confidence,
+        })
+        .collect();
+    pairings.sort_by_key(|p| std::cmp::Reverse(p.confidence));
+    pairings
+}
+
+/// (column in the file, template name) for every accepted pairing.
+/// `decisions` holds the reviewer's accept/skip by file column; pairings
+/// without one keep their default.
+pub fn rename_plan(pairings: &[Pairing], decisions: &HashMap<String, bool>) -> Vec<(String, String)> {
+    pairings
+        .iter()
+        .filter(|p| decisions.get(&p.actual).copied().unwrap_or_else(|| p.accepted_by_default()))
+        .map(|p| (p.actual.clone(), p.expected.clone()))
+        .collect()
+}
+
+/// Renames the columns of the plan. A rename is left out when its column
+/// is gone or its target name is already taken. Returns the renames made.
+pub fn apply_plan(df: &mut DataFrame, plan: &[(String, String)]) -> Result<Vec<(String, String)>, String> {
+    let mut applied = Vec::new();
+    for (from, to) in plan {
+        let has = |name: &str| df.get_column_names().iter().any(|c| c.as_str() == name);
+        if !has(from) || has(to) {
+            continue;
+        }
+        df.rename(from, PlSmallStr::from_str(to))
+            .map_err(|e| format!("Failed to rename column '{}' to '{}': {e}", from, to))?;
+        applied.push((from.clone(), to.clone()));
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &[&str] = &["sample", "barcode", "DateOfOnset", "Province"];
+
+    // "Sample" differs in case only, "DateOfOnst" is misspelt, "Notes" is
+    // unrelated and Province is missing
+    fn samples() -> DataFrame {
+        df!(
+            "Sample" => ["S01", "S02", "S03", "S04"],
+            "barcode" => ["barcode01", "barcode02", "barcode03", "barcode04"],
+            "DateOfOnst" => [None, Some(" 2024-02-01 "), Some(""), Some("2024-02-03")],
+            "Notes" => ["a", "b", "c", "d"]
+        )
+        .unwrap()
+    }
+
+    fn names(df: &DataFrame) -> Vec<String> {
+        df.get_column_names().iter().map(|c| c.to_string()).collect()
+    }
+
+    fn pairing(expected: &str, actual: &str, confidence: u8) -> Pairing {
+        Pairing { expected: expected.to_string(), actual: actual.to_string(), confidence, preview: Vec::new() }
+    }
+
+    fn plan(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect()
+    }
+
+    #[test]
+    fn pairings_are_scored_and_previewed() {
+        let found = pairings(&samples(), TEMPLATE);
+        let pairs: Vec<(&str, &str)> = found.iter().map(|p| (p.expected.as_str(), p.actual.as_str())).collect();
+        assert_eq!(pairs, [("sample", "Sample"), ("DateOfOnset", "DateOfOnst")]);
+
+        // A case difference is certain; a misspelling less so, most certain first
+        assert_eq!(found[0].confidence, 100);
+        assert!(found[1].confidence > 50 && found[1].confidence < 100, "{}", found[1].confidence);
+        assert!(found[0].accepted_by_default());
+
+        // Up to three values, empty cells skipped
+        assert_eq!(found[0].preview, ["S01", "S02", "S03"]);
+        assert_eq!(found[1].preview, ["2024-02-01", "2024-02-03"]);
+
+        assert!(pairings(&samples().select(["barcode", "Notes"]).unwrap(), TEMPLATE).is_empty());
+    }
+
+    #[test]
+    fn decisions_override_the_defaults() {
+        let pairings = [pairing("sample", "Sample", 100), pairing("Province", "Provnce", ACCEPTED_BY_DEFAULT - 1)];
+        assert_eq!(rename_plan(&pairings, &HashMap::new()), plan(&[("Sample", "sample")]));
+
+        let decisions = HashMap::from([("Sample".to_string(), false), ("Provnce".to_string(), true)]);
+        assert_eq!(rename_plan(&pairings, &decisions), plan(&[("Provnce", "Province")]));
+
+        // Decisions for columns without a pairing change nothing
+        let decisions = HashMap::from([("Notes".to_string(), true)]);
+        assert_eq!(rename_plan(&pairings, &decisions), plan(&[("Sample", "sample")]));
+
+        let all_skipped = HashMap::from([("Sample".to_string(), false)]);
+        assert!(rename_plan(&pairings[..1], &all_skipped).is_empty());
+    }
+
+    #[test]
+    fn the_plan_renames_in_place() {
+        let mut df = samples();
+        let applied = apply_plan(&mut df, &plan(&[("Sample", "sample"), ("DateOfOnst", "DateOfOnset")])).unwrap();
+        assert_eq!(applied, plan(&[("Sample", "sample"), ("DateOfOnst", "DateOfOnset")]));
+        assert_eq!(names(&df), ["sample", "barcode", "DateOfOnset", "Notes"]);
+        let values: Vec<Option<&str>> = df.column("sample").unwrap().str().unwrap().into_iter().collect();
+        assert_eq!(values, [Some("S01"), Some("S02"), Some("S03"), Some("S04")]);
+
+        // Nothing is left to resolve
+        assert!(pairings(&df, TEMPLATE).is_empty());
+    }
+
+    #[test]
+    fn renames_that_cannot_apply_are_left_out() {
+        let mut df = samples();
+        // "Gone" is not in the file, and "barcode" is taken
+        let applied =
+            apply_plan(&mut df, &plan(&[("Gone", "Province"), ("Notes", "barcode"), ("Sample", "sample")])).unwrap();
+        assert_eq!(applied, plan(&[("Sample", "sample")]));
+        assert_eq!(names(&df), ["sample", "barcode", "DateOfOnst", "Notes"]);
+
+        // Applying the same plan twice is harmless
+        assert!(apply_plan(&mut df, &plan(&[("Sample", "sample")])).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::merge::{validate_date, validate_run_number};
+use crate::minknow::MinKnowData;
+
+/// Result of a PCR control check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ControlStatus {
+    Pass,
+    Fail,
+    // Nobody filled it in; the template expects a result
+    #[default]
+    NotRecorded,
+    // The run had no such control
+    NotApplicable,
+}
+
+impl ControlStatus {
+    /// Maps the UI combo values ("Positive Passed", "Negative Failed", "Not applicable", ...)
+    pub fn from_ui(selection: &str) -> Self {
+        match selection {
+            "Positive Passed" | "Negative Passed" => ControlStatus::Pass,
+            "Positive Failed" | "Negative Failed" => ControlStatus::Fail,
+            "Not applicable" => ControlStatus::NotApplicable,
+            _ => ControlStatus::NotRecorded,
+        }
+    }
+
+    /// Token written to the output, per the template spec.
+    /// NotRecorded is empty and leaves the cell alone.
+    pub fn output_value(&self) -> &'static str {
+        match self {
+            ControlStatus::Pass => "Pass",
+            ControlStatus::Fail => "Fail",
+            ControlStatus::NotRecorded => "",
+            ControlStatus::NotApplicable => "NA",
+        }
+    }
+
+    /// Inverse of `from_ui` for the "Positive" or "Negative" combo
+    pub fn ui_label(&self, control: &str) -> String {
+        match self {
+            ControlStatus::Pass => format!("{} Passed", control),
+            ControlStatus::Fail => format!("{} Failed", control),
+            ControlStatus::NotRecorded => "Unselected".to_string(),
+            ControlStatus::NotApplicable => "Not applicable".to_string(),
+        }
+    }
+}
+
+/// How a run constant is written into its column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillPolicy {
+    // Only cells that are empty get the value
+    #[default]
+    FillEmpty,
+    // Every cell gets the value, replacing per-sample data
+    Overwrite,
+    // The column is left as it is
+    Skip,
+}
+
+/// Per-column fill policies; columns without a rule use `default`
+#[derive(Debug, Clone, Default)]
+pub struct FillPolicies {
+    pub default: FillPolicy,
+    pub columns: HashMap<String, FillPolicy>,
+}
+
+impl FillPolicies {
+    /// The "Overwrite existing values" checkbox alone: fill empty cells
+    /// when ticked, leave every column alone otherwise
+    pub fn from_checkbox(fill: bool) -> Self {
+        Self {
+            default: if fill { FillPolicy::FillEmpty } else { FillPolicy::Skip },
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn for_column(&self, column: &str) -> FillPolicy {
+        self.columns.get(column).copied().unwrap_or(self.default)
+    }
+
+    pub fn has_overwrite(&self) -> bool {
+        self.default == FillPolicy::Overwrite
+            || self.columns.values().any(|p| *p == FillPolicy::Overwrite)
+    }
+}
+
+/// What goes into empty DateReported cells. Pre-filled dates are never
+/// replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DateReportedPolicy {
+    #[default]
+    LeaveEmpty,
+    // The day the output is written
+    Today,
+    // YYYY-MM-DD entered by the user
+    Fixed(String),
+}
+
+impl DateReportedPolicy {
+    pub fn from_ui(choice: &str, date: &str) -> Self {
+        match choice {
+            "Today" => DateReportedPolicy::Today,
+            "Date" => DateReportedPolicy::Fixed(date.trim().to_string()),
+            _ => DateReportedPolicy::LeaveEmpty,
+        }
+    }
+
+    pub fn ui_label(&self) -> &'static str {
+        match self {
+            DateReportedPolicy::LeaveEmpty => "Leave empty",
+            DateReportedPolicy::Today => "Today",
+            DateReportedPolicy::Fixed(_) => "Date",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            DateReportedPolicy::Fixed(date) if date.is_empty() => {
+                Err("Enter the date of reporting (YYYY-MM-DD) or choose another DateReported option.".to_string())
+            }
+            DateReportedPolicy::Fixed(date) => match validate_date(date, "DateReported") {
+                Some(e) => Err(e),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// The date to fill in, if any; `today` is YYYY-MM-DD
+    pub fn date(&self, today: &str) -> Option<String> {
+        match self {
+            DateReportedPolicy::LeaveEmpty => None,
+            DateReportedPolicy::Today => Some(today.to_string()),
+            DateReportedPolicy::Fixed(date) => Some(date.clone()).filter(|d| !d.is_empty()),
+        }
+    }
+}
+
+/// Values that are the same for every sample of a run.
+/// Built from the UI by the GUI, or deserialized from JSON by scripts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConstants {
+    pub run_num: String,
+    pub lab: String,
+    pub pir_ver: String,
+    pub rt_date: String,
+    pub fasta_date: String,
+    pub fc_uses: String,
+    pub pos_con: ControlStatus,
+    pub neg_con: ControlStatus,
+    // From the MinKNOW report (or typed in when there is none)
+    pub minknow_ver: Option<String>,
+    pub seq_date: Option<String>,
+    pub fc_id: Option<String>,
+    pub fc_pores: Option<String>,
+    pub seq_hours: Option<String>,
+    pub seq_kit: Option<String>,
+    // DDNS-specific
+    pub vp1_date: String,
+    pub pcr_machine: String,
+    pub vp1_pcr_machine: String,
+    pub rtpcr_primers: String,
+    pub vp1_primers: String,
+}
+
+impl RunConstants {
+    /// Copies the MinKNOW-derived values in
+    pub fn with_minknow(mut self, data: Option<&MinKnowData>) -> Self {
+        self.minknow_ver = data.map(|d| d.minknow_ver.clone());
+        self.seq_date = data.map(|d| d.seq_date.clone());
+        self.fc_id = data.map(|d| d.fc_id.clone());
+        self.fc_pores = data.map(|d| d.fc_pores.clone());
+        self.seq_hours = data.map(|d| d.seq_hours.clone());
+        self.seq_kit = data.map(|d| d.seq_kit.clone());
+        self
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Invalid run constants JSON: {e}"))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read run constants '{}': {e}", path))?;
+        Self::from_json(&text)
+    }
+
+    /// Controls left as NotRecorded ("Positive" / "Negative")
+    pub fn unrecorded_controls(&self) -> Vec<String> {
+        [("Positive", self.pos_con), ("Negative", self.neg_con)]
+            .iter()
+            .filter(|(_, status)| *status == ControlStatus::NotRecorded)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// Format checks on the run number and dates
+    pub fn validate(&self, mode: &str) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        // Validate run number
+        if let Some(err) = validate_run_number(&self.run_num) {
+            errors.push(err);
+        }
+
+        // Validate dates
+        let mut date_fields: Vec<(&str, &str)> = vec![
+            (&self.rt_date, "RT PCR Date"),
+            (&self.fasta_date, "Fasta Generation Date"),
+        ];
+
+        // Add optional seq_date if present
+        if let Some(ref seq_date) = self.seq_date {
+            date_fields.push((seq_date.as_str(), "Sequencing Date"));
+        }
+
+        // Add DDNS-specific dates
+        if mode != "minION" {
+            date_fields.push((&self.vp1_date, "VP1 PCR Date"));
+        }
+
+        for (val, name) in date_fields {
+            if let Some(err) = validate_date(val, name) {
+                errors.push(err);
+            }
+        }
+
+        if !errors.is_empty() {
+            errors.push(String::from("Expected yyyy-mm-dd."));
+            Err(format!(
+                "{}\n\nRefer to the Guide for more information.",
+                errors.join("\n\n")
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constants() -> RunConstants {
+        RunConstants {
+            run_num: "20240305_001".to_string(),
+            lab: "INRB".to_string(),
+            rt_date: "2024-03-01".to_string(),
+            fasta_date: "2024-03-06".to_string(),
+            fc_uses: "2".to_string(),
+            pos_con: ControlStatus::Pass,
+            neg_con: ControlStatus::NotApplicable,
+            vp1_date: "2024-03-02".to_string(),
+            rtpcr_primers: "PanEV".to_string(),
+            ..RunConstants::default()
+        }
+        .with_minknow(Some(&MinKnowData {
+            minknow_ver: "24.02.8".to_string(),
+            fc_id: "FAX12345".to_string(),
+            seq_date: "2024-03-05".to_string(),
+            ..MinKnowData::default()
+        }))
+    }
+
+    #[test]
+    fn run_constants_round_trip_through_json() {
+        let params = constants();
+        let text = serde_json::to_string(&params).unwrap();
+        assert_eq!(RunConstants::from_json(&text).unwrap(), params);
+        assert_eq!(params.fc_id.as_deref(), Some("FAX12345"));
+
+        // Scripts only give what they know; the rest is defaulted
+        let partial = RunConstants::from_json(r#"{"run_num": "20240305_001", "pos_con": "Fail"}"#).unwrap();
+        assert_eq!(partial.pos_con, ControlStatus::Fail);
+        assert_eq!(partial.neg_con, ControlStatus::NotRecorded);
+        assert_eq!(partial.minknow_ver, None);
+
+        let err = RunConstants::from_json(r#"{"pos_con": "Passed"}"#).unwrap_err();
+        assert!(err.starts_with("Invalid run constants JSON"), "{}", err);
+    }
+
+    #[test]
+    fn control_combo_values_map_to_the_enum_and_back() {
+        let cases = [
+            ("Positive Passed", "Positive", ControlStatus::Pass, "Pass"),
+            ("Negative Passed", "Negative", ControlStatus::Pass, "Pass"),
+            ("Positive Failed", "Positive", ControlStatus::Fail, "Fail"),
+            ("Negative Failed", "Negative", ControlStatus::Fail, "Fail"),
+            ("Not applicable", "Negative", ControlStatus::NotApplicable, "NA"),
+            ("Unselected", "Positive", ControlStatus::NotRecorded, ""),
+        ];
+        for (label, control, status, token) in cases {
+            assert_eq!(ControlStatus::from_ui(label), status, "{}", label);
+            assert_eq!(status.ui_label(control), label);
+            assert_eq!(status.output_value(), token);
+        }
+        assert_eq!(ControlStatus::from_ui(""), ControlStatus::NotRecorded);
+    }
+
+    #[test]
+    fn validation_reports_every_bad_field() {
+        assert_eq!(constants().validate("DDNS"), Ok(()));
+
+        let params = RunConstants {
+            run_num: "RUN-1".to_string(),
+            vp1_date: "02/03/2024".to_string(),
+            seq_date: Some("5 March".to_string()),
+            ..constants()
+        };
+        let err = params.validate("DDNS").unwrap_err();
+        assert!(err.contains("Invalid run number format: RUN-1"), "{}", err);
+        assert!(err.contains("Invalid date format for field VP1 PCR Date: 02/03/2024"), "{}", err);
+        assert!(err.contains("Invalid date format for field Sequencing Date: 5 March"), "{}", err);
+
+        // minION has no VP1 PCR
+        let err = params.validate("minION").unwrap_err();
+        assert!(!err.contains("VP1 PCR Date"), "{}", err);
+    }
+
+    #[test]
+    fn unrecorded_controls_are_listed() {
+        assert!(constants().unrecorded_controls().is_empty());
+        assert_eq!(RunConstants::default().unrecorded_controls(), ["Positive", "Negative"]);
+    }
+}
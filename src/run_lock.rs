@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::naming::sanitize_file_component;
+use crate::warnings::Lang;
+
+// A lock older than this is assumed left behind by a crashed merge
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Who holds a run lock, as written into the lock file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LockOwner {
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub computer: String,
+    #[serde(default)]
+    pub pid: u32,
+    // YYYY-MM-DD HH:MM:SS
+    #[serde(default)]
+    pub started: String,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        let env = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|n| std::env::var(n).ok().filter(|v| !v.trim().is_empty()))
+                .unwrap_or_else(|| "unknown".to_string())
+        };
+        Self {
+            user: env(&["USERNAME", "USER"]),
+            computer: env(&["COMPUTERNAME", "HOSTNAME"]),
+            pid: std::process::id(),
+            started: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+
+    /// "jdoe on LAB-PC-2 since 2026-03-01 10:15:02"
+    pub fn describe(&self) -> String {
+        format!("{} on {} since {}", self.user, self.computer, self.started)
+    }
+}
+
+/// What the user is told when another merge holds the lock for `run`
+pub fn held_message(run: &str, owner: &LockOwner, lang: Lang) -> String {
+    match lang {
+        Lang::En => format!(
+            "Run {} is already being merged into this folder ({}).\n\nWait for that merge to finish, then try again.",
+            run,
+            owner.describe()
+        ),
+        Lang::Fr => format!(
+            "Le run {} est déjà en cours de fusion dans ce dossier ({}).\n\nAttendez la fin de cette fusion, puis réessayez.",
+            run,
+            owner.describe()
+        ),
+    }
+}
+
+/// Why a run could not be locked
+#[derive(Debug)]
+pub enum LockError {
+    // Another merge of this run is in progress
+    Held(LockOwner),
+    // The lock is older than STALE_LOCK_AGE; may be overridden after asking
+    Stale(LockOwner, Duration),
+    Io(String),
+}
+
+/// Lock file for one run in one destination folder. Held from just after
+/// the output name is known until the merge ends, so two people merging
+/// the same run into a shared folder cannot interleave backups and
+/// writes. Dropping it removes the file.
+pub struct RunLock {
+    path: PathBuf,
+}
+
+/// `.merger_<run>.lock` in `destination`
+pub fn lock_path(destination: &Path, run: &str) -> PathBuf {
+    let run = sanitize_file_component(run);
+    let run = if run.is_empty() { "run".to_string() } else { run };
+    destination.join(format!(".merger_{}.lock", run))
+}
+
+fn lock_age(path: &Path) -> Duration {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .unwrap_or_default()
+}
+
+fn read_owner(path: &Path) -> LockOwner {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+impl RunLock {
+    /// Takes the lock for `run`. With `override_stale`, a lock older than
+    /// STALE_LOCK_AGE is replaced; a recent one never is.
+    pub fn acquire(destination: &Path, run: &str, override_stale: bool) -> Result<Self, LockError> {
+        let path = lock_path(destination, run);
+
+        if path.exists() {
+            let owner = read_owner(&path);
+            let age = lock_age(&path);
+            if age < STALE_LOCK_AGE {
+                return Err(LockError::Held(owner));
+            }
+            if !override_stale {
+                return Err(LockError::Stale(owner, age));
+            }
+            fs::remove_file(&path)
+                .map_err(|e| LockError::Io(format!("Failed to remove the old lock '{}': {e}", path.display())))?;
+        }
+
+        // create_new: of two merges starting together, exactly one gets it
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(LockError::Held(read_owner(&path)));
+            }
+            Err(e) => {
+                return Err(LockError::Io(format!("Failed to create the lock '{}': {e}", path.display())));
+            }
+        };
+        let owner = serde_json::to_string(&LockOwner::current()).unwrap_or_default();
+        let lock = Self { path };
+        file.write_all(owner.as_bytes())
+            .map_err(|e| LockError::Io(format!("Failed to write the lock '{}': {e}", lock.path.display())))?;
+        Ok(lock)
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    // Backdates the lock as if its merge had started `age` ago
+    fn age_lock(path: &Path, age: Duration) {
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn second_acquire_is_held() {
+        let dir = TempWorkspace::new().unwrap();
+        let first = RunLock::acquire(dir.path(), "RUN01", false).unwrap();
+        match RunLock::acquire(dir.path(), "RUN01", true) {
+            Err(LockError::Held(owner)) => {
+                assert_eq!(owner.pid, std::process::id());
+                // The message names who is merging, where and since when
+                let current = LockOwner::current();
+                let message = held_message("RUN01", &owner, Lang::En);
+                assert!(message.starts_with("Run RUN01 is already being merged"), "{}", message);
+                assert!(
+                    message.contains(&format!("({} on {} since {})", current.user, current.computer, owner.started)),
+                    "{}",
+                    message
+                );
+                assert_eq!(owner.started.len(), "2026-03-01 10:15:02".len(), "{}", owner.started);
+                assert!(held_message("RUN01", &owner, Lang::Fr).contains(&owner.describe()));
+            }
+            other => panic!("expected Held, got {:?}", other.err()),
+        }
+
+        // Other runs in the same folder are not blocked
+        let _other = RunLock::acquire(dir.path(), "RUN02", false).unwrap();
+
+        drop(first);
+        assert!(!lock_path(dir.path(), "RUN01").exists());
+        let _again = RunLock::acquire(dir.path(), "RUN01", false).unwrap();
+    }
+
+    #[test]
+    fn stale_lock_needs_override() {
+        let dir = TempWorkspace::new().unwrap();
+        let path = lock_path(dir.path(), "RUN01");
+        std::mem::forget(RunLock::acquire(dir.path(), "RUN01", false).unwrap());
+        age_lock(&path, STALE_LOCK_AGE + Duration::from_secs(60));
+
+        match RunLock::acquire(dir.path(), "RUN01", false) {
+            Err(LockError::Stale(_, age)) => assert!(age >= STALE_LOCK_AGE),
+            other => panic!("expected Stale, got {:?}", other.err()),
+        }
+        assert!(path.exists());
+
+        let lock = RunLock::acquire(dir.path(), "RUN01", true).unwrap();
+        assert!(lock_age(&path) < STALE_LOCK_AGE);
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn recent_lock_is_never_overridden() {
+        let dir = TempWorkspace::new().unwrap();
+        let path = lock_path(dir.path(), "RUN01");
+        std::mem::forget(RunLock::acquire(dir.path(), "RUN01", false).unwrap());
+        age_lock(&path, STALE_LOCK_AGE - Duration::from_secs(60));
+
+        assert!(matches!(RunLock::acquire(dir.path(), "RUN01", true), Err(LockError::Held(_))));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn unreadable_owner_still_holds() {
+        let dir = TempWorkspace::new().unwrap();
+        fs::write(lock_path(dir.path(), "RUN01"), "not json").unwrap();
+        match RunLock::acquire(dir.path(), "RUN01", false) {
+            Err(LockError::Held(owner)) => assert_eq!(owner.user, ""),
+            other => panic!("expected Held, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn lock_path_is_sanitized() {
+        let path = lock_path(Path::new("out"), "../RUN 01");
+        assert_eq!(path.parent(), Some(Path::new("out")));
+        assert!(path.file_name().unwrap().to_string_lossy().starts_with(".merger_"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::settings::settings_dir;
+
+// Holds how many startups in a row never got as far as showing the window
+const SENTINEL_FILE: &str = "startup_attempts";
+// This many unfinished startups in a row and the next one is in safe mode
+pub const FAILED_STARTS_BEFORE_SAFE_MODE: u32 = 2;
+
+/// What a startup loads. Safe mode skips everything user-provided that
+/// could be what keeps crashing the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupPlan {
+    pub safe_mode: bool,
+    pub load_settings: bool,
+    pub check_updates: bool,
+    pub watch_settings: bool,
+}
+
+impl StartupPlan {
+    pub fn for_failed_starts(failed: u32) -> Self {
+        let safe_mode = failed >= FAILED_STARTS_BEFORE_SAFE_MODE;
+        Self {
+            safe_mode,
+            load_settings: !safe_mode,
+            check_updates: !safe_mode,
+            watch_settings: !safe_mode,
+        }
+    }
+}
+
+fn failed_starts(dir: &Path) -> u32 {
+    fs::read_to_string(dir.join(SENTINEL_FILE))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Records a startup attempt in `dir` and decides what it may load.
+/// The attempt counts as failed until `startup_succeeded_in` clears it.
+pub fn begin_startup_in(dir: &Path) -> StartupPlan {
+    let failed = failed_starts(dir);
+    let written = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(dir.join(SENTINEL_FILE), (failed + 1).to_string()));
+    if let Err(e) = written {
+        eprintln!("Failed to record startup attempt: {e}");
+    }
+    StartupPlan::for_failed_starts(failed)
+}
+
+pub fn startup_succeeded_in(dir: &Path) {
+    if let Err(e) = fs::remove_file(dir.join(SENTINEL_FILE)) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            eprintln!("Failed to clear startup attempts: {e}");
+        }
+    }
+}
+
+pub fn begin_startup() -> StartupPlan {
+    match settings_dir() {
+        Some(dir) => begin_startup_in(&dir),
+        None => StartupPlan::for_failed_starts(0),
+    }
+}
+
+pub fn startup_succeeded() {
+    if let Some(dir) = settings_dir() {
+        startup_succeeded_in(&dir);
+    }
+}
+
+/// Moves settings.json aside (kept as a dated .bak) so the next start
+/// uses defaults. Returns the backup, or None if there was nothing to reset.
+pub fn reset_settings_in(dir: &Path) -> Result<Option<PathBuf>, String> {
+    let path = dir.join("settings.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let backup = dir.join(format!("settings.{}.bak.json", stamp));
+    fs::rename(&path, &backup)
+        .map_err(|e| format!("Failed to move '{}' aside: {e}", path.display()))?;
+    Ok(Some(backup))
+}
+
+pub fn reset_settings() -> Result<Option<PathBuf>, String> {
+    let dir = settings_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    reset_settings_in(&dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    #[test]
+    fn safe_mode_starts_after_two_failed_starts() {
+        assert!(!StartupPlan::for_failed_starts(0).safe_mode);
+        assert!(!StartupPlan::for_failed_starts(1).safe_mode);
+        let plan = StartupPlan::for_failed_starts(2);
+        assert_eq!(
+            plan,
+            StartupPlan { safe_mode: true, load_settings: false, check_updates: false, watch_settings: false }
+        );
+        assert!(StartupPlan::for_failed_starts(7).safe_mode);
+    }
+
+    #[test]
+    fn shown_window_clears_the_attempts() {
+        let dir = TempWorkspace::new().unwrap();
+        assert!(!begin_startup_in(dir.path()).safe_mode);
+        startup_succeeded_in(dir.path());
+        assert!(!dir.path().join(SENTINEL_FILE).exists());
+
+        // One failure, then a good start: the count starts over
+        assert!(!begin_startup_in(dir.path()).safe_mode);
+        assert!(!begin_startup_in(dir.path()).safe_mode);
+        startup_succeeded_in(dir.path());
+        assert!(!begin_startup_in(dir.path()).safe_mode);
+        // Clearing twice is harmless
+        startup_succeeded_in(dir.path());
+        startup_succeeded_in(dir.path());
+    }
+
+    #[test]
+    fn unreadable_sentinel_counts_as_no_failures() {
+        let dir = TempWorkspace::new().unwrap();
+        fs::write(dir.path().join(SENTINEL_FILE), "garbage").unwrap();
+        assert!(!begin_startup_in(dir.path()).safe_mode);
+        assert_eq!(failed_starts(dir.path()), 1);
+    }
+
+    // A startup that crashes on a settings file it cannot parse, as a bad
+    // hand edit once did
+    fn start(dir: &Path) -> Result<StartupPlan, String> {
+        let plan = begin_startup_in(dir);
+        if plan.load_settings {
+            let text = fs::read_to_string(dir.join("settings.json")).unwrap_or_else(|_| "{}".to_string());
+            serde_json::from_str::<serde_json::Value>(&text).map_err(|e| format!("crashed: {e}"))?;
+        }
+        startup_succeeded_in(dir);
+        Ok(plan)
+    }
+
+    #[test]
+    fn corrupt_settings_lead_to_safe_mode_and_a_reset() {
+        let dir = TempWorkspace::new().unwrap();
+        fs::write(dir.path().join("settings.json"), "{ \"ui_scale\": ").unwrap();
+
+        assert!(start(dir.path()).is_err());
+        assert!(start(dir.path()).is_err());
+        let plan = start(dir.path()).unwrap();
+        assert!(plan.safe_mode && !plan.load_settings && !plan.check_updates);
+
+        let backup = reset_settings_in(dir.path()).unwrap().unwrap();
+        assert!(backup.file_name().unwrap().to_string_lossy().starts_with("settings."));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "{ \"ui_scale\": ");
+        assert_eq!(reset_settings_in(dir.path()).unwrap(), None);
+
+        // With the settings moved aside the next start is a normal one
+        assert!(!start(dir.path()).unwrap().safe_mode);
+    }
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::capacity::{PlateCapacity, DEFAULT_MAX_ROWS, DEFAULT_MIN_ROWS};
+use crate::controls::{default_control_rows, ControlRow};
+use crate::hooks::DEFAULT_HOOK_TIMEOUT_SECS;
+use crate::merge::{default_comment_columns, CommentPolicy, DEFAULT_EPID_SOURCES, DEFAULT_MIN_EPI_MATCH_PERCENT, DEFAULT_SENSITIVE_PATTERNS};
+use crate::naming::DEFAULT_OUTPUT_PATTERN;
+use crate::output::{default_excel_safe_columns, default_numeric_columns};
+use crate::reconcile::UnsampledEpiReport;
+use crate::clock::DateZone;
+use crate::run_constants::DateReportedPolicy;
+use crate::staleness::DEFAULT_STALE_DAYS;
+
+/// User choice for the colour scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThemePreference {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemePreference {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "Light" => ThemePreference::Light,
+            "Dark" => ThemePreference::Dark,
+            _ => ThemePreference::System,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThemePreference::System => "System",
+            ThemePreference::Light => "Light",
+            ThemePreference::Dark => "Dark",
+        }
+    }
+}
+
+/// Persisted user settings, stored as JSON in the config dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Bumped on every write, so instances can tell a newer file from their own copy
+    pub revision: u64,
+    pub theme: ThemePreference,
+    // None = let the platform decide
+    pub ui_scale: Option<f32>,
+    // Debug: load every EpiInfo column instead of only the ones the merge uses
+    pub load_all_epiinfo_columns: bool,
+    // Refuse to write an output while any warning is outstanding
+    pub strict_mode: bool,
+    // Columns prefixed in the Excel-safe output variant
+    pub excel_safe_columns: Vec<String>,
+    // Reuse the parsed EpiInfo export across merges while it is unchanged
+    pub cache_epiinfo: bool,
+    // Local-only log of merges for the monthly summary
+    pub usage_metrics: bool,
+    // EpiInfo columns the EPID is taken from when empty, in priority order
+    pub epid_sources: Vec<String>,
+    // Free-text columns both files may fill, merged per policy instead of
+    // taken from EpiInfo
+    pub comment_columns: BTreeMap<String, CommentPolicy>,
+    // Warn when the EpiInfo export is older than this many days (0 = never)
+    pub epiinfo_stale_days: u64,
+    // Output file name, with {run} {mode} {lab} {date} {flowcell} {timestamp}
+    pub output_name_pattern: String,
+    // Write outputs under destination/{mode}/{yyyy-mm}/ instead of flat
+    pub organize_outputs_by_mode: bool,
+    // Last directory used per file dialog slot ("sample_file", "destination", ...)
+    pub last_dirs: BTreeMap<String, String>,
+    // Append BasecallerVersion / BasecallModel from the MinKNOW report to the output
+    pub provenance_columns: bool,
+    // Most data rows a run may have, controls included
+    pub plate_max_rows: usize,
+    // Warn below this many rows, as the sheet may be truncated (0 = never)
+    pub plate_min_rows: usize,
+    // What empty DateReported cells get just before the output is written
+    pub date_reported: DateReportedPolicy,
+    // Calendar for "today" and report dates: Local, or Utc for machines kept on UTC
+    pub date_zone: DateZone,
+    // Show the EpiInfo columns to be imported before every merge
+    pub review_epiinfo_columns: bool,
+    // EpiInfo columns whose name contains one of these are left out unless opted in
+    pub sensitive_column_patterns: Vec<String>,
+    // Columns whose decimal numbers are written with a consistent separator
+    pub numeric_columns: Vec<String>,
+    // Decimal commas in those columns when the output is semicolon separated
+    pub localized_numbers: bool,
+    // Confirm before writing when fewer samples matched EpiInfo (0 = never ask)
+    pub min_epi_match_percent: u8,
+    // Add a row for each control below that the samples file leaves out
+    pub ensure_control_rows: bool,
+    pub control_rows: Vec<ControlRow>,
+    // Pair samples that have no exact ICLabID by case/spaces, lab prefix
+    // or trailing number; weakest pairings are listed for review
+    pub sample_match_fallbacks: bool,
+    // Lab prefixes ignored when pairing ("CMR-", "LAB")
+    pub sample_id_prefixes: Vec<String>,
+    // Add a MatchMethod column saying how each sample was paired
+    pub annotate_match_method: bool,
+    // Command run after a successful merge, with {output} {run} {destination}
+    // (empty = none). Its result is reported but never undoes the merge.
+    pub post_merge_hook: String,
+    pub post_merge_hook_timeout_secs: u64,
+    // Write <output>_summary.json next to each merged output
+    pub summary_json: bool,
+    // Name of the configuration bundle the settings were last switched to ("" = none)
+    pub active_bundle: String,
+    // Optional {run}_epi_without_samples.csv of EpiInfo records no sample matched
+    pub unsampled_epi_report: UnsampledEpiReport,
+    // Replace sample and EPID with this computer's pseudonyms in run packages
+    pub pseudonymize_package_ids: bool,
+    // Filled in by the first-run setup: "en" or "fr" ("" = English)
+    pub language: String,
+    // Prefilled into the Lab field
+    pub lab_name: String,
+    // Prefilled destination folder ("" = none)
+    pub default_destination: String,
+    // Look for a newer release on start
+    pub check_updates: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            revision: 0,
+            theme: ThemePreference::System,
+            ui_scale: None,
+            load_all_epiinfo_columns: false,
+            strict_mode: false,
+            excel_safe_columns: default_excel_safe_columns(),
+            cache_epiinfo: true,
+            usage_metrics: true,
+            epid_sources: DEFAULT_EPID_SOURCES.iter().map(|s| s.to_string()).collect(),
+            comment_columns: default_comment_columns(),
+            epiinfo_stale_days: DEFAULT_STALE_DAYS,
+            output_name_pattern: DEFAULT_OUTPUT_PATTERN.to_string(),
+            organize_outputs_by_mode: false,
+            last_dirs: BTreeMap::new(),
+            provenance_columns: false,
+            plate_max_rows: DEFAULT_MAX_ROWS,
+            plate_min_rows: DEFAULT_MIN_ROWS,
+            date_reported: DateReportedPolicy::LeaveEmpty,
+            date_zone: DateZone::Local,
+            review_epiinfo_columns: false,
+            sensitive_column_patterns: DEFAULT_SENSITIVE_PATTERNS.iter().map(|s| s.to_string()).collect(),
+            numeric_columns: default_numeric_columns(),
+            localized_numbers: false,
+            min_epi_match_percent: DEFAULT_MIN_EPI_MATCH_PERCENT,
+            ensure_control_rows: false,
+            control_rows: default_control_rows(),
+            sample_match_fallbacks: false,
+            sample_id_prefixes: Vec::new(),
+            annotate_match_method: false,
+            post_merge_hook: String::new(),
+            post_merge_hook_timeout_secs: DEFAULT_HOOK_TIMEOUT_SECS,
+            summary_json: false,
+            active_bundle: String::new(),
+            unsampled_epi_report: UnsampledEpiReport::default(),
+            pseudonymize_package_ids: false,
+            language: String::new(),
+            lab_name: String::new(),
+            default_destination: String::new(),
+            check_updates: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn plate_capacity(&self) -> PlateCapacity {
+        PlateCapacity {
+            max_rows: self.plate_max_rows,
+            min_rows: self.plate_min_rows,
+        }
+    }
+}
+
+pub fn settings_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("merger"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    settings_dir().map(|d| d.join("settings.json"))
+}
+
+/// Whether settings were ever saved on this computer; without them the
+/// first-run setup is offered
+pub fn settings_file_exists() -> bool {
+    settings_path().is_some_and(|p| p.exists())
+}
+
+// Reads a settings file, falling back to defaults if missing or unreadable
+fn read_settings_file(path: &Path) -> Settings {
+    match fs::read_to_string(path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable settings file '{}': {e}", path.display());
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+// Loads settings, falling back to defaults if missing or unreadable
+pub fn load_settings() -> Settings {
+    match settings_path() {
+        Some(path) => read_settings_file(&path),
+        None => Settings::default(),
+    }
+}
+
+/// Last modification time of settings.json, for change polling
+pub fn settings_modified() -> Option<SystemTime> {
+    settings_path()
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok())
+}
+
+/// Applies `change` to the settings currently on disk and writes them back
+/// under an exclusive lock. Other instances and the CLI may have written
+/// since this one loaded, so the change is never applied to a stale copy.
+/// Returns the settings as written.
+pub fn update_settings_in(dir: &Path, change: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create config directory '{}': {e}", dir.display()))?;
+
+    let lock_path = dir.join("settings.lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("Failed to open settings lock '{}': {e}", lock_path.display()))?;
+    lock.lock()
+        .map_err(|e| format!("Failed to lock settings '{}': {e}", lock_path.display()))?;
+
+    let path = dir.join("settings.json");
+    let mut settings = read_settings_file(&path);
+    change(&mut settings);
+    settings.revision += 1;
+
+    // Write then rename so readers never see a half-written file
+    let tmp = dir.join("settings.json.tmp");
+    let s = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialise settings: {e}"))?;
+    fs::write(&tmp, s).map_err(|e| format!("Failed to write settings '{}': {e}", tmp.display()))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to write settings '{}': {e}", path.display()))?;
+
+    // The lock is released when `lock` is closed
+    drop(lock);
+    Ok(settings)
+}
+
+pub fn update_settings(change: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    let dir = settings_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+    update_settings_in(&dir, change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use std::thread;
+
+    #[test]
+    fn interleaved_writers_lose_no_update() {
+        const WRITERS: usize = 4;
+        const UPDATES: usize = 25;
+        let dir = TempWorkspace::new().unwrap();
+
+        let revisions: Vec<Vec<u64>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..WRITERS)
+                .map(|writer| {
+                    let dir = dir.path();
+                    scope.spawn(move || {
+                        (0..UPDATES)
+                            .map(|update| {
+                                update_settings_in(dir, |s| {
+                                    s.last_dirs.insert(format!("w{}_{}", writer, update), "/data".to_string());
+                                })
+                                .unwrap()
+                                .revision
+                            })
+                            .collect::<Vec<u64>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let settings = read_settings_file(&dir.path().join("settings.json"));
+        assert_eq!(settings.last_dirs.len(), WRITERS * UPDATES);
+        assert_eq!(settings.revision, (WRITERS * UPDATES) as u64);
+
+        // Each writer saw its revisions increase, and no two writes shared one
+        for seen in &revisions {
+            assert!(seen.windows(2).all(|w| w[0] < w[1]), "{:?}", seen);
+        }
+        let mut all: Vec<u64> = revisions.concat();
+        all.sort();
+        assert_eq!(all, (1..=(WRITERS * UPDATES) as u64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn update_keeps_what_another_instance_wrote() {
+        let dir = TempWorkspace::new().unwrap();
+        // Another instance (or the CLI) saved first
+        update_settings_in(dir.path(), |s| s.output_name_pattern = "{lab}_{run}.csv".to_string()).unwrap();
+
+        let written = update_settings_in(dir.path(), |s| {
+            s.last_dirs.insert("sample_file".to_string(), "/data/runs".to_string());
+        })
+        .unwrap();
+        assert_eq!(written.output_name_pattern, "{lab}_{run}.csv");
+        assert_eq!(written.revision, 2);
+        assert!(!dir.path().join("settings.json.tmp").exists());
+    }
+
+    #[test]
+    fn unreadable_settings_fall_back_to_defaults() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("settings.json");
+        assert_eq!(read_settings_file(&path).revision, 0);
+        fs::write(&path, "{ broken").unwrap();
+        assert_eq!(read_settings_file(&path).output_name_pattern, DEFAULT_OUTPUT_PATTERN);
+        // Missing fields take their defaults
+        fs::write(&path, r#"{"revision": 7}"#).unwrap();
+        let settings = read_settings_file(&path);
+        assert_eq!(settings.revision, 7);
+        assert_eq!(settings.plate_max_rows, DEFAULT_MAX_ROWS);
+    }
+}
@@ -0,0 +1,336 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bundle::{apply_bundle, bundle_settings, check_bundle_source, import_bundle};
+use crate::settings::{settings_dir, update_settings_in, Settings};
+
+/// Steps of the first-run setup, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    Language,
+    Lab,
+    Destination,
+    Bundle,
+    Updates,
+    // All collected; Finish writes the settings
+    Review,
+}
+
+const STEPS: &[WizardStep] = &[
+    WizardStep::Language,
+    WizardStep::Lab,
+    WizardStep::Destination,
+    WizardStep::Bundle,
+    WizardStep::Updates,
+    WizardStep::Review,
+];
+
+impl WizardStep {
+    /// Name the UI switches its page on
+    pub fn id(&self) -> &'static str {
+        match self {
+            WizardStep::Language => "language",
+            WizardStep::Lab => "lab",
+            WizardStep::Destination => "destination",
+            WizardStep::Bundle => "bundle",
+            WizardStep::Updates => "updates",
+            WizardStep::Review => "review",
+        }
+    }
+
+    /// Steps that may be left empty with "Skip"
+    pub fn optional(&self) -> bool {
+        matches!(self, WizardStep::Destination | WizardStep::Bundle)
+    }
+
+    fn index(&self) -> usize {
+        STEPS.iter().position(|s| s == self).unwrap_or(0)
+    }
+}
+
+/// Values collected by the wizard. Nothing is written until `finish`, so
+/// cancelling at any step leaves no settings file behind.
+#[derive(Debug, Clone)]
+pub struct SetupWizard {
+    pub step: WizardStep,
+    pub french: bool,
+    pub lab: String,
+    // Default destination folder ("" = none)
+    pub destination: String,
+    // Country bundle to import (zip or folder), None = none
+    pub bundle: Option<PathBuf>,
+    pub check_updates: bool,
+    // Name of the bundle once checked
+    pub bundle_name: Option<String>,
+}
+
+impl Default for SetupWizard {
+    fn default() -> Self {
+        Self {
+            step: WizardStep::Language,
+            french: false,
+            lab: String::new(),
+            destination: String::new(),
+            bundle: None,
+            check_updates: true,
+            bundle_name: None,
+        }
+    }
+}
+
+// A folder we can create files in; checked by writing and removing a probe
+fn check_destination(path: &str) -> Result<(), String> {
+    let dir = Path::new(path.trim());
+    if !dir.is_dir() {
+        return Err(format!("'{}' is not a folder.", dir.display()));
+    }
+    let probe = dir.join(".merger_write_test");
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("Cannot write to '{}': {e}", dir.display()))
+}
+
+impl SetupWizard {
+    /// Checks the current step's value; Ok when the wizard may move on
+    pub fn validate(&mut self) -> Result<(), String> {
+        match self.step {
+            WizardStep::Lab if self.lab.trim().is_empty() => Err("Please enter the lab name.".to_string()),
+            WizardStep::Destination if !self.destination.trim().is_empty() => check_destination(&self.destination),
+            WizardStep::Bundle => {
+                self.bundle_name = match &self.bundle {
+                    Some(source) => Some(check_bundle_source(source)?.name),
+                    None => None,
+                };
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the current step and moves to the next one
+    pub fn next(&mut self) -> Result<(), String> {
+        self.validate()?;
+        if let Some(next) = STEPS.get(self.step.index() + 1) {
+            self.step = *next;
+        }
+        Ok(())
+    }
+
+    pub fn back(&mut self) {
+        if let Some(idx) = self.step.index().checked_sub(1) {
+            self.step = STEPS[idx];
+        }
+    }
+
+    /// Leaves an optional step empty and moves on
+    pub fn skip(&mut self) -> Result<(), String> {
+        match self.step {
+            WizardStep::Destination => self.destination.clear(),
+            WizardStep::Bundle => {
+                self.bundle = None;
+                self.bundle_name = None;
+            }
+            step => return Err(format!("The {} step cannot be skipped.", step.id())),
+        }
+        self.next()
+    }
+
+    /// Imports the bundle (if any) and writes the settings into `dir`.
+    /// Only valid on the review step.
+    pub fn finish_in(&self, dir: &Path) -> Result<Settings, String> {
+        if self.step != WizardStep::Review {
+            return Err("The setup is not complete yet.".to_string());
+        }
+        let bundle = match &self.bundle {
+            Some(source) => {
+                let manifest = import_bundle(source)?;
+                Some((bundle_settings(&manifest.name)?, manifest.name))
+            }
+            None => None,
+        };
+        update_settings_in(dir, |settings| {
+            if let Some((values, name)) = bundle {
+                apply_bundle(settings, values, &name);
+            }
+            settings.language = if self.french { "fr" } else { "en" }.to_string();
+            settings.lab_name = self.lab.trim().to_string();
+            settings.default_destination = self.destination.trim().to_string();
+            settings.check_updates = self.check_updates;
+        })
+    }
+
+    /// One line per collected value, for the review step
+    pub fn review_text(&self) -> String {
+        let none = if self.french { "(aucun)" } else { "(none)" };
+        let or_none = |s: &str| if s.trim().is_empty() { none.to_string() } else { s.trim().to_string() };
+        let bundle = self.bundle_name.clone().unwrap_or_else(|| none.to_string());
+        if self.french {
+            format!(
+                "Langue : français\nLaboratoire : {}\nDestination : {}\nConfiguration pays : {}\nRecherche de mises à jour : {}",
+                or_none(&self.lab),
+                or_none(&self.destination),
+                bundle,
+                if self.check_updates { "oui" } else { "non" }
+            )
+        } else {
+            format!(
+                "Language: English\nLab: {}\nDestination: {}\nCountry bundle: {}\nCheck for updates: {}",
+                or_none(&self.lab),
+                or_none(&self.destination),
+                bundle,
+                if self.check_updates { "yes" } else { "no" }
+            )
+        }
+    }
+
+    pub fn finish(&self) -> Result<Settings, String> {
+        let dir = settings_dir().ok_or_else(|| "Cannot determine config directory".to_string())?;
+        self.finish_in(&dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    const MANIFEST: &str = r#"{"bundle_version": 1, "name": "DRC 2026", "country": "COD", "files": ["settings.json"]}"#;
+
+    fn bundle_folder(dir: &mut TempWorkspace, with_settings: bool) -> PathBuf {
+        let folder = dir.file("bundle");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("bundle.json"), MANIFEST).unwrap();
+        if with_settings {
+            fs::write(folder.join("settings.json"), r#"{"sample_id_prefixes": ["COD"]}"#).unwrap();
+        }
+        folder
+    }
+
+    // Language and lab filled in, on the destination step
+    fn at_destination() -> SetupWizard {
+        let mut wizard = SetupWizard { french: true, ..SetupWizard::default() };
+        wizard.next().unwrap();
+        wizard.lab = " INRB ".to_string();
+        wizard.next().unwrap();
+        assert_eq!(wizard.step, WizardStep::Destination);
+        wizard
+    }
+
+    #[test]
+    fn steps_follow_in_order() {
+        let mut wizard = SetupWizard::default();
+        let mut seen = vec![wizard.step.id()];
+        wizard.lab = "INRB".to_string();
+        while wizard.step != WizardStep::Review {
+            wizard.next().unwrap();
+            seen.push(wizard.step.id());
+        }
+        assert_eq!(seen, ["language", "lab", "destination", "bundle", "updates", "review"]);
+
+        // Next on the last step stays there; back walks to the first
+        wizard.next().unwrap();
+        assert_eq!(wizard.step, WizardStep::Review);
+        for _ in 0..10 {
+            wizard.back();
+        }
+        assert_eq!(wizard.step, WizardStep::Language);
+    }
+
+    #[test]
+    fn each_step_is_validated_before_moving_on() {
+        let mut wizard = SetupWizard::default();
+        wizard.next().unwrap();
+        wizard.lab = "  ".to_string();
+        assert!(wizard.next().is_err());
+        assert_eq!(wizard.step, WizardStep::Lab);
+
+        let mut wizard = at_destination();
+        let mut dir = TempWorkspace::new().unwrap();
+        wizard.destination = dir.file("missing").to_string_lossy().to_string();
+        assert!(wizard.next().unwrap_err().contains("not a folder"));
+        assert_eq!(wizard.step, WizardStep::Destination);
+
+        // A writable folder passes, and the write probe is removed
+        wizard.destination = dir.path().to_string_lossy().to_string();
+        wizard.next().unwrap();
+        assert!(!dir.path().join(".merger_write_test").exists());
+
+        // A bundle missing a listed file is refused, a complete one passes
+        wizard.bundle = Some(bundle_folder(&mut dir, false));
+        assert!(wizard.next().is_err());
+        assert_eq!(wizard.step, WizardStep::Bundle);
+        fs::write(wizard.bundle.as_ref().unwrap().join("settings.json"), "{}").unwrap();
+        wizard.next().unwrap();
+        assert_eq!(wizard.step, WizardStep::Updates);
+        assert!(wizard.review_text().contains("DRC 2026"));
+    }
+
+    #[test]
+    fn optional_steps_can_be_skipped() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let mut wizard = at_destination();
+        wizard.destination = "/no/such/folder".to_string();
+        wizard.skip().unwrap();
+        assert_eq!((wizard.step, wizard.destination.as_str()), (WizardStep::Bundle, ""));
+
+        // Skipping drops a bundle that was chosen, even an invalid one
+        wizard.bundle = Some(bundle_folder(&mut dir, false));
+        wizard.skip().unwrap();
+        assert_eq!(wizard.step, WizardStep::Updates);
+        assert!(wizard.bundle.is_none());
+
+        let err = wizard.skip().unwrap_err();
+        assert!(err.contains("updates"), "{err}");
+        assert_eq!(wizard.step, WizardStep::Updates);
+        assert!(WizardStep::Destination.optional() && WizardStep::Bundle.optional());
+        assert!(!WizardStep::Lab.optional() && !WizardStep::Language.optional());
+
+        wizard.next().unwrap();
+        assert_eq!(
+            wizard.review_text(),
+            "Langue : français\nLaboratoire : INRB\nDestination : (aucun)\nConfiguration pays : (aucun)\nRecherche de mises à jour : oui"
+        );
+    }
+
+    #[test]
+    fn finishing_writes_the_settings_once() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let config = dir.file("config");
+        let destination = dir.file("runs");
+        fs::create_dir_all(&destination).unwrap();
+
+        let mut wizard = at_destination();
+        wizard.destination = destination.to_string_lossy().to_string();
+        wizard.next().unwrap();
+        wizard.skip().unwrap();
+        wizard.check_updates = false;
+        wizard.next().unwrap();
+
+        let settings = wizard.finish_in(&config).unwrap();
+        assert_eq!(settings.language, "fr");
+        assert_eq!(settings.lab_name, "INRB");
+        assert_eq!(settings.default_destination, destination.to_string_lossy());
+        assert!(!settings.check_updates);
+
+        let saved: Settings = serde_json::from_str(&fs::read_to_string(config.join("settings.json")).unwrap()).unwrap();
+        assert_eq!((saved.language, saved.lab_name, saved.check_updates), ("fr".to_string(), "INRB".to_string(), false));
+    }
+
+    #[test]
+    fn cancelling_leaves_no_settings_file() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let config = dir.file("config");
+
+        // Finish before the review step writes nothing
+        let mut wizard = at_destination();
+        assert!(wizard.finish_in(&config).is_err());
+        wizard.skip().unwrap();
+        wizard.skip().unwrap();
+        assert!(wizard.finish_in(&config).is_err());
+        assert!(!config.exists());
+
+        // Closing the wizard on any step is dropping it
+        drop(wizard);
+        assert!(!config.exists());
+    }
+}
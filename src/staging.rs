@@ -0,0 +1,286 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::workspace::{temp_root, TempWorkspace};
+
+/// Checks an artifact once it is at its destination
+pub type ReadBack = Box<dyn Fn(&Path) -> Result<(), String>>;
+
+struct StagedFile {
+    // Shown in errors, e.g. "Excel-safe copy"
+    label: String,
+    staged: PathBuf,
+    dest: PathBuf,
+    // Artifacts with a read-back check are copied, so the staged file is
+    // still there if the check fails
+    read_back: Option<ReadBack>,
+}
+
+/// Output artifacts of one merge, written into a temp workspace first and
+/// moved to the destination only once every one of them was produced.
+/// Dropping without `commit` leaves the destination untouched.
+pub struct StagedOutputs {
+    workspace: TempWorkspace,
+    files: Vec<StagedFile>,
+}
+
+impl StagedOutputs {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            workspace: TempWorkspace::new()?,
+            files: Vec::new(),
+        })
+    }
+
+    /// Writes one artifact into the workspace through `write`
+    pub fn stage(
+        &mut self,
+        label: &str,
+        dest: &Path,
+        write: impl FnOnce(&mut File) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let name = dest
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "artifact".to_string());
+        let staged = self.workspace.file(&name);
+
+        let mut file = File::create(&staged)
+            .map_err(|e| format!("Failed to produce the {}: {e}", label))?;
+        write(&mut file)
+            .and_then(|_| file.sync_all().map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to produce the {}: {e}", label))?;
+
+        self.files.push(StagedFile {
+            label: label.to_string(),
+            staged,
+            dest: dest.to_path_buf(),
+            read_back: None,
+        });
+        Ok(())
+    }
+
+    /// Like `stage`, and `read_back` checks the file at its destination
+    /// after the commit
+    pub fn stage_verified(
+        &mut self,
+        label: &str,
+        dest: &Path,
+        write: impl FnOnce(&mut File) -> Result<(), String>,
+        read_back: ReadBack,
+    ) -> Result<(), String> {
+        self.stage(label, dest, write)?;
+        if let Some(file) = self.files.last_mut() {
+            file.read_back = Some(read_back);
+        }
+        Ok(())
+    }
+
+    /// Moves every staged artifact to its destination. Files being replaced
+    /// are set aside first; if one move fails, the artifacts already moved
+    /// are deleted, the set-aside files are put back and the failing
+    /// artifact is named in the error. The same happens when a read-back
+    /// check fails, and the staged file is then kept for recovery.
+    pub fn commit(self) -> Result<(), String> {
+        // (destination, set-aside copy of what it replaced)
+        let mut committed: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+        for file in &self.files {
+            let moved = set_aside(&file.dest).and_then(|previous| {
+                committed.push((file.dest.clone(), previous));
+                if file.read_back.is_some() {
+                    copy_file(&file.staged, &file.dest)
+                } else {
+                    move_file(&file.staged, &file.dest)
+                }
+            });
+            if let Err(e) = moved {
+                roll_back(&committed);
+                return Err(format!(
+                    "Failed to save the {} to '{}': {e}. No output files were changed.",
+                    file.label,
+                    file.dest.display()
+                ));
+            }
+        }
+
+        for file in &self.files {
+            let Some(read_back) = &file.read_back else {
+                continue;
+            };
+            if let Err(e) = read_back(&file.dest) {
+                roll_back(&committed);
+                let kept = match keep_for_recovery(&file.staged) {
+                    Ok(path) => format!(" The {} as produced was kept at '{}'.", file.label, path.display()),
+                    Err(e) => format!(" The produced {} could not be kept: {e}", file.label),
+                };
+                return Err(format!(
+                    "The {} saved to '{}' failed its read-back check: {e} No output files were changed.{}",
+                    file.label,
+                    file.dest.display(),
+                    kept
+                ));
+            }
+        }
+
+        for (_, previous) in committed {
+            if let Some(previous) = previous {
+                let _ = fs::remove_file(previous);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Deletes the artifacts already moved and puts back what they replaced
+fn roll_back(committed: &[(PathBuf, Option<PathBuf>)]) {
+    for (dest, previous) in committed.iter().rev() {
+        let _ = fs::remove_file(dest);
+        if let Some(previous) = previous {
+            let _ = fs::rename(previous, dest);
+        }
+    }
+}
+
+/// Staged outputs that failed their read-back check are moved here, out
+/// of the workspace that is removed after the merge
+pub fn recovery_dir() -> PathBuf {
+    temp_root().join("recovered")
+}
+
+fn keep_for_recovery(staged: &Path) -> io::Result<PathBuf> {
+    let dir = recovery_dir();
+    fs::create_dir_all(&dir)?;
+    let name = staged
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "artifact".to_string());
+    let kept = dir.join(format!("{}_{}", chrono::Local::now().format("%Y%m%d_%H%M%S"), name));
+    move_file(staged, &kept)?;
+    Ok(kept)
+}
+
+// Moves an existing destination file to a hidden sibling
+fn set_aside(dest: &Path) -> io::Result<Option<PathBuf>> {
+    if !dest.exists() {
+        return Ok(None);
+    }
+    let previous = hidden_sibling(dest, "prev");
+    fs::rename(dest, &previous)?;
+    Ok(Some(previous))
+}
+
+fn hidden_sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Rename, or copy + fsync + delete when the workspace is on another
+/// device. The copy goes to a hidden sibling first so a failed copy never
+/// leaves a half-written file under the real name.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_file(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Copy + fsync through a hidden sibling, leaving `from` in place
+pub fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    let partial = hidden_sibling(to, "part");
+    let copied = fs::copy(from, &partial)
+        .and_then(|_| File::open(&partial)?.sync_all())
+        .and_then(|_| fs::rename(&partial, to));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_text(text: &'static str) -> impl FnOnce(&mut File) -> Result<(), String> {
+        move |file: &mut File| file.write_all(text.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn every_artifact_lands_and_replaced_files_are_cleaned_up() {
+        let dest = TempWorkspace::new().unwrap();
+        let output = dest.path().join("run_output.csv");
+        fs::write(&output, "old").unwrap();
+
+        let mut staged = StagedOutputs::new().unwrap();
+        staged.stage("output CSV", &output, write_text("new")).unwrap();
+        staged.stage("Excel-safe copy", &dest.path().join("run_excel.csv"), write_text("excel")).unwrap();
+        staged.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&output).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dest.path().join("run_excel.csv")).unwrap(), "excel");
+        assert!(!hidden_sibling(&output, "prev").exists());
+    }
+
+    #[test]
+    fn failing_third_artifact_leaves_the_destination_as_it_was() {
+        let dest = TempWorkspace::new().unwrap();
+        let output = dest.path().join("run_output.csv");
+        fs::write(&output, "old").unwrap();
+        let before = fs::read_dir(dest.path()).unwrap().count();
+
+        let mut staged = StagedOutputs::new().unwrap();
+        staged.stage("output CSV", &output, write_text("new")).unwrap();
+        staged.stage("Excel-safe copy", &dest.path().join("run_excel.csv"), write_text("excel")).unwrap();
+        // Its folder does not exist, so moving it into place fails
+        let missing = dest.path().join("missing").join("run_unsampled.csv");
+        staged.stage("EpiInfo records without samples", &missing, write_text("epi")).unwrap();
+
+        let error = staged.commit().unwrap_err();
+        assert!(error.contains("EpiInfo records without samples"), "{}", error);
+        assert!(error.contains("No output files were changed"));
+
+        assert_eq!(fs::read_to_string(&output).unwrap(), "old");
+        assert!(!dest.path().join("run_excel.csv").exists());
+        assert_eq!(fs::read_dir(dest.path()).unwrap().count(), before);
+    }
+
+    #[test]
+    fn failed_read_back_rolls_back_and_keeps_the_produced_file() {
+        let dest = TempWorkspace::new().unwrap();
+        let output = dest.path().join("run_output.csv");
+        fs::write(&output, "old").unwrap();
+
+        let mut staged = StagedOutputs::new().unwrap();
+        staged.stage("Excel-safe copy", &dest.path().join("run_excel.csv"), write_text("excel")).unwrap();
+        staged
+            .stage_verified("output CSV", &output, write_text("new"), Box::new(|_: &Path| Err("3 rows of 5 read back.".to_string())))
+            .unwrap();
+
+        let error = staged.commit().unwrap_err();
+        assert!(error.contains("failed its read-back check: 3 rows of 5 read back."), "{}", error);
+        assert_eq!(fs::read_to_string(&output).unwrap(), "old");
+        assert!(!dest.path().join("run_excel.csv").exists());
+
+        let kept = error.split("was kept at '").nth(1).and_then(|rest| rest.split('\'').next()).unwrap();
+        assert_eq!(fs::read_to_string(kept).unwrap(), "new");
+        fs::remove_file(kept).unwrap();
+    }
+
+    #[test]
+    fn dropping_without_commit_changes_nothing() {
+        let dest = TempWorkspace::new().unwrap();
+        let output = dest.path().join("run_output.csv");
+
+        let mut staged = StagedOutputs::new().unwrap();
+        staged.stage("output CSV", &output, write_text("new")).unwrap();
+        drop(staged);
+
+        assert!(!output.exists());
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_STALE_DAYS: u64 = 14;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Whole days between `modified` and `now`. A modification time in the
+/// future (badly set clock) counts as fresh rather than failing.
+pub fn age_days(modified: SystemTime, now: SystemTime) -> u64 {
+    now.duration_since(modified)
+        .map(|d| d.as_secs() / DAY.as_secs())
+        .unwrap_or(0)
+}
+
+/// Age is reported only once it is strictly above the threshold; 0 disables the check
+pub fn is_stale(age_days: u64, threshold_days: u64) -> bool {
+    threshold_days > 0 && age_days > threshold_days
+}
+
+/// Age in days of a file if it is older than the threshold
+pub fn stale_file_age(path: &Path, threshold_days: u64) -> Option<u64> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let age = age_days(modified, SystemTime::now());
+    is_stale(age, threshold_days).then_some(age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    #[test]
+    fn age_counts_whole_days() {
+        let now = SystemTime::now();
+        assert_eq!(age_days(now, now), 0);
+        assert_eq!(age_days(now - DAY + Duration::from_secs(1), now), 0);
+        assert_eq!(age_days(now - DAY, now), 1);
+        assert_eq!(age_days(now - DAY * 90 - Duration::from_secs(3600), now), 90);
+    }
+
+    #[test]
+    fn future_modification_time_counts_as_fresh() {
+        let now = SystemTime::now();
+        assert_eq!(age_days(now + DAY * 400, now), 0);
+        assert!(!is_stale(age_days(now + DAY * 400, now), DEFAULT_STALE_DAYS));
+    }
+
+    #[test]
+    fn only_ages_above_the_threshold_are_stale() {
+        assert!(!is_stale(14, 14));
+        assert!(is_stale(15, 14));
+        assert!(is_stale(3, 2));
+        // 0 turns the check off
+        assert!(!is_stale(1000, 0));
+    }
+
+    #[test]
+    fn file_age_is_read_from_its_modification_time() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("epiinfo.csv");
+        std::fs::write(&path, "ICLabID\n").unwrap();
+        assert_eq!(stale_file_age(&path, DEFAULT_STALE_DAYS), None);
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - DAY * 30).unwrap();
+        drop(file);
+        assert_eq!(stale_file_age(&path, DEFAULT_STALE_DAYS), Some(30));
+        assert_eq!(stale_file_age(&path, 0), None);
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + DAY * 30).unwrap();
+        drop(file);
+        assert_eq!(stale_file_age(&path, DEFAULT_STALE_DAYS), None);
+
+        assert_eq!(stale_file_age(&dir.path().join("missing.csv"), DEFAULT_STALE_DAYS), None);
+    }
+}
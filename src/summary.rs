@@ -0,0 +1,561 @@
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::metrics::MetricsRecord;
+use crate::template::fingerprint_bytes;
+use crate::warnings::{Lang, ValidationWarning};
+
+/// Version of the summary JSON. Adding a field keeps it; renaming,
+/// removing or changing the meaning of one bumps it, together with
+/// `misc/merge_summary.example.json` and a line in the README.
+pub const SUMMARY_SCHEMA_VERSION: u32 = 2;
+
+/// One input file as it was read
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputFile {
+    pub file: String,
+    // "semicolon, UTF-8, decimal comma"
+    pub profile: String,
+    // FNV-1a of the file's bytes, empty when it could not be re-read
+    pub fingerprint: String,
+}
+
+impl InputFile {
+    pub fn new(path: &str, file: String, profile: String) -> Self {
+        Self {
+            file,
+            profile,
+            fingerprint: std::fs::read(path).map(|bytes| fingerprint_bytes(&bytes)).unwrap_or_default(),
+        }
+    }
+}
+
+/// Non-empty cells of one output column
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnFill {
+    pub column: String,
+    pub filled: usize,
+}
+
+/// Key facts about a finished merge. Every consumer (clipboard text,
+/// usage metrics, summary JSON) formats from this one struct.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeSummary {
+    pub schema_version: u32,
+    pub app_version: String,
+    pub run_number: String,
+    pub date: String,
+    pub mode: String,
+    pub sample_count: usize,
+    pub controls: usize,
+    // None when no Epi Info file was merged
+    pub matched: Option<usize>,
+    pub unmatched: Option<usize>,
+    // (matched, non-control samples) and whether a low rate was accepted
+    pub match_rate: Option<(usize, usize)>,
+    pub low_match_accepted: bool,
+    // Samples paired per matching strategy ("exact", "numeric-tail", ...)
+    pub match_methods: Vec<(String, usize)>,
+    pub flow_cell: String,
+    pub pores: String,
+    pub run_qc: String,
+    pub output_file: String,
+    // Status of the post-merge hook ("exit 0"), None when none is set
+    pub post_merge_hook: Option<String>,
+    pub inputs: Vec<InputFile>,
+    // Output columns in order
+    pub column_fill: Vec<ColumnFill>,
+    // Warnings per code, e.g. ("W006", 3)
+    pub warning_counts: Vec<(String, usize)>,
+    // Milliseconds per stage, in pipeline order ("minknow", "read_sample",
+    // "read_epi", "join", "fills", "validation", "write", "hook"); stages
+    // a merge skips are left out
+    pub stage_ms: Vec<(String, u64)>,
+    // Sum of stage_ms
+    pub total_ms: u64,
+    // Output rows without an EPID, None when no Epi Info file was merged
+    pub null_epid: Option<usize>,
+    // Merged with "No barcoding": barcode checks skipped, column left blank
+    pub no_barcoding: bool,
+}
+
+/// Wall-clock time of the merge stages, each measured from the end of the
+/// last. Needs no window, so the CLI can time a merge the same way.
+pub struct StageClock {
+    last: Instant,
+    stages: Vec<(String, u64)>,
+}
+
+impl StageClock {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn end(&mut self, stage: &str) {
+        let now = Instant::now();
+        self.stages.push((stage.to_string(), now.duration_since(self.last).as_millis() as u64));
+        self.last = now;
+    }
+
+    pub fn into_millis(self) -> Vec<(String, u64)> {
+        self.stages
+    }
+}
+
+pub fn total_ms(stages: &[(String, u64)]) -> u64 {
+    stages.iter().map(|(_, ms)| ms).sum()
+}
+
+fn or_dash(s: &str) -> &str {
+    if s.trim().is_empty() {
+        "-"
+    } else {
+        s
+    }
+}
+
+impl MergeSummary {
+    /// Compact plain-text block for pasting into chat apps
+    pub fn to_clipboard_text(&self, lang: Lang) -> String {
+        let epi = match (self.matched, self.unmatched) {
+            (Some(m), Some(u)) => match lang {
+                Lang::En => format!("{} matched / {} unmatched", m, u),
+                Lang::Fr => format!("{} appariés / {} non appariés", m, u),
+            },
+            _ => match lang {
+                Lang::En => "no Epi Info file".to_string(),
+                Lang::Fr => "pas de fichier Epi Info".to_string(),
+            },
+        };
+
+        // Controls are left out of the rate
+        let epi = match (self.match_rate, lang) {
+            (Some((m, total)), Lang::En) if total > 0 => format!(
+                "{} (match rate {}% of {} samples{})",
+                epi,
+                m * 100 / total,
+                total,
+                if self.low_match_accepted { ", continued after warning" } else { "" }
+            ),
+            (Some((m, total)), Lang::Fr) if total > 0 => format!(
+                "{} (taux d'appariement {} % sur {} échantillons{})",
+                epi,
+                m * 100 / total,
+                total,
+                if self.low_match_accepted { ", poursuivi après avertissement" } else { "" }
+            ),
+            _ => epi,
+        };
+
+        let mut lines = match lang {
+            Lang::En => vec![
+                format!("Run {} ({})", or_dash(&self.run_number), self.mode),
+                format!("Date: {}", self.date),
+                format!("Samples: {}", self.sample_count),
+                format!("Epi Info: {}", epi),
+                format!("Flow cell: {} ({} pores)", or_dash(&self.flow_cell), or_dash(&self.pores)),
+                format!("RunQC: {}", or_dash(&self.run_qc)),
+                format!("Output: {}", self.output_file),
+            ],
+            Lang::Fr => vec![
+                format!("Exécution {} ({})", or_dash(&self.run_number), self.mode),
+                format!("Date : {}", self.date),
+                format!("Échantillons : {}", self.sample_count),
+                format!("Epi Info : {}", epi),
+                format!("Flow cell : {} ({} pores)", or_dash(&self.flow_cell), or_dash(&self.pores)),
+                format!("RunQC : {}", or_dash(&self.run_qc)),
+                format!("Sortie : {}", self.output_file),
+            ],
+        };
+        if self.no_barcoding {
+            lines.push(match lang {
+                Lang::En => "Barcoding: none (barcode column left blank)".to_string(),
+                Lang::Fr => "Codes-barres : aucun (colonne barcode laissée vide)".to_string(),
+            });
+        }
+        // Only worth a line when something other than exact matching was used
+        if self.match_methods.iter().any(|(method, _)| method != "exact") {
+            let counts: Vec<String> = self.match_methods.iter().map(|(m, n)| format!("{} {}", n, m)).collect();
+            lines.push(match lang {
+                Lang::En => format!("Matching: {}", counts.join(", ")),
+                Lang::Fr => format!("Appariement : {}", counts.join(", ")),
+            });
+        }
+        if !self.stage_ms.is_empty() {
+            lines.push(match lang {
+                Lang::En => format!("Timings: {}", self.timings_text(lang)),
+                Lang::Fr => format!("Durées : {}", self.timings_text(lang)),
+            });
+        }
+        if let Some(status) = &self.post_merge_hook {
+            lines.push(match lang {
+                Lang::En => format!("Post-merge hook: {}", status),
+                Lang::Fr => format!("Commande après fusion : {}", status),
+            });
+        }
+
+        lines.join("\n")
+    }
+
+    /// The stage that took longest, None before any stage was timed
+    pub fn slowest_stage(&self) -> Option<&(String, u64)> {
+        self.stage_ms.iter().max_by_key(|(_, ms)| *ms)
+    }
+
+    /// "minknow 12 ms, read_sample 40 ms, ... (total 160 ms, slowest: join)"
+    pub fn timings_text(&self, lang: Lang) -> String {
+        let stages: Vec<String> = self.stage_ms.iter().map(|(stage, ms)| format!("{} {} ms", stage, ms)).collect();
+        let slowest = self.slowest_stage().map(|(stage, _)| stage.as_str()).unwrap_or("-");
+        match lang {
+            Lang::En => format!("{} (total {} ms, slowest: {})", stages.join(", "), self.total_ms, slowest),
+            Lang::Fr => format!("{} (total {} ms, la plus lente : {})", stages.join(", "), self.total_ms, slowest),
+        }
+    }
+
+    /// The summary as pretty JSON, for `<output>_summary.json`
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize the merge summary: {e}"))
+    }
+
+    /// The line this merge adds to the usage metrics
+    pub fn metrics_record(&self) -> MetricsRecord {
+        MetricsRecord {
+            date: self.date.clone(),
+            mode: self.mode.clone(),
+            sample_count: self.sample_count,
+            matched_count: self.matched,
+            match_rate: self
+                .match_rate
+                .filter(|(_, total)| *total > 0)
+                .map(|(matched, total)| matched as f64 * 100.0 / total as f64),
+            null_epid: self.null_epid,
+        }
+    }
+}
+
+/// Non-empty cells of every column of `df`, in column order
+pub fn column_fill(df: &DataFrame) -> Vec<ColumnFill> {
+    df.get_columns()
+        .iter()
+        .map(|c| {
+            let filled = c
+                .cast(&DataType::String)
+                .ok()
+                .and_then(|c| c.str().ok().map(|s| s.into_iter().flatten().filter(|v| !v.trim().is_empty()).count()))
+                .unwrap_or(0);
+            ColumnFill {
+                column: c.name().to_string(),
+                filled,
+            }
+        })
+        .collect()
+}
+
+/// Warnings per code in order of first appearance
+pub fn warning_counts(warnings: &[ValidationWarning]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for w in warnings {
+        match counts.iter_mut().find(|(code, _)| code == w.code()) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((w.code().to_string(), 1)),
+        }
+    }
+    counts
+}
+
+/// Places text on the system clipboard
+pub fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Clipboard unavailable: {e}"))?;
+    clipboard
+        .set_text(text.to_string())
+        .map_err(|e| format!("Failed to copy to clipboard: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../misc/merge_summary.example.json");
+
+    // The merge `misc/merge_summary.example.json` describes
+    fn example_summary() -> MergeSummary {
+        let owned = |pairs: &[(&str, usize)]| pairs.iter().map(|(k, n)| (k.to_string(), *n)).collect::<Vec<_>>();
+        let stage_ms: Vec<(String, u64)> = [
+            ("minknow", 6),
+            ("read_sample", 14),
+            ("read_epi", 31),
+            ("join", 48),
+            ("fills", 22),
+            ("validation", 42),
+            ("write", 9),
+        ]
+        .iter()
+        .map(|(stage, ms)| (stage.to_string(), *ms))
+        .collect();
+        MergeSummary {
+            schema_version: SUMMARY_SCHEMA_VERSION,
+            app_version: "1.2.1".into(),
+            run_number: "MIN-24-031".into(),
+            date: "2026-03-14".into(),
+            mode: "DDNS".into(),
+            sample_count: 24,
+            controls: 2,
+            matched: Some(21),
+            unmatched: Some(1),
+            match_rate: Some((21, 22)),
+            low_match_accepted: false,
+            match_methods: owned(&[("exact", 21), ("none", 3)]),
+            flow_cell: "FAX12345".into(),
+            pores: "1432".into(),
+            run_qc: "Pass".into(),
+            output_file: "MIN-24-031_merger_output.csv".into(),
+            post_merge_hook: None,
+            inputs: vec![
+                InputFile {
+                    file: "barcodes.csv".into(),
+                    profile: "comma, UTF-8".into(),
+                    fingerprint: "5f1c9a0e".into(),
+                },
+                InputFile {
+                    file: "EpiInfo_export.csv".into(),
+                    profile: "semicolon, Windows-1252, decimal comma".into(),
+                    fingerprint: "c04b7d21".into(),
+                },
+            ],
+            column_fill: [("sample", 24), ("barcode", 24), ("EPID", 21)]
+                .iter()
+                .map(|(column, filled)| ColumnFill {
+                    column: column.to_string(),
+                    filled: *filled,
+                })
+                .collect(),
+            warning_counts: owned(&[("W006", 1), ("W021", 1)]),
+            total_ms: total_ms(&stage_ms),
+            stage_ms,
+            null_epid: Some(3),
+            no_barcoding: false,
+        }
+    }
+
+    #[test]
+    fn summary_json_matches_the_documented_example() {
+        let written: serde_json::Value = serde_json::from_str(&example_summary().to_json().unwrap()).unwrap();
+        let example: serde_json::Value = serde_json::from_str(EXAMPLE).unwrap();
+        assert_eq!(written, example);
+    }
+
+    #[test]
+    fn example_reads_back_into_the_same_summary() {
+        let read: MergeSummary = serde_json::from_str(EXAMPLE).unwrap();
+        assert_eq!(read.to_json().unwrap(), example_summary().to_json().unwrap());
+    }
+
+    #[test]
+    fn summaries_from_older_versions_still_read() {
+        let read: MergeSummary = serde_json::from_str(r#"{"schema_version": 1, "run_number": "MIN-23-001"}"#).unwrap();
+        assert_eq!(read.run_number, "MIN-23-001");
+        assert_eq!(read.null_epid, None);
+        assert!(!read.no_barcoding);
+    }
+
+    fn basic_summary() -> MergeSummary {
+        MergeSummary {
+            run_number: "MIN-24-031".into(),
+            date: "2026-03-14".into(),
+            mode: "DDNS".into(),
+            sample_count: 24,
+            matched: Some(21),
+            unmatched: Some(3),
+            flow_cell: "FAX12345".into(),
+            pores: "1432".into(),
+            run_qc: "Pass".into(),
+            output_file: "MIN-24-031_merger_output.csv".into(),
+            ..MergeSummary::default()
+        }
+    }
+
+    #[test]
+    fn clipboard_text_in_english() {
+        assert_eq!(
+            basic_summary().to_clipboard_text(Lang::En),
+            "Run MIN-24-031 (DDNS)\n\
+             Date: 2026-03-14\n\
+             Samples: 24\n\
+             Epi Info: 21 matched / 3 unmatched\n\
+             Flow cell: FAX12345 (1432 pores)\n\
+             RunQC: Pass\n\
+             Output: MIN-24-031_merger_output.csv"
+        );
+    }
+
+    #[test]
+    fn clipboard_text_in_french() {
+        assert_eq!(
+            basic_summary().to_clipboard_text(Lang::Fr),
+            "Exécution MIN-24-031 (DDNS)\n\
+             Date : 2026-03-14\n\
+             Échantillons : 24\n\
+             Epi Info : 21 appariés / 3 non appariés\n\
+             Flow cell : FAX12345 (1432 pores)\n\
+             RunQC : Pass\n\
+             Sortie : MIN-24-031_merger_output.csv"
+        );
+    }
+
+    #[test]
+    fn clipboard_text_marks_missing_values() {
+        let summary = MergeSummary {
+            run_number: " ".into(),
+            matched: None,
+            unmatched: None,
+            flow_cell: String::new(),
+            pores: String::new(),
+            run_qc: String::new(),
+            ..basic_summary()
+        };
+        let text = summary.to_clipboard_text(Lang::En);
+        assert!(text.starts_with("Run - (DDNS)\n"), "{}", text);
+        assert!(text.contains("\nEpi Info: no Epi Info file\n"), "{}", text);
+        assert!(text.contains("\nFlow cell: - (- pores)\nRunQC: -\n"), "{}", text);
+        assert!(summary.to_clipboard_text(Lang::Fr).contains("pas de fichier Epi Info"));
+    }
+
+    #[test]
+    fn clipboard_text_adds_rate_and_hook_lines() {
+        let summary = MergeSummary {
+            match_rate: Some((21, 22)),
+            low_match_accepted: true,
+            post_merge_hook: Some("exit 0".into()),
+            ..basic_summary()
+        };
+        let text = summary.to_clipboard_text(Lang::En);
+        assert!(
+            text.contains("Epi Info: 21 matched / 3 unmatched (match rate 95% of 22 samples, continued after warning)"),
+            "{}",
+            text
+        );
+        assert!(text.ends_with("\nPost-merge hook: exit 0"), "{}", text);
+    }
+
+    // The merge without the window, timed the way the window times it, on
+    // inputs large enough for every stage to take a measurable time
+    fn timed_merge(dir: &mut crate::workspace::TempWorkspace, rows: usize) -> Vec<(String, u64)> {
+        use crate::matching::{match_samples, MatchOptions};
+        use crate::merge::{
+            add_optional_columns, build_output, default_comment_columns, epiinfo_columns_for_mode, merge_with_epiinfo,
+            validate_columns, DEFAULT_EPID_SOURCES,
+        };
+        use crate::model::{EpiData, MergedRun, SampleSheet};
+        use crate::run_constants::{FillPolicies, RunConstants};
+
+        let columns = crate::template::expected_columns_for_mode("DDNS");
+        let mut samples = columns.join(",") + "\n";
+        let mut epiinfo = "ICLabID,EpidNumber,DateOfOnset,Province,District\n".to_string();
+        for n in 0..rows {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|c| match *c {
+                    "sample" => format!("S{n:05}"),
+                    "barcode" => format!("barcode{:02}", n % 96 + 1),
+                    "SampleType" => "Stool".to_string(),
+                    _ => String::new(),
+                })
+                .collect();
+            samples += &(row.join(",") + "\n");
+            epiinfo += &format!("S{n:05},NIE-KAN-24-{n:05},2024-02-01,Kano,Nassarawa\n");
+        }
+        let pores: Vec<serde_json::Value> = (0..rows).map(|n| serde_json::json!([n, 1500 - n % 100])).collect();
+        let report = serde_json::json!({
+            "software_versions": [{"title": "MinKNOW", "value": "24.02.8"}],
+            "run_setup": [{"title": "Flow cell ID", "value": "FAX12345"}],
+            "pore_scan": {"series_data": [{"name": "Pore available", "data": pores}]},
+        });
+        let mut write = |name: &str, text: &str| {
+            let path = dir.file(name);
+            std::fs::write(&path, text).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let (sample_path, epi_path) = (write("samples.csv", &samples), write("epiinfo.csv", &epiinfo));
+        let (report_path, output_path) = (write("report.json", &report.to_string()), write("output.csv", ""));
+
+        let mut stages = StageClock::start();
+        let minknow = crate::minknow::parse_minknow_report(&report_path).unwrap().remove(0);
+        stages.end("minknow");
+        let (sample_df, _) = crate::csv::read_csv_normalized(&sample_path).unwrap();
+        let sheet = SampleSheet::new(sample_df).unwrap();
+        stages.end("read_sample");
+        let sources: Vec<String> = DEFAULT_EPID_SOURCES.iter().map(|s| s.to_string()).collect();
+        let keep = epiinfo_columns_for_mode("DDNS", &sources);
+        let (epi_df, _, _) = crate::csv::read_csv_projected(&epi_path, Some(&keep)).unwrap();
+        let epi = EpiData::new(epi_df).unwrap();
+        stages.end("read_epi");
+        let matches = match_samples(&sheet.samples(), &epi, &MatchOptions::default());
+        let run: MergedRun = merge_with_epiinfo(sheet, epi, &matches, &sources, &default_comment_columns()).unwrap();
+        let (df, _) = run.into_parts();
+        stages.end("join");
+        let params = RunConstants { run_num: "24-031".into(), fc_id: Some(minknow.fc_id), ..RunConstants::default() };
+        let (df, _) = add_optional_columns(df, "DDNS").unwrap();
+        let mut df = build_output(df, "DDNS", &params, &FillPolicies::default()).unwrap();
+        stages.end("fills");
+        validate_columns(&df, "DDNS").unwrap();
+        crate::domains::check_value_domains(&df, "DDNS");
+        stages.end("validation");
+        let mut file = std::fs::File::create(&output_path).unwrap();
+        CsvWriter::new(&mut file).finish(&mut df).unwrap();
+        stages.end("write");
+        stages.into_millis()
+    }
+
+    #[test]
+    fn every_merge_stage_is_timed_in_order() {
+        let mut dir = crate::workspace::TempWorkspace::new().unwrap();
+        let started = Instant::now();
+        let stage_ms = timed_merge(&mut dir, 20_000);
+        let elapsed = started.elapsed().as_millis() as u64;
+
+        let names: Vec<&str> = stage_ms.iter().map(|(stage, _)| stage.as_str()).collect();
+        assert_eq!(names, ["minknow", "read_sample", "read_epi", "join", "fills", "validation", "write"]);
+        for (stage, ms) in &stage_ms {
+            assert!(*ms > 0, "{stage} took 0 ms");
+        }
+
+        // Each stage starts where the last ended, so the running total only
+        // grows and never passes the time the whole merge took
+        let running: Vec<u64> = stage_ms
+            .iter()
+            .scan(0, |sum, (_, ms)| {
+                *sum += ms;
+                Some(*sum)
+            })
+            .collect();
+        assert!(running.windows(2).all(|w| w[0] < w[1]), "{running:?}");
+
+        let summary = MergeSummary { total_ms: total_ms(&stage_ms), stage_ms, ..MergeSummary::default() };
+        assert_eq!(summary.total_ms, *running.last().unwrap());
+        assert!(summary.total_ms <= elapsed, "{} ms of stages in {elapsed} ms", summary.total_ms);
+
+        let (slowest, _) = summary.slowest_stage().unwrap().clone();
+        assert!(summary.stage_ms.iter().all(|(_, ms)| *ms <= summary.slowest_stage().unwrap().1));
+        let text = summary.timings_text(Lang::En);
+        assert!(text.ends_with(&format!("(total {} ms, slowest: {})", summary.total_ms, slowest)), "{text}");
+        assert!(summary.to_clipboard_text(Lang::Fr).contains(&format!("la plus lente : {slowest}")));
+
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["total_ms"], summary.total_ms);
+        assert_eq!(json["stage_ms"][0][0], "minknow");
+    }
+
+    #[test]
+    fn untimed_summaries_have_no_timings_line() {
+        let summary = basic_summary();
+        assert!(summary.slowest_stage().is_none());
+        assert!(!summary.to_clipboard_text(Lang::En).contains("Timings"));
+        assert_eq!(total_ms(&[]), 0);
+    }
+}
@@ -1,5 +1,9 @@
 use polars::prelude::*;
 
+use crate::merge::fill_run_constants;
+use crate::plate_map::index_to_well;
+use crate::run_constants::{FillPolicies, RunConstants};
+
 pub fn expected_ddns_columns() -> Vec<&'static str> {
     vec![
         "sample", "barcode", "IsQCRetest", "IfRetestOriginalRun", "EPID",
@@ -18,7 +22,7 @@ pub fn expected_minion_columns() -> Vec<&'static str> {
     vec![
         "sample", "barcode", "IsQCRetest", "IfRetestOriginalRun", "institute", "EPID", "CaseOrContact", "CountryOfSampleOrigin",
         "SpecimenNumber", "DateOfOnset", "DateStoolCollected", "DateStoolReceivedinLab", "DateStoolsuspension",
-        "DateFinalCultureResult", "FlaskNumber",
+        "TypeofPositiveControl", "DatePositiveControlreconstituted", "DateFinalCultureResult", "FlaskNumber",
         "FinalCellCultureResult", "DateFinalITDresult", "ITDResult", "ITDMixture", "DateSangerResultGenerated",
         "SangerSequenceID", "SequencingLab", "DelaysInProcessingForSequencing", "DetailsOfDelays", "IsclassificationQCRetest",
         "RTPCRcomments", "DateRNAExtraction", "DateRTPCR", "PositiveControlPCRCheck", "NegativeControlPCRheck",
@@ -28,6 +32,20 @@ pub fn expected_minion_columns() -> Vec<&'static str> {
     ]
 }
 
+/// Template columns that sheets made from an older template may lack.
+/// They are added empty instead of failing the column check.
+pub fn optional_columns_for_mode(mode: &str) -> &'static [&'static str] {
+    if mode == "minION" {
+        &["TypeofPositiveControl", "DatePositiveControlreconstituted"]
+    } else {
+        &[]
+    }
+}
+
+/// Optional columns appended after the template when provenance columns
+/// are turned on. Not part of either template.
+pub const PROVENANCE_COLUMNS: &[&str] = &["BasecallerVersion", "BasecallModel", "SequencerHostname"];
+
 pub fn expected_columns_for_mode(mode: &str) -> Vec<&'static str> {
     if mode == "minION" {
         expected_minion_columns()
@@ -36,6 +54,23 @@ pub fn expected_columns_for_mode(mode: &str) -> Vec<&'static str> {
     }
 }
 
+/// Short fingerprint of a mode's column list (names and order), so two
+/// installs can tell whether they use the same template. FNV-1a: stable
+/// across builds and platforms, unlike the std hasher.
+pub fn template_fingerprint(mode: &str) -> String {
+    fingerprint_bytes(expected_columns_for_mode(mode).join(",").as_bytes())
+}
+
+/// FNV-1a of `bytes` as 8 hex digits; also used for input file fingerprints
+pub fn fingerprint_bytes(bytes: &[u8]) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    format!("{:08x}", hash)
+}
+
 pub fn create_minion_template() -> PolarsResult<DataFrame> {
     df![
         "sample" => Vec::<String>::new(),
@@ -146,3 +181,112 @@ pub fn create_template_for_mode(mode: &str) -> PolarsResult<DataFrame> {
         create_ddns_template()
     }
 }
+
+/// A template with `rows` rows ready to fill during the run: barcodes
+/// (and wells, for DDNS) assigned in plate order, the run constants typed
+/// so far in their columns and every other cell empty. Constants are
+/// checked the way the merge checks them so the sheet merges cleanly.
+pub fn create_prefilled_template(mode: &str, rows: usize, params: &RunConstants) -> Result<DataFrame, String> {
+    if rows == 0 {
+        return Err("Choose how many rows the template should have.".to_string());
+    }
+    params.validate(mode)?;
+
+    // Same columns, in the same order, as the empty template
+    let template = create_template_for_mode(mode).map_err(|e| format!("Failed to create {} template: {e}", mode))?;
+    let columns: Vec<Column> = template
+        .get_column_names()
+        .into_iter()
+        .map(|name| {
+            let values: Vec<String> = match name.as_str() {
+                "barcode" => (1..=rows).map(|n| format!("barcode{:02}", n)).collect(),
+                "Well" => (0..rows).map(|idx| index_to_well(idx).unwrap_or_default()).collect(),
+                _ => vec![String::new(); rows],
+            };
+            Series::new(name.clone(), values).into_column()
+        })
+        .collect();
+    let df = DataFrame::new(columns).map_err(|e| format!("Failed to create the template rows: {e}"))?;
+
+    // Every cell is empty, so filling empty cells writes every constant
+    fill_run_constants(df, mode, params, &FillPolicies::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capacity::{check_plate_capacity, PlateCapacity};
+    use crate::csv::read_csv_normalized;
+    use crate::domains::check_value_domains;
+    use crate::merge::{run_constant_values, validate_columns};
+    use crate::run_constants::ControlStatus;
+    use crate::workspace::TempWorkspace;
+
+    fn constants() -> RunConstants {
+        RunConstants {
+            run_num: "20240305_001".to_string(),
+            lab: "INRB".to_string(),
+            rt_date: "2024-03-01".to_string(),
+            fasta_date: "2024-03-06".to_string(),
+            fc_uses: "2".to_string(),
+            pos_con: ControlStatus::Pass,
+            neg_con: ControlStatus::NotApplicable,
+            vp1_date: "2024-03-02".to_string(),
+            rtpcr_primers: "PanEV".to_string(),
+            ..RunConstants::default()
+        }
+    }
+
+    // Writes the template the way the template button does and reads it back as a sample sheet
+    fn round_trip(df: &mut DataFrame) -> DataFrame {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = dir.file("template.csv");
+        let mut file = std::fs::File::create(&path).unwrap();
+        CsvWriter::new(&mut file).finish(df).unwrap();
+        read_csv_normalized(&path.to_string_lossy()).unwrap().0
+    }
+
+    #[test]
+    fn prefilled_templates_are_ingested_cleanly() {
+        let params = constants();
+        for mode in ["DDNS", "minION"] {
+            let mut template = create_prefilled_template(mode, 96, &params).unwrap();
+            let empty = create_template_for_mode(mode).unwrap();
+            assert_eq!(template.get_column_names(), empty.get_column_names(), "{}", mode);
+
+            let df = round_trip(&mut template);
+            assert_eq!(df.height(), 96);
+            validate_columns(&df, mode).unwrap();
+            assert!(check_value_domains(&df, mode).is_empty(), "{}", mode);
+            assert_eq!(check_plate_capacity(&df, &PlateCapacity::default()).unwrap(), None, "{}", mode);
+
+            let barcodes = df.column("barcode").unwrap().str().unwrap();
+            assert_eq!(barcodes.get(0), Some("barcode01"));
+            assert_eq!(barcodes.get(95), Some("barcode96"));
+            if mode == "DDNS" {
+                let wells = df.column("Well").unwrap().str().unwrap();
+                assert_eq!((wells.get(0), wells.get(95)), (Some("A1"), Some("H12")));
+            }
+
+            // Every constant typed so far is in its column on every row
+            for (column, value) in run_constant_values(mode, &params) {
+                let cells = df.column(column).unwrap().cast(&DataType::String).unwrap();
+                let cells = cells.str().unwrap();
+                let expected = if value.is_empty() { None } else { Some(value.as_str()) };
+                assert!(cells.into_iter().all(|v| v == expected), "{} {}", mode, column);
+            }
+            // Everything else stays empty for the lab to fill
+            assert_eq!(df.column("sample").unwrap().null_count(), 96);
+        }
+    }
+
+    #[test]
+    fn prefilled_template_needs_rows_and_valid_constants() {
+        let err = create_prefilled_template("DDNS", 0, &constants()).unwrap_err();
+        assert!(err.contains("how many rows"), "{}", err);
+
+        let params = RunConstants { rt_date: "01/03/2024".to_string(), ..constants() };
+        let err = create_prefilled_template("DDNS", 4, &params).unwrap_err();
+        assert!(err.contains("RT PCR Date"), "{}", err);
+    }
+}
@@ -0,0 +1,408 @@
+use chrono::NaiveDate;
+use polars::prelude::*;
+use regex::Regex;
+
+use crate::csv::read_csv_normalized;
+use crate::domains::{check_value_domains, line_of};
+use crate::merge::validate_date;
+use crate::template::{expected_columns_for_mode, fingerprint_bytes, optional_columns_for_mode, PROVENANCE_COLUMNS};
+use crate::warnings::ValidationWarning;
+
+/// Result of re-checking a finished output. Nothing is ever written.
+pub enum VerifyOutcome {
+    Clean,
+    Warnings(Vec<ValidationWarning>),
+    // The file cannot be checked further (missing or unknown columns)
+    Structural(String),
+}
+
+impl VerifyOutcome {
+    /// Process exit code for the CLI: 0 clean, 1 warnings, 2 structural failure
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VerifyOutcome::Clean => 0,
+            VerifyOutcome::Warnings(_) => 1,
+            VerifyOutcome::Structural(_) => 2,
+        }
+    }
+}
+
+// Dates that must not decrease along a sample's life, in order.
+// Columns a mode does not have are skipped.
+const DATE_SEQUENCE: &[&str] = &[
+    "DateOfOnset",
+    "DateStoolCollected",
+    "DateStoolReceivedinLab",
+    "DateRNAextraction",
+    "DateRNAExtraction",
+    "DateRTPCR",
+    "DateSeqRunLoaded",
+    "DateFastaGenerated",
+];
+
+fn cell(df: &DataFrame, column: &str, idx: usize) -> Option<String> {
+    df.column(column)
+        .ok()
+        .and_then(|c| c.str().ok().and_then(|s| s.get(idx).map(|v| v.trim().to_string())))
+        .filter(|v| !v.is_empty())
+}
+
+// Rows whose text is compared when an output is read back; spread over
+// the file and always including the last row, where truncation shows
+const READBACK_SAMPLE_ROWS: usize = 32;
+
+fn sample_rows(height: usize) -> Vec<usize> {
+    if height <= READBACK_SAMPLE_ROWS {
+        return (0..height).collect();
+    }
+    let step = height as f64 / (READBACK_SAMPLE_ROWS - 1) as f64;
+    let mut rows: Vec<usize> = (0..READBACK_SAMPLE_ROWS - 1).map(|i| (i as f64 * step) as usize).collect();
+    rows.push(height - 1);
+    rows.dedup();
+    rows
+}
+
+// Fingerprint of the text columns of `rows`; other columns are left out
+// as their text depends on how numbers were formatted when written
+fn sampled_cells_fingerprint(df: &DataFrame, columns: &[String], rows: &[usize]) -> String {
+    let mut text = String::new();
+    for column in columns {
+        let values = df.column(column).ok().and_then(|c| c.str().ok().cloned());
+        for &row in rows {
+            let value = values.as_ref().and_then(|v| v.get(row)).unwrap_or("");
+            text.push_str(value.trim());
+            text.push('\u{1f}');
+        }
+    }
+    fingerprint_bytes(text.as_bytes())
+}
+
+/// What a written output must read back as: its size and a fingerprint
+/// of sampled text cells. Taken before writing, so no copy of the frame
+/// is kept while the file is saved.
+pub struct WrittenShape {
+    rows: usize,
+    width: usize,
+    // Text columns and rows the fingerprint covers
+    columns: Vec<String>,
+    sample: Vec<usize>,
+    fingerprint: String,
+}
+
+impl WrittenShape {
+    pub fn of(df: &DataFrame) -> Self {
+        let columns: Vec<String> = df
+            .get_columns()
+            .iter()
+            .filter(|c| c.dtype() == &DataType::String)
+            .map(|c| c.name().to_string())
+            .collect();
+        let sample = sample_rows(df.height());
+        let fingerprint = sampled_cells_fingerprint(df, &columns, &sample);
+        Self {
+            rows: df.height(),
+            width: df.width(),
+            columns,
+            sample,
+            fingerprint,
+        }
+    }
+}
+
+/// Reads a just-written output back with the normal CSV reader and checks
+/// it holds what was in memory: same row and column counts, and the same
+/// text in a sample of cells. Network drives and antivirus tools have been
+/// seen to leave a file cut short even though the write succeeded.
+pub fn verify_written(path: &str, expected: &WrittenShape) -> Result<(), String> {
+    let (written, _) = read_csv_normalized(path)
+        .map_err(|e| format!("The output could not be read back: {e}"))?;
+
+    if written.width() != expected.width {
+        return Err(format!(
+            "The output read back has {} columns instead of {}.",
+            written.width(),
+            expected.width
+        ));
+    }
+    if written.height() != expected.rows {
+        return Err(format!(
+            "The output read back has {} rows instead of {}; it was probably cut short while saving.",
+            written.height(),
+            expected.rows
+        ));
+    }
+    if sampled_cells_fingerprint(&written, &expected.columns, &expected.sample) != expected.fingerprint {
+        return Err("The output read back differs from what was written (sampled cells do not match).".to_string());
+    }
+    Ok(())
+}
+
+/// Checks an existing output against the mode's current column template
+/// and value rules
+pub fn verify_output(df: &DataFrame, mode: &str) -> VerifyOutcome {
+    // Optional provenance columns are neither missing nor unknown
+    let actual: Vec<String> = df
+        .get_column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .filter(|c| !PROVENANCE_COLUMNS.contains(&c.as_str()))
+        .collect();
+    // Outputs written before the optional template columns existed lack them
+    let optional = optional_columns_for_mode(mode);
+    let expected: Vec<&str> = expected_columns_for_mode(mode)
+        .into_iter()
+        .filter(|c| !optional.contains(c) || actual.iter().any(|a| a == c))
+        .collect();
+
+    let missing: Vec<&str> = expected
+        .iter()
+        .copied()
+        .filter(|c| !actual.iter().any(|a| a == c))
+        .collect();
+    let unknown: Vec<&str> = actual
+        .iter()
+        .map(|s| s.as_str())
+        .filter(|a| !expected.contains(a))
+        .collect();
+    if !missing.is_empty() || !unknown.is_empty() {
+        let mut msg = format!("The file does not match the current {} template.", mode);
+        if !missing.is_empty() {
+            msg.push_str(&format!("\nMissing columns: {}", missing.join(", ")));
+        }
+        if !unknown.is_empty() {
+            msg.push_str(&format!("\nUnknown columns: {}", unknown.join(", ")));
+        }
+        return VerifyOutcome::Structural(msg);
+    }
+
+    let mut warnings = Vec::new();
+
+    if actual.iter().map(|s| s.as_str()).ne(expected.iter().copied()) {
+        warnings.push(ValidationWarning::ColumnOrderDiffers { mode: mode.to_string() });
+    }
+
+    // Value domains and retest linkage
+    warnings.extend(check_value_domains(df, mode));
+
+    let epid_regex = Regex::new(r"^[A-Z]{3}(-[A-Z0-9]+){3,}$").unwrap();
+
+    for idx in 0..df.height() {
+        let line = line_of(idx);
+
+        // Date formats
+        for column in expected.iter().filter(|c| c.starts_with("Date")) {
+            if let Some(v) = cell(df, column, idx) {
+                if validate_date(&v, column).is_some() {
+                    warnings.push(ValidationWarning::InvalidValue {
+                        line,
+                        column: column.to_string(),
+                        value: v,
+                        expected: "YYYY-MM-DD".to_string(),
+                    });
+                }
+            }
+        }
+
+        // EPID format
+        if let Some(v) = cell(df, "EPID", idx) {
+            if !epid_regex.is_match(&v) {
+                warnings.push(ValidationWarning::InvalidEpid { line, value: v });
+            }
+        }
+
+        // Date logic: each date on or after the previous one present
+        let dates: Vec<(&str, NaiveDate)> = DATE_SEQUENCE
+            .iter()
+            .filter_map(|c| {
+                cell(df, c, idx)
+                    .and_then(|v| NaiveDate::parse_from_str(&v, "%Y-%m-%d").ok())
+                    .map(|d| (*c, d))
+            })
+            .collect();
+        for pair in dates.windows(2) {
+            let ((earlier, d1), (later, d2)) = (pair[0], pair[1]);
+            if d2 < d1 {
+                warnings.push(ValidationWarning::DateOrder {
+                    line,
+                    earlier: earlier.to_string(),
+                    later: later.to_string(),
+                });
+            }
+        }
+    }
+
+    if warnings.is_empty() {
+        VerifyOutcome::Clean
+    } else {
+        VerifyOutcome::Warnings(warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    fn output(rows: usize) -> DataFrame {
+        let samples: Vec<String> = (1..=rows).map(|i| format!("S{:03}", i)).collect();
+        let barcodes: Vec<String> = (1..=rows).map(|i| format!("barcode{:02}", i % 96 + 1)).collect();
+        df!("sample" => samples, "barcode" => barcodes).unwrap()
+    }
+
+    // Writes `df` like the merge does and returns the file's text
+    fn written(dir: &mut TempWorkspace, df: &mut DataFrame) -> (String, String) {
+        let path = dir.file("output.csv");
+        let file = std::fs::File::create(&path).unwrap();
+        CsvWriter::new(file).finish(df).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let text = std::fs::read_to_string(&path).unwrap();
+        (path, text)
+    }
+
+    #[test]
+    fn healthy_output_reads_back() {
+        let mut dir = TempWorkspace::new().unwrap();
+        for rows in [1, 32, 500] {
+            let mut df = output(rows);
+            let expected = WrittenShape::of(&df);
+            let (path, _) = written(&mut dir, &mut df);
+            assert_eq!(verify_written(&path, &expected), Ok(()), "{} rows", rows);
+        }
+    }
+
+    #[test]
+    fn truncated_output_is_caught() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let mut df = output(500);
+        let expected = WrittenShape::of(&df);
+        let (path, text) = written(&mut dir, &mut df);
+
+        // Cut off mid-file, as a network drive dropping the tail would
+        std::fs::write(&path, &text[..text.len() / 2]).unwrap();
+        let error = verify_written(&path, &expected).unwrap_err();
+        assert!(error.contains("rows instead of 500"), "{}", error);
+    }
+
+    #[test]
+    fn changed_cell_is_caught() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let mut df = output(10);
+        let expected = WrittenShape::of(&df);
+        let (path, text) = written(&mut dir, &mut df);
+
+        std::fs::write(&path, text.replace("S010", "S01O")).unwrap();
+        let error = verify_written(&path, &expected).unwrap_err();
+        assert!(error.contains("sampled cells do not match"), "{}", error);
+    }
+
+    #[test]
+    fn missing_column_is_caught() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let df = output(3);
+        let expected = WrittenShape::of(&df);
+        let mut narrower = df.select(["sample"]).unwrap();
+        let (path, _) = written(&mut dir, &mut narrower);
+        let error = verify_written(&path, &expected).unwrap_err();
+        assert!(error.contains("1 columns instead of 2"), "{}", error);
+    }
+
+    #[test]
+    fn sampled_rows_cover_the_last_row() {
+        assert_eq!(sample_rows(3), vec![0, 1, 2]);
+        let rows = sample_rows(1000);
+        assert!(rows.len() <= READBACK_SAMPLE_ROWS);
+        assert_eq!(rows.first(), Some(&0));
+        assert_eq!(rows.last(), Some(&999));
+    }
+
+    // A finished DDNS output of one row; `values` are set on top of a clean
+    // row and `without` columns are left out
+    fn output_file(dir: &mut TempWorkspace, values: &[(&str, &str)], without: &[&str]) -> String {
+        let clean = [
+            ("sample", "S01"),
+            ("barcode", "barcode01"),
+            ("EPID", "NIE-KAN-KAN-24-001"),
+            ("DateOfOnset", "2024-02-01"),
+            ("DateStoolCollected", "2024-02-03"),
+            ("DateRTPCR", "2024-02-20"),
+        ];
+        let columns: Vec<&str> = expected_columns_for_mode("DDNS")
+            .into_iter()
+            .filter(|c| !without.contains(c))
+            .collect();
+        let row: Vec<&str> = columns
+            .iter()
+            .map(|c| {
+                values
+                    .iter()
+                    .chain(clean.iter())
+                    .find(|(name, _)| name == c)
+                    .map_or("", |(_, v)| *v)
+            })
+            .collect();
+        let path = dir.file("finished_output.csv");
+        std::fs::write(&path, format!("{}\n{}\n", columns.join(","), row.join(","))).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn verify_file(path: &str) -> VerifyOutcome {
+        let (df, _) = read_csv_normalized(path).unwrap();
+        verify_output(&df, "DDNS")
+    }
+
+    #[test]
+    fn clean_output_verifies_clean() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = output_file(&mut dir, &[], &[]);
+        let outcome = verify_file(&path);
+        assert!(matches!(outcome, VerifyOutcome::Clean));
+        assert_eq!(outcome.exit_code(), 0);
+    }
+
+    #[test]
+    fn value_problems_verify_as_warnings() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = output_file(
+            &mut dir,
+            &[("EPID", "24-001"), ("DateStoolCollected", "2024-01-20"), ("DateRTPCR", "20/02/2024")],
+            &[],
+        );
+        let outcome = verify_file(&path);
+        assert_eq!(outcome.exit_code(), 1);
+        let VerifyOutcome::Warnings(warnings) = outcome else {
+            panic!("expected warnings");
+        };
+        assert!(warnings.iter().any(|w| matches!(w, ValidationWarning::InvalidEpid { line: 2, value } if value == "24-001")));
+        assert!(warnings.iter().any(|w| matches!(w, ValidationWarning::InvalidValue { column, .. } if column == "DateRTPCR")));
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            ValidationWarning::DateOrder { line: 2, earlier, later } if earlier == "DateOfOnset" && later == "DateStoolCollected"
+        )));
+    }
+
+    #[test]
+    fn missing_template_column_is_structural() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let path = output_file(&mut dir, &[], &["RunNumber"]);
+        let outcome = verify_file(&path);
+        assert_eq!(outcome.exit_code(), 2);
+        let VerifyOutcome::Structural(msg) = outcome else {
+            panic!("expected a structural failure");
+        };
+        assert!(msg.contains("Missing columns: RunNumber"), "{}", msg);
+    }
+
+    #[test]
+    fn cli_exit_codes_follow_the_outcome() {
+        let mut dir = TempWorkspace::new().unwrap();
+        let args = |path: String| vec!["verify".to_string(), path, "--mode".to_string(), "DDNS".to_string()];
+
+        assert_eq!(crate::cli::run_cli(&args(output_file(&mut dir, &[], &[]))), Some(0));
+        assert_eq!(crate::cli::run_cli(&args(output_file(&mut dir, &[("EPID", "24-001")], &[]))), Some(1));
+        assert_eq!(crate::cli::run_cli(&args(output_file(&mut dir, &[], &["RunNumber"]))), Some(2));
+        // Unreadable file and bad arguments
+        let missing = dir.path().join("missing.csv").to_string_lossy().to_string();
+        assert_eq!(crate::cli::run_cli(&args(missing)), Some(2));
+        assert_eq!(crate::cli::run_cli(&["verify".to_string()]), Some(2));
+    }
+}
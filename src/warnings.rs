@@ -0,0 +1,1014 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::merge::ui_field_label;
+
+/// Display language for user-facing messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Fr,
+}
+
+impl Lang {
+    pub fn from_french(is_french: bool) -> Self {
+        if is_french {
+            Lang::Fr
+        } else {
+            Lang::En
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Severity {
+    Info,
+    Warning,
+}
+
+/// Pipeline warnings with stable codes. Codes and context field names are
+/// part of the JSON contract partners script against: never renumber,
+/// only add.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "code", content = "context")]
+pub enum ValidationWarning {
+    #[serde(rename = "W001")]
+    UnmatchedSamples { samples: Vec<String> },
+    #[serde(rename = "W002")]
+    DuplicateSampleReplaced { sample: String },
+    #[serde(rename = "W003")]
+    EpiInfoColumnsSkipped { count: usize },
+    #[serde(rename = "W004")]
+    PreviousOutputBackedUp { path: String },
+    #[serde(rename = "W005")]
+    RowsAppended { new_rows: usize, existing_rows: usize, replaced_rows: usize },
+    #[serde(rename = "W006")]
+    ExcelSafeCopyWritten { path: String },
+    #[serde(rename = "W007")]
+    BarcodesOnlyExpanded { added_columns: usize },
+    #[serde(rename = "W008")]
+    EpiInfoCacheUsed,
+    #[serde(rename = "W009")]
+    IgnoredFields { mode: String, fields: Vec<String> },
+    #[serde(rename = "W010")]
+    EpidFilled { sources: Vec<(String, usize)> },
+    #[serde(rename = "W011")]
+    ColumnOrderDiffers { mode: String },
+    #[serde(rename = "W012")]
+    InvalidValue { line: usize, column: String, value: String, expected: String },
+    #[serde(rename = "W013")]
+    InvalidEpid { line: usize, value: String },
+    #[serde(rename = "W014")]
+    DateOrder { line: usize, earlier: String, later: String },
+    #[serde(rename = "W015")]
+    StaleEpiInfo { age_days: u64 },
+    #[serde(rename = "W016")]
+    RetestWithoutOriginalRun { line: usize },
+    // "Positive" / "Negative"
+    #[serde(rename = "W017")]
+    ControlsNotRecorded { controls: Vec<String> },
+    // Placeholder names without braces, e.g. "lab"
+    #[serde(rename = "W018")]
+    EmptyNamePlaceholders { placeholders: Vec<String> },
+    // Run constant columns by fill rule, shown when per-column rules are set
+    #[serde(rename = "W019")]
+    FillRulesApplied {
+        filled: Vec<String>,
+        overwritten: Vec<String>,
+        skipped: Vec<String>,
+    },
+    // (original, renamed); a blank original is an unnamed column
+    #[serde(rename = "W020")]
+    HeadersRenamed { file: String, renames: Vec<(String, String)> },
+    #[serde(rename = "W021")]
+    FewRows { rows: usize, minimum: usize },
+    #[serde(rename = "W022")]
+    DateReportedFilled { date: String, rows: usize },
+    // used: "report" or "manual"
+    #[serde(rename = "W023")]
+    SeqDateMismatch { manual: String, report: String, used: String },
+    // Sensitive or unticked EpiInfo columns left out of the merge
+    #[serde(rename = "W024")]
+    EpiInfoColumnsExcluded { columns: Vec<String> },
+    // (file name, detected profile) per input CSV
+    #[serde(rename = "W025")]
+    InputProfiles { files: Vec<(String, String)> },
+    // Few samples matched the EpiInfo file; the user chose to continue
+    #[serde(rename = "W026")]
+    LowEpiMatchRate { matched: usize, samples: usize, threshold: u8 },
+    // Fully empty rows at the end of an input, dropped before validation
+    #[serde(rename = "W027")]
+    TrailingBlankRowsDropped { file: String, count: usize },
+    // Fully empty rows between filled rows, removed after confirmation
+    #[serde(rename = "W028")]
+    InteriorBlankRowsRemoved { file: String, lines: Vec<String> },
+    // Sheet from an older template; these optional columns were added empty
+    #[serde(rename = "W029")]
+    OptionalColumnsAdded { columns: Vec<String> },
+    // Controls missing from the samples file, added as rows
+    #[serde(rename = "W030")]
+    ControlRowsAdded { samples: Vec<String> },
+    // A control that could not be added: its barcode is used by `used_by`,
+    // or (None) the plate is full
+    #[serde(rename = "W031")]
+    ControlRowSkipped { sample: String, barcode: String, used_by: Option<String> },
+    #[serde(rename = "W032")]
+    ReportCacheUsed,
+    // The post-merge hook from settings ran; `status` is e.g. "exit 0"
+    #[serde(rename = "W033")]
+    PostMergeHookRan { command: String, status: String, output: Vec<String> },
+    // The hook failed, timed out or could not start. The output stays written.
+    #[serde(rename = "W034")]
+    PostMergeHookFailed { command: String, status: String, output: Vec<String> },
+    // Samples paired with EpiInfo only by their trailing number, (sample, ICLabID)
+    #[serde(rename = "W035")]
+    WeakSampleMatches { pairs: Vec<(String, String)> },
+    // How many samples each matching strategy paired, when any fallback was used
+    #[serde(rename = "W036")]
+    SampleMatchMethods { counts: Vec<(String, usize)> },
+    // Barcode cells rewritten to barcodeNN, as "line 4: BC04 → barcode04"
+    #[serde(rename = "W037")]
+    BarcodesNormalized { changes: Vec<String> },
+    // Rows with barcode "unclassified", removed after confirmation
+    #[serde(rename = "W038")]
+    UnclassifiedRowsRemoved { lines: Vec<String> },
+    // Join-key headers typed in another case, renamed to the template's
+    #[serde(rename = "W039")]
+    HeaderCaseFixed { file: String, renames: Vec<(String, String)> },
+    // Combined MinKNOW report with several positions; the one used
+    #[serde(rename = "W040")]
+    ReportPositionChosen { position: String, fc_id: String, runs: usize },
+    // EpiInfo records of the period with no sample, written for reconciliation
+    #[serde(rename = "W041")]
+    UnsampledEpiReportWritten { path: String, records: usize, undated: usize, missing_columns: Vec<String> },
+    #[serde(rename = "W042")]
+    UnsampledEpiReportSkipped { reason: String },
+    // Columns named like a Windows device (CON, NUL, COM1, ...)
+    #[serde(rename = "W043")]
+    ReservedColumnNames { file: String, columns: Vec<String> },
+    // Columns renamed onto the template in the column resolution
+    #[serde(rename = "W044")]
+    ColumnsResolved { file: String, renames: Vec<(String, String)> },
+    // Samples file on the 2023 layout, upgraded to the current column names
+    #[serde(rename = "W045")]
+    LegacyLayoutUpgraded { file: String, renamed: Vec<(String, String)>, dropped: Vec<String> },
+    // Typed run constants that differed from values in the samples file;
+    // decision: "overwrite" (every row got the typed value) or "keep"
+    #[serde(rename = "W046")]
+    RunConstantConflict { columns: Vec<String>, decision: String },
+    // Input that was an online-only cloud placeholder when the merge started
+    #[serde(rename = "W047")]
+    CloudPlaceholderInput { file: String },
+    // Input named like a sync client's conflicting copy
+    #[serde(rename = "W048")]
+    ConflictedCopyInput { file: String },
+    // Merged with "No barcoding"; cleared = barcode cells that held a value
+    #[serde(rename = "W049")]
+    NoBarcoding { cleared: usize },
+    // Output name pattern without {run}; the run number was put in front
+    #[serde(rename = "W050")]
+    RunAddedToOutputName { pattern: String },
+}
+
+// A header as it can be shown on one line: tabs and line breaks spelled out
+fn visible(header: &str) -> String {
+    header.replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+// Shows at most this many items of a list inside one message
+const MAX_LISTED: usize = 10;
+
+fn short_list(items: &[String]) -> String {
+    if items.len() <= MAX_LISTED {
+        items.join(", ")
+    } else {
+        format!("{}, … (+{})", items[..MAX_LISTED].join(", "), items.len() - MAX_LISTED)
+    }
+}
+
+// Captured hook output, indented under the message
+fn hook_output(lines: &[String]) -> String {
+    lines.iter().map(|l| format!("\n    {}", l)).collect()
+}
+
+impl ValidationWarning {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationWarning::UnmatchedSamples { .. } => "W001",
+            ValidationWarning::DuplicateSampleReplaced { .. } => "W002",
+            ValidationWarning::EpiInfoColumnsSkipped { .. } => "W003",
+            ValidationWarning::PreviousOutputBackedUp { .. } => "W004",
+            ValidationWarning::RowsAppended { .. } => "W005",
+            ValidationWarning::ExcelSafeCopyWritten { .. } => "W006",
+            ValidationWarning::BarcodesOnlyExpanded { .. } => "W007",
+            ValidationWarning::EpiInfoCacheUsed => "W008",
+            ValidationWarning::IgnoredFields { .. } => "W009",
+            ValidationWarning::EpidFilled { .. } => "W010",
+            ValidationWarning::ColumnOrderDiffers { .. } => "W011",
+            ValidationWarning::InvalidValue { .. } => "W012",
+            ValidationWarning::InvalidEpid { .. } => "W013",
+            ValidationWarning::DateOrder { .. } => "W014",
+            ValidationWarning::StaleEpiInfo { .. } => "W015",
+            ValidationWarning::RetestWithoutOriginalRun { .. } => "W016",
+            ValidationWarning::ControlsNotRecorded { .. } => "W017",
+            ValidationWarning::EmptyNamePlaceholders { .. } => "W018",
+            ValidationWarning::FillRulesApplied { .. } => "W019",
+            ValidationWarning::HeadersRenamed { .. } => "W020",
+            ValidationWarning::FewRows { .. } => "W021",
+            ValidationWarning::DateReportedFilled { .. } => "W022",
+            ValidationWarning::SeqDateMismatch { .. } => "W023",
+            ValidationWarning::EpiInfoColumnsExcluded { .. } => "W024",
+            ValidationWarning::InputProfiles { .. } => "W025",
+            ValidationWarning::LowEpiMatchRate { .. } => "W026",
+            ValidationWarning::TrailingBlankRowsDropped { .. } => "W027",
+            ValidationWarning::InteriorBlankRowsRemoved { .. } => "W028",
+            ValidationWarning::OptionalColumnsAdded { .. } => "W029",
+            ValidationWarning::ControlRowsAdded { .. } => "W030",
+            ValidationWarning::ControlRowSkipped { .. } => "W031",
+            ValidationWarning::ReportCacheUsed => "W032",
+            ValidationWarning::PostMergeHookRan { .. } => "W033",
+            ValidationWarning::PostMergeHookFailed { .. } => "W034",
+            ValidationWarning::WeakSampleMatches { .. } => "W035",
+            ValidationWarning::SampleMatchMethods { .. } => "W036",
+            ValidationWarning::BarcodesNormalized { .. } => "W037",
+            ValidationWarning::UnclassifiedRowsRemoved { .. } => "W038",
+            ValidationWarning::HeaderCaseFixed { .. } => "W039",
+            ValidationWarning::ReportPositionChosen { .. } => "W040",
+            ValidationWarning::UnsampledEpiReportWritten { .. } => "W041",
+            ValidationWarning::UnsampledEpiReportSkipped { .. } => "W042",
+            ValidationWarning::ReservedColumnNames { .. } => "W043",
+            ValidationWarning::ColumnsResolved { .. } => "W044",
+            ValidationWarning::LegacyLayoutUpgraded { .. } => "W045",
+            ValidationWarning::RunConstantConflict { .. } => "W046",
+            ValidationWarning::CloudPlaceholderInput { .. } => "W047",
+            ValidationWarning::ConflictedCopyInput { .. } => "W048",
+            ValidationWarning::NoBarcoding { .. } => "W049",
+            ValidationWarning::RunAddedToOutputName { .. } => "W050",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationWarning::UnmatchedSamples { .. }
+            | ValidationWarning::LowEpiMatchRate { .. }
+            | ValidationWarning::InteriorBlankRowsRemoved { .. }
+            | ValidationWarning::UnclassifiedRowsRemoved { .. }
+            | ValidationWarning::ControlRowSkipped { .. }
+            | ValidationWarning::PostMergeHookFailed { .. }
+            | ValidationWarning::UnsampledEpiReportSkipped { .. }
+            | ValidationWarning::LegacyLayoutUpgraded { .. }
+            | ValidationWarning::RunConstantConflict { .. }
+            | ValidationWarning::CloudPlaceholderInput { .. }
+            | ValidationWarning::ConflictedCopyInput { .. }
+            | ValidationWarning::ReservedColumnNames { .. }
+            | ValidationWarning::WeakSampleMatches { .. }
+            | ValidationWarning::DuplicateSampleReplaced { .. }
+            | ValidationWarning::ColumnOrderDiffers { .. }
+            | ValidationWarning::InvalidValue { .. }
+            | ValidationWarning::InvalidEpid { .. }
+            | ValidationWarning::DateOrder { .. }
+            | ValidationWarning::RetestWithoutOriginalRun { .. }
+            | ValidationWarning::ControlsNotRecorded { .. }
+            | ValidationWarning::EmptyNamePlaceholders { .. }
+            | ValidationWarning::RunAddedToOutputName { .. }
+            | ValidationWarning::FewRows { .. } => Severity::Warning,
+            ValidationWarning::EpiInfoColumnsSkipped { .. }
+            | ValidationWarning::PreviousOutputBackedUp { .. }
+            | ValidationWarning::RowsAppended { .. }
+            | ValidationWarning::ExcelSafeCopyWritten { .. }
+            | ValidationWarning::BarcodesOnlyExpanded { .. }
+            | ValidationWarning::EpiInfoCacheUsed
+            | ValidationWarning::ReportCacheUsed
+            | ValidationWarning::PostMergeHookRan { .. }
+            | ValidationWarning::SampleMatchMethods { .. }
+            | ValidationWarning::BarcodesNormalized { .. }
+            | ValidationWarning::FillRulesApplied { .. }
+            | ValidationWarning::HeadersRenamed { .. }
+            | ValidationWarning::HeaderCaseFixed { .. }
+            | ValidationWarning::ColumnsResolved { .. }
+            | ValidationWarning::ReportPositionChosen { .. }
+            | ValidationWarning::UnsampledEpiReportWritten { .. }
+            | ValidationWarning::DateReportedFilled { .. }
+            | ValidationWarning::SeqDateMismatch { .. }
+            | ValidationWarning::EpiInfoColumnsExcluded { .. }
+            | ValidationWarning::InputProfiles { .. }
+            | ValidationWarning::TrailingBlankRowsDropped { .. }
+            | ValidationWarning::OptionalColumnsAdded { .. }
+            | ValidationWarning::ControlRowsAdded { .. }
+            | ValidationWarning::IgnoredFields { .. }
+            | ValidationWarning::EpidFilled { .. }
+            | ValidationWarning::NoBarcoding { .. }
+            | ValidationWarning::StaleEpiInfo { .. } => Severity::Info,
+        }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        let text = match (self, lang) {
+            (ValidationWarning::UnmatchedSamples { samples }, Lang::En) => format!(
+                "{} sample(s) had no match in the Epi Info file: {}",
+                samples.len(),
+                short_list(samples)
+            ),
+            (ValidationWarning::UnmatchedSamples { samples }, Lang::Fr) => format!(
+                "{} échantillon(s) sans correspondance dans le fichier Epi Info : {}",
+                samples.len(),
+                short_list(samples)
+            ),
+            (ValidationWarning::DuplicateSampleReplaced { sample }, Lang::En) => format!(
+                "Sample '{}' was already in the output with different data; the new row was kept.",
+                sample
+            ),
+            (ValidationWarning::DuplicateSampleReplaced { sample }, Lang::Fr) => format!(
+                "L'échantillon '{}' figurait déjà dans la sortie avec des données différentes ; la nouvelle ligne a été conservée.",
+                sample
+            ),
+            (ValidationWarning::EpiInfoColumnsSkipped { count }, Lang::En) => {
+                format!("Skipped {} unused Epi Info column(s) at read time.", count)
+            }
+            (ValidationWarning::EpiInfoColumnsSkipped { count }, Lang::Fr) => {
+                format!("{} colonne(s) Epi Info non utilisée(s) ignorée(s) à la lecture.", count)
+            }
+            (ValidationWarning::PreviousOutputBackedUp { path }, Lang::En) => {
+                format!("Previous output backed up as {}.", path)
+            }
+            (ValidationWarning::PreviousOutputBackedUp { path }, Lang::Fr) => {
+                format!("La sortie précédente a été sauvegardée sous {}.", path)
+            }
+            (ValidationWarning::RowsAppended { new_rows, existing_rows, replaced_rows }, Lang::En) => format!(
+                "Appended {} new row(s) to {} existing row(s) ({} replaced).",
+                new_rows, existing_rows, replaced_rows
+            ),
+            (ValidationWarning::RowsAppended { new_rows, existing_rows, replaced_rows }, Lang::Fr) => format!(
+                "{} nouvelle(s) ligne(s) ajoutée(s) à {} ligne(s) existante(s) ({} remplacée(s)).",
+                new_rows, existing_rows, replaced_rows
+            ),
+            (ValidationWarning::ExcelSafeCopyWritten { path }, Lang::En) => {
+                format!("Excel-safe copy written to {}.", path)
+            }
+            (ValidationWarning::ExcelSafeCopyWritten { path }, Lang::Fr) => {
+                format!("Copie compatible Excel écrite dans {}.", path)
+            }
+            (ValidationWarning::BarcodesOnlyExpanded { added_columns }, Lang::En) => format!(
+                "The sample file only had sample and barcode columns; {} empty column(s) were added.",
+                added_columns
+            ),
+            (ValidationWarning::BarcodesOnlyExpanded { added_columns }, Lang::Fr) => format!(
+                "Le fichier d'échantillons ne contenait que les colonnes sample et barcode ; {} colonne(s) vide(s) ajoutée(s).",
+                added_columns
+            ),
+            (ValidationWarning::EpiInfoCacheUsed, Lang::En) => {
+                "The Epi Info file was unchanged since the last merge; the cached copy was used.".to_string()
+            }
+            (ValidationWarning::EpiInfoCacheUsed, Lang::Fr) => {
+                "Le fichier Epi Info n'a pas changé depuis la dernière fusion ; la copie en cache a été utilisée.".to_string()
+            }
+            (ValidationWarning::WeakSampleMatches { pairs }, Lang::En) => {
+                let pairs: Vec<String> = pairs.iter().map(|(s, id)| format!("{} → {}", s, id)).collect();
+                format!(
+                    "These samples were paired with Epi Info by their trailing number only; please check them: {}",
+                    short_list(&pairs)
+                )
+            }
+            (ValidationWarning::WeakSampleMatches { pairs }, Lang::Fr) => {
+                let pairs: Vec<String> = pairs.iter().map(|(s, id)| format!("{} → {}", s, id)).collect();
+                format!(
+                    "Ces échantillons ont été appariés à Epi Info par leur numéro final uniquement ; veuillez les vérifier : {}",
+                    short_list(&pairs)
+                )
+            }
+            (ValidationWarning::SampleMatchMethods { counts }, Lang::En) => {
+                let counts: Vec<String> = counts.iter().map(|(m, n)| format!("{} {}", n, m)).collect();
+                format!("Epi Info matching: {}", counts.join(", "))
+            }
+            (ValidationWarning::SampleMatchMethods { counts }, Lang::Fr) => {
+                let counts: Vec<String> = counts.iter().map(|(m, n)| format!("{} {}", n, m)).collect();
+                format!("Appariement Epi Info : {}", counts.join(", "))
+            }
+            (ValidationWarning::BarcodesNormalized { changes }, Lang::En) => {
+                format!("Barcodes renamed to the barcodeNN form: {}", short_list(changes))
+            }
+            (ValidationWarning::BarcodesNormalized { changes }, Lang::Fr) => {
+                format!("Codes-barres renommés au format barcodeNN : {}", short_list(changes))
+            }
+            (ValidationWarning::UnclassifiedRowsRemoved { lines }, Lang::En) => {
+                format!("Rows with barcode \"unclassified\" removed: lines {}", short_list(lines))
+            }
+            (ValidationWarning::UnclassifiedRowsRemoved { lines }, Lang::Fr) => {
+                format!("Lignes avec le code-barres « unclassified » retirées : lignes {}", short_list(lines))
+            }
+            (ValidationWarning::PostMergeHookRan { command, status, output }, Lang::En) => {
+                format!("Post-merge hook ran ({}): {}{}", status, command, hook_output(output))
+            }
+            (ValidationWarning::PostMergeHookRan { command, status, output }, Lang::Fr) => {
+                format!("Commande après fusion exécutée ({}) : {}{}", status, command, hook_output(output))
+            }
+            (ValidationWarning::PostMergeHookFailed { command, status, output }, Lang::En) => format!(
+                "The post-merge hook failed ({}): {}{}\nThe output was written; run the command again by hand if needed.",
+                status, command, hook_output(output)
+            ),
+            (ValidationWarning::PostMergeHookFailed { command, status, output }, Lang::Fr) => format!(
+                "La commande après fusion a échoué ({}) : {}{}\nLa sortie a bien été écrite ; relancez la commande à la main si nécessaire.",
+                status, command, hook_output(output)
+            ),
+            (ValidationWarning::ReportCacheUsed, Lang::En) => {
+                "The MinKNOW report was unchanged since it was last read; the values already read were used.".to_string()
+            }
+            (ValidationWarning::ReportCacheUsed, Lang::Fr) => {
+                "Le rapport MinKNOW n'a pas changé depuis sa dernière lecture ; les valeurs déjà lues ont été utilisées.".to_string()
+            }
+            (ValidationWarning::IgnoredFields { mode, fields }, Lang::En) => format!(
+                "The following entries were ignored in {} mode: {}",
+                mode,
+                fields.iter().map(|f| ui_field_label(f, false)).collect::<Vec<_>>().join(", ")
+            ),
+            (ValidationWarning::IgnoredFields { mode, fields }, Lang::Fr) => format!(
+                "Les entrées suivantes ont été ignorées en mode {} : {}",
+                mode,
+                fields.iter().map(|f| ui_field_label(f, true)).collect::<Vec<_>>().join(", ")
+            ),
+            (ValidationWarning::EpidFilled { sources }, Lang::En) => format!(
+                "EPID filled from Epi Info: {}",
+                sources.iter().map(|(s, n)| format!("{} from {}", n, s)).collect::<Vec<_>>().join(", ")
+            ),
+            (ValidationWarning::EpidFilled { sources }, Lang::Fr) => format!(
+                "EPID complété depuis Epi Info : {}",
+                sources.iter().map(|(s, n)| format!("{} depuis {}", n, s)).collect::<Vec<_>>().join(", ")
+            ),
+            (ValidationWarning::ColumnOrderDiffers { mode }, Lang::En) => {
+                format!("The columns are not in the current {} template order.", mode)
+            }
+            (ValidationWarning::ColumnOrderDiffers { mode }, Lang::Fr) => {
+                format!("Les colonnes ne suivent pas l'ordre actuel du modèle {}.", mode)
+            }
+            (ValidationWarning::InvalidValue { line, column, value, expected }, Lang::En) => format!(
+                "Line {}: {} is '{}', expected {}.",
+                line, column, value, expected
+            ),
+            (ValidationWarning::InvalidValue { line, column, value, expected }, Lang::Fr) => format!(
+                "Ligne {} : {} vaut '{}', attendu {}.",
+                line, column, value, expected
+            ),
+            (ValidationWarning::InvalidEpid { line, value }, Lang::En) => {
+                format!("Line {}: EPID '{}' does not look like CCC-XXX-XXX-YY-NNN.", line, value)
+            }
+            (ValidationWarning::InvalidEpid { line, value }, Lang::Fr) => {
+                format!("Ligne {} : l'EPID '{}' ne ressemble pas à CCC-XXX-XXX-YY-NNN.", line, value)
+            }
+            (ValidationWarning::DateOrder { line, earlier, later }, Lang::En) => {
+                format!("Line {}: {} is before {}.", line, later, earlier)
+            }
+            (ValidationWarning::DateOrder { line, earlier, later }, Lang::Fr) => {
+                format!("Ligne {} : {} est antérieure à {}.", line, later, earlier)
+            }
+            (ValidationWarning::StaleEpiInfo { age_days }, Lang::En) => format!(
+                "The Epi Info export is {} days old; recent cases may be missing. Consider exporting a fresh copy.",
+                age_days
+            ),
+            (ValidationWarning::StaleEpiInfo { age_days }, Lang::Fr) => format!(
+                "L'export Epi Info date de {} jours ; des cas récents peuvent manquer. Pensez à réexporter.",
+                age_days
+            ),
+            (ValidationWarning::RetestWithoutOriginalRun { line }, Lang::En) => {
+                format!("Line {}: IsQCRetest is Yes but IfRetestOriginalRun is empty.", line)
+            }
+            (ValidationWarning::RetestWithoutOriginalRun { line }, Lang::Fr) => {
+                format!("Ligne {} : IsQCRetest vaut Yes mais IfRetestOriginalRun est vide.", line)
+            }
+            (ValidationWarning::ControlsNotRecorded { controls }, Lang::En) => {
+                format!(
+                    "PCR control check not recorded: {}. Select Passed, Failed or Not applicable.",
+                    controls.join(", ")
+                )
+            }
+            (ValidationWarning::ControlsNotRecorded { controls }, Lang::Fr) => {
+                let controls: Vec<&str> = controls
+                    .iter()
+                    .map(|c| if c == "Positive" { "positif" } else { "négatif" })
+                    .collect();
+                format!(
+                    "Contrôle PCR non renseigné : {}. Choisissez Passed, Failed ou Not applicable.",
+                    controls.join(", ")
+                )
+            }
+            (ValidationWarning::EmptyNamePlaceholders { placeholders }, Lang::En) => {
+                let names: Vec<String> = placeholders.iter().map(|p| format!("{{{}}}", p)).collect();
+                format!("The output file name is missing {}: the value was empty.", names.join(", "))
+            }
+            (ValidationWarning::EmptyNamePlaceholders { placeholders }, Lang::Fr) => {
+                let names: Vec<String> = placeholders.iter().map(|p| format!("{{{}}}", p)).collect();
+                format!("Le nom du fichier de sortie ne contient pas {} : la valeur était vide.", names.join(", "))
+            }
+            (ValidationWarning::FillRulesApplied { filled, overwritten, skipped }, Lang::En) => {
+                let list = |cols: &Vec<String>| if cols.is_empty() { "none".to_string() } else { short_list(cols) };
+                format!(
+                    "Run constants: empty cells filled in {}; overwritten in {}; skipped: {}.",
+                    list(filled),
+                    list(overwritten),
+                    list(skipped)
+                )
+            }
+            (ValidationWarning::FillRulesApplied { filled, overwritten, skipped }, Lang::Fr) => {
+                let list = |cols: &Vec<String>| if cols.is_empty() { "aucune".to_string() } else { short_list(cols) };
+                format!(
+                    "Constantes de run : cellules vides remplies dans {} ; écrasées dans {} ; ignorées : {}.",
+                    list(filled),
+                    list(overwritten),
+                    list(skipped)
+                )
+            }
+            (ValidationWarning::HeadersRenamed { file, renames }, Lang::En) => {
+                let pairs: Vec<String> = renames
+                    .iter()
+                    .map(|(from, to)| format!("{} → {}", if from.trim().is_empty() { "(blank)".to_string() } else { visible(from) }, to))
+                    .collect();
+                format!("Duplicate, blank or malformed column headers in {} were renamed: {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::HeadersRenamed { file, renames }, Lang::Fr) => {
+                let pairs: Vec<String> = renames
+                    .iter()
+                    .map(|(from, to)| format!("{} → {}", if from.trim().is_empty() { "(vide)".to_string() } else { visible(from) }, to))
+                    .collect();
+                format!("En-têtes de colonnes en double, vides ou mal formés renommés dans {} : {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::LegacyLayoutUpgraded { file, renamed, dropped }, Lang::En) => {
+                let pairs: Vec<String> = renamed.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!(
+                    "{} uses the 2023 samples layout and was upgraded for this merge (renamed: {}; dropped: {}). Please switch to the current template.",
+                    file,
+                    if pairs.is_empty() { "-".to_string() } else { short_list(&pairs) },
+                    if dropped.is_empty() { "-".to_string() } else { short_list(dropped) }
+                )
+            }
+            (ValidationWarning::LegacyLayoutUpgraded { file, renamed, dropped }, Lang::Fr) => {
+                let pairs: Vec<String> = renamed.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!(
+                    "{} utilise la présentation d'échantillons de 2023 et a été mis à jour pour cette fusion (renommées : {} ; retirées : {}). Veuillez passer au modèle actuel.",
+                    file,
+                    if pairs.is_empty() { "-".to_string() } else { short_list(&pairs) },
+                    if dropped.is_empty() { "-".to_string() } else { short_list(dropped) }
+                )
+            }
+            (ValidationWarning::RunConstantConflict { columns, decision }, Lang::En) => {
+                if decision == "overwrite" {
+                    format!("Typed run constants replaced the samples file's differing values in every row: {}", short_list(columns))
+                } else {
+                    format!(
+                        "Typed run constants differ from the samples file's values and were only filled into empty cells, so these columns hold more than one value: {}",
+                        short_list(columns)
+                    )
+                }
+            }
+            (ValidationWarning::RunConstantConflict { columns, decision }, Lang::Fr) => {
+                if decision == "overwrite" {
+                    format!(
+                        "Les constantes d'exécution saisies ont remplacé les valeurs différentes du fichier d'échantillons sur toutes les lignes : {}",
+                        short_list(columns)
+                    )
+                } else {
+                    format!(
+                        "Les constantes d'exécution saisies diffèrent des valeurs du fichier d'échantillons et n'ont rempli que les cellules vides ; ces colonnes contiennent plusieurs valeurs : {}",
+                        short_list(columns)
+                    )
+                }
+            }
+            (ValidationWarning::NoBarcoding { cleared }, Lang::En) => {
+                if *cleared > 0 {
+                    format!("Merged without barcoding: barcode checks were skipped and {} barcode value(s) were left out of the output.", cleared)
+                } else {
+                    "Merged without barcoding: barcode checks were skipped and the barcode column is blank.".to_string()
+                }
+            }
+            (ValidationWarning::NoBarcoding { cleared }, Lang::Fr) => {
+                if *cleared > 0 {
+                    format!("Fusion sans codes-barres : vérifications des codes-barres ignorées et {} valeur(s) de code-barres retirée(s) de la sortie.", cleared)
+                } else {
+                    "Fusion sans codes-barres : vérifications des codes-barres ignorées, la colonne barcode est vide.".to_string()
+                }
+            }
+            (ValidationWarning::RunAddedToOutputName { pattern }, Lang::En) => format!(
+                "The output name pattern '{}' has no {{run}}, so the run number was put in front of the file name. Add {{run}} where it should go.",
+                pattern
+            ),
+            (ValidationWarning::RunAddedToOutputName { pattern }, Lang::Fr) => format!(
+                "Le modèle de nom de sortie '{}' ne contient pas {{run}} : le numéro de run a été ajouté au début du nom du fichier. Ajoutez {{run}} là où il doit figurer.",
+                pattern
+            ),
+            (ValidationWarning::CloudPlaceholderInput { file }, Lang::En) => format!(
+                "{} was an online-only cloud file and had to be downloaded by the sync client (OneDrive, ...) to be read; check that it is the latest version.",
+                file
+            ),
+            (ValidationWarning::CloudPlaceholderInput { file }, Lang::Fr) => format!(
+                "{} n'était disponible qu'en ligne et a dû être téléchargé par le client de synchronisation (OneDrive, ...) pour être lu ; vérifiez qu'il s'agit de la dernière version.",
+                file
+            ),
+            (ValidationWarning::ConflictedCopyInput { file }, Lang::En) => format!(
+                "{} is named like a copy made by a cloud sync conflict; check that it is the right version of the file.",
+                file
+            ),
+            (ValidationWarning::ConflictedCopyInput { file }, Lang::Fr) => format!(
+                "{} porte le nom d'une copie créée par un conflit de synchronisation ; vérifiez qu'il s'agit de la bonne version du fichier.",
+                file
+            ),
+            (ValidationWarning::ColumnsResolved { file, renames }, Lang::En) => {
+                let pairs: Vec<String> = renames.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!("Columns of {} renamed to the template's names as reviewed: {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::ColumnsResolved { file, renames }, Lang::Fr) => {
+                let pairs: Vec<String> = renames.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!("Colonnes de {} renommées selon le modèle après vérification : {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::HeaderCaseFixed { file, renames }, Lang::En) => {
+                let pairs: Vec<String> = renames.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!("Column headers in {} were set to the template's case: {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::HeaderCaseFixed { file, renames }, Lang::Fr) => {
+                let pairs: Vec<String> = renames.iter().map(|(from, to)| format!("{} → {}", from, to)).collect();
+                format!("En-têtes de colonnes de {} mis à la casse du modèle : {}", file, short_list(&pairs))
+            }
+            (ValidationWarning::ReportPositionChosen { position, fc_id, runs }, Lang::En) => format!(
+                "The MinKNOW report holds {} runs; position {} (flow cell {}) was used.",
+                runs, position, fc_id
+            ),
+            (ValidationWarning::ReportPositionChosen { position, fc_id, runs }, Lang::Fr) => format!(
+                "Le rapport MinKNOW contient {} runs ; la position {} (flow cell {}) a été utilisée.",
+                runs, position, fc_id
+            ),
+            (ValidationWarning::UnsampledEpiReportWritten { path, records, undated, missing_columns }, Lang::En) => {
+                let mut text = format!("{} EpiInfo record(s) without a sample were written to {}.", records, path);
+                if *undated > 0 {
+                    text.push_str(&format!(" {} record(s) without a readable date were left out.", undated));
+                }
+                if !missing_columns.is_empty() {
+                    text.push_str(&format!(" Columns not in the export: {}.", short_list(missing_columns)));
+                }
+                text
+            }
+            (ValidationWarning::UnsampledEpiReportWritten { path, records, undated, missing_columns }, Lang::Fr) => {
+                let mut text = format!("{} fiche(s) Epi Info sans échantillon écrites dans {}.", records, path);
+                if *undated > 0 {
+                    text.push_str(&format!(" {} fiche(s) sans date lisible ont été écartées.", undated));
+                }
+                if !missing_columns.is_empty() {
+                    text.push_str(&format!(" Colonnes absentes de l'export : {}.", short_list(missing_columns)));
+                }
+                text
+            }
+            (ValidationWarning::UnsampledEpiReportSkipped { reason }, Lang::En) => {
+                format!("The EpiInfo records without samples were not written: {}", reason)
+            }
+            (ValidationWarning::UnsampledEpiReportSkipped { reason }, Lang::Fr) => {
+                format!("Les fiches Epi Info sans échantillon n'ont pas été écrites : {}", reason)
+            }
+            (ValidationWarning::ReservedColumnNames { file, columns }, Lang::En) => format!(
+                "{} has columns named like Windows devices ({}). The CSV is fine, but spreadsheet and other tools may refuse them; consider renaming.",
+                file,
+                short_list(columns)
+            ),
+            (ValidationWarning::ReservedColumnNames { file, columns }, Lang::Fr) => format!(
+                "{} a des colonnes portant un nom réservé par Windows ({}). Le CSV reste valide, mais des tableurs et d'autres outils peuvent les refuser ; pensez à les renommer.",
+                file,
+                short_list(columns)
+            ),
+            (ValidationWarning::FewRows { rows, minimum }, Lang::En) => format!(
+                "The run has only {} rows (expected at least {}). Check the sample sheet was not cut short.",
+                rows, minimum
+            ),
+            (ValidationWarning::FewRows { rows, minimum }, Lang::Fr) => format!(
+                "Le run ne compte que {} lignes (au moins {} attendues). Vérifiez que la feuille d'échantillons n'est pas tronquée.",
+                rows, minimum
+            ),
+            (ValidationWarning::DateReportedFilled { date, rows }, Lang::En) => {
+                format!("DateReported set to {} for {} sample(s) that had no date.", date, rows)
+            }
+            (ValidationWarning::DateReportedFilled { date, rows }, Lang::Fr) => {
+                format!("DateReported fixée au {} pour {} échantillon(s) sans date.", date, rows)
+            }
+            (ValidationWarning::SeqDateMismatch { manual, report, used }, Lang::En) => format!(
+                "The sequencing date entered ({}) differs from the MinKNOW report ({}); the {} date was used.",
+                manual,
+                report,
+                if used == "manual" { "entered" } else { "report" }
+            ),
+            (ValidationWarning::SeqDateMismatch { manual, report, used }, Lang::Fr) => format!(
+                "La date de séquençage saisie ({}) diffère du rapport MinKNOW ({}) ; la date {} a été utilisée.",
+                manual,
+                report,
+                if used == "manual" { "saisie" } else { "du rapport" }
+            ),
+            (ValidationWarning::EpiInfoColumnsExcluded { columns }, Lang::En) => {
+                format!("EpiInfo columns not imported: {}", short_list(columns))
+            }
+            (ValidationWarning::EpiInfoColumnsExcluded { columns }, Lang::Fr) => {
+                format!("Colonnes Epi Info non importées : {}", short_list(columns))
+            }
+            (ValidationWarning::LowEpiMatchRate { matched, samples, threshold }, Lang::En) => format!(
+                "Only {} of {} samples matched the Epi Info file (below {}%); the merge was continued anyway.",
+                matched, samples, threshold
+            ),
+            (ValidationWarning::LowEpiMatchRate { matched, samples, threshold }, Lang::Fr) => format!(
+                "Seulement {} échantillons sur {} correspondent au fichier Epi Info (moins de {} %) ; la fusion a été poursuivie.",
+                matched, samples, threshold
+            ),
+            (ValidationWarning::TrailingBlankRowsDropped { file, count }, Lang::En) => {
+                format!("{} empty row(s) at the end of {} were ignored.", count, file)
+            }
+            (ValidationWarning::TrailingBlankRowsDropped { file, count }, Lang::Fr) => {
+                format!("{} ligne(s) vide(s) à la fin de {} ont été ignorées.", count, file)
+            }
+            (ValidationWarning::ControlRowsAdded { samples }, Lang::En) => format!(
+                "Control rows added because the samples file had none: {}",
+                samples.join(", ")
+            ),
+            (ValidationWarning::ControlRowsAdded { samples }, Lang::Fr) => format!(
+                "Lignes de contrôle ajoutées car absentes du fichier d'échantillons : {}",
+                samples.join(", ")
+            ),
+            (ValidationWarning::ControlRowSkipped { sample, barcode, used_by }, Lang::En) => match used_by {
+                Some(other) => format!(
+                    "Control {} was not added: its barcode {} is already used by {}.",
+                    sample, barcode, other
+                ),
+                None => format!("Control {} was not added: the plate is full.", sample),
+            },
+            (ValidationWarning::ControlRowSkipped { sample, barcode, used_by }, Lang::Fr) => match used_by {
+                Some(other) => format!(
+                    "Le contrôle {} n'a pas été ajouté : son code-barres {} est déjà utilisé par {}.",
+                    sample, barcode, other
+                ),
+                None => format!("Le contrôle {} n'a pas été ajouté : la plaque est pleine.", sample),
+            },
+            (ValidationWarning::OptionalColumnsAdded { columns }, Lang::En) => format!(
+                "The samples file is from an older template; these columns were added empty: {}",
+                columns.join(", ")
+            ),
+            (ValidationWarning::OptionalColumnsAdded { columns }, Lang::Fr) => format!(
+                "Le fichier d'échantillons provient d'un ancien modèle ; ces colonnes ont été ajoutées vides : {}",
+                columns.join(", ")
+            ),
+            (ValidationWarning::InteriorBlankRowsRemoved { file, lines }, Lang::En) => {
+                format!("Empty rows removed from {}: lines {}", file, short_list(lines))
+            }
+            (ValidationWarning::InteriorBlankRowsRemoved { file, lines }, Lang::Fr) => {
+                format!("Lignes vides retirées de {} : lignes {}", file, short_list(lines))
+            }
+            (ValidationWarning::InputProfiles { files }, Lang::En) => {
+                let files: Vec<String> = files.iter().map(|(f, p)| format!("{} ({})", f, p)).collect();
+                format!("Input formats: {}", files.join("; "))
+            }
+            (ValidationWarning::InputProfiles { files }, Lang::Fr) => {
+                let files: Vec<String> = files.iter().map(|(f, p)| format!("{} ({})", f, p)).collect();
+                format!("Formats des fichiers d'entrée : {}", files.join(" ; "))
+            }
+        };
+        format!("[{}] {}", self.code(), text)
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(Lang::En))
+    }
+}
+
+/// Renders a list of warnings, one per line
+pub fn render_warnings(warnings: &[ValidationWarning], lang: Lang) -> String {
+    warnings
+        .iter()
+        .map(|w| w.render(lang))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Dialogs show at most this many warnings; the rest are counted
+pub const MAX_SHOWN_WARNINGS: usize = 50;
+
+/// What a dialog shows of a warning list: the first warnings in order,
+/// and how many of each code were left out
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarningDigest<'a> {
+    pub shown: &'a [ValidationWarning],
+    // (code, count) of the warnings not shown, in order of first appearance
+    pub hidden: Vec<(&'static str, usize)>,
+}
+
+impl WarningDigest<'_> {
+    pub fn hidden_count(&self) -> usize {
+        self.hidden.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Splits `warnings` into the first `limit` and per-code counts of the rest
+pub fn digest_warnings(warnings: &[ValidationWarning], limit: usize) -> WarningDigest<'_> {
+    let (shown, rest) = warnings.split_at(limit.min(warnings.len()));
+    let mut hidden: Vec<(&'static str, usize)> = Vec::new();
+    for warning in rest {
+        let code = warning.code();
+        match hidden.iter_mut().find(|(c, _)| *c == code) {
+            Some((_, n)) => *n += 1,
+            None => hidden.push((code, 1)),
+        }
+    }
+    WarningDigest { shown, hidden }
+}
+
+/// Like `render_warnings`, but long lists stop after MAX_SHOWN_WARNINGS
+/// with a count per code of what was left out. Rendering thousands of
+/// lines into one dialog string freezes the window.
+pub fn render_warnings_capped(warnings: &[ValidationWarning], lang: Lang) -> String {
+    let digest = digest_warnings(warnings, MAX_SHOWN_WARNINGS);
+    let mut text = render_warnings(digest.shown, lang);
+    if !digest.hidden.is_empty() {
+        let counts: Vec<String> = digest.hidden.iter().map(|(code, n)| format!("{} ×{}", code, n)).collect();
+        text.push_str(&match lang {
+            Lang::En => format!("\n… and {} more ({})", digest.hidden_count(), counts.join(", ")),
+            Lang::Fr => format!("\n… et {} de plus ({})", digest.hidden_count(), counts.join(", ")),
+        });
+    }
+    text
+}
+
+/// Writes every warning, one per line, without building the whole text
+pub fn write_warnings(out: &mut impl std::io::Write, warnings: &[ValidationWarning], lang: Lang) -> std::io::Result<()> {
+    for warning in warnings {
+        writeln!(out, "{}", warning.render(lang))?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflicted(n: usize) -> ValidationWarning {
+        ValidationWarning::ConflictedCopyInput { file: format!("samples_{}.csv", n) }
+    }
+
+    fn placeholder(n: usize) -> ValidationWarning {
+        ValidationWarning::CloudPlaceholderInput { file: format!("epi_{}.csv", n) }
+    }
+
+    #[test]
+    fn short_lists_are_shown_whole() {
+        let warnings: Vec<ValidationWarning> = (0..MAX_SHOWN_WARNINGS).map(conflicted).collect();
+        let text = render_warnings_capped(&warnings, Lang::En);
+        assert_eq!(text, render_warnings(&warnings, Lang::En));
+        assert_eq!(text.lines().count(), MAX_SHOWN_WARNINGS);
+        assert!(!text.contains("more"));
+    }
+
+    #[test]
+    fn long_lists_are_cut_with_counts_per_code() {
+        let mut warnings: Vec<ValidationWarning> = (0..MAX_SHOWN_WARNINGS).map(conflicted).collect();
+        warnings.extend([placeholder(1), conflicted(99), placeholder(2), ValidationWarning::NoBarcoding { cleared: 0 }]);
+
+        let text = render_warnings_capped(&warnings, Lang::En);
+        assert_eq!(text.lines().count(), MAX_SHOWN_WARNINGS + 1);
+        assert!(text.ends_with("\n… and 4 more (W047 ×2, W048 ×1, W049 ×1)"), "{}", text);
+        assert!(!text.contains("epi_1.csv"));
+
+        let text = render_warnings_capped(&warnings, Lang::Fr);
+        assert!(text.ends_with("\n… et 4 de plus (W047 ×2, W048 ×1, W049 ×1)"), "{}", text);
+    }
+
+    #[test]
+    fn digest_groups_hidden_warnings_in_order_of_first_appearance() {
+        let warnings = vec![conflicted(1), placeholder(1), conflicted(2), placeholder(2), conflicted(3)];
+        let digest = digest_warnings(&warnings, 1);
+        assert_eq!(digest.shown.len(), 1);
+        assert_eq!(digest.hidden, vec![("W047", 2), ("W048", 2)]);
+        assert_eq!(digest.hidden_count(), 4);
+
+        let all = digest_warnings(&warnings, 10);
+        assert_eq!(all.shown.len(), 5);
+        assert!(all.hidden.is_empty());
+    }
+
+    #[test]
+    fn full_list_has_one_line_per_warning() {
+        let warnings: Vec<ValidationWarning> = (0..MAX_SHOWN_WARNINGS + 5).map(conflicted).collect();
+        let mut out = Vec::new();
+        write_warnings(&mut out, &warnings, Lang::En).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), MAX_SHOWN_WARNINGS + 5);
+        assert!(text.contains("samples_54.csv"));
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    // One warning of each code, in code order
+    fn every_warning() -> Vec<ValidationWarning> {
+        use ValidationWarning::*;
+        vec![
+            UnmatchedSamples { samples: strings(&["S1", "S2"]) },
+            DuplicateSampleReplaced { sample: "S1".into() },
+            EpiInfoColumnsSkipped { count: 12 },
+            PreviousOutputBackedUp { path: "run1_backup.csv".into() },
+            RowsAppended { new_rows: 3, existing_rows: 10, replaced_rows: 1 },
+            ExcelSafeCopyWritten { path: "run1_excel.csv".into() },
+            BarcodesOnlyExpanded { added_columns: 40 },
+            EpiInfoCacheUsed,
+            IgnoredFields { mode: "DDNS".into(), fields: strings(&["vp1_primers"]) },
+            EpidFilled { sources: vec![("EpidNo".into(), 2)] },
+            ColumnOrderDiffers { mode: "minION".into() },
+            InvalidValue { line: 4, column: "SampleQC".into(), value: "Maybe".into(), expected: "Pass, Fail".into() },
+            InvalidEpid { line: 5, value: "NIE-?".into() },
+            DateOrder { line: 6, earlier: "DateStoolCollected".into(), later: "DateOfOnset".into() },
+            StaleEpiInfo { age_days: 9 },
+            RetestWithoutOriginalRun { line: 7 },
+            ControlsNotRecorded { controls: strings(&["Positive"]) },
+            EmptyNamePlaceholders { placeholders: strings(&["lab"]) },
+            FillRulesApplied { filled: strings(&["RunNumber"]), overwritten: strings(&["FlowCellID"]), skipped: vec![] },
+            HeadersRenamed { file: "epi.csv".into(), renames: pairs(&[("", "Column_3")]) },
+            FewRows { rows: 4, minimum: 8 },
+            DateReportedFilled { date: "2026-03-01".into(), rows: 20 },
+            SeqDateMismatch { manual: "2026-03-01".into(), report: "2026-03-04".into(), used: "report".into() },
+            EpiInfoColumnsExcluded { columns: strings(&["PatientName"]) },
+            InputProfiles { files: pairs(&[("samples.csv", "UTF-8, ';'")]) },
+            LowEpiMatchRate { matched: 2, samples: 10, threshold: 50 },
+            TrailingBlankRowsDropped { file: "samples.csv".into(), count: 50 },
+            InteriorBlankRowsRemoved { file: "samples.csv".into(), lines: strings(&["12"]) },
+            OptionalColumnsAdded { columns: strings(&["TypeofPositiveControl"]) },
+            ControlRowsAdded { samples: strings(&["NTC"]) },
+            ControlRowSkipped { sample: "PC".into(), barcode: "barcode96".into(), used_by: Some("S96".into()) },
+            ReportCacheUsed,
+            PostMergeHookRan { command: "notify".into(), status: "exit 0".into(), output: strings(&["sent"]) },
+            PostMergeHookFailed { command: "notify".into(), status: "exit 3".into(), output: vec![] },
+            WeakSampleMatches { pairs: pairs(&[("S7", "CMR-0007")]) },
+            SampleMatchMethods { counts: vec![("exact".into(), 9), ("number".into(), 1)] },
+            BarcodesNormalized { changes: strings(&["line 4: BC04 → barcode04"]) },
+            UnclassifiedRowsRemoved { lines: strings(&["9"]) },
+            HeaderCaseFixed { file: "samples.csv".into(), renames: pairs(&[("Sample", "sample")]) },
+            ReportPositionChosen { position: "X3".into(), fc_id: "FAX1".into(), runs: 2 },
+            UnsampledEpiReportWritten { path: "run1_epi.csv".into(), records: 4, undated: 1, missing_columns: vec![] },
+            UnsampledEpiReportSkipped { reason: "no dates".into() },
+            ReservedColumnNames { file: "samples.csv".into(), columns: strings(&["CON"]) },
+            ColumnsResolved { file: "samples.csv".into(), renames: pairs(&[("Barcode ID", "barcode")]) },
+            LegacyLayoutUpgraded { file: "samples.csv".into(), renamed: pairs(&[("EpidNo", "EPID")]), dropped: vec![] },
+            RunConstantConflict { columns: strings(&["RunNumber"]), decision: "keep".into() },
+            CloudPlaceholderInput { file: "epi.csv".into() },
+            ConflictedCopyInput { file: "samples (1).csv".into() },
+            NoBarcoding { cleared: 1 },
+            RunAddedToOutputName { pattern: "{date}_{flowcell}.csv".into() },
+        ]
+    }
+
+    #[test]
+    fn codes_are_unique_and_match_the_json_tag() {
+        let warnings = every_warning();
+        for (i, warning) in warnings.iter().enumerate() {
+            let code = format!("W{:03}", i + 1);
+            assert_eq!(warning.code(), code);
+            assert_eq!(serde_json::to_value(warning).unwrap()["code"], code.as_str());
+        }
+    }
+
+    #[test]
+    fn every_code_renders_in_both_languages() {
+        for warning in every_warning() {
+            let (en, fr) = (warning.render(Lang::En), warning.render(Lang::Fr));
+            let prefix = format!("[{}] ", warning.code());
+            assert!(en.starts_with(&prefix) && fr.starts_with(&prefix), "{}", en);
+            assert!(en.len() > prefix.len() + 5, "{}", en);
+            assert_ne!(en, fr, "{} has no French text", warning.code());
+            assert_eq!(warning.to_string(), en);
+        }
+    }
+
+    // Partners script against these; a change here needs a new code instead
+    #[test]
+    fn context_json_is_stable() {
+        let json = |w: ValidationWarning| serde_json::to_string(&w).unwrap();
+        assert_eq!(
+            json(ValidationWarning::RowsAppended { new_rows: 3, existing_rows: 10, replaced_rows: 1 }),
+            r#"{"code":"W005","context":{"new_rows":3,"existing_rows":10,"replaced_rows":1}}"#
+        );
+        assert_eq!(json(ValidationWarning::EpiInfoCacheUsed), r#"{"code":"W008"}"#);
+        assert_eq!(
+            json(ValidationWarning::EpidFilled { sources: vec![("EpidNo".into(), 2)] }),
+            r#"{"code":"W010","context":{"sources":[["EpidNo",2]]}}"#
+        );
+        assert_eq!(
+            json(ValidationWarning::ControlRowSkipped { sample: "PC".into(), barcode: "barcode96".into(), used_by: None }),
+            r#"{"code":"W031","context":{"sample":"PC","barcode":"barcode96","used_by":null}}"#
+        );
+    }
+
+    #[test]
+    fn reserved_column_names_are_listed_in_both_languages() {
+        let warning = ValidationWarning::ReservedColumnNames {
+            file: "samples.csv".to_string(),
+            columns: vec!["CON".to_string(), "nul.txt".to_string()],
+        };
+        assert_eq!(warning.code(), "W043");
+        for lang in [Lang::En, Lang::Fr] {
+            let text = render_warnings(std::slice::from_ref(&warning), lang);
+            assert!(text.contains("samples.csv") && text.contains("CON") && text.contains("nul.txt"), "{text}");
+        }
+    }
+}
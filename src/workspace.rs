@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+// Leftover workspaces older than this are removed at startup
+pub const STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Root folder for every temporary artifact the app creates
+pub fn temp_root() -> PathBuf {
+    std::env::temp_dir().join("merger")
+}
+
+/// True if `path` lives under the temp root. Outputs must never point here.
+pub fn is_temp_path(path: &Path) -> bool {
+    let root = temp_root();
+    let root = root.canonicalize().unwrap_or(root);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.starts_with(&root)
+}
+
+/// Per-merge scratch directory under the temp root.
+/// Everything inside is removed when the workspace is dropped, whether
+/// the merge succeeded or not.
+pub struct TempWorkspace {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl TempWorkspace {
+    pub fn new() -> Result<Self, String> {
+        Self::new_in(&temp_root())
+    }
+
+    pub fn new_in(root: &Path) -> Result<Self, String> {
+        let stamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let dir = root.join(format!(
+            "merge_{}_{}_{}",
+            stamp,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create temp folder '{}': {e}", dir.display()))?;
+
+        Ok(Self { dir, files: Vec::new() })
+    }
+
+    #[cfg(test)]
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reserves a tracked path inside the workspace. Only the file name of
+    /// `name` is used so callers cannot escape the workspace.
+    pub fn file(&mut self, name: &str) -> PathBuf {
+        let file_name = Path::new(name)
+            .file_name()
+            .map(|s| s.to_os_string())
+            .unwrap_or_else(|| "artifact".into());
+        let path = self.dir.join(file_name);
+        self.files.push(path.clone());
+        path
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        for file in &self.files {
+            let _ = fs::remove_file(file);
+        }
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove temp folder '{}': {e}", self.dir.display());
+            }
+        }
+    }
+}
+
+/// Removes workspaces under `root` last modified more than `max_age` ago,
+/// e.g. left behind by a crash. Returns how many were removed.
+pub fn sweep_stale_in(root: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(root) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| now.duration_since(t).ok());
+        if matches!(age, Some(age) if age > max_age) && fs::remove_dir_all(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    removed
+}
+
+pub fn sweep_stale() -> usize {
+    sweep_stale_in(&temp_root(), STALE_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_is_created_and_removed_on_drop() {
+        let root = TempWorkspace::new().unwrap();
+        let mut workspace = TempWorkspace::new_in(root.path()).unwrap();
+        let dir = workspace.path().to_path_buf();
+        assert!(dir.is_dir());
+        assert!(dir.starts_with(root.path()));
+
+        let file = workspace.file("../../escape.csv");
+        assert_eq!(file, dir.join("escape.csv"));
+        fs::write(&file, "sample\n").unwrap();
+        fs::create_dir(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested").join("untracked.txt"), "x").unwrap();
+
+        drop(workspace);
+        assert!(!dir.exists());
+        assert!(root.path().is_dir());
+    }
+
+    #[test]
+    fn workspaces_get_distinct_folders() {
+        let root = TempWorkspace::new().unwrap();
+        let a = TempWorkspace::new_in(root.path()).unwrap();
+        let b = TempWorkspace::new_in(root.path()).unwrap();
+        assert_ne!(a.path(), b.path());
+    }
+
+    #[test]
+    fn temp_paths_are_recognized() {
+        let workspace = TempWorkspace::new().unwrap();
+        assert!(is_temp_path(workspace.path()));
+        assert!(is_temp_path(&workspace.path().join("not_yet_written.csv")));
+        assert!(!is_temp_path(Path::new("/srv/lab/outputs")));
+    }
+
+    // Backdating a folder needs a handle on it, which Windows only gives
+    // with backup semantics
+    #[cfg(unix)]
+    #[test]
+    fn sweep_removes_only_aged_folders() {
+        let root = TempWorkspace::new().unwrap();
+        let age = |dir: &Path, by: Duration| {
+            fs::File::open(dir).unwrap().set_modified(SystemTime::now() - by).unwrap();
+        };
+        let old = root.path().join("merge_old");
+        fs::create_dir_all(old.join("inner")).unwrap();
+        age(&old, STALE_AFTER + Duration::from_secs(3600));
+        let recent = root.path().join("merge_recent");
+        fs::create_dir(&recent).unwrap();
+        age(&recent, STALE_AFTER - Duration::from_secs(3600));
+        let stray_file = root.path().join("notes.txt");
+        fs::write(&stray_file, "kept").unwrap();
+
+        assert_eq!(sweep_stale_in(root.path(), STALE_AFTER), 1);
+        assert!(!old.exists());
+        assert!(recent.is_dir());
+        assert!(stray_file.is_file());
+    }
+
+    #[test]
+    fn sweep_of_a_missing_root_removes_nothing() {
+        let root = TempWorkspace::new().unwrap();
+        assert_eq!(sweep_stale_in(&root.path().join("missing"), Duration::ZERO), 0);
+    }
+}